@@ -15,7 +15,10 @@ use crate::events::llmp::COMPRESS_THRESHOLD;
 use crate::{
     Error,
     events::{BrokerEventResult, Event, llmp::LLMP_TAG_EVENT_TO_BOTH},
-    monitors::{Monitor, stats::ClientStatsManager},
+    monitors::{
+        Monitor,
+        stats::{ClientStatsManager, StatsHistory},
+    },
 };
 
 /// centralized hook
@@ -113,6 +116,21 @@ where
         })
     }
 
+    /// Create an event broker from a raw broker, continuing the [`StatsHistory`] of an earlier run.
+    ///
+    /// The broker outlives the restarts of its clients, so this is only needed if the broker itself restarts.
+    pub fn with_stats_history(monitor: MT, history: StatsHistory) -> Result<Self, Error> {
+        let mut hook = Self::new(monitor)?;
+        hook.client_stats_manager.set_history(history);
+        Ok(hook)
+    }
+
+    /// The [`StatsHistory`] recorded by this broker, e.g., to store it for the next run
+    #[must_use]
+    pub fn stats_history(&self) -> &StatsHistory {
+        self.client_stats_manager.history()
+    }
+
     /// Handle arriving events in the broker
     fn handle_in_broker(
         monitor: &mut MT,
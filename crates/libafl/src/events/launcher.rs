@@ -12,7 +12,7 @@
 //! On `Unix` systems, the [`Launcher`] will use `fork` if the `fork` feature is used for `LibAFL`.
 //! Else, it will start subsequent nodes with the same commandline, and will set special `env` variables accordingly.
 
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
 use core::{
     fmt::{self, Debug, Formatter},
     net::SocketAddr,
@@ -20,9 +20,11 @@ use core::{
     time::Duration,
 };
 
+#[cfg(unix)]
+use libafl_bolts::os::resource_limits::ResourceLimits;
 use libafl_bolts::{
     core_affinity::{CoreId, Cores},
-    shmem::ShMemProvider,
+    shmem::{ShMem, ShMemProvider},
     tuples::tuple_list,
 };
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
@@ -54,7 +56,7 @@ use crate::events::multi_machine::{NodeDescriptor, TcpMultiMachineHooks};
 use crate::{
     Error,
     events::{
-        EventConfig, EventManagerHooksTuple,
+        ClientProfile, ClientProfiles, EventConfig, EventManagerHooksTuple,
         llmp::{LlmpRestartingEventManager, LlmpShouldSaveState, ManagerKind, RestartingMgr},
     },
     monitors::Monitor,
@@ -73,6 +75,9 @@ pub struct ClientDescription {
     id: usize,
     overcommit_id: usize,
     core_id: CoreId,
+    profile: Option<ClientProfile>,
+    #[cfg(unix)]
+    resource_limits: Option<ResourceLimits>,
 }
 
 impl ClientDescription {
@@ -83,6 +88,9 @@ impl ClientDescription {
             id,
             overcommit_id,
             core_id,
+            profile: None,
+            #[cfg(unix)]
+            resource_limits: None,
         }
     }
 
@@ -104,6 +112,30 @@ impl ClientDescription {
         self.overcommit_id
     }
 
+    /// The [`ClientProfile`] assigned to this client, if the launcher was given [`ClientProfiles`]
+    #[must_use]
+    pub fn profile(&self) -> Option<&ClientProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Set the [`ClientProfile`] for this client
+    pub fn set_profile(&mut self, profile: Option<ClientProfile>) {
+        self.profile = profile;
+    }
+
+    /// The [`ResourceLimits`] the restarting manager applies to each fuzzing process of this client
+    #[cfg(unix)]
+    #[must_use]
+    pub fn resource_limits(&self) -> Option<&ResourceLimits> {
+        self.resource_limits.as_ref()
+    }
+
+    /// Set the [`ResourceLimits`] for this client
+    #[cfg(unix)]
+    pub fn set_resource_limits(&mut self, resource_limits: Option<ResourceLimits>) {
+        self.resource_limits = resource_limits;
+    }
+
     /// Create a string representation safe for environment variables
     #[must_use]
     pub fn to_safe_string(&self) -> String {
@@ -111,6 +143,8 @@ impl ClientDescription {
     }
 
     /// Parse the string created by [`Self::to_safe_string`].
+    ///
+    /// The profile and the resource limits are not part of the string, they need to be assigned again.
     #[must_use]
     pub fn from_safe_string(input: &str) -> Self {
         let mut iter = input.split('_');
@@ -121,6 +155,9 @@ impl ClientDescription {
             id,
             overcommit_id,
             core_id,
+            profile: None,
+            #[cfg(unix)]
+            resource_limits: None,
         }
     }
 }
@@ -180,6 +217,15 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The [`ClientProfiles`] to assign to the clients.
+    /// Each client finds its profile in its [`ClientDescription`], and reports the profile name to the monitor.
+    #[builder(default, setter(strip_option))]
+    profiles: Option<ClientProfiles>,
+    /// The [`ResourceLimits`] for each client.
+    /// A client hitting one of the limits reports an [`crate::executors::ExitKind::Oom`] and gets restarted.
+    #[cfg(unix)]
+    #[builder(default, setter(strip_option))]
+    resource_limits: Option<ResourceLimits>,
 }
 
 impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
@@ -190,18 +236,70 @@ impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
             .field("broker_port", &self.broker_port)
             .field("core", &self.cores)
             .field("spawn_broker", &self.spawn_broker)
-            .field("remote_broker_addr", &self.remote_broker_addr);
+            .field("remote_broker_addr", &self.remote_broker_addr)
+            .field("profiles", &self.profiles);
         #[cfg(unix)]
         {
             dbg_struct
                 .field("stdout_file", &self.stdout_file)
-                .field("stderr_file", &self.stderr_file);
+                .field("stderr_file", &self.stderr_file)
+                .field("resource_limits", &self.resource_limits);
         }
 
         dbg_struct.finish_non_exhaustive()
     }
 }
 
+impl<CF, MT, SP> Launcher<'_, CF, MT, SP> {
+    /// The [`ClientDescription`]s of all clients to spawn, in launch order, with their [`ClientProfile`]s
+    /// and [`ResourceLimits`] assigned
+    fn client_descriptions(&self, core_ids: &[CoreId]) -> Result<Vec<ClientDescription>, Error> {
+        let mut client_descriptions = vec![];
+        for bind_to in core_ids {
+            if self.cores.ids.contains(bind_to) {
+                for overcommit_id in 0..self.overcommit {
+                    client_descriptions.push(ClientDescription::new(
+                        client_descriptions.len() + 1,
+                        overcommit_id,
+                        *bind_to,
+                    ));
+                }
+            }
+        }
+        if let Some(profiles) = &self.profiles {
+            profiles.assign(&mut client_descriptions)?;
+        }
+        #[cfg(unix)]
+        if let Some(resource_limits) = &self.resource_limits {
+            for client_description in &mut client_descriptions {
+                client_description.set_resource_limits(Some(resource_limits.clone()));
+            }
+        }
+        Ok(client_descriptions)
+    }
+}
+
+/// Report the [`ClientProfile`] of a newly started client to the monitor
+fn report_profile<EMH, I, S, SHM, SP>(
+    client_description: &ClientDescription,
+    mgr: &mut LlmpRestartingEventManager<EMH, I, S, SHM, SP>,
+) -> Result<(), Error>
+where
+    I: Serialize,
+    SHM: ShMem,
+    SP: ShMemProvider<ShMem = SHM>,
+{
+    if let Some(profile) = client_description.profile() {
+        log::info!(
+            "Client {} uses profile {}",
+            client_description.id(),
+            profile.name()
+        );
+        mgr.send_event(&profile.user_stats_event())?;
+    }
+    Ok(())
+}
+
 impl<CF, MT, SP> Launcher<'_, CF, MT, SP>
 where
     MT: Monitor + Clone,
@@ -215,7 +313,7 @@ where
             LlmpRestartingEventManager<(), I, S, SP::ShMem, SP>,
             ClientDescription,
         ) -> Result<(), Error>,
-        I: DeserializeOwned,
+        I: DeserializeOwned + Serialize,
         S: DeserializeOwned + Serialize,
         SP: ShMemProvider,
     {
@@ -233,7 +331,7 @@ where
     pub fn launch_with_hooks<EMH, I, S>(&mut self, hooks: EMH) -> Result<(), Error>
     where
         S: DeserializeOwned + Serialize,
-        I: DeserializeOwned,
+        I: DeserializeOwned + Serialize,
         EMH: EventManagerHooksTuple<I, S> + Clone + Copy,
         CF: FnOnce(
             Option<S>,
@@ -254,6 +352,7 @@ where
         }
 
         let core_ids = get_core_ids()?;
+        let client_descriptions = self.client_descriptions(&core_ids)?;
         let mut handles = vec![];
 
         log::info!("spawning on cores: {:?}", self.cores);
@@ -271,7 +370,7 @@ where
         let mut index = 0_usize;
         for bind_to in core_ids {
             if self.cores.ids.contains(&bind_to) {
-                for overcommit_id in 0..self.overcommit {
+                for _ in 0..self.overcommit {
                     index += 1;
                     self.shmem_provider.pre_fork()?;
                     // # Safety
@@ -312,8 +411,7 @@ where
                                 }
                             }
 
-                            let client_description =
-                                ClientDescription::new(index, overcommit_id, bind_to);
+                            let client_description = client_descriptions[index - 1].clone();
 
                             // Fuzzer client. keeps retrying the connection to broker till the broker starts
                             let builder = RestartingMgr::<EMH, I, MT, S, SP>::builder()
@@ -325,7 +423,11 @@ where
                                 .configuration(self.configuration)
                                 .serialize_state(self.serialize_state)
                                 .hooks(hooks);
-                            let (state, mgr) = builder.build().launch()?;
+                            let (state, mut mgr) = builder.build().launch()?;
+
+                            if state.is_none() {
+                                report_profile(&client_description, &mut mgr)?;
+                            }
 
                             return (self.run_client.take().unwrap())(
                                 state,
@@ -392,7 +494,7 @@ where
             ClientDescription,
         ) -> Result<(), Error>,
         EMH: EventManagerHooksTuple<I, S> + Clone + Copy,
-        I: DeserializeOwned,
+        I: DeserializeOwned + Serialize,
         S: DeserializeOwned + Serialize,
     {
         use libafl_bolts::core_affinity::get_core_ids;
@@ -401,7 +503,17 @@ where
 
         let mut handles = match is_client {
             Ok(core_conf) => {
-                let client_description = ClientDescription::from_safe_string(&core_conf);
+                let mut client_description = ClientDescription::from_safe_string(&core_conf);
+                // Assign the profile and resource limits again, they are not part of the env.
+                if let Some(other) = self
+                    .client_descriptions(&get_core_ids()?)?
+                    .into_iter()
+                    .find(|other| other.id() == client_description.id())
+                {
+                    client_description.set_profile(other.profile);
+                    #[cfg(unix)]
+                    client_description.set_resource_limits(other.resource_limits);
+                }
                 // the actual client. do the fuzzing
 
                 let builder = RestartingMgr::<EMH, I, MT, S, SP>::builder()
@@ -414,7 +526,11 @@ where
                     .serialize_state(self.serialize_state)
                     .hooks(hooks);
 
-                let (state, mgr) = builder.build().launch()?;
+                let (state, mut mgr) = builder.build().launch()?;
+
+                if state.is_none() {
+                    report_profile(&client_description, &mut mgr)?;
+                }
 
                 return (self.run_client.take().unwrap())(state, mgr, client_description);
             }
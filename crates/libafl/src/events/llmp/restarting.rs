@@ -5,7 +5,7 @@
 
 #[cfg(feature = "std")]
 use alloc::string::ToString;
-use alloc::vec::Vec;
+use alloc::{borrow::Cow, vec::Vec};
 use core::{
     marker::PhantomData,
     net::SocketAddr,
@@ -16,6 +16,10 @@ use core::{
 #[cfg(feature = "std")]
 use std::net::TcpStream;
 
+#[cfg(unix)]
+use libafl_bolts::os::RESOURCE_LIMIT_EXIT;
+#[cfg(target_os = "linux")]
+use libafl_bolts::os::resource_limits::Cgroup;
 #[cfg(any(windows, not(feature = "fork")))]
 use libafl_bolts::os::startable_self;
 #[cfg(all(unix, not(miri)))]
@@ -61,7 +65,12 @@ use crate::{
         std_report_progress,
     },
     inputs::Input,
-    monitors::Monitor,
+    monitors::{
+        Monitor,
+        stats::{
+            AggregatorOps, RESOURCE_LIMIT_HITS_STATS_NAME, StatsHistory, UserStats, UserStatsValue,
+        },
+    },
     state::{
         HasCurrentStageId, HasCurrentTestcase, HasExecutions, HasImported, HasLastReportTime,
         HasSolutions, MaybeHasClientPerfMonitor, Stoppable,
@@ -91,6 +100,8 @@ pub struct LlmpRestartingEventManager<EMH, I, S, SHM, SP> {
     staterestorer: Option<StateRestorer<SHM, SP>>,
     /// Decide if the state restorer must save the serialized state
     save_state: LlmpShouldSaveState,
+    /// The number of resource limit hits of this client so far, reported with the next progress report
+    resource_limit_hits: Option<u64>,
     phantom: PhantomData<(I, S)>,
 }
 
@@ -110,18 +121,37 @@ where
     }
 
     fn report_progress(&mut self, state: &mut S) -> Result<(), Error> {
+        if let Some(resource_limit_hits) = self.resource_limit_hits.take() {
+            let executions = *state.executions();
+            self.fire(
+                state,
+                EventWithStats::with_current_time(
+                    Event::UpdateUserStats {
+                        name: Cow::Borrowed(RESOURCE_LIMIT_HITS_STATS_NAME),
+                        value: UserStats::new(
+                            UserStatsValue::Number(resource_limit_hits),
+                            AggregatorOps::Sum,
+                        ),
+                        phantom: PhantomData,
+                    },
+                    executions,
+                ),
+            )?;
+        }
         std_report_progress(self, state)
     }
 }
 
-impl<EMH, I, S, SHM, SP> EventFirer<I, S> for LlmpRestartingEventManager<EMH, I, S, SHM, SP>
+impl<EMH, I, S, SHM, SP> LlmpRestartingEventManager<EMH, I, S, SHM, SP>
 where
     I: Serialize,
-    S: Serialize,
     SHM: ShMem,
     SP: ShMemProvider<ShMem = SHM>,
 {
-    fn fire(&mut self, _state: &mut S, event: EventWithStats<I>) -> Result<(), Error> {
+    /// Serialize an [`Event`] and send it to the broker.
+    ///
+    /// Unlike [`EventFirer::fire`], this does not need a state, so it can be used before the state exists.
+    pub fn send_event(&mut self, event: &EventWithStats<I>) -> Result<(), Error> {
         // Check if we are going to crash in the event, in which case we store our current state for the next runner
         #[cfg(feature = "llmp_compression")]
         let flags = LLMP_FLAG_INITIALIZED;
@@ -129,10 +159,10 @@ where
         self.event_buffer.resize(self.event_buffer.capacity(), 0);
 
         // Serialize the event, reallocating event_buffer if needed
-        let written_len = match postcard::to_slice(&event, &mut self.event_buffer) {
+        let written_len = match postcard::to_slice(event, &mut self.event_buffer) {
             Ok(written) => written.len(),
             Err(postcard::Error::SerializeBufferFull) => {
-                let serialized = postcard::to_allocvec(&event)?;
+                let serialized = postcard::to_allocvec(event)?;
                 self.event_buffer = serialized;
                 self.event_buffer.len()
             }
@@ -166,6 +196,19 @@ where
         }
 
         self.last_sent = current_time();
+        Ok(())
+    }
+}
+
+impl<EMH, I, S, SHM, SP> EventFirer<I, S> for LlmpRestartingEventManager<EMH, I, S, SHM, SP>
+where
+    I: Serialize,
+    S: Serialize,
+    SHM: ShMem,
+    SP: ShMemProvider<ShMem = SHM>,
+{
+    fn fire(&mut self, _state: &mut S, event: EventWithStats<I>) -> Result<(), Error> {
+        self.send_event(&event)?;
 
         if self.staterestorer.is_some() {
             self.intermediate_save()?;
@@ -365,6 +408,11 @@ const _ENV_FUZZER_SENDER: &str = "_AFL_ENV_FUZZER_SENDER";
 const _ENV_FUZZER_RECEIVER: &str = "_AFL_ENV_FUZZER_RECEIVER";
 /// The llmp (2 way) connection from a fuzzer to the broker (broadcasting all other fuzzer messages)
 const _ENV_FUZZER_BROKER_CLIENT_INITIAL: &str = "_AFL_ENV_FUZZER_BROKER_CLIENT";
+/// The cgroup the fuzzer should move itself to
+#[cfg(target_os = "linux")]
+const _ENV_FUZZER_CGROUP: &str = "_AFL_ENV_FUZZER_CGROUP";
+/// The number of times previous fuzzers of this client hit their resource limits
+const _ENV_FUZZER_RESOURCE_LIMIT_HITS: &str = "_AFL_ENV_FUZZER_RESOURCE_LIMIT_HITS";
 
 /// Builder for `LlmpRestartingEventManager`
 #[derive(Debug)]
@@ -438,6 +486,7 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
             event_buffer: Vec::with_capacity(INITIAL_EVENT_BUFFER_SIZE),
             staterestorer,
             save_state: LlmpShouldSaveState::OnRestart,
+            resource_limit_hits: None,
             phantom: PhantomData,
         })
     }
@@ -671,6 +720,10 @@ pub struct RestartingMgr<EMH, I, MT, S, SP> {
     /// The monitor to use
     #[builder(default = None)]
     monitor: Option<MT>,
+    /// The [`StatsHistory`] of an earlier run, to continue it in the broker.
+    /// The history lives in the broker, so it is kept across restarts of the clients either way.
+    #[builder(default = None)]
+    stats_history: Option<StatsHistory>,
     /// The broker port to use
     #[builder(default = 1337_u16)]
     broker_port: u16,
@@ -744,8 +797,10 @@ where
                         LlmpConnection::on_port(self.shmem_provider.clone(), self.broker_port)?;
                     match connection {
                         LlmpConnection::IsBroker { broker } => {
-                            let llmp_hook =
-                                StdLlmpEventHook::<I, MT>::new(self.monitor.take().unwrap())?;
+                            let llmp_hook = StdLlmpEventHook::<I, MT>::with_stats_history(
+                                self.monitor.take().unwrap(),
+                                self.stats_history.take().unwrap_or_default(),
+                            )?;
 
                             // Yep, broker. Just loop here.
                             log::info!(
@@ -769,7 +824,10 @@ where
                     }
                 }
                 ManagerKind::Broker => {
-                    let llmp_hook = StdLlmpEventHook::new(self.monitor.take().unwrap())?;
+                    let llmp_hook = StdLlmpEventHook::with_stats_history(
+                        self.monitor.take().unwrap(),
+                        self.stats_history.take().unwrap_or_default(),
+                    )?;
 
                     let broker = LlmpBroker::create_attach_to_tcp(
                         self.shmem_provider.clone(),
@@ -826,6 +884,12 @@ where
                 staterestorer.write_to_env(_ENV_FUZZER_SENDER)?;
             }
 
+            #[cfg(target_os = "linux")]
+            let cgroup = self.create_cgroup();
+            #[cfg(target_os = "linux")]
+            let mut cgroup_oom_kills = 0;
+            let mut resource_limit_hits: u64 = 0;
+
             let mut ctr: u64 = 0;
             // Client->parent loop
             loop {
@@ -884,10 +948,49 @@ where
                     return Err(Error::shutting_down());
                 }
 
+                #[cfg(not(unix))]
+                let resource_limit_hit = false;
+                #[cfg(unix)]
+                let resource_limit_hit = {
+                    #[cfg_attr(not(target_os = "linux"), expect(unused_mut))]
+                    let mut hit = child_status == RESOURCE_LIMIT_EXIT;
+                    // The kernel's OOM killer sends a SIGKILL, the child can't tell us about it.
+                    #[cfg(target_os = "linux")]
+                    if let Some(cgroup) = &cgroup {
+                        let oom_kills = cgroup.oom_kills().unwrap_or(cgroup_oom_kills);
+                        if oom_kills > cgroup_oom_kills {
+                            cgroup_oom_kills = oom_kills;
+                            hit = true;
+                        }
+                    }
+                    hit
+                };
+
+                if resource_limit_hit {
+                    resource_limit_hits += 1;
+                    log::warn!(
+                        "Fuzzer-respawner: client hit its resource limits ({resource_limit_hits} times so far)"
+                    );
+                    // The next fuzzer reports the hits, our own llmp client is not the one in use.
+                    // # Safety
+                    // The respawner is single-threaded.
+                    unsafe {
+                        std::env::set_var(
+                            _ENV_FUZZER_RESOURCE_LIMIT_HITS,
+                            resource_limit_hits.to_string(),
+                        );
+                    }
+                }
+
                 if !staterestorer.has_content() && !self.serialize_state.oom_safe() {
                     if let Err(err) = mgr.detach_from_broker(self.broker_port) {
                         log::error!("Failed to detach from broker: {err}");
                     }
+                    if resource_limit_hit {
+                        return Err(Error::illegal_state(
+                            "Fuzzer-respawner: The child hit its resource limits before it could store its state. Use an OOM-safe `LlmpShouldSaveState`, such as `LlmpShouldSaveState::OOMSafeOnRestart`, to keep fuzzing.",
+                        ));
+                    }
                     #[cfg(unix)]
                     assert_ne!(
                         9, child_status,
@@ -920,6 +1023,18 @@ where
             log::error!("Failed to setup signal handlers: {_e}");
         }
 
+        // Apply the resource limits of this client, so that they cover everything the fuzzer does.
+        #[cfg(unix)]
+        if let ManagerKind::Client { client_description } = &self.kind {
+            if let Some(resource_limits) = client_description.resource_limits() {
+                #[cfg(target_os = "linux")]
+                if let Ok(cgroup) = std::env::var(_ENV_FUZZER_CGROUP) {
+                    Cgroup::add_process(cgroup, std::process::id())?;
+                }
+                resource_limits.apply()?;
+            }
+        }
+
         if let Some(core_id) = core_id {
             let core_id: CoreId = core_id;
             core_id.set_affinity()?;
@@ -956,6 +1071,10 @@ where
                         )?,
                 )
             };
+        mgr.resource_limit_hits = std::env::var(_ENV_FUZZER_RESOURCE_LIMIT_HITS)
+            .ok()
+            .and_then(|hits| hits.parse().ok());
+
         // We reset the staterestorer, the next staterestorer and receiver (after crash) will reuse the page from the initial message.
         if self.serialize_state.oom_safe() {
             mgr.intermediate_save()?;
@@ -971,6 +1090,40 @@ where
 
         Ok((state, mgr))
     }
+
+    /// Create the cgroup for this client, if its resource limits ask for one.
+    ///
+    /// The fuzzers find the cgroup in the env, and move themselves into it.
+    #[cfg(target_os = "linux")]
+    fn create_cgroup(&self) -> Option<Cgroup> {
+        let ManagerKind::Client { client_description } = &self.kind else {
+            return None;
+        };
+        let resource_limits = client_description.resource_limits()?;
+        let parent = resource_limits.cgroup_parent()?;
+        let name = format!(
+            "libafl-{}-client-{}",
+            std::process::id(),
+            client_description.id()
+        );
+        match Cgroup::create(parent, &name, resource_limits.memory_limit()) {
+            Ok(cgroup) => {
+                // # Safety
+                // The respawner is single-threaded.
+                unsafe {
+                    std::env::set_var(_ENV_FUZZER_CGROUP, cgroup.path());
+                }
+                Some(cgroup)
+            }
+            Err(err) => {
+                log::warn!(
+                    "Could not create a cgroup in {}, only using rlimits: {err}",
+                    parent.display()
+                );
+                None
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -12,6 +12,10 @@ pub mod centralized;
 pub use centralized::*;
 #[cfg(feature = "std")]
 pub mod launcher;
+#[cfg(feature = "std")]
+pub mod profiles;
+#[cfg(feature = "std")]
+pub use profiles::*;
 
 pub mod llmp;
 pub use llmp::*;
@@ -0,0 +1,373 @@
+//! Client profiles describe how an individual client spawned by the [`Launcher`](crate::events::launcher::Launcher) should fuzz.
+//!
+//! A [`ClientProfile`] lists the mutator set, scheduler, stages, power schedule, and whether `cmplog` is enabled.
+//! Profiles are assigned to clients either by core ranges, or by ratio, using [`ClientProfiles`].
+//! Each client receives its profile through its [`ClientDescription`], so the `run_client` closure can build
+//! its fuzzer from it instead of hand-written `match` statements on core ids.
+//! This allows AFL++-style `-M`/`-S` diversity ensembles, with per-profile stats in the monitors.
+
+use alloc::{string::String, vec::Vec};
+use core::marker::PhantomData;
+
+use libafl_bolts::{Error, core_affinity::Cores};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{Event, EventWithStats, launcher::ClientDescription},
+    monitors::stats::{AggregatorOps, CLIENT_PROFILE_STATS_NAME, UserStats, UserStatsValue},
+    schedulers::powersched::PowerSchedule,
+};
+
+/// The set of mutators a client should use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MutatorSet {
+    /// The standard havoc mutations
+    Havoc,
+    /// Havoc mutations, plus token mutations
+    HavocTokens,
+    /// Grimoire mutations for (text-based) generalized inputs
+    Grimoire,
+    /// `MOpt` scheduled havoc mutations
+    Mopt,
+    /// Unicode-aware mutations
+    Unicode,
+    /// A user-defined mutator set, identified by name
+    Custom(String),
+}
+
+/// The corpus scheduler a client should use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SchedulerKind {
+    /// A simple FIFO queue
+    Queue,
+    /// A random scheduler
+    Rand,
+    /// A power schedule queue, using the profile's [`PowerSchedule`]
+    PowerQueue,
+    /// A weighted scheduler, using the profile's [`PowerSchedule`]
+    Weighted,
+    /// A user-defined scheduler, identified by name
+    Custom(String),
+}
+
+/// A stage a client should run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StageKind {
+    /// Calibrate new testcases
+    Calibration,
+    /// Colorization, for `cmplog`-based mutations
+    Colorization,
+    /// Tracing with a `cmplog` executor
+    Tracing,
+    /// Input-to-state replacement mutations
+    InputToState,
+    /// Generalization, for grimoire
+    Generalization,
+    /// The standard mutational stage
+    Mutational,
+    /// A mutational stage with power scheduling
+    Power,
+    /// Sync testcases from a foreign directory
+    Sync,
+    /// A user-defined stage, identified by name
+    Custom(String),
+}
+
+/// A client profile, describing the fuzzing strategy a single client should use.
+///
+/// The profile is a description only. The `run_client` closure of the [`Launcher`](crate::events::launcher::Launcher)
+/// reads it from [`ClientDescription::profile`] and builds the matching components.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientProfile {
+    name: String,
+    mutators: MutatorSet,
+    scheduler: SchedulerKind,
+    stages: Vec<StageKind>,
+    cmplog: bool,
+    power_schedule: Option<PowerSchedule>,
+}
+
+impl ClientProfile {
+    /// Create a new [`ClientProfile`] with the given name, using havoc mutations, a queue scheduler
+    /// and a single mutational stage.
+    #[must_use]
+    pub fn new<N>(name: N) -> Self
+    where
+        N: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            mutators: MutatorSet::Havoc,
+            scheduler: SchedulerKind::Queue,
+            stages: vec![StageKind::Mutational],
+            cmplog: false,
+            power_schedule: None,
+        }
+    }
+
+    /// Set the mutator set
+    #[must_use]
+    pub fn with_mutators(mut self, mutators: MutatorSet) -> Self {
+        self.mutators = mutators;
+        self
+    }
+
+    /// Set the scheduler
+    #[must_use]
+    pub fn with_scheduler(mut self, scheduler: SchedulerKind) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Set the stages, in the order they should run
+    #[must_use]
+    pub fn with_stages(mut self, stages: Vec<StageKind>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Enable or disable `cmplog`
+    #[must_use]
+    pub fn with_cmplog(mut self, cmplog: bool) -> Self {
+        self.cmplog = cmplog;
+        self
+    }
+
+    /// Set the power schedule
+    #[must_use]
+    pub fn with_power_schedule(mut self, power_schedule: PowerSchedule) -> Self {
+        self.power_schedule = Some(power_schedule);
+        self
+    }
+
+    /// The name of this profile, as reported to the monitor
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The mutator set
+    #[must_use]
+    pub fn mutators(&self) -> &MutatorSet {
+        &self.mutators
+    }
+
+    /// The scheduler
+    #[must_use]
+    pub fn scheduler(&self) -> &SchedulerKind {
+        &self.scheduler
+    }
+
+    /// The stages, in the order they should run
+    #[must_use]
+    pub fn stages(&self) -> &[StageKind] {
+        &self.stages
+    }
+
+    /// Checks if the given stage is part of this profile
+    #[must_use]
+    pub fn has_stage(&self, stage: &StageKind) -> bool {
+        self.stages.contains(stage)
+    }
+
+    /// If `cmplog` is enabled
+    #[must_use]
+    pub fn cmplog(&self) -> bool {
+        self.cmplog
+    }
+
+    /// The power schedule, if any
+    #[must_use]
+    pub fn power_schedule(&self) -> Option<PowerSchedule> {
+        self.power_schedule
+    }
+
+    /// The event reporting this profile's name to the monitor
+    #[must_use]
+    pub fn user_stats_event<I>(&self) -> EventWithStats<I> {
+        EventWithStats::with_current_time(
+            Event::UpdateUserStats {
+                name: CLIENT_PROFILE_STATS_NAME.into(),
+                value: UserStats::new(
+                    UserStatsValue::String(self.name.clone().into()),
+                    AggregatorOps::None,
+                ),
+                phantom: PhantomData,
+            },
+            0,
+        )
+    }
+}
+
+/// How a [`ClientProfile`] is assigned to clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProfileAssignment {
+    /// All clients bound to one of these cores use the profile
+    Cores(Cores),
+    /// The profile gets this share of all clients not assigned by [`ProfileAssignment::Cores`]
+    Ratio(usize),
+}
+
+/// A list of [`ClientProfile`]s together with their [`ProfileAssignment`].
+///
+/// Core assignments take precedence, in the order they were added.
+/// All remaining clients are distributed according to the ratios, using a smooth weighted round-robin.
+/// The assignment is deterministic, so clients that are respawned by the [`Launcher`](crate::events::launcher::Launcher)
+/// always end up with the same profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientProfiles {
+    profiles: Vec<(ClientProfile, ProfileAssignment)>,
+}
+
+impl ClientProfiles {
+    /// Create an empty list of [`ClientProfile`]s
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign the profile to all clients running on the given cores
+    #[must_use]
+    pub fn with_cores(mut self, profile: ClientProfile, cores: Cores) -> Self {
+        self.profiles
+            .push((profile, ProfileAssignment::Cores(cores)));
+        self
+    }
+
+    /// Assign the profile to a share of `weight` of the remaining clients
+    #[must_use]
+    pub fn with_ratio(mut self, profile: ClientProfile, weight: usize) -> Self {
+        self.profiles
+            .push((profile, ProfileAssignment::Ratio(weight)));
+        self
+    }
+
+    /// All profiles, with their assignment
+    #[must_use]
+    pub fn profiles(&self) -> &[(ClientProfile, ProfileAssignment)] {
+        &self.profiles
+    }
+
+    /// Checks if no profiles have been added
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    /// Get a profile by name
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ClientProfile> {
+        self.profiles
+            .iter()
+            .map(|(profile, _)| profile)
+            .find(|profile| profile.name() == name)
+    }
+
+    /// Assign the profiles to the given clients, in launch order.
+    ///
+    /// Clients that neither match a core assignment, nor get a ratio share (because no ratios were given),
+    /// stay without a profile.
+    pub fn assign(&self, clients: &mut [ClientDescription]) -> Result<(), Error> {
+        let mut unassigned = Vec::with_capacity(clients.len());
+        for client in clients.iter_mut() {
+            let by_core = self.profiles.iter().find(|(_, assignment)| {
+                matches!(assignment, ProfileAssignment::Cores(cores) if cores.contains(client.core_id()))
+            });
+            match by_core {
+                Some((profile, _)) => client.set_profile(Some(profile.clone())),
+                None => unassigned.push(client),
+            }
+        }
+
+        let weighted = self
+            .profiles
+            .iter()
+            .filter_map(|(profile, assignment)| match assignment {
+                ProfileAssignment::Ratio(weight) if *weight > 0 => Some((profile, *weight)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if weighted.is_empty() {
+            return Ok(());
+        }
+        let total = weighted.iter().map(|(_, weight)| weight).sum::<usize>();
+        if total > isize::MAX as usize {
+            return Err(Error::illegal_argument("Profile ratios are too large"));
+        }
+
+        // Smooth weighted round-robin: every client picks the profile that lags furthest behind its share.
+        let mut current = vec![0_isize; weighted.len()];
+        for client in unassigned {
+            for (cur, (_, weight)) in current.iter_mut().zip(&weighted) {
+                *cur += *weight as isize;
+            }
+            let (best, _) = current
+                .iter()
+                .enumerate()
+                .max_by(|(a_idx, a), (b_idx, b)| a.cmp(b).then(b_idx.cmp(a_idx)))
+                .unwrap();
+            current[best] -= total as isize;
+            client.set_profile(Some(weighted[best].0.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::core_affinity::{CoreId, Cores};
+
+    use super::{ClientProfile, ClientProfiles};
+    use crate::events::launcher::ClientDescription;
+
+    fn clients(cores: &[usize]) -> Vec<ClientDescription> {
+        cores
+            .iter()
+            .enumerate()
+            .map(|(idx, core)| ClientDescription::new(idx + 1, 0, CoreId(*core)))
+            .collect()
+    }
+
+    fn names(clients: &[ClientDescription]) -> Vec<Option<&str>> {
+        clients
+            .iter()
+            .map(|client| client.profile().map(ClientProfile::name))
+            .collect()
+    }
+
+    #[test]
+    fn test_profiles_by_cores_and_ratio() {
+        let profiles = ClientProfiles::new()
+            .with_cores(ClientProfile::new("main"), Cores::from(vec![0]))
+            .with_ratio(ClientProfile::new("explore"), 2)
+            .with_ratio(ClientProfile::new("cmplog").with_cmplog(true), 1);
+
+        let mut clients = clients(&[0, 1, 2, 3, 4, 5, 6]);
+        profiles.assign(&mut clients).unwrap();
+
+        assert_eq!(
+            names(&clients),
+            vec![
+                Some("main"),
+                Some("explore"),
+                Some("cmplog"),
+                Some("explore"),
+                Some("explore"),
+                Some("cmplog"),
+                Some("explore"),
+            ]
+        );
+        assert!(clients[2].profile().unwrap().cmplog());
+    }
+
+    #[test]
+    fn test_profiles_without_ratio() {
+        let profiles =
+            ClientProfiles::new().with_cores(ClientProfile::new("main"), Cores::from(vec![1]));
+
+        let mut clients = clients(&[0, 1]);
+        profiles.assign(&mut clients).unwrap();
+
+        assert_eq!(names(&clients), vec![None, Some("main")]);
+    }
+}
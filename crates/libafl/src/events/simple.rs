@@ -41,7 +41,10 @@ use crate::{
 };
 #[cfg(feature = "std")]
 use crate::{
-    monitors::{SimplePrintingMonitor, stats::ClientStats},
+    monitors::{
+        SimplePrintingMonitor,
+        stats::{ClientStats, StatsHistory},
+    },
     state::HasSolutions,
 };
 
@@ -319,6 +322,7 @@ where
             state,
             self.inner.client_stats_manager.start_time(),
             self.inner.client_stats_manager.client_stats(),
+            self.inner.client_stats_manager.history(),
         ))
     }
 }
@@ -527,7 +531,9 @@ where
 
         // If we're restarting, deserialize the old state.
         let (state, mgr) =
-            match staterestorer.restore::<(S, Duration, HashMap<ClientId, ClientStats>)>()? {
+            match staterestorer
+                .restore::<(S, Duration, HashMap<ClientId, ClientStats>, StatsHistory)>()?
+            {
                 None => {
                     log::info!("First run. Let's set it all up");
                     // Mgr to send and receive msgs from/to all other fuzzer instances
@@ -537,7 +543,7 @@ where
                     )
                 }
                 // Restoring from a previous run, deserialize state and corpus.
-                Some((state, start_time, clients_stats)) => {
+                Some((state, start_time, clients_stats, history)) => {
                     log::info!("Subsequent run. Loaded previous state.");
                     // We reset the staterestorer, the next staterestorer and receiver (after crash) will reuse the page from the initial message.
                     staterestorer.reset();
@@ -548,6 +554,7 @@ where
                     this.inner
                         .client_stats_manager
                         .update_all_client_stats(clients_stats);
+                    this.inner.client_stats_manager.set_history(history);
 
                     (Some(state), this)
                 }
@@ -6,7 +6,8 @@ pub mod unix_signal_handler {
     use std::{io::Write, panic};
 
     use libafl_bolts::os::{
-        SIGNAL_RECURSION_EXIT,
+        RESOURCE_LIMIT_EXIT, SIGNAL_RECURSION_EXIT,
+        resource_limits::limit_hit,
         unix_signals::{Signal, SignalHandler, ucontext_t},
     };
     use libc::siginfo_t;
@@ -199,6 +200,8 @@ pub mod unix_signal_handler {
     /// Crash-Handler for in-process fuzzing.
     /// Will be used for signal handling.
     /// It will store the current State to shmem, then exit.
+    /// Crashes caused by one of the process' [`libafl_bolts::os::resource_limits::ResourceLimits`]
+    /// are reported as [`ExitKind::Oom`], and exit with [`RESOURCE_LIMIT_EXIT`].
     ///
     /// # Safety
     /// Well, signal handling is not safe
@@ -218,6 +221,12 @@ pub mod unix_signal_handler {
         I: Input + Clone,
     {
         unsafe {
+            // Read errno first, before anything else overwrites it.
+            let errno = std::io::Error::last_os_error()
+                .raw_os_error()
+                .unwrap_or_default();
+            let limit = limit_hit(signal, errno);
+
             #[cfg(all(target_os = "android", target_arch = "aarch64"))]
             let _context = _context.map(|p| {
                 &mut *(((core::ptr::from_mut(p) as *mut libc::c_void as usize) + 128)
@@ -225,6 +234,9 @@ pub mod unix_signal_handler {
             });
 
             log::error!("Crashed with {signal}");
+            if let Some(limit) = limit {
+                log::error!("The {limit} resource limit was hit");
+            }
             if data.is_valid() {
                 let executor = data.executor_mut::<E>();
                 // disarms timeout in case of timeout
@@ -262,8 +274,14 @@ pub mod unix_signal_handler {
                     input,
                     fuzzer,
                     event_mgr,
-                    ExitKind::Crash,
+                    if limit.is_some() {
+                        ExitKind::Oom
+                    } else {
+                        ExitKind::Crash
+                    },
                 );
+            } else if limit.is_some() {
+                log::error!("Resource limit hit outside of the target. Exiting.");
             } else {
                 {
                     log::error!("Double crash\n");
@@ -308,6 +326,9 @@ pub mod unix_signal_handler {
                 // TODO tell the parent to not restart
             }
 
+            if limit.is_some() {
+                libc::_exit(RESOURCE_LIMIT_EXIT);
+            }
             libc::_exit(128 + (signal as i32));
         }
     }
@@ -233,6 +233,7 @@ pub fn common_signals() -> Vec<Signal> {
         Signal::SigIllegalInstruction,
         Signal::SigSegmentationFault,
         Signal::SigTrap,
+        Signal::SigXCpu,
     ]
 }
 
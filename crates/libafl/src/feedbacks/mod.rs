@@ -796,6 +796,21 @@ impl ExitKindLogic for TimeoutLogic {
     }
 }
 
+/// Name used by `OomExitKindFeedback`
+pub const OOM_FEEDBACK_NAME: &str = "OomExitKindFeedback";
+
+/// Logic which finds all [`ExitKind::Oom`] exits interesting
+#[derive(Debug, Copy, Clone)]
+pub struct OomLogic;
+
+impl ExitKindLogic for OomLogic {
+    const NAME: Cow<'static, str> = Cow::Borrowed(OOM_FEEDBACK_NAME);
+
+    fn check_exit_kind(kind: &ExitKind) -> Result<bool, Error> {
+        Ok(matches!(kind, ExitKind::Oom))
+    }
+}
+
 /// Logic which finds all [`ExitKind::Diff`] exits interesting
 #[derive(Debug, Copy, Clone)]
 pub struct GenericDiffLogic;
@@ -892,6 +907,8 @@ where
 pub type CrashFeedback = ExitKindFeedback<CrashLogic>;
 /// A [`TimeoutFeedback`] reduces the timeout value of a run.
 pub type TimeoutFeedback = ExitKindFeedback<TimeoutLogic>;
+/// An [`OomExitKindFeedback`] reports as interesting if the target ran out of memory, or hit another resource limit.
+pub type OomExitKindFeedback = ExitKindFeedback<OomLogic>;
 /// A [`DiffExitKindFeedback`] checks if there is a difference in the [`ExitKind`]s in a [`crate::executors::DiffExecutor`].
 pub type DiffExitKindFeedback = ExitKindFeedback<GenericDiffLogic>;
 
@@ -0,0 +1,667 @@
+//! The [`AlertingMonitor`] wraps another monitor, and fires alerts when rules over the fuzzer stats match.
+//!
+//! Rules are written in a small DSL, one condition per rule:
+//!
+//! - `<metric> <op> <value>`, with `op` one of `>`, `>=`, `<`, `<=`, `==`, `!=`, for example `edges_stale > 4h` or `exec_sec < 100`.
+//!   Values can have a duration suffix, `s`, `m`, `h` or `d`. Durations are compared in seconds.
+//!   The rule fires once when the condition starts to hold, and again only after it stopped holding in between.
+//! - `<metric> increased`, `<metric> decreased` or `<metric> changed`, for example `objectives increased`.
+//!   The rule fires every time the value changes accordingly.
+//!
+//! The metrics are `corpus`, `objectives`, `executions`, `exec_sec`, `clients`, `run_time`, `edges`,
+//! `edges_stale`, `corpus_stale`, `objectives_stale`, and `user.<name>` for numeric user stats.
+//! The `*_stale` metrics are the seconds since the value last increased.
+//!
+//! Each rule has a list of [`AlertAction`]s: running a local command, appending to a file, or `POST`ing a JSON webhook.
+//! Webhooks are sent from a background thread, so a slow server never stalls the fuzzer.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use libafl_bolts::{ClientId, Error, current_time};
+use serde::Serialize;
+
+use crate::monitors::{
+    Monitor,
+    http::post_json,
+    stats::{ClientStats, ClientStatsManager},
+};
+
+/// A metric an [`AlertRule`] checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertMetric {
+    /// The global corpus size
+    Corpus,
+    /// The global number of objectives
+    Objectives,
+    /// The total executions
+    Executions,
+    /// The global executions per second
+    ExecsPerSec,
+    /// The number of clients
+    Clients,
+    /// The run time, in seconds
+    RunTime,
+    /// The number of edges hit
+    Edges,
+    /// The seconds since the number of edges hit last increased
+    EdgesStale,
+    /// The seconds since the last new corpus entry
+    CorpusStale,
+    /// The seconds since the last new objective
+    ObjectivesStale,
+    /// A numeric (aggregated) user stats
+    UserStats(String),
+}
+
+impl FromStr for AlertMetric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "corpus" => Self::Corpus,
+            "objectives" => Self::Objectives,
+            "executions" => Self::Executions,
+            "exec_sec" => Self::ExecsPerSec,
+            "clients" => Self::Clients,
+            "run_time" => Self::RunTime,
+            "edges" => Self::Edges,
+            "edges_stale" => Self::EdgesStale,
+            "corpus_stale" => Self::CorpusStale,
+            "objectives_stale" => Self::ObjectivesStale,
+            _ => match s.strip_prefix("user.") {
+                Some(name) if !name.is_empty() => Self::UserStats(name.to_string()),
+                _ => return Err(Error::illegal_argument(format!("Unknown alert metric {s}"))),
+            },
+        })
+    }
+}
+
+/// A comparison operator of an [`AlertCondition`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+}
+
+impl CompareOp {
+    fn compare(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::Greater => value > threshold,
+            Self::GreaterEqual => value >= threshold,
+            Self::Less => value < threshold,
+            Self::LessEqual => value <= threshold,
+            Self::Equal => (value - threshold).abs() < f64::EPSILON,
+            Self::NotEqual => (value - threshold).abs() >= f64::EPSILON,
+        }
+    }
+}
+
+/// The condition of an [`AlertRule`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertCondition {
+    /// The metric compares to the given value
+    Compare(CompareOp, f64),
+    /// The metric increased since the last check
+    Increased,
+    /// The metric decreased since the last check
+    Decreased,
+    /// The metric changed since the last check
+    Changed,
+}
+
+/// Parse a rule value, such as `100`, `0.5`, or `4h`, into a number (durations in seconds)
+fn parse_value(s: &str) -> Result<f64, Error> {
+    let (number, factor) = match s.char_indices().last() {
+        Some((idx, 's')) => (&s[..idx], 1.0),
+        Some((idx, 'm')) => (&s[..idx], 60.0),
+        Some((idx, 'h')) => (&s[..idx], 60.0 * 60.0),
+        Some((idx, 'd')) => (&s[..idx], 24.0 * 60.0 * 60.0),
+        _ => (s, 1.0),
+    };
+    number
+        .parse::<f64>()
+        .map(|number| number * factor)
+        .map_err(|_| Error::illegal_argument(format!("Invalid alert rule value {s}")))
+}
+
+/// A single alerting rule, parsed from the rule DSL, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    source: String,
+    metric: AlertMetric,
+    condition: AlertCondition,
+}
+
+impl AlertRule {
+    /// The rule, as written
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The metric this rule checks
+    #[must_use]
+    pub fn metric(&self) -> &AlertMetric {
+        &self.metric
+    }
+
+    /// The condition of this rule
+    #[must_use]
+    pub fn condition(&self) -> AlertCondition {
+        self.condition
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = s.split_whitespace().collect::<Vec<_>>();
+        let (metric, condition) = match tokens.as_slice() {
+            [metric, "increased"] => (metric, AlertCondition::Increased),
+            [metric, "decreased"] => (metric, AlertCondition::Decreased),
+            [metric, "changed"] => (metric, AlertCondition::Changed),
+            [metric, op, value] => {
+                let op = match *op {
+                    ">" => CompareOp::Greater,
+                    ">=" => CompareOp::GreaterEqual,
+                    "<" => CompareOp::Less,
+                    "<=" => CompareOp::LessEqual,
+                    "==" => CompareOp::Equal,
+                    "!=" => CompareOp::NotEqual,
+                    _ => {
+                        return Err(Error::illegal_argument(format!(
+                            "Invalid operator {op} in alert rule {s}"
+                        )));
+                    }
+                };
+                (metric, AlertCondition::Compare(op, parse_value(value)?))
+            }
+            _ => {
+                return Err(Error::illegal_argument(format!(
+                    "Invalid alert rule {s}, expected `<metric> <op> <value>` or `<metric> increased`"
+                )));
+            }
+        };
+        Ok(Self {
+            source: tokens.join(" "),
+            metric: metric.parse()?,
+            condition,
+        })
+    }
+}
+
+impl Display for AlertRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// What to do when an [`AlertRule`] fires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertAction {
+    /// Run a command using `sh -c`.
+    /// The alert is passed in the `LIBAFL_ALERT_RULE`, `LIBAFL_ALERT_VALUE` and `LIBAFL_ALERT_JSON` env variables.
+    Command(String),
+    /// Append the alert as a line of JSON to the file
+    AppendToFile(PathBuf),
+    /// `POST` the alert as JSON to the given `http://` url.
+    /// For `https`, use a [`AlertAction::Command`] running `curl`, or a local relay.
+    Webhook(String),
+}
+
+/// A fired alert, as passed to the [`AlertAction`]s
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// The rule that fired
+    pub rule: String,
+    /// The value of the rule's metric
+    pub value: f64,
+    /// The previous value of the rule's metric, if any
+    pub previous: Option<f64>,
+    /// The run time of the fuzzer, in seconds
+    pub run_time: u64,
+}
+
+/// The state of an [`AlertRule`]
+#[derive(Debug)]
+struct RuleState {
+    rule: AlertRule,
+    actions: Vec<AlertAction>,
+    previous: Option<f64>,
+    active: bool,
+}
+
+/// The rules of an [`AlertingMonitor`] and what they keep track of, shared between its clones
+#[derive(Debug, Default)]
+struct AlertingState {
+    rules: Vec<RuleState>,
+    /// The highest number of edges seen so far, for `edges_stale`
+    max_edges: Option<u64>,
+    /// The time the number of edges last increased
+    last_edges_increase: Option<Duration>,
+    /// Commands that may still be running
+    children: Vec<Child>,
+    /// The channel to the webhook thread, taking the url and the JSON body, started on first use
+    webhooks: Option<Sender<(String, String)>>,
+}
+
+/// A monitor wrapper checking [`AlertRule`]s at each `display`, and running their [`AlertAction`]s when they fire.
+///
+/// Action failures are logged, but never stop the fuzzer.
+/// Clones share the rules and their state.
+#[derive(Debug, Clone)]
+pub struct AlertingMonitor<M> {
+    monitor: M,
+    state: Arc<Mutex<AlertingState>>,
+}
+
+impl<M> Monitor for AlertingMonitor<M>
+where
+    M: Monitor,
+{
+    fn display(
+        &mut self,
+        client_stats_manager: &mut ClientStatsManager,
+        event_msg: &str,
+        sender_id: ClientId,
+    ) -> Result<(), Error> {
+        self.state.lock().unwrap().check_rules(client_stats_manager);
+        self.monitor
+            .display(client_stats_manager, event_msg, sender_id)
+    }
+}
+
+impl<M> AlertingMonitor<M> {
+    /// Create a new [`AlertingMonitor`] wrapping `monitor`, without any rules
+    #[must_use]
+    pub fn new(monitor: M) -> Self {
+        Self {
+            monitor,
+            state: Arc::new(Mutex::new(AlertingState::default())),
+        }
+    }
+
+    /// Add a rule in the rule DSL, with the actions to run when it fires
+    pub fn with_rule<A>(self, rule: &str, actions: A) -> Result<Self, Error>
+    where
+        A: IntoIterator<Item = AlertAction>,
+    {
+        self.state.lock().unwrap().rules.push(RuleState {
+            rule: rule.parse()?,
+            actions: actions.into_iter().collect(),
+            previous: None,
+            active: false,
+        });
+        Ok(self)
+    }
+
+    /// The wrapped monitor
+    pub fn inner(&self) -> &M {
+        &self.monitor
+    }
+
+    /// The wrapped monitor (mutable)
+    pub fn inner_mut(&mut self) -> &mut M {
+        &mut self.monitor
+    }
+}
+
+impl AlertingState {
+    /// Check all rules against the current stats, and run the actions of all rules that fire
+    fn check_rules(&mut self, client_stats_manager: &mut ClientStatsManager) {
+        let cur_time = current_time();
+        self.children
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        // Without edge data, the stale timer keeps running
+        if let Some(edges) = client_stats_manager
+            .edges_coverage()
+            .map(|coverage| coverage.edges_hit)
+        {
+            if self.max_edges.is_none_or(|max_edges| edges > max_edges) {
+                self.max_edges = Some(edges);
+                self.last_edges_increase = Some(cur_time);
+            }
+        }
+
+        let run_time = client_stats_manager.global_stats().run_time.as_secs();
+        let mut alerts = Vec::new();
+        for idx in 0..self.rules.len() {
+            let Some(value) = self.metric_value(client_stats_manager, &self.rules[idx].rule.metric)
+            else {
+                continue;
+            };
+            let state = &mut self.rules[idx];
+            let previous = state.previous.replace(value);
+            let fires = match state.rule.condition {
+                AlertCondition::Compare(op, threshold) => {
+                    let holds = op.compare(value, threshold);
+                    let fires = holds && !state.active;
+                    state.active = holds;
+                    fires
+                }
+                AlertCondition::Increased => previous.is_some_and(|previous| value > previous),
+                AlertCondition::Decreased => previous.is_some_and(|previous| value < previous),
+                AlertCondition::Changed => {
+                    previous.is_some_and(|previous| (value - previous).abs() >= f64::EPSILON)
+                }
+            };
+            if fires {
+                alerts.push((
+                    idx,
+                    Alert {
+                        rule: state.rule.source.clone(),
+                        value,
+                        previous,
+                        run_time,
+                    },
+                ));
+            }
+        }
+
+        for (idx, alert) in alerts {
+            log::info!("Alert: {} (value: {})", alert.rule, alert.value);
+            for action in self.rules[idx].actions.clone() {
+                if let Err(err) = self.run_action(&action, &alert) {
+                    log::error!("Failed to run alert action {action:?}: {err}");
+                }
+            }
+        }
+    }
+
+    /// The current value of a metric, if it is known
+    #[expect(clippy::cast_precision_loss)]
+    fn metric_value(
+        &self,
+        client_stats_manager: &mut ClientStatsManager,
+        metric: &AlertMetric,
+    ) -> Option<f64> {
+        let cur_time = current_time();
+        let start_time = client_stats_manager.start_time();
+        let since_last = |time: Duration| {
+            let time = if time > start_time { time } else { start_time };
+            cur_time.saturating_sub(time).as_secs_f64()
+        };
+        let latest = |time: fn(&ClientStats) -> Duration| {
+            client_stats_manager
+                .client_stats()
+                .values()
+                .filter(|client| client.enabled())
+                .map(time)
+                .max()
+                .unwrap_or_default()
+        };
+
+        Some(match metric {
+            AlertMetric::CorpusStale => since_last(latest(|client| client.last_corpus_time())),
+            AlertMetric::ObjectivesStale => {
+                since_last(latest(|client| client.last_objective_time()))
+            }
+            AlertMetric::EdgesStale => since_last(self.last_edges_increase?),
+            AlertMetric::Edges => self.max_edges? as f64,
+            AlertMetric::UserStats(name) => client_stats_manager
+                .aggregated()
+                .get(name.as_str())?
+                .as_f64()?,
+            _ => {
+                let global_stats = client_stats_manager.global_stats();
+                match metric {
+                    AlertMetric::Corpus => global_stats.corpus_size as f64,
+                    AlertMetric::Objectives => global_stats.objective_size as f64,
+                    AlertMetric::Executions => global_stats.total_execs as f64,
+                    AlertMetric::ExecsPerSec => global_stats.execs_per_sec,
+                    AlertMetric::Clients => global_stats.client_stats_count as f64,
+                    AlertMetric::RunTime => global_stats.run_time.as_secs_f64(),
+                    _ => unreachable!(),
+                }
+            }
+        })
+    }
+
+    /// Run a single action for the given alert
+    fn run_action(&mut self, action: &AlertAction, alert: &Alert) -> Result<(), Error> {
+        let json = serde_json::to_string(alert)
+            .map_err(|err| Error::serialize(format!("Failed to serialize alert: {err}")))?;
+        match action {
+            AlertAction::Command(command) => {
+                let child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("LIBAFL_ALERT_RULE", &alert.rule)
+                    .env("LIBAFL_ALERT_VALUE", alert.value.to_string())
+                    .env("LIBAFL_ALERT_JSON", &json)
+                    .stdin(Stdio::null())
+                    .spawn()?;
+                self.children.push(child);
+            }
+            AlertAction::AppendToFile(path) => {
+                let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+                writeln!(file, "{json}")?;
+            }
+            AlertAction::Webhook(url) => {
+                let webhooks = self.webhooks.get_or_insert_with(|| {
+                    let (sender, receiver) = mpsc::channel::<(String, String)>();
+                    // The thread ends once the monitor, and with it the sender, is dropped
+                    thread::spawn(move || {
+                        for (url, json) in receiver {
+                            if let Err(err) = post_json(&url, &json) {
+                                log::error!("Failed to send alert to webhook {url}: {err}");
+                            }
+                        }
+                    });
+                    sender
+                });
+                webhooks
+                    .send((url.clone(), json))
+                    .map_err(|_| Error::unknown("The webhook thread is gone"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use super::{AlertAction, AlertCondition, AlertMetric, AlertRule, AlertingMonitor, CompareOp};
+    use crate::monitors::{
+        Monitor, NopMonitor,
+        stats::{AggregatorOps, ClientStatsManager, UserStats, UserStatsValue},
+    };
+
+    #[test]
+    fn test_parse_alert_rules() {
+        let rule: AlertRule = "edges_stale > 4h".parse().unwrap();
+        assert_eq!(rule.metric(), &AlertMetric::EdgesStale);
+        assert_eq!(
+            rule.condition(),
+            AlertCondition::Compare(CompareOp::Greater, 4.0 * 60.0 * 60.0)
+        );
+
+        let rule: AlertRule = "objectives  increased".parse().unwrap();
+        assert_eq!(rule.metric(), &AlertMetric::Objectives);
+        assert_eq!(rule.condition(), AlertCondition::Increased);
+        assert_eq!(rule.source(), "objectives increased");
+
+        let rule: AlertRule = "user.stability <= 0.9".parse().unwrap();
+        assert_eq!(rule.metric(), &AlertMetric::UserStats("stability".into()));
+
+        assert!("exec_sec".parse::<AlertRule>().is_err());
+        assert!("exec_sec ~ 10".parse::<AlertRule>().is_err());
+        assert!("foo > 10".parse::<AlertRule>().is_err());
+        assert!("corpus > 10x".parse::<AlertRule>().is_err());
+    }
+
+    #[test]
+    fn test_alerting_monitor() {
+        let path = env::temp_dir().join(format!("libafl_alerts_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut monitor = AlertingMonitor::new(NopMonitor::new())
+            .with_rule(
+                "objectives increased",
+                [AlertAction::AppendToFile(path.clone())],
+            )
+            .unwrap()
+            .with_rule("corpus >= 2", [AlertAction::AppendToFile(path.clone())])
+            .unwrap();
+
+        let mut client_stats_manager = ClientStatsManager::new();
+        client_stats_manager
+            .client_stats_insert(ClientId(0))
+            .unwrap();
+        monitor
+            .display(&mut client_stats_manager, "test", ClientId(0))
+            .unwrap();
+
+        for size in 1..=3 {
+            client_stats_manager
+                .update_client_stats_for(ClientId(0), |client| {
+                    client.update_objective_size(size);
+                    client.update_corpus_size(size);
+                })
+                .unwrap();
+            monitor
+                .display(&mut client_stats_manager, "test", ClientId(0))
+                .unwrap();
+        }
+
+        let alerts = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // Three objective increases, the corpus rule only fires once
+        assert_eq!(alerts.lines().count(), 4);
+        assert_eq!(
+            alerts
+                .lines()
+                .filter(|line| line.contains("corpus >= 2"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_alerting_edges_stale() {
+        let monitor = AlertingMonitor::new(NopMonitor::new());
+        let mut cloned = monitor.clone();
+        let monitor = monitor
+            .with_rule("edges_stale > 1h", Vec::<AlertAction>::new())
+            .unwrap();
+        // Clones share the rules
+        assert_eq!(cloned.state.lock().unwrap().rules.len(), 1);
+
+        let mut client_stats_manager = ClientStatsManager::new();
+        client_stats_manager
+            .client_stats_insert(ClientId(0))
+            .unwrap();
+        for _ in 0..2 {
+            cloned
+                .display(&mut client_stats_manager, "test", ClientId(0))
+                .unwrap();
+            // No edge data yet, the stale timer is not restarted
+            assert_eq!(monitor.state.lock().unwrap().last_edges_increase, None);
+        }
+
+        client_stats_manager
+            .update_client_stats_for(ClientId(0), |client| {
+                client.update_user_stats(
+                    "edges".into(),
+                    UserStats::new(UserStatsValue::Ratio(10, 100), AggregatorOps::Avg),
+                );
+            })
+            .unwrap();
+        cloned
+            .display(&mut client_stats_manager, "test", ClientId(0))
+            .unwrap();
+        let increase = monitor.state.lock().unwrap().last_edges_increase;
+        assert!(increase.is_some());
+
+        thread::sleep(Duration::from_millis(10));
+        cloned
+            .display(&mut client_stats_manager, "test", ClientId(0))
+            .unwrap();
+        assert_eq!(monitor.state.lock().unwrap().last_edges_increase, increase);
+        assert_eq!(monitor.state.lock().unwrap().max_edges, Some(10));
+    }
+
+    #[test]
+    fn test_alerting_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/alert", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0_u8; 1024];
+            // Read until the JSON body is complete
+            while !request.ends_with(b"}") {
+                let len = stream.read(&mut buf).unwrap();
+                if len == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..len]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            sender.send(String::from_utf8(request).unwrap()).unwrap();
+        });
+
+        let mut monitor = AlertingMonitor::new(NopMonitor::new())
+            .with_rule("corpus >= 1", [AlertAction::Webhook(url)])
+            .unwrap();
+        let mut client_stats_manager = ClientStatsManager::new();
+        client_stats_manager
+            .client_stats_insert(ClientId(0))
+            .unwrap();
+        client_stats_manager
+            .update_client_stats_for(ClientId(0), |client| client.update_corpus_size(1))
+            .unwrap();
+        monitor
+            .display(&mut client_stats_manager, "test", ClientId(0))
+            .unwrap();
+
+        let request = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(request.starts_with("POST /alert HTTP/1.1\r\n"));
+        assert!(request.contains("\"rule\":\"corpus >= 1\""));
+    }
+}
@@ -4,7 +4,11 @@ use core::{
     fmt::{Debug, Formatter},
     time::Duration,
 };
-use std::{fs::OpenOptions, io::Write, path::PathBuf};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
 
 use libafl_bolts::{ClientId, Error, current_time};
 use serde_json::json;
@@ -65,6 +69,28 @@ impl Monitor for OnDiskJsonAggregateMonitor {
                         .iter()
                         .map(|(k, v)| (k.clone().into_owned(), json!(v))),
                 );
+
+                let profiles = client_stats_manager.profile_stats();
+                if !profiles.is_empty() {
+                    obj.insert(
+                        "profiles".into(),
+                        profiles
+                            .iter()
+                            .map(|profile| {
+                                (
+                                    profile.name.clone(),
+                                    json!({
+                                        "clients": profile.client_stats_count,
+                                        "corpus": profile.corpus_size,
+                                        "objectives": profile.objective_size,
+                                        "executions": profile.total_execs,
+                                        "exec_sec": profile.execs_per_sec,
+                                    }),
+                                )
+                            })
+                            .collect(),
+                    );
+                }
             }
 
             writeln!(&file, "{json_value}").expect("Unable to write JSON to file");
@@ -94,3 +120,61 @@ impl OnDiskJsonAggregateMonitor {
         }
     }
 }
+
+/// A monitor that writes the [`crate::monitors::stats::StatsHistory`] of the stats manager to a JSON file.
+///
+/// The file is overwritten on each update, and holds one object per stat, mapping to a list of `[time, value]` pairs.
+/// Times are in seconds since the start of the fuzzing run.
+#[derive(Debug, Clone)]
+pub struct OnDiskJsonHistoryMonitor {
+    json_path: PathBuf,
+    last_update: Duration,
+    update_interval: Duration,
+}
+
+impl Monitor for OnDiskJsonHistoryMonitor {
+    fn display(
+        &mut self,
+        client_stats_manager: &mut ClientStatsManager,
+        _event_msg: &str,
+        _sender_id: ClientId,
+    ) -> Result<(), Error> {
+        let cur_time = current_time();
+        if cur_time - self.last_update < self.update_interval {
+            return Ok(());
+        }
+        self.last_update = cur_time;
+
+        let json_value = client_stats_manager
+            .history()
+            .to_json(client_stats_manager.start_time());
+
+        // Write to a temporary file first, so readers never see a partial file
+        let tmp_path = self.json_path.with_extension("json.tmp");
+        fs::write(&tmp_path, json_value.to_string())?;
+        fs::rename(&tmp_path, &self.json_path)?;
+        Ok(())
+    }
+}
+
+impl OnDiskJsonHistoryMonitor {
+    /// Creates a new [`OnDiskJsonHistoryMonitor`], updating the file every 60 seconds
+    pub fn new<P>(json_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_interval(json_path, Duration::from_secs(60))
+    }
+
+    /// Creates a new [`OnDiskJsonHistoryMonitor`] with custom update interval
+    pub fn with_interval<P>(json_path: P, update_interval: Duration) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            json_path: json_path.into(),
+            last_update: current_time() - update_interval,
+            update_interval,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! A minimal blocking HTTP/1.1 client, enough for monitors pushing JSON to a local collector or webhook
+
+use alloc::string::ToString;
+use core::time::Duration;
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use libafl_bolts::Error;
+
+/// The timeout for connecting to, and talking to, the server
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `POST` the `json` body to an `http://` url, and check for a `2xx` status
+pub(crate) fn post_json(url: &str, json: &str) -> Result<(), Error> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(Error::illegal_argument(format!(
+            "Only http:// urls are supported, got {url}"
+        )));
+    };
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = if path.is_empty() { "/" } else { path };
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::illegal_argument(format!("Could not resolve {host}")))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{json}",
+        json.len()
+    )?;
+
+    // `HTTP/1.1 200`
+    let mut status_line = [0_u8; 12];
+    stream.read_exact(&mut status_line)?;
+    let status = core::str::from_utf8(&status_line[9..12]).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        Err(Error::unknown(format!("{url} returned status {status}")))
+    }
+}
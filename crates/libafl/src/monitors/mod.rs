@@ -13,10 +13,18 @@ pub mod disk;
 #[cfg(feature = "std")]
 pub use disk::{OnDiskJsonMonitor, OnDiskTomlMonitor};
 
+#[cfg(feature = "std")]
+pub mod alerting;
+#[cfg(feature = "std")]
+pub use alerting::{AlertAction, AlertRule, AlertingMonitor};
+
+#[cfg(feature = "std")]
+pub(crate) mod http;
+
 #[cfg(feature = "std")]
 pub mod disk_aggregate;
 #[cfg(feature = "std")]
-pub use disk_aggregate::OnDiskJsonAggregateMonitor;
+pub use disk_aggregate::{OnDiskJsonAggregateMonitor, OnDiskJsonHistoryMonitor};
 
 #[cfg(all(feature = "tui_monitor", feature = "std"))]
 pub mod tui;
@@ -29,6 +37,9 @@ pub mod prometheus;
 #[cfg(feature = "statsd_monitor")]
 pub mod statsd;
 
+#[cfg(feature = "otlp_monitor")]
+pub mod otlp;
+
 #[cfg(feature = "std")]
 use alloc::vec::Vec;
 use core::{
@@ -38,6 +49,8 @@ use core::{
 };
 
 use libafl_bolts::ClientId;
+#[cfg(feature = "otlp_monitor")]
+pub use otlp::OtlpMonitor;
 #[cfg(feature = "prometheus_monitor")]
 pub use prometheus::PrometheusMonitor;
 #[cfg(feature = "statsd_monitor")]
@@ -8,7 +8,10 @@ use core::{
 
 use libafl_bolts::{ClientId, Error, current_time};
 
-use crate::monitors::{Monitor, stats::ClientStatsManager};
+use crate::monitors::{
+    Monitor,
+    stats::{ClientStatsManager, prettify_float},
+};
 
 /// Tracking monitor during fuzzing and display both per-client and cumulative info.
 #[derive(Clone)]
@@ -62,6 +65,20 @@ where
 
         (self.print_fn)(&global_fmt);
 
+        for profile in client_stats_manager.profile_stats() {
+            let fmt = format!(
+                " {}  (PROFILE) {}: clients: {}, corpus: {}, objectives: {}, executions: {}, exec/sec: {}",
+                " ".repeat(head.len()),
+                profile.name,
+                profile.client_stats_count,
+                profile.corpus_size,
+                profile.objective_size,
+                profile.total_execs,
+                prettify_float(profile.execs_per_sec)
+            );
+            (self.print_fn)(&fmt);
+        }
+
         client_stats_manager.client_stats_insert(sender_id)?;
         let cur_time = current_time();
         let exec_sec = client_stats_manager
@@ -0,0 +1,687 @@
+//! The [`OtlpMonitor`] exports the fuzzer stats to an [OpenTelemetry](https://opentelemetry.io) collector,
+//! using OTLP over HTTP with JSON encoding.
+//!
+//! The global stats, and all numeric user stats, are exported as metrics.
+//! With the `introspection` feature, the [`ClientPerfStats`] of each client can additionally be exported as spans:
+//! for each export interval, one `fuzz_one` span per client, with child spans for the scheduler, the manager,
+//! each stage and its measured features (such as `TargetExecution`, the executor run), and each feedback.
+//! The clients only report clock cycles, summed up, so these spans are laid out back to back,
+//! scaled to the wall-clock time of the interval.
+//!
+//! The exports are sent from a background thread, so a slow collector never stalls the fuzzer.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+use std::{
+    sync::mpsc::{self, SyncSender, TrySendError},
+    thread,
+};
+
+use libafl_bolts::{
+    ClientId, Error, current_nanos, current_time,
+    rands::{Rand, StdRand},
+};
+use serde_json::{Value, json};
+
+#[cfg(feature = "introspection")]
+use crate::monitors::stats::{ClientPerfStats, PerfFeature};
+use crate::monitors::{Monitor, http::post_json, stats::ClientStatsManager};
+
+/// The default OTLP/HTTP endpoint of a local collector
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318";
+
+/// The default interval between two exports
+pub const DEFAULT_OTLP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The name of the instrumentation scope of all exported metrics and spans
+const OTLP_SCOPE: &str = "libafl";
+
+/// How many exports may wait for the sender thread, further exports are dropped
+const OTLP_QUEUE_LEN: usize = 16;
+
+/// A monitor exporting metrics, and optionally spans, to an OpenTelemetry collector via OTLP/HTTP.
+///
+/// Failed exports are logged, the next interval exports the then current stats.
+/// Clones share the sender thread, if it was started before cloning.
+#[derive(Debug, Clone)]
+pub struct OtlpMonitor {
+    endpoint: String,
+    service_name: String,
+    interval: Duration,
+    last_export: Option<Duration>,
+    #[cfg(feature = "introspection")]
+    export_spans: bool,
+    #[cfg(feature = "introspection")]
+    last_perf_stats: hashbrown::HashMap<ClientId, ClientPerfStats>,
+    rand: StdRand,
+    /// The queue of the sender thread, taking the url and the JSON body, started on first use
+    sender: Option<SyncSender<(String, String)>>,
+}
+
+impl OtlpMonitor {
+    /// Create a new [`OtlpMonitor`], exporting to the collector at `endpoint`, such as [`DEFAULT_OTLP_ENDPOINT`].
+    ///
+    /// The signals are sent to `<endpoint>/v1/metrics` and `<endpoint>/v1/traces`.
+    #[must_use]
+    pub fn new<S>(endpoint: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            service_name: "libafl".into(),
+            interval: DEFAULT_OTLP_INTERVAL,
+            last_export: None,
+            #[cfg(feature = "introspection")]
+            export_spans: false,
+            #[cfg(feature = "introspection")]
+            last_perf_stats: hashbrown::HashMap::new(),
+            rand: StdRand::with_seed(current_nanos()),
+            sender: None,
+        }
+    }
+
+    /// Set the `service.name` resource attribute, `libafl` by default
+    #[must_use]
+    pub fn with_service_name<S>(mut self, service_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.service_name = service_name.into();
+        self
+    }
+
+    /// Set the interval between two exports
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Also export the introspection stats of the clients as spans
+    #[cfg(feature = "introspection")]
+    #[must_use]
+    pub fn with_spans(mut self, export_spans: bool) -> Self {
+        self.export_spans = export_spans;
+        self
+    }
+
+    /// The resource all signals belong to
+    fn resource(&self) -> Value {
+        json!({
+            "attributes": [
+                string_attribute("service.name", &self.service_name),
+                string_attribute("telemetry.sdk.name", "libafl"),
+                string_attribute("telemetry.sdk.version", env!("CARGO_PKG_VERSION")),
+            ]
+        })
+    }
+
+    /// The metrics of the global stats, and of all numeric user stats, as OTLP `ExportMetricsServiceRequest`
+    fn metrics(&self, client_stats_manager: &mut ClientStatsManager, now: Duration) -> Value {
+        let start_time = client_stats_manager.start_time();
+        let global_stats = client_stats_manager.global_stats();
+        let mut metrics: Vec<Value> = vec![
+            gauge_int(
+                "libafl.corpus.size",
+                "{entry}",
+                global_stats.corpus_size,
+                now,
+            ),
+            gauge_int(
+                "libafl.objectives.size",
+                "{entry}",
+                global_stats.objective_size,
+                now,
+            ),
+            gauge_int(
+                "libafl.clients",
+                "{client}",
+                global_stats.client_stats_count as u64,
+                now,
+            ),
+            gauge_double(
+                "libafl.executions.rate",
+                "{execution}/s",
+                global_stats.execs_per_sec,
+                now,
+            ),
+            gauge_double(
+                "libafl.run_time",
+                "s",
+                global_stats.run_time.as_secs_f64(),
+                now,
+            ),
+            json!({
+                "name": "libafl.executions",
+                "unit": "{execution}",
+                "sum": {
+                    // Cumulative
+                    "aggregationTemporality": 2,
+                    "isMonotonic": true,
+                    "dataPoints": [{
+                        "asInt": global_stats.total_execs.to_string(),
+                        "startTimeUnixNano": unix_nanos(start_time),
+                        "timeUnixNano": unix_nanos(now),
+                    }],
+                },
+            }),
+        ];
+        if let Some(edges_coverage) = client_stats_manager.edges_coverage() {
+            metrics.push(gauge_int(
+                "libafl.edges.hit",
+                "{edge}",
+                edges_coverage.edges_hit,
+                now,
+            ));
+            metrics.push(gauge_int(
+                "libafl.edges.total",
+                "{edge}",
+                edges_coverage.edges_total,
+                now,
+            ));
+        }
+        for (name, value) in client_stats_manager.aggregated() {
+            if let Some(value) = value.as_f64() {
+                metrics.push(gauge_double(
+                    &format!("libafl.user.{name}"),
+                    "1",
+                    value,
+                    now,
+                ));
+            }
+        }
+
+        json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{
+                    "scope": { "name": OTLP_SCOPE, "version": env!("CARGO_PKG_VERSION") },
+                    "metrics": metrics,
+                }],
+            }],
+        })
+    }
+
+    /// The spans of the introspection stats since the last export, as OTLP `ExportTraceServiceRequest`
+    #[cfg(feature = "introspection")]
+    #[expect(clippy::cast_precision_loss)]
+    fn spans(&mut self, client_stats_manager: &ClientStatsManager, now: Duration) -> Option<Value> {
+        let interval_start = self
+            .last_export
+            .unwrap_or_else(|| client_stats_manager.start_time());
+        let wall_time = now.saturating_sub(interval_start);
+
+        let mut spans = Vec::new();
+        for (client_id, client) in client_stats_manager.client_stats() {
+            let perf_stats = &client.introspection_stats;
+            // If the client restarted, its counters started over, and are the delta on their own
+            let previous = self
+                .last_perf_stats
+                .get(client_id)
+                .filter(|previous| previous.elapsed_cycles() <= perf_stats.elapsed_cycles());
+            let elapsed = perf_stats
+                .elapsed_cycles()
+                .saturating_sub(previous.map_or(0, ClientPerfStats::elapsed_cycles));
+            if elapsed == 0 {
+                continue;
+            }
+
+            let trace_id = format!("{:016x}{:016x}", self.rand.next(), self.rand.next());
+            let root_id = format!("{:016x}", self.rand.next());
+            let mut builder = SpanBuilder {
+                trace_id: &trace_id,
+                rand: &mut self.rand,
+                spans: &mut spans,
+                scale: wall_time.as_secs_f64() / elapsed as f64,
+                client_id: *client_id,
+            };
+            let mut cursor = interval_start;
+
+            let scheduler = perf_stats
+                .scheduler_cycles()
+                .saturating_sub(previous.map_or(0, ClientPerfStats::scheduler_cycles));
+            builder.push("scheduler", &root_id, &mut cursor, scheduler, Vec::new());
+            let manager = perf_stats
+                .manager_cycles()
+                .saturating_sub(previous.map_or(0, ClientPerfStats::manager_cycles));
+            builder.push("manager", &root_id, &mut cursor, manager, Vec::new());
+
+            for (stage_index, features) in perf_stats.used_stages() {
+                let previous_features = previous
+                    .and_then(|previous| {
+                        previous
+                            .used_stages()
+                            .find(|(previous_index, _)| *previous_index == stage_index)
+                    })
+                    .map(|(_, features)| *features)
+                    .unwrap_or_default();
+                let stage_cycles = features
+                    .iter()
+                    .zip(previous_features)
+                    .map(|(cycles, previous)| cycles.saturating_sub(previous))
+                    .sum::<u64>();
+                let stage_id = format!("{:016x}", builder.rand.next());
+                let mut stage_cursor = cursor;
+                builder.push_with_id(
+                    &stage_id,
+                    "stage",
+                    &root_id,
+                    &mut cursor,
+                    stage_cycles,
+                    vec![int_attribute("libafl.stage.index", stage_index as u64)],
+                );
+                for (feature_index, (cycles, previous)) in
+                    features.iter().zip(previous_features).enumerate()
+                {
+                    let feature: PerfFeature = feature_index.into();
+                    builder.push(
+                        &format!("{feature:?}"),
+                        &stage_id,
+                        &mut stage_cursor,
+                        cycles.saturating_sub(previous),
+                        Vec::new(),
+                    );
+                }
+            }
+
+            for (name, cycles) in perf_stats.feedbacks() {
+                let previous = previous
+                    .and_then(|previous| previous.feedbacks().get(name))
+                    .copied()
+                    .unwrap_or_default();
+                builder.push(
+                    "feedback",
+                    &root_id,
+                    &mut cursor,
+                    cycles.saturating_sub(previous),
+                    vec![string_attribute("libafl.feedback.name", name)],
+                );
+            }
+
+            builder.spans.push(span(
+                &trace_id,
+                &root_id,
+                "",
+                "fuzz_one",
+                interval_start,
+                now,
+                vec![
+                    int_attribute("libafl.client", u64::from(client_id.0)),
+                    int_attribute("libafl.cycles", elapsed),
+                ],
+            ));
+            self.last_perf_stats.insert(*client_id, perf_stats.clone());
+        }
+
+        if spans.is_empty() {
+            return None;
+        }
+        Some(json!({
+            "resourceSpans": [{
+                "resource": self.resource(),
+                "scopeSpans": [{
+                    "scope": { "name": OTLP_SCOPE, "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        }))
+    }
+
+    /// Queue `body` for the sender thread, to be `POST`ed to `<endpoint>/<path>`
+    fn send(&mut self, path: &str, body: &Value) {
+        let sender = self.sender.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::sync_channel::<(String, String)>(OTLP_QUEUE_LEN);
+            // The thread ends once the monitor and its clones, and with them the sender, are dropped
+            thread::spawn(move || {
+                for (url, body) in receiver {
+                    if let Err(err) = post_json(&url, &body) {
+                        log::warn!("Failed to export to {url}: {err}");
+                    }
+                }
+            });
+            sender
+        });
+        let url = format!("{}/{path}", self.endpoint);
+        match sender.try_send((url, body.to_string())) {
+            Ok(()) => {}
+            Err(TrySendError::Full((url, _))) => {
+                log::warn!("The collector is too slow, dropping the export to {url}");
+            }
+            Err(TrySendError::Disconnected((url, _))) => {
+                log::warn!("The OTLP sender thread is gone, dropping the export to {url}");
+            }
+        }
+    }
+
+    /// Export all signals to the collector
+    fn export(&mut self, client_stats_manager: &mut ClientStatsManager, now: Duration) {
+        let metrics = self.metrics(client_stats_manager, now);
+        self.send("v1/metrics", &metrics);
+
+        #[cfg(feature = "introspection")]
+        if self.export_spans {
+            if let Some(spans) = self.spans(client_stats_manager, now) {
+                self.send("v1/traces", &spans);
+            }
+        }
+    }
+}
+
+impl Default for OtlpMonitor {
+    fn default() -> Self {
+        Self::new(DEFAULT_OTLP_ENDPOINT)
+    }
+}
+
+impl Monitor for OtlpMonitor {
+    fn display(
+        &mut self,
+        client_stats_manager: &mut ClientStatsManager,
+        _event_msg: &str,
+        _sender_id: ClientId,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if self
+            .last_export
+            .is_none_or(|last_export| now >= last_export + self.interval)
+        {
+            self.export(client_stats_manager, now);
+            self.last_export = Some(now);
+        }
+        Ok(())
+    }
+}
+
+/// Lays out child spans back to back, converting clock cycles to wall-clock time
+#[cfg(feature = "introspection")]
+struct SpanBuilder<'a> {
+    trace_id: &'a str,
+    rand: &'a mut StdRand,
+    spans: &'a mut Vec<Value>,
+    /// Wall-clock seconds per cycle
+    scale: f64,
+    client_id: ClientId,
+}
+
+#[cfg(feature = "introspection")]
+impl SpanBuilder<'_> {
+    /// Add a span of `cycles` at `cursor`, and advance the `cursor`. Empty spans are skipped.
+    fn push(
+        &mut self,
+        name: &str,
+        parent_id: &str,
+        cursor: &mut Duration,
+        cycles: u64,
+        attributes: Vec<Value>,
+    ) {
+        let span_id = format!("{:016x}", self.rand.next());
+        self.push_with_id(&span_id, name, parent_id, cursor, cycles, attributes);
+    }
+
+    /// Add a span with the given id
+    #[expect(clippy::cast_precision_loss)]
+    fn push_with_id(
+        &mut self,
+        span_id: &str,
+        name: &str,
+        parent_id: &str,
+        cursor: &mut Duration,
+        cycles: u64,
+        mut attributes: Vec<Value>,
+    ) {
+        if cycles == 0 {
+            return;
+        }
+        let start = *cursor;
+        *cursor += Duration::from_secs_f64(cycles as f64 * self.scale);
+        attributes.push(int_attribute("libafl.client", u64::from(self.client_id.0)));
+        attributes.push(int_attribute("libafl.cycles", cycles));
+        self.spans.push(span(
+            self.trace_id,
+            span_id,
+            parent_id,
+            name,
+            start,
+            *cursor,
+            attributes,
+        ));
+    }
+}
+
+/// An OTLP span
+#[cfg(feature = "introspection")]
+fn span(
+    trace_id: &str,
+    span_id: &str,
+    parent_id: &str,
+    name: &str,
+    start: Duration,
+    end: Duration,
+    attributes: Vec<Value>,
+) -> Value {
+    json!({
+        "traceId": trace_id,
+        "spanId": span_id,
+        "parentSpanId": parent_id,
+        "name": name,
+        // Internal
+        "kind": 1,
+        "startTimeUnixNano": unix_nanos(start),
+        "endTimeUnixNano": unix_nanos(end),
+        "attributes": attributes,
+    })
+}
+
+/// A time since the epoch, as OTLP JSON `fixed64`
+fn unix_nanos(time: Duration) -> String {
+    time.as_nanos().to_string()
+}
+
+/// An OTLP string attribute
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// An OTLP int attribute
+#[cfg(feature = "introspection")]
+fn int_attribute(key: &str, value: u64) -> Value {
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+/// An OTLP gauge metric with a single int data point
+fn gauge_int(name: &str, unit: &str, value: u64, now: Duration) -> Value {
+    json!({
+        "name": name,
+        "unit": unit,
+        "gauge": {
+            "dataPoints": [{ "asInt": value.to_string(), "timeUnixNano": unix_nanos(now) }],
+        },
+    })
+}
+
+/// An OTLP gauge metric with a single double data point
+fn gauge_double(name: &str, unit: &str, value: f64, now: Duration) -> Value {
+    json!({
+        "name": name,
+        "unit": unit,
+        "gauge": {
+            "dataPoints": [{ "asDouble": value, "timeUnixNano": unix_nanos(now) }],
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use core::time::Duration;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc::{self, Receiver},
+        thread,
+        time::Instant,
+    };
+
+    use libafl_bolts::ClientId;
+    use serde_json::Value;
+
+    use super::OtlpMonitor;
+    #[cfg(feature = "introspection")]
+    use crate::monitors::stats::ClientPerfStats;
+    use crate::monitors::{Monitor, stats::ClientStatsManager};
+
+    /// A local collector, sending the path and the body of each request to the returned channel
+    fn collector() -> (String, Receiver<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut request_line = String::new();
+                stream.read_line(&mut request_line).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    stream.read_line(&mut header).unwrap();
+                    let header = header.trim_end().to_ascii_lowercase();
+                    if header.is_empty() {
+                        break;
+                    }
+                    if let Some(len) = header.strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).unwrap();
+                stream
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+                if sender
+                    .send((path, serde_json::from_slice(&body).unwrap()))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        (endpoint, receiver)
+    }
+
+    #[test]
+    fn test_otlp_metrics() {
+        let (endpoint, receiver) = collector();
+        let mut monitor = OtlpMonitor::new(endpoint).with_interval(Duration::from_secs(3600));
+
+        let mut client_stats_manager = ClientStatsManager::new();
+        client_stats_manager
+            .client_stats_insert(ClientId(0))
+            .unwrap();
+        client_stats_manager
+            .update_client_stats_for(ClientId(0), |client| {
+                client.update_corpus_size(42);
+            })
+            .unwrap();
+        monitor
+            .display(&mut client_stats_manager, "test", ClientId(0))
+            .unwrap();
+        // Within the interval, nothing is exported
+        monitor
+            .display(&mut client_stats_manager, "test", ClientId(0))
+            .unwrap();
+
+        let (path, body) = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(path, "/v1/metrics");
+        let metrics = body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap();
+        let corpus_size = metrics
+            .iter()
+            .find(|metric| metric["name"] == "libafl.corpus.size")
+            .unwrap();
+        assert_eq!(corpus_size["gauge"]["dataPoints"][0]["asInt"], "42");
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_otlp_slow_collector() {
+        // The collector never answers, each export would wait for the timeout
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let mut monitor = OtlpMonitor::new(endpoint).with_interval(Duration::ZERO);
+        let mut cloned = monitor.clone();
+
+        let mut client_stats_manager = ClientStatsManager::new();
+        client_stats_manager
+            .client_stats_insert(ClientId(0))
+            .unwrap();
+        let start = Instant::now();
+        for _ in 0..32 {
+            monitor
+                .display(&mut client_stats_manager, "test", ClientId(0))
+                .unwrap();
+            cloned
+                .display(&mut client_stats_manager, "test", ClientId(0))
+                .unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        drop(listener);
+    }
+
+    #[cfg(feature = "introspection")]
+    #[test]
+    fn test_otlp_spans_after_restart() {
+        use libafl_bolts::{cpu::read_time_counter, current_time};
+
+        let mut monitor = OtlpMonitor::new("http://127.0.0.1:1").with_spans(true);
+        let mut client_stats_manager = ClientStatsManager::new();
+        client_stats_manager
+            .client_stats_insert(ClientId(0))
+            .unwrap();
+
+        let mut perf_stats = ClientPerfStats::new();
+        perf_stats.update_scheduler(1 << 30);
+        perf_stats.set_current_time(read_time_counter() + (1 << 40));
+        client_stats_manager
+            .update_client_stats_for(ClientId(0), |client| {
+                client.update_introspection_stats(perf_stats);
+            })
+            .unwrap();
+        let now = current_time();
+        assert!(monitor.spans(&client_stats_manager, now).is_some());
+
+        // The client restarted, so its counters went down
+        let mut perf_stats = ClientPerfStats::new();
+        perf_stats.update_scheduler(10);
+        perf_stats.set_current_time(read_time_counter() + 1000);
+        client_stats_manager
+            .update_client_stats_for(ClientId(0), |client| {
+                client.update_introspection_stats(perf_stats);
+            })
+            .unwrap();
+        let spans = monitor
+            .spans(&client_stats_manager, now + Duration::from_secs(1))
+            .unwrap();
+        let spans = spans["resourceSpans"][0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap();
+        let scheduler = spans
+            .iter()
+            .find(|span| span["name"] == "scheduler")
+            .unwrap();
+        let cycles = scheduler["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|attribute| attribute["key"] == "libafl.cycles")
+            .unwrap();
+        assert_eq!(cycles["value"]["intValue"], "10");
+    }
+}
@@ -4,6 +4,7 @@
 //!
 //! The client (i.e., the fuzzer) sets up an HTTP endpoint (/metrics).
 //! The endpoint contains metrics such as execution rate.
+//! The history of the stats, as kept by the [`ClientStatsManager`], is served as JSON at /history.
 //!
 //! A prometheus server (can use a precompiled binary or docker) then scrapes
 //! the endpoint at regular intervals (configurable via prometheus.yml file).
@@ -38,7 +39,7 @@ use core::{
     sync::atomic::AtomicU64,
     time::Duration,
 };
-use std::thread;
+use std::{sync::RwLock, thread};
 
 // using thread in order to start the HTTP server in a separate thread
 use futures::executor::block_on;
@@ -78,6 +79,8 @@ where
     print_fn: F,
     prometheus_global_stats: PrometheusStats, // global prometheus metrics
     prometheus_client_stats: PrometheusStats, // per-client prometheus metrics
+    history_json: Arc<RwLock<String>>,        // the stats history, served at /history
+    last_history_record: Option<Duration>,
 }
 
 impl<F> Debug for PrometheusMonitor<F>
@@ -318,6 +321,16 @@ where
                 })
                 .set(value);
         }
+        // Only serialize the history again once something new was recorded
+        let history = client_stats_manager.history();
+        if history.last_record() != self.last_history_record {
+            self.last_history_record = history.last_record();
+            let json = history
+                .to_json(client_stats_manager.start_time())
+                .to_string();
+            *self.history_json.write().unwrap() = json;
+        }
+
         (self.print_fn)(&fmt);
         Ok(())
     }
@@ -335,6 +348,8 @@ where
         let prometheus_global_stats_clone = prometheus_global_stats.clone();
         let prometheus_client_stats = PrometheusStats::default();
         let prometheus_client_stats_clone = prometheus_client_stats.clone();
+        let history_json = Arc::new(RwLock::new(String::from("{}")));
+        let history_json_clone = history_json.clone();
 
         // Need to run the metrics server in a different thread to avoid blocking
         thread::spawn(move || {
@@ -342,6 +357,7 @@ where
                 listener,
                 prometheus_global_stats_clone,
                 prometheus_client_stats_clone,
+                history_json_clone,
             ))
             .map_err(|err| log::error!("{err:?}"))
             .ok();
@@ -350,6 +366,8 @@ where
             print_fn,
             prometheus_global_stats,
             prometheus_client_stats,
+            history_json,
+            last_history_record: None,
         }
     }
     /// Creates the monitor with a given `start_time`.
@@ -362,11 +380,12 @@ where
     }
 }
 
-/// Set up an HTTP endpoint /metrics
+/// Set up an HTTP endpoint /metrics, and the stats history at /history
 pub(crate) async fn serve_metrics(
     listener: String,
     global_stats: PrometheusStats,
     client_stats: PrometheusStats,
+    history_json: Arc<RwLock<String>>,
 ) -> Result<(), std::io::Error> {
     let mut registry = Registry::default();
 
@@ -446,6 +465,7 @@ pub(crate) async fn serve_metrics(
 
     let mut app = tide::with_state(State {
         registry: Arc::new(registry),
+        history_json,
     });
 
     app.at("/")
@@ -459,6 +479,14 @@ pub(crate) async fn serve_metrics(
             .build();
         Ok(response)
     });
+    app.at("/history").get(|req: Request<State>| async move {
+        let encoded = req.state().history_json.read().unwrap().clone();
+        let response = tide::Response::builder(200)
+            .body(encoded)
+            .content_type("application/json")
+            .build();
+        Ok(response)
+    });
     app.listen(listener).await?;
 
     Ok(())
@@ -477,4 +505,5 @@ pub struct Labels {
 #[derive(Clone)]
 struct State {
     registry: Arc<Registry>,
+    history_json: Arc<RwLock<String>>,
 }
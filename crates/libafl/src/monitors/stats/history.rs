@@ -0,0 +1,311 @@
+//! Bounded, downsampled history of the fuzzer statistics.
+//!
+//! The [`ClientStatsManager`](super::ClientStatsManager) records the global stats and all numeric user stats
+//! into a [`StatsHistory`], so every [`Monitor`](crate::monitors::Monitor) can query the same history.
+
+use alloc::{borrow::Cow, collections::VecDeque};
+use core::time::Duration;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use serde_json::{Map, Value, json};
+
+/// The name of the [`TimeSeries`] of the global executions per second
+pub const HISTORY_EXECS_PER_SEC: &str = "execs_per_sec";
+/// The name of the [`TimeSeries`] of the global corpus size
+pub const HISTORY_CORPUS_SIZE: &str = "corpus_size";
+/// The name of the [`TimeSeries`] of the global objective size
+pub const HISTORY_OBJECTIVE_SIZE: &str = "objective_size";
+/// The name of the [`TimeSeries`] of the number of edges hit
+pub const HISTORY_EDGES_HIT: &str = "edges_hit";
+
+/// The default number of points per [`TimeSeries`]
+pub const DEFAULT_HISTORY_CAPACITY: usize = 1024;
+/// The default (initial) resolution of a [`TimeSeries`]
+pub const DEFAULT_HISTORY_RESOLUTION: Duration = Duration::from_secs(1);
+
+/// A single point in a [`TimeSeries`]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeSeriesPoint {
+    /// The time of this point, since the epoch
+    pub time: Duration,
+    /// The value
+    pub value: f64,
+}
+
+/// A bounded time series.
+///
+/// Each point covers (at least) `resolution`. Values arriving within the resolution of the last point replace its value.
+/// Once the series is full, every other point is dropped and the resolution doubles.
+/// This way, the series always covers the whole run, with the most detail for short runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeSeries {
+    points: VecDeque<TimeSeriesPoint>,
+    resolution: Duration,
+    capacity: usize,
+}
+
+impl TimeSeries {
+    /// Create a new [`TimeSeries`] holding at most `capacity` points, with the given initial `resolution`
+    #[must_use]
+    pub fn new(capacity: usize, resolution: Duration) -> Self {
+        Self {
+            points: VecDeque::new(),
+            resolution,
+            capacity: capacity.max(2),
+        }
+    }
+
+    /// Add a value at the given `time`
+    pub fn push(&mut self, time: Duration, value: f64) {
+        if let Some(last) = self.points.back_mut() {
+            if time < last.time + self.resolution {
+                last.value = value;
+                return;
+            }
+        }
+        self.points.push_back(TimeSeriesPoint { time, value });
+        if self.points.len() > self.capacity {
+            self.downsample();
+        }
+    }
+
+    /// Drop every other point and double the resolution, keeping the latest point
+    fn downsample(&mut self) {
+        let len = self.points.len();
+        let mut idx = 0;
+        self.points.retain(|_| {
+            idx += 1;
+            (len - idx) % 2 == 0
+        });
+        self.resolution *= 2;
+    }
+
+    /// All points, oldest first
+    pub fn points(&self) -> impl DoubleEndedIterator<Item = &TimeSeriesPoint> + ExactSizeIterator {
+        self.points.iter()
+    }
+
+    /// All points since `time`
+    pub fn since(&self, time: Duration) -> impl DoubleEndedIterator<Item = &TimeSeriesPoint> {
+        self.points.iter().filter(move |point| point.time >= time)
+    }
+
+    /// The latest point, if any
+    #[must_use]
+    pub fn latest(&self) -> Option<&TimeSeriesPoint> {
+        self.points.back()
+    }
+
+    /// The current resolution
+    #[must_use]
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// The number of points
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Checks if there are no points
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+}
+
+/// The history of the global stats and all numeric user stats, as [`TimeSeries`].
+///
+/// User stats are kept apart from the global stats, so their names cannot clash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsHistory {
+    global: HashMap<Cow<'static, str>, TimeSeries>,
+    user_stats: HashMap<Cow<'static, str>, TimeSeries>,
+    capacity: usize,
+    resolution: Duration,
+    last_record: Option<Duration>,
+}
+
+impl StatsHistory {
+    /// Create a new [`StatsHistory`], each [`TimeSeries`] holding at most `capacity` points,
+    /// recorded at most once per `resolution`
+    #[must_use]
+    pub fn new(capacity: usize, resolution: Duration) -> Self {
+        Self {
+            global: HashMap::new(),
+            user_stats: HashMap::new(),
+            capacity,
+            resolution,
+            last_record: None,
+        }
+    }
+
+    /// Checks if the last record is at least one resolution ago, i.e., if it's time to record again
+    #[must_use]
+    pub fn should_record(&self, time: Duration) -> bool {
+        self.last_record
+            .is_none_or(|last_record| time >= last_record + self.resolution)
+    }
+
+    /// Mark the records of `time` as done
+    pub fn recorded(&mut self, time: Duration) {
+        self.last_record = Some(time);
+    }
+
+    /// Record a value of the global stats
+    pub fn record_global<N>(&mut self, name: N, time: Duration, value: f64)
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let (capacity, resolution) = (self.capacity, self.resolution);
+        self.global
+            .entry(name.into())
+            .or_insert_with(|| TimeSeries::new(capacity, resolution))
+            .push(time, value);
+    }
+
+    /// Record a value of a user stats
+    pub fn record_user_stats<N>(&mut self, name: N, time: Duration, value: f64)
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let (capacity, resolution) = (self.capacity, self.resolution);
+        self.user_stats
+            .entry(name.into())
+            .or_insert_with(|| TimeSeries::new(capacity, resolution))
+            .push(time, value);
+    }
+
+    /// The history of a global stat, such as [`HISTORY_EXECS_PER_SEC`]
+    #[must_use]
+    pub fn global(&self, name: &str) -> Option<&TimeSeries> {
+        self.global.get(name)
+    }
+
+    /// The history of a user stats
+    #[must_use]
+    pub fn user_stats(&self, name: &str) -> Option<&TimeSeries> {
+        self.user_stats.get(name)
+    }
+
+    /// All global stats histories
+    pub fn global_iter(&self) -> impl Iterator<Item = (&Cow<'static, str>, &TimeSeries)> {
+        self.global.iter()
+    }
+
+    /// All user stats histories
+    pub fn user_stats_iter(&self) -> impl Iterator<Item = (&Cow<'static, str>, &TimeSeries)> {
+        self.user_stats.iter()
+    }
+
+    /// Checks if nothing has been recorded yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.user_stats.is_empty()
+    }
+
+    /// The time of the last record, if any
+    #[must_use]
+    pub fn last_record(&self) -> Option<Duration> {
+        self.last_record
+    }
+
+    /// The history as JSON, with one object for the global stats and one for the user stats.
+    ///
+    /// Each maps the stat name to a list of `[time, value]` pairs, with times in seconds since `start_time`.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn to_json(&self, start_time: Duration) -> Value {
+        let to_json = |series: &TimeSeries| {
+            series
+                .points()
+                .map(|point| {
+                    json!([
+                        point.time.saturating_sub(start_time).as_secs_f64(),
+                        point.value
+                    ])
+                })
+                .collect::<Value>()
+        };
+        let global = self
+            .global
+            .iter()
+            .map(|(name, series)| (name.clone().into_owned(), to_json(series)))
+            .collect::<Map<_, _>>();
+        let user_stats = self
+            .user_stats
+            .iter()
+            .map(|(name, series)| (name.clone().into_owned(), to_json(series)))
+            .collect::<Map<_, _>>();
+        json!({
+            "global": global,
+            "user_stats": user_stats,
+        })
+    }
+}
+
+impl Default for StatsHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY, DEFAULT_HISTORY_RESOLUTION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{StatsHistory, TimeSeries};
+
+    #[test]
+    fn test_time_series_downsampling() {
+        let mut series = TimeSeries::new(4, Duration::from_secs(1));
+        for secs in 0..4_u32 {
+            series.push(Duration::from_secs(secs.into()), f64::from(secs));
+        }
+        // Within the resolution of the last point, only the value changes
+        series.push(Duration::from_millis(3500), 10.0);
+        assert_eq!(series.len(), 4);
+        assert!((series.latest().unwrap().value - 10.0).abs() < f64::EPSILON);
+
+        series.push(Duration::from_secs(4), 4.0);
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.resolution(), Duration::from_secs(2));
+        let times = series
+            .points()
+            .map(|point| point.time.as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(times, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_stats_history() {
+        let mut history = StatsHistory::new(16, Duration::from_secs(1));
+        assert!(history.should_record(Duration::from_secs(0)));
+        history.record_global("corpus_size", Duration::from_secs(0), 1.0);
+        history.record_user_stats("corpus_size", Duration::from_secs(0), 2.0);
+        history.recorded(Duration::from_secs(0));
+        assert!(!history.should_record(Duration::from_millis(500)));
+        assert!(history.should_record(Duration::from_secs(1)));
+
+        let global = history.global("corpus_size").unwrap().latest().unwrap();
+        assert!((global.value - 1.0).abs() < f64::EPSILON);
+        let user_stats = history.user_stats("corpus_size").unwrap().latest().unwrap();
+        assert!((user_stats.value - 2.0).abs() < f64::EPSILON);
+
+        #[cfg(feature = "std")]
+        {
+            let json = history.to_json(Duration::ZERO);
+            assert_eq!(
+                json["global"]["corpus_size"],
+                serde_json::json!([[0.0, 1.0]])
+            );
+            assert_eq!(
+                json["user_stats"]["corpus_size"],
+                serde_json::json!([[0.0, 2.0]])
+            );
+        }
+    }
+}
@@ -2,7 +2,7 @@
 
 #[cfg(feature = "std")]
 use alloc::string::ToString;
-use alloc::{borrow::Cow, string::String};
+use alloc::{borrow::Cow, string::String, vec::Vec};
 use core::time::Duration;
 
 use hashbrown::HashMap;
@@ -10,13 +10,26 @@ use libafl_bolts::{ClientId, Error, current_time, format_duration};
 #[cfg(feature = "std")]
 use serde_json::Value;
 
-use super::{ClientStats, EdgeCoverage, ProcessTiming, user_stats::UserStatsValue};
+use super::{
+    ClientStats, EdgeCoverage, ProcessTiming,
+    history::{
+        HISTORY_CORPUS_SIZE, HISTORY_EDGES_HIT, HISTORY_EXECS_PER_SEC, HISTORY_OBJECTIVE_SIZE,
+        StatsHistory,
+    },
+    user_stats::UserStatsValue,
+};
 #[cfg(feature = "std")]
 use super::{
     ItemGeometry,
     user_stats::{AggregatorOps, UserStats},
 };
 
+/// The name of the user stats a client reports the name of its `ClientProfile` with.
+pub const CLIENT_PROFILE_STATS_NAME: &str = "profile";
+
+/// The name of the user stats a client reports the number of its resource limit hits with.
+pub const RESOURCE_LIMIT_HITS_STATS_NAME: &str = "resource_limit_hits";
+
 /// Manager of all client's statistics
 #[derive(Debug)]
 pub struct ClientStatsManager {
@@ -30,6 +43,8 @@ pub struct ClientStatsManager {
     /// This will be erased to `None` every time a client is updated with crucial stats.
     cached_global_stats: Option<GlobalStats>,
     start_time: Duration,
+    /// The history of the global stats and the aggregated user stats
+    history: StatsHistory,
 }
 
 impl ClientStatsManager {
@@ -41,6 +56,16 @@ impl ClientStatsManager {
             cached_aggregated_user_stats: HashMap::new(),
             cached_global_stats: None,
             start_time: current_time(),
+            history: StatsHistory::default(),
+        }
+    }
+
+    /// Create a new client stats manager, using the given (empty or restored) [`StatsHistory`]
+    #[must_use]
+    pub fn with_history(history: StatsHistory) -> Self {
+        Self {
+            history,
+            ..Self::new()
         }
     }
 
@@ -86,7 +111,7 @@ impl ClientStatsManager {
 
     /// Update sepecific client stats.
     ///
-    /// This will potentially clear the global stats cache.
+    /// This will potentially clear the global stats cache, and record the stats into the [`StatsHistory`].
     pub fn update_client_stats_for<T, F: FnOnce(&mut ClientStats) -> T>(
         &mut self,
         client_id: ClientId,
@@ -98,6 +123,7 @@ impl ClientStatsManager {
             if stat.stats_status.basic_stats_updated {
                 self.cached_global_stats = None;
             }
+            self.maybe_record_history();
             Ok(res)
         } else {
             Err(Error::key_not_found(format!(
@@ -132,6 +158,49 @@ impl ClientStatsManager {
     pub fn aggregated(&self) -> &HashMap<Cow<'static, str>, UserStatsValue> {
         &self.cached_aggregated_user_stats
     }
+    /// The history of the global stats and all numeric aggregated user stats
+    #[must_use]
+    pub fn history(&self) -> &StatsHistory {
+        &self.history
+    }
+
+    /// Replace the history, e.g., with the history of a previous run
+    pub fn set_history(&mut self, history: StatsHistory) {
+        self.history = history;
+    }
+
+    /// Record the current stats into the [`StatsHistory`], unless the last record is too recent
+    #[expect(clippy::cast_precision_loss)]
+    fn maybe_record_history(&mut self) {
+        let cur_time = current_time();
+        if !self.history.should_record(cur_time) {
+            return;
+        }
+        let global_stats = self.global_stats();
+        let execs_per_sec = global_stats.execs_per_sec;
+        let corpus_size = global_stats.corpus_size;
+        let objective_size = global_stats.objective_size;
+        let edges_hit = self.edges_coverage().map(|coverage| coverage.edges_hit);
+
+        self.history
+            .record_global(HISTORY_EXECS_PER_SEC, cur_time, execs_per_sec);
+        self.history
+            .record_global(HISTORY_CORPUS_SIZE, cur_time, corpus_size as f64);
+        self.history
+            .record_global(HISTORY_OBJECTIVE_SIZE, cur_time, objective_size as f64);
+        if let Some(edges_hit) = edges_hit {
+            self.history
+                .record_global(HISTORY_EDGES_HIT, cur_time, edges_hit as f64);
+        }
+        for (name, value) in &self.cached_aggregated_user_stats {
+            if let Some(value) = value.as_f64() {
+                self.history
+                    .record_user_stats(name.clone(), cur_time, value);
+            }
+        }
+        self.history.recorded(cur_time);
+    }
+
     /// Time this fuzzing run stated
     #[must_use]
     pub fn start_time(&self) -> Duration {
@@ -181,6 +250,45 @@ impl ClientStatsManager {
         global_stats
     }
 
+    /// Get the stats aggregated per client profile, sorted by profile name.
+    ///
+    /// Clients are grouped by the [`CLIENT_PROFILE_STATS_NAME`] user stats they reported.
+    /// Clients without a profile are not part of any group.
+    pub fn profile_stats(&mut self) -> Vec<ProfileStats> {
+        let cur_time = current_time();
+        let mut profiles: Vec<ProfileStats> = Vec::new();
+        for client in self
+            .client_stats
+            .values_mut()
+            .filter(|client| client.enabled)
+        {
+            let Some(UserStatsValue::String(name)) = client
+                .get_user_stats(CLIENT_PROFILE_STATS_NAME)
+                .map(|stats| stats.value().clone())
+            else {
+                continue;
+            };
+            let idx = match profiles.iter().position(|profile| profile.name == name) {
+                Some(idx) => idx,
+                None => {
+                    profiles.push(ProfileStats {
+                        name: name.into_owned(),
+                        ..ProfileStats::default()
+                    });
+                    profiles.len() - 1
+                }
+            };
+            let profile = &mut profiles[idx];
+            profile.client_stats_count += 1;
+            profile.corpus_size += client.corpus_size;
+            profile.objective_size += client.objective_size;
+            profile.total_execs += client.executions;
+            profile.execs_per_sec += client.execs_per_sec(cur_time);
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
     /// Get process timing. `execs_per_sec_pretty` could be retrieved from `GlobalStats`.
     #[must_use]
     pub fn process_timing(&self, execs_per_sec_pretty: String, total_execs: u64) -> ProcessTiming {
@@ -280,6 +388,23 @@ impl Default for ClientStatsManager {
     }
 }
 
+/// Statistics aggregated over all clients using the same client profile.
+#[derive(Debug, Default, Clone)]
+pub struct ProfileStats {
+    /// The name of the profile
+    pub name: String,
+    /// Count the number of enabled clients using this profile
+    pub client_stats_count: usize,
+    /// Amount of elements in the corpus (combined for all clients of this profile)
+    pub corpus_size: u64,
+    /// Amount of elements in the objectives (combined for all clients of this profile)
+    pub objective_size: u64,
+    /// Total executions
+    pub total_execs: u64,
+    /// Executions per second
+    pub execs_per_sec: f64,
+}
+
 /// Global statistics which aggregates client stats.
 #[derive(Debug, Default)]
 pub struct GlobalStats {
@@ -1,5 +1,6 @@
 //! Statistics used for Monitors to display.
 
+pub mod history;
 pub mod manager;
 #[cfg(feature = "introspection")]
 pub mod perf_stats;
@@ -13,7 +14,10 @@ use core::time::Duration;
 
 use hashbrown::HashMap;
 use libafl_bolts::current_time;
-pub use manager::ClientStatsManager;
+pub use history::{StatsHistory, TimeSeries, TimeSeriesPoint};
+pub use manager::{
+    CLIENT_PROFILE_STATS_NAME, ClientStatsManager, ProfileStats, RESOURCE_LIMIT_HITS_STATS_NAME,
+};
 #[cfg(feature = "introspection")]
 pub use perf_stats::{ClientPerfStats, PerfFeature};
 use serde::{Deserialize, Serialize};
@@ -415,7 +419,7 @@ impl ClientStats {
 }
 
 /// Prettifies float values for human-readable output
-fn prettify_float(value: f64) -> String {
+pub(crate) fn prettify_float(value: f64) -> String {
     let (value, suffix) = match value {
         value if value >= 1_000_000.0 => (value / 1_000_000.0, "M"),
         value if value >= 1_000.0 => (value / 1_000.0, "k"),
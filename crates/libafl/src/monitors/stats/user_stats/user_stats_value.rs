@@ -30,6 +30,20 @@ impl UserStatsValue {
         }
     }
 
+    /// The value as `f64`, if it is numeric.
+    ///
+    /// Ratios are returned as fraction, or `None` if the denominator is `0`.
+    #[must_use]
+    #[expect(clippy::cast_precision_loss)]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(*x as f64),
+            Self::Float(x) | Self::Percent(x) => Some(*x),
+            Self::Ratio(_, 0) | Self::String(_) => None,
+            Self::Ratio(x, y) => Some(*x as f64 / *y as f64),
+        }
+    }
+
     /// Divide by the number of elements
     #[expect(clippy::cast_precision_loss)]
     pub fn stats_div(&mut self, divisor: usize) -> Option<Self> {
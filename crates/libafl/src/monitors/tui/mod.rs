@@ -35,7 +35,9 @@ use crate::monitors::stats::perf_stats::{ClientPerfStats, PerfFeature};
 use crate::monitors::{
     Monitor,
     stats::{
-        ClientStats, EdgeCoverage, ItemGeometry, ProcessTiming, manager::ClientStatsManager,
+        ClientStats, EdgeCoverage, ItemGeometry, ProcessTiming, TimeSeries,
+        history::{HISTORY_CORPUS_SIZE, HISTORY_EXECS_PER_SEC, HISTORY_OBJECTIVE_SIZE},
+        manager::ClientStatsManager,
         user_stats::UserStats,
     },
 };
@@ -117,6 +119,22 @@ impl TimedStats {
         }
     }
 
+    /// Replace the datapoints with the points of `series` within the time window.
+    /// The times are made relative to `start_time`.
+    #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn fill_from(&mut self, series: &TimeSeries, start_time: Duration) {
+        self.series.clear();
+        let Some(latest) = series.latest() else {
+            return;
+        };
+        let since = latest.time.saturating_sub(self.window);
+        self.series
+            .extend(series.since(since).map(|point| TimedStat {
+                time: point.time.saturating_sub(start_time),
+                item: point.value as u64,
+            }));
+    }
+
     /// Change the window duration
     pub fn update_window(&mut self, window: Duration) {
         self.window = window;
@@ -333,7 +351,6 @@ impl From<TuiMonitorConfig> for TuiMonitor {
 }
 
 impl Monitor for TuiMonitor {
-    #[expect(clippy::cast_sign_loss)]
     fn display(
         &mut self,
         client_stats_manager: &mut ClientStatsManager,
@@ -344,25 +361,30 @@ impl Monitor for TuiMonitor {
 
         {
             let global_stats = client_stats_manager.global_stats();
-            // TODO implement floating-point support for TimedStat
-            let execsec = global_stats.execs_per_sec as u64;
             let totalexec = global_stats.total_execs;
-            let run_time = global_stats.run_time;
             let exec_per_sec_pretty = global_stats.execs_per_sec_pretty.clone();
             let total_execs = global_stats.total_execs;
             let mut ctx = self.context.write().unwrap();
             ctx.total_corpus_count = global_stats.corpus_size;
             ctx.total_solutions = global_stats.objective_size;
-            ctx.corpus_size_timed
-                .add(run_time, global_stats.corpus_size);
-            ctx.objective_size_timed
-                .add(run_time, global_stats.objective_size);
             let total_process_timing =
                 client_stats_manager.process_timing(exec_per_sec_pretty, total_execs);
 
             ctx.total_process_timing = total_process_timing;
-            ctx.execs_per_sec_timed.add(run_time, execsec);
-            ctx.start_time = client_stats_manager.start_time();
+            let start_time = client_stats_manager.start_time();
+            ctx.start_time = start_time;
+
+            // The graphs show the history kept by the stats manager, so they survive restarts
+            let history = client_stats_manager.history();
+            if let Some(series) = history.global(HISTORY_CORPUS_SIZE) {
+                ctx.corpus_size_timed.fill_from(series, start_time);
+            }
+            if let Some(series) = history.global(HISTORY_OBJECTIVE_SIZE) {
+                ctx.objective_size_timed.fill_from(series, start_time);
+            }
+            if let Some(series) = history.global(HISTORY_EXECS_PER_SEC) {
+                ctx.execs_per_sec_timed.fill_from(series, start_time);
+            }
             ctx.total_execs = totalexec;
             ctx.clients_num = client_stats_manager.client_stats().len();
             ctx.total_map_density = client_stats_manager.edges_coverage().map_or(
@@ -18,7 +18,7 @@ use std::{
 };
 
 use hashbrown::HashSet;
-use libafl_bolts::{AsSlice, HasLen, rands::Rand};
+use libafl_bolts::{AsSlice, HasLen, nonzero, rands::Rand};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
@@ -437,6 +437,90 @@ impl TokenReplace {
     }
 }
 
+/// Replaces the first occurrence of a `from` pattern at or after `off` with its `to` counterpart.
+/// Patterns are matched in native and in swapped byte order.
+fn replace_first_pattern<const N: usize>(
+    bytes: &mut [u8],
+    off: usize,
+    patterns: &[([u8; N], [u8; N])],
+) -> MutationResult {
+    if bytes.len() < N {
+        return MutationResult::Skipped;
+    }
+    for i in off..=bytes.len() - N {
+        let window = &mut bytes[i..i + N];
+        for (from, to) in patterns {
+            if window == from {
+                window.copy_from_slice(to);
+                return MutationResult::Mutated;
+            }
+            let (mut from_swapped, mut to_swapped) = (*from, *to);
+            from_swapped.reverse();
+            to_swapped.reverse();
+            if *window == from_swapped {
+                window.copy_from_slice(&to_swapped);
+                return MutationResult::Mutated;
+            }
+        }
+    }
+    MutationResult::Skipped
+}
+
+/// Input-to-state replacement for a floating-point compare, given as raw bits.
+/// The replacement is randomly moved by one ulp, so that ordered compares can flip, too.
+fn i2s_replace_fp<S, const N: usize>(
+    state: &mut S,
+    bytes: &mut [u8],
+    off: usize,
+    (v1, v2, v1_is_const): (u64, u64, bool),
+    to_bytes: impl Fn(u64) -> [u8; N],
+) -> MutationResult
+where
+    S: HasRand,
+{
+    let ulp = match state.rand_mut().below(nonzero!(3)) {
+        0 => -1,
+        1 => 0,
+        _ => 1,
+    };
+    let patterns = [
+        (to_bytes(v1), to_bytes(v2.wrapping_add_signed(ulp))),
+        (to_bytes(v2), to_bytes(v1.wrapping_add_signed(ulp))),
+    ];
+    replace_first_pattern(bytes, off, &patterns[usize::from(v1_is_const)..])
+}
+
+/// Input-to-state replacement for a jump table: a byte holding the logged index is replaced
+/// with another valid index, to reach a different case of the `switch`.
+fn i2s_replace_jump_table<S>(
+    state: &mut S,
+    bytes: &mut [u8],
+    off: usize,
+    (index, entries): (u64, u64),
+) -> MutationResult
+where
+    S: HasRand,
+{
+    let entries = entries.min(256);
+    let Some(choices) = NonZero::new(entries.saturating_sub(1) as usize) else {
+        return MutationResult::Skipped;
+    };
+    if index >= entries {
+        return MutationResult::Skipped;
+    }
+    let mut target = state.rand_mut().below(choices) as u64;
+    if target >= index {
+        target += 1;
+    }
+    for byte in bytes.iter_mut().skip(off) {
+        if u64::from(*byte) == index {
+            *byte = target as u8;
+            return MutationResult::Mutated;
+        }
+    }
+    MutationResult::Skipped
+}
+
 /// A `I2SRandReplace` [`Mutator`] replaces a random matching input-2-state comparison operand with the other.
 /// It needs a valid [`CmpValuesMetadata`] in the state.
 #[derive(Debug, Default)]
@@ -603,6 +687,18 @@ where
                     }
                 }
             }
+            CmpValues::F32((v1, v2, v1_is_const)) => {
+                let v = (u64::from(*v1), u64::from(*v2), *v1_is_const);
+                result = i2s_replace_fp(state, bytes, off, v, |x| (x as u32).to_ne_bytes());
+            }
+            CmpValues::F64((v1, v2, v1_is_const)) => {
+                let v = (*v1, *v2, *v1_is_const);
+                result = i2s_replace_fp(state, bytes, off, v, u64::to_ne_bytes);
+            }
+            CmpValues::JumpTable(v) => {
+                let v = *v;
+                result = i2s_replace_jump_table(state, bytes, off, v);
+            }
         }
 
         Ok(result)
@@ -815,6 +911,16 @@ where
                     }
                 }
             }
+            CmpValues::F32(v) => {
+                let v = (u64::from(v.0), u64::from(v.1), v.2);
+                result = i2s_replace_fp(state, bytes, off, v, |x| (x as u32).to_ne_bytes());
+            }
+            CmpValues::F64(v) => {
+                result = i2s_replace_fp(state, bytes, off, v, u64::to_ne_bytes);
+            }
+            CmpValues::JumpTable(v) => {
+                result = i2s_replace_jump_table(state, bytes, off, v);
+            }
         }
 
         Ok(result)
@@ -869,8 +975,7 @@ impl AflppRedQueen {
         clippy::cast_sign_loss,
         clippy::too_many_arguments,
         clippy::too_many_lines,
-        clippy::cast_possible_wrap,
-        clippy::cast_precision_loss
+        clippy::cast_possible_wrap
     )]
     pub fn cmp_extend_encoding(
         &self,
@@ -1150,14 +1255,13 @@ impl AflppRedQueen {
                 let repl_new: u64;
 
                 if attr & CMP_ATTRIBUTE_IS_GREATER != 0 {
+                    // `repl` holds the raw bits of the floating-point operand
                     if hshape == 4 && its_len >= 4 {
-                        let mut g = repl as f32;
-                        g += 1.0;
-                        repl_new = u64::from(g as u32);
+                        let g = f32::from_bits(repl as u32) + 1.0;
+                        repl_new = u64::from(g.to_bits());
                     } else if hshape == 8 && its_len >= 8 {
-                        let mut g = repl as f64;
-                        g += 1.0;
-                        repl_new = g as u64;
+                        let g = f64::from_bits(repl) + 1.0;
+                        repl_new = g.to_bits();
                     } else {
                         return Ok(false);
                     }
@@ -1180,14 +1284,13 @@ impl AflppRedQueen {
                         return Ok(true);
                     }
                 } else {
+                    // `repl` holds the raw bits of the floating-point operand
                     if hshape == 4 && its_len >= 4 {
-                        let mut g = repl as f32;
-                        g -= 1.0;
-                        repl_new = u64::from(g as u32);
+                        let g = f32::from_bits(repl as u32) - 1.0;
+                        repl_new = u64::from(g.to_bits());
                     } else if hshape == 8 && its_len >= 8 {
-                        let mut g = repl as f64;
-                        g -= 1.0;
-                        repl_new = g as u64;
+                        let g = f64::from_bits(repl) - 1.0;
+                        repl_new = g.to_bits();
                     } else {
                         return Ok(false);
                     }
@@ -1262,6 +1365,100 @@ impl AflppRedQueen {
         Ok(false)
     }
 
+    /// The byte at `buf_idx` is the index into a jump table, in both the original and the
+    /// colorized run: try each other entry of the table.
+    /// Entries past 255 cannot be reached through a single byte.
+    fn jump_table_encoding(
+        (orig_index, entries): (u64, u64),
+        (new_index, _): (u64, u64),
+        another_buf: &[u8],
+        buf: &[u8],
+        buf_idx: usize,
+        taint_len: usize,
+        vec: &mut Vec<Vec<u8>>,
+    ) -> bool {
+        // The colorization has to change the index, or this byte is not what selects it
+        if taint_len == 0
+            || orig_index == new_index
+            || buf.get(buf_idx).map(|&b| u64::from(b)) != Some(orig_index)
+            || another_buf.get(buf_idx).map(|&b| u64::from(b)) != Some(new_index)
+        {
+            return false;
+        }
+        for target in 0..entries.min(256) {
+            if target != orig_index {
+                let mut cloned = buf.to_vec();
+                cloned[buf_idx] = target as u8;
+                vec.push(cloned);
+            }
+        }
+        true
+    }
+
+    /// Floating-point cmplog pattern matching.
+    ///
+    /// Tries both operands of the compare against the input, in native and in swapped byte order.
+    /// The operands are the raw bits of the values, `swapped_orig` and `swapped_new` the same bits
+    /// byte-swapped at the width of the compare.
+    #[expect(clippy::too_many_arguments)]
+    fn cmp_extend_fp_encoding(
+        &self,
+        orig: (u64, u64),
+        new: (u64, u64),
+        swapped_orig: (u64, u64),
+        swapped_new: (u64, u64),
+        attribute: u8,
+        another_buf: &[u8],
+        buf: &[u8],
+        buf_idx: usize,
+        taint_len: usize,
+        input_len: usize,
+        hshape: usize,
+        vec: &mut Vec<Vec<u8>>,
+    ) -> Result<bool, Error> {
+        let attribute = attribute | CMP_ATTRIBUTE_IS_FP;
+        let mut cmp_found = false;
+        if new.0 != orig.0 && orig.0 != orig.1 {
+            for (o, n) in [(orig, new), (swapped_orig, swapped_new)] {
+                cmp_found |= self.cmp_extend_encoding(
+                    o.0,
+                    o.1,
+                    n.0,
+                    n.1,
+                    attribute,
+                    another_buf,
+                    buf,
+                    buf_idx,
+                    taint_len,
+                    input_len,
+                    hshape,
+                    vec,
+                )?;
+            }
+        }
+
+        if new.1 != orig.1 && orig.0 != orig.1 {
+            for (o, n) in [(orig, new), (swapped_orig, swapped_new)] {
+                cmp_found |= self.cmp_extend_encoding(
+                    o.1,
+                    o.0,
+                    n.1,
+                    n.0,
+                    Self::swapa(attribute),
+                    another_buf,
+                    buf,
+                    buf_idx,
+                    taint_len,
+                    input_len,
+                    hshape,
+                    vec,
+                )?;
+            }
+        }
+
+        Ok(cmp_found)
+    }
+
     /// rtn part from AFL++
     #[expect(clippy::too_many_arguments)]
     pub fn rtn_extend_encoding(
@@ -1798,6 +1995,38 @@ where
                                 }
                             }
                         }
+                        (CmpValues::F32(orig), CmpValues::F32(new)) => {
+                            self.cmp_extend_fp_encoding(
+                                (orig.0.into(), orig.1.into()),
+                                (new.0.into(), new.1.into()),
+                                (orig.0.swap_bytes().into(), orig.1.swap_bytes().into()),
+                                (new.0.swap_bytes().into(), new.1.swap_bytes().into()),
+                                header.attribute().value(),
+                                new_bytes,
+                                orig_bytes,
+                                cmp_buf_idx,
+                                taint_len,
+                                input_len,
+                                hshape,
+                                &mut ret,
+                            )?;
+                        }
+                        (CmpValues::F64(orig), CmpValues::F64(new)) => {
+                            self.cmp_extend_fp_encoding(
+                                (orig.0, orig.1),
+                                (new.0, new.1),
+                                (orig.0.swap_bytes(), orig.1.swap_bytes()),
+                                (new.0.swap_bytes(), new.1.swap_bytes()),
+                                header.attribute().value(),
+                                new_bytes,
+                                orig_bytes,
+                                cmp_buf_idx,
+                                taint_len,
+                                input_len,
+                                hshape,
+                                &mut ret,
+                            )?;
+                        }
                         (CmpValues::Bytes(orig), CmpValues::Bytes(new)) => {
                             let (orig_v0, orig_v1, new_v0, new_v1) =
                                 (&orig.0, &orig.1, &new.0, &new.1);
@@ -1882,6 +2111,17 @@ where
                                 );
                             }
                         }
+                        (CmpValues::JumpTable(orig), CmpValues::JumpTable(new)) => {
+                            Self::jump_table_encoding(
+                                *orig,
+                                *new,
+                                new_bytes,
+                                orig_bytes,
+                                cmp_buf_idx,
+                                taint_len,
+                                &mut ret,
+                            );
+                        }
                         (_, _) => {
                             // not gonna happen
                         }
@@ -2107,8 +2347,10 @@ mod tests {
     #[cfg(feature = "std")]
     use std::fs;
 
+    use super::replace_first_pattern;
     #[cfg(feature = "std")]
     use super::{AflppRedQueen, Tokens};
+    use crate::mutators::MutationResult;
 
     #[cfg(feature = "std")]
     #[test]
@@ -2128,6 +2370,53 @@ token2="B"
         let _res = fs::remove_file("test.tkns");
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_jump_table_encoding() {
+        let orig = b"ab\x02c";
+        let colorized = b"xy\x05z";
+        let mut ret = Vec::new();
+
+        // The index is not at 0, or it did not follow the colorization
+        assert!(!AflppRedQueen::jump_table_encoding(
+            (2, 4),
+            (5, 4),
+            colorized,
+            orig,
+            0,
+            4,
+            &mut ret
+        ));
+        assert!(!AflppRedQueen::jump_table_encoding(
+            (2, 4),
+            (2, 4),
+            orig,
+            orig,
+            2,
+            2,
+            &mut ret
+        ));
+        assert!(ret.is_empty());
+
+        assert!(AflppRedQueen::jump_table_encoding(
+            (2, 4),
+            (5, 4),
+            colorized,
+            orig,
+            2,
+            2,
+            &mut ret
+        ));
+        assert_eq!(
+            ret,
+            [
+                b"ab\x00c".to_vec(),
+                b"ab\x01c".to_vec(),
+                b"ab\x03c".to_vec()
+            ]
+        );
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_token_mutations() {
@@ -2160,4 +2449,37 @@ token2="B"
             &mut vec,
         );
     }
+
+    #[test]
+    fn test_replace_first_pattern() {
+        let from = 1.5_f32.to_bits().to_ne_bytes();
+        let to = 2.25_f32.to_bits().to_ne_bytes();
+
+        let mut bytes = [0xaa; 12];
+        bytes[5..9].copy_from_slice(&from);
+        assert_eq!(
+            replace_first_pattern(&mut bytes, 0, &[(from, to)]),
+            MutationResult::Mutated
+        );
+        assert_eq!(bytes[5..9], to);
+
+        // swapped byte order is matched, too
+        let mut swapped = from;
+        swapped.reverse();
+        let mut bytes = [0xaa; 12];
+        bytes[2..6].copy_from_slice(&swapped);
+        assert_eq!(
+            replace_first_pattern(&mut bytes, 0, &[(from, to)]),
+            MutationResult::Mutated
+        );
+        let mut expected = to;
+        expected.reverse();
+        assert_eq!(bytes[2..6], expected);
+
+        // occurrences before the offset are left alone
+        assert_eq!(
+            replace_first_pattern(&mut bytes, 3, &[(to, from)]),
+            MutationResult::Skipped
+        );
+    }
 }
@@ -59,6 +59,15 @@ pub enum CmpValues {
     U64((u64, u64, bool)),
     /// Two vecs of u8 values/byte
     Bytes((CmplogBytes, CmplogBytes)),
+    /// A single-precision floating-point compare, as raw bits.
+    /// (side 1 of comparison, side 2 of comparison, side 1 value is const)
+    F32((u32, u32, bool)),
+    /// A double-precision floating-point compare, as raw bits.
+    /// (side 1 of comparison, side 2 of comparison, side 1 value is const)
+    F64((u64, u64, bool)),
+    /// An indirect jump through a jump table.
+    /// (index used for this execution, number of entries in the table)
+    JumpTable((u64, u64)),
 }
 
 impl CmpValues {
@@ -71,7 +80,10 @@ impl CmpValues {
         )
     }
 
-    /// Converts the value to a u64 tuple
+    /// Converts the value to a u64 tuple.
+    ///
+    /// Floating-point compares and jump tables do not have a meaningful integer ordering
+    /// and return `None`.
     #[must_use]
     pub fn to_u64_tuple(&self) -> Option<(u64, u64, bool)> {
         match self {
@@ -79,7 +91,10 @@ impl CmpValues {
             CmpValues::U16(t) => Some((u64::from(t.0), u64::from(t.1), t.2)),
             CmpValues::U32(t) => Some((u64::from(t.0), u64::from(t.1), t.2)),
             CmpValues::U64(t) => Some(*t),
-            CmpValues::Bytes(_) => None,
+            CmpValues::Bytes(_)
+            | CmpValues::F32(_)
+            | CmpValues::F64(_)
+            | CmpValues::JumpTable(_) => None,
         }
     }
 }
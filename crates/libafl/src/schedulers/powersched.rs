@@ -157,7 +157,7 @@ impl SchedulerMetadata {
 }
 
 /// The struct for the powerschedule algorithm
-#[derive(Debug, Clone, Serialize, Deserialize, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq)]
 pub struct PowerSchedule {
     base: BaseSchedule,
     avoid_crash: bool,
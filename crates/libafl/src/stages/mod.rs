@@ -33,6 +33,8 @@ pub use mutational::{MutationalStage, StdMutationalStage};
 pub use power::{PowerMutationalStage, StdPowerMutationalStage};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
+pub use source_coverage::{LlvmCovReporter, SourceCoverageMetadata, SourceCoverageStage};
+#[cfg(feature = "std")]
 pub use sync::*;
 #[cfg(feature = "std")]
 pub use time_tracker::TimeTrackingStageWrapper;
@@ -78,6 +80,8 @@ pub mod logics;
 pub mod nop;
 pub mod power;
 #[cfg(feature = "std")]
+pub mod source_coverage;
+#[cfg(feature = "std")]
 pub mod sync;
 #[cfg(feature = "std")]
 pub mod time_tracker;
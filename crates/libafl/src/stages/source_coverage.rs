@@ -0,0 +1,501 @@
+//! The [`SourceCoverageStage`] replays the corpus against a build instrumented with
+//! `-fprofile-instr-generate -fcoverage-mapping` and writes source-level coverage reports.
+//!
+//! The heavy lifting is done by the [`LlvmCovReporter`], which can also be used on its own to
+//! create a report for a corpus directory on demand.
+
+use alloc::{string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::{AsSlice, current_time, impl_serdeany};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, HasMetadata,
+    corpus::{Corpus, CorpusId},
+    inputs::HasTargetBytes,
+    stages::{Restartable, Stage},
+    state::HasCorpus,
+};
+
+/// The placeholder in the arguments of the coverage binary that is replaced by the input file.
+/// Without it, the input is passed on stdin.
+const INPUT_PLACEHOLDER: &str = "@@";
+
+/// Creates source-level coverage reports with `llvm-profdata` and `llvm-cov`.
+///
+/// Each replayed input runs the coverage binary once, with `LLVM_PROFILE_FILE` pointing to a
+/// scratch directory in `output_dir`. [`LlvmCovReporter::merge`] folds the raw profiles into
+/// `merged.profdata`, so replaying can continue incrementally, and [`LlvmCovReporter::report`]
+/// writes `coverage.lcov` and, optionally, an HTML report to `html/`.
+#[derive(Debug, Clone)]
+pub struct LlvmCovReporter {
+    binary: PathBuf,
+    args: Vec<OsString>,
+    objects: Vec<PathBuf>,
+    output_dir: PathBuf,
+    llvm_profdata: PathBuf,
+    llvm_cov: PathBuf,
+    timeout: Duration,
+    html: bool,
+}
+
+impl LlvmCovReporter {
+    /// Create a new [`LlvmCovReporter`] for the coverage `binary`, writing to `output_dir`.
+    pub fn new<A, B>(binary: A, output_dir: B) -> Result<Self, Error>
+    where
+        A: Into<PathBuf>,
+        B: Into<PathBuf>,
+    {
+        let output_dir = output_dir.into();
+        let raw_dir = output_dir.join("raw");
+        fs::create_dir_all(&raw_dir).map_err(|e| {
+            Error::os_error(e, format!("Error creating directory {}", raw_dir.display()))
+        })?;
+        Ok(Self {
+            binary: binary.into(),
+            args: Vec::new(),
+            objects: Vec::new(),
+            output_dir,
+            llvm_profdata: PathBuf::from("llvm-profdata"),
+            llvm_cov: PathBuf::from("llvm-cov"),
+            timeout: Duration::from_secs(5),
+            html: true,
+        })
+    }
+
+    /// Set the arguments of the coverage binary. An argument `@@` is replaced by the path of the
+    /// input file, otherwise the input is passed on stdin.
+    #[must_use]
+    pub fn args<IT, O>(mut self, args: IT) -> Self
+    where
+        IT: IntoIterator<Item = O>,
+        O: Into<OsString>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add shared libraries of the target built with coverage mapping to the report
+    #[must_use]
+    pub fn objects<IT, P>(mut self, objects: IT) -> Self
+    where
+        IT: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.objects = objects.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the `llvm-profdata` binary, if it is not in `PATH` or has a version suffix
+    #[must_use]
+    pub fn llvm_profdata<P>(mut self, llvm_profdata: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.llvm_profdata = llvm_profdata.into();
+        self
+    }
+
+    /// Set the `llvm-cov` binary, if it is not in `PATH` or has a version suffix
+    #[must_use]
+    pub fn llvm_cov<P>(mut self, llvm_cov: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.llvm_cov = llvm_cov.into();
+        self
+    }
+
+    /// Set the timeout for a single replay, after which the coverage binary is killed
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set whether [`LlvmCovReporter::report`] also writes an HTML report, the default
+    #[must_use]
+    pub fn html(mut self, html: bool) -> Self {
+        self.html = html;
+        self
+    }
+
+    /// The directory the reports are written to
+    #[must_use]
+    pub fn output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+
+    /// The merged profile, once [`LlvmCovReporter::merge`] has run
+    #[must_use]
+    pub fn profdata_path(&self) -> PathBuf {
+        self.output_dir.join("merged.profdata")
+    }
+
+    /// The lcov report, once [`LlvmCovReporter::report`] has run
+    #[must_use]
+    pub fn lcov_path(&self) -> PathBuf {
+        self.output_dir.join("coverage.lcov")
+    }
+
+    /// Run the coverage binary once with `input`, leaving a raw profile for the next merge.
+    ///
+    /// Inputs that crash the binary or time out leave no profile and are skipped.
+    pub fn replay(&mut self, input: &[u8]) -> Result<(), Error> {
+        let input_file = self.output_dir.join(".cur_input");
+        let uses_file = self.args.iter().any(|arg| arg == INPUT_PLACEHOLDER);
+        if uses_file {
+            File::create(&input_file)?.write_all(input)?;
+        }
+
+        let mut command = Command::new(&self.binary);
+        for arg in &self.args {
+            if arg == INPUT_PLACEHOLDER {
+                command.arg(&input_file);
+            } else {
+                command.arg(arg);
+            }
+        }
+        command
+            .env(
+                "LLVM_PROFILE_FILE",
+                self.output_dir.join("raw").join("%p-%m.profraw"),
+            )
+            .stdin(if uses_file {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let mut child = command.spawn().map_err(|e| {
+            Error::os_error(e, format!("Could not spawn {}", self.binary.display()))
+        })?;
+        // Feed stdin from a thread, a target that does not read its input would otherwise block
+        // the write on a full pipe, before the timeout is checked.
+        let writer = child.stdin.take().map(|mut stdin| {
+            let input = input.to_vec();
+            // the target may exit without reading all of its input
+            thread::spawn(move || drop(stdin.write_all(&input)))
+        });
+
+        let start = Instant::now();
+        while child.try_wait()?.is_none() {
+            if start.elapsed() > self.timeout {
+                log::info!("Replay in {} timed out", self.binary.display());
+                child.kill()?;
+                child.wait()?;
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        // The pipe is closed once the child is gone, so the writer is done as well
+        if let Some(writer) = writer {
+            drop(writer.join());
+        }
+        Ok(())
+    }
+
+    /// Merge the raw profiles of all replays since the last merge into `merged.profdata`
+    pub fn merge(&mut self) -> Result<(), Error> {
+        let raw_dir = self.output_dir.join("raw");
+        let mut raw_profiles = Vec::new();
+        for entry in fs::read_dir(&raw_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "profraw") {
+                raw_profiles.push(path);
+            }
+        }
+        if raw_profiles.is_empty() {
+            return Ok(());
+        }
+
+        let profdata = self.profdata_path();
+        let merging = self.output_dir.join("merged.profdata.tmp");
+        let mut command = Command::new(&self.llvm_profdata);
+        command.arg("merge").arg("-sparse").arg("-o").arg(&merging);
+        if profdata.exists() {
+            command.arg(&profdata);
+        }
+        command.args(&raw_profiles);
+        run_tool(&mut command, &self.llvm_profdata)?;
+
+        fs::rename(&merging, &profdata)?;
+        for raw_profile in raw_profiles {
+            fs::remove_file(raw_profile)?;
+        }
+        Ok(())
+    }
+
+    /// Write `coverage.lcov` and the HTML report from `merged.profdata`
+    pub fn report(&mut self) -> Result<(), Error> {
+        let profdata = self.profdata_path();
+        if !profdata.exists() {
+            return Err(Error::illegal_state(format!(
+                "No profile to report on in {}, replay and merge first",
+                self.output_dir.display()
+            )));
+        }
+        let mut instr_profile = OsString::from("-instr-profile=");
+        instr_profile.push(&profdata);
+
+        let mut command = Command::new(&self.llvm_cov);
+        command
+            .arg("export")
+            .arg("-format=lcov")
+            .arg(&instr_profile)
+            .arg(&self.binary);
+        for object in &self.objects {
+            command.arg("-object").arg(object);
+        }
+        let lcov = run_tool(&mut command, &self.llvm_cov)?;
+        fs::write(self.lcov_path(), lcov)?;
+
+        if self.html {
+            let mut output_dir = OsString::from("-output-dir=");
+            output_dir.push(self.output_dir.join("html"));
+
+            let mut command = Command::new(&self.llvm_cov);
+            command
+                .arg("show")
+                .arg("-format=html")
+                .arg(&output_dir)
+                .arg(&instr_profile)
+                .arg(&self.binary);
+            for object in &self.objects {
+                command.arg("-object").arg(object);
+            }
+            run_tool(&mut command, &self.llvm_cov)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run an llvm tool to completion and return its stdout
+fn run_tool(command: &mut Command, tool: &Path) -> Result<Vec<u8>, Error> {
+    let output = command
+        .output()
+        .map_err(|e| Error::os_error(e, format!("Could not run {}", tool.display())))?;
+    if !output.status.success() {
+        return Err(Error::unknown(format!(
+            "{} failed with {}: {}",
+            tool.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Metadata used to store the last corpus entry replayed by the [`SourceCoverageStage`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    expect(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct SourceCoverageMetadata {
+    last_corpus: Option<CorpusId>,
+}
+
+impl_serdeany!(SourceCoverageMetadata);
+
+/// The [`SourceCoverageStage`] periodically replays new corpus entries with a [`LlvmCovReporter`]
+/// and refreshes its lcov and HTML reports.
+#[derive(Debug)]
+pub struct SourceCoverageStage<I> {
+    reporter: LlvmCovReporter,
+    interval: Duration,
+    last_report: Duration,
+    phantom: PhantomData<I>,
+}
+
+impl<E, EM, I, S, Z> Stage<E, EM, S, Z> for SourceCoverageStage<I>
+where
+    I: Clone + HasTargetBytes,
+    S: HasCorpus<I> + HasMetadata,
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_report) < self.interval {
+            return Ok(());
+        }
+        self.last_report = now;
+        self.update_report(state)
+    }
+}
+
+impl<I, S> Restartable<S> for SourceCoverageStage<I> {
+    #[inline]
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+impl<I> SourceCoverageStage<I> {
+    /// Create a new [`SourceCoverageStage`] reporting at most once per `interval`.
+    ///
+    /// The first report is created the first time the stage runs.
+    #[must_use]
+    pub fn new(reporter: LlvmCovReporter, interval: Duration) -> Self {
+        Self {
+            reporter,
+            interval,
+            last_report: Duration::ZERO,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The [`LlvmCovReporter`] of this stage
+    #[must_use]
+    pub fn reporter(&self) -> &LlvmCovReporter {
+        &self.reporter
+    }
+
+    /// Replay all corpus entries added since the last report and refresh the reports now,
+    /// regardless of the interval. Call this e.g. at the end of a campaign.
+    pub fn update_report<S>(&mut self, state: &mut S) -> Result<(), Error>
+    where
+        I: Clone + HasTargetBytes,
+        S: HasCorpus<I> + HasMetadata,
+    {
+        let last_corpus = state
+            .metadata_map()
+            .get::<SourceCoverageMetadata>()
+            .and_then(|meta| meta.last_corpus);
+        let mut corpus_id = next_to_replay::<_, I>(state.corpus(), last_corpus);
+
+        let mut last_corpus = None;
+        while let Some(id) = corpus_id {
+            let input = state.corpus().cloned_input_for_id(id)?;
+            self.reporter.replay(input.target_bytes().as_slice())?;
+            last_corpus = Some(id);
+            corpus_id = state.corpus().next(id);
+        }
+
+        if let Some(last_corpus) = last_corpus {
+            self.reporter.merge()?;
+            self.reporter.report()?;
+            state.metadata_map_mut().insert(SourceCoverageMetadata {
+                last_corpus: Some(last_corpus),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The first corpus entry after `last_corpus`, which may have been removed from the corpus since
+fn next_to_replay<C, I>(corpus: &C, last_corpus: Option<CorpusId>) -> Option<CorpusId>
+where
+    C: Corpus<I>,
+{
+    match last_corpus {
+        Some(last_corpus) if corpus.get(last_corpus).is_ok() => corpus.next(last_corpus),
+        Some(last_corpus) => {
+            // ids grow with every added entry, so skip over everything up to the removed one
+            let mut corpus_id = corpus.first();
+            while let Some(id) = corpus_id {
+                if id > last_corpus {
+                    break;
+                }
+                corpus_id = corpus.next(id);
+            }
+            corpus_id
+        }
+        None => corpus.first(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::time::Instant;
+
+    use super::{LlvmCovReporter, next_to_replay};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        inputs::BytesInput,
+    };
+
+    #[test]
+    fn test_next_to_replay() {
+        let mut corpus = InMemoryCorpus::<BytesInput>::new();
+        assert_eq!(next_to_replay(&corpus, None), None);
+
+        let ids: Vec<_> = (0..4)
+            .map(|i| corpus.add(Testcase::new(BytesInput::new(vec![i]))).unwrap())
+            .collect();
+        assert_eq!(next_to_replay(&corpus, None), Some(ids[0]));
+        assert_eq!(next_to_replay(&corpus, Some(ids[1])), Some(ids[2]));
+        assert_eq!(next_to_replay(&corpus, Some(ids[3])), None);
+
+        // the last replayed entry is gone, continue after it anyway
+        corpus.remove(ids[1]).unwrap();
+        assert_eq!(next_to_replay(&corpus, Some(ids[1])), Some(ids[2]));
+        corpus.remove(ids[3]).unwrap();
+        assert_eq!(next_to_replay(&corpus, Some(ids[3])), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_replay_unread_stdin() {
+        let output_dir = std::env::temp_dir().join(format!(
+            "libafl_source_coverage_stdin_test_{}",
+            std::process::id()
+        ));
+        let mut reporter = LlvmCovReporter::new("sleep", &output_dir)
+            .unwrap()
+            .args(["10"])
+            .timeout(Duration::from_millis(100));
+
+        // far more than fits into the pipe, and never read by the target
+        let start = Instant::now();
+        reporter.replay(&vec![0x41; 1 << 22]).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        std::fs::remove_dir_all(output_dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_without_profiles() {
+        let output_dir = std::env::temp_dir().join(format!(
+            "libafl_source_coverage_test_{}",
+            std::process::id()
+        ));
+        let mut reporter = LlvmCovReporter::new("/nonexistent", &output_dir)
+            .unwrap()
+            .llvm_profdata("/nonexistent");
+
+        // nothing was replayed, so nothing is merged and there is nothing to report on
+        reporter.merge().unwrap();
+        assert!(!reporter.profdata_path().exists());
+        assert!(reporter.report().is_err());
+
+        std::fs::remove_dir_all(output_dir).unwrap();
+    }
+}
@@ -14,6 +14,9 @@ pub use unix_signals::CTRL_C_EXIT;
 #[cfg(all(unix, feature = "alloc"))]
 pub mod pipes;
 
+#[cfg(all(unix, feature = "std"))]
+pub mod resource_limits;
+
 #[cfg(all(unix, feature = "std"))]
 use alloc::{borrow::Cow, ffi::CString};
 #[cfg(all(unix, feature = "std"))]
@@ -53,6 +56,10 @@ pub struct ChildHandle {
 /// The special exit code when the target signal handler is crashing recursively
 pub const SIGNAL_RECURSION_EXIT: i32 = 101;
 
+/// The special exit code when the target hit one of the [`resource_limits::ResourceLimits`]
+#[cfg(all(unix, feature = "std"))]
+pub const RESOURCE_LIMIT_EXIT: i32 = 102;
+
 #[cfg(unix)]
 impl ChildHandle {
     /// Block until the child exited and the status code becomes available
@@ -64,6 +71,34 @@ impl ChildHandle {
         }
         libc::WEXITSTATUS(status)
     }
+
+    /// Wait up to `timeout` for the child to exit.
+    /// Returns the status code, or `None` if the child is still running.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn wait_timeout(&self, timeout: core::time::Duration) -> Option<i32> {
+        let start = std::time::Instant::now();
+        loop {
+            let mut status = -1;
+            let pid = unsafe { libc::waitpid(self.pid, &raw mut status, libc::WNOHANG) };
+            if pid != 0 {
+                return Some(libc::WEXITSTATUS(status));
+            }
+            if start.elapsed() >= timeout {
+                return None;
+            }
+            std::thread::sleep(core::time::Duration::from_millis(10));
+        }
+    }
+
+    /// Kill the child with `SIGKILL` and reap it
+    pub fn kill(&self) {
+        let mut status = -1;
+        unsafe {
+            libc::kill(self.pid, libc::SIGKILL);
+            libc::waitpid(self.pid, &raw mut status, 0);
+        }
+    }
 }
 
 /// The `ForkResult` (result of a fork)
@@ -212,3 +247,31 @@ pub fn null_fd() -> Result<RawFd, Error> {
         Ok(NULL_FILE.get_or_init(move || null_file).as_raw_fd())
     }
 }
+
+#[cfg(all(test, unix, feature = "std"))]
+mod tests {
+    use core::time::Duration;
+
+    use super::{ForkResult, fork};
+
+    #[test]
+    fn test_child_wait_timeout() {
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Parent(child) => {
+                assert_eq!(child.wait_timeout(Duration::from_millis(50)), None);
+                child.kill();
+            }
+            ForkResult::Child => {
+                std::thread::sleep(Duration::from_secs(10));
+                unsafe { libc::_exit(0) };
+            }
+        }
+
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Parent(child) => {
+                assert_eq!(child.wait_timeout(Duration::from_secs(10)), Some(42));
+            }
+            ForkResult::Child => unsafe { libc::_exit(42) },
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Per-process resource limits, applied via `setrlimit` and, on `Linux`, a cgroup v2 sub-hierarchy.
+//!
+//! A leaking or runaway target should only take down its own fuzzer process, not the whole machine.
+//! [`ResourceLimits`] are applied in the fuzzing process, right after it got (re)spawned.
+//! Once a limit is active, [`limit_hit`] can tell if a crash was caused by it.
+
+use core::{
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+#[cfg(target_os = "linux")]
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, os::unix_signals::Signal};
+
+/// If a memory limit has been applied to this process
+static MEMORY_LIMIT_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// If a cpu time limit has been applied to this process
+static CPU_TIME_LIMIT_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// If an open files limit has been applied to this process
+static OPEN_FILES_LIMIT_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// The time a process may continue to run after it received `SIGXCPU`, before the kernel kills it.
+/// This gives the signal handler the chance to store the state.
+const CPU_TIME_GRACE_SECS: u64 = 5;
+
+/// The resource limits of a single fuzzer process.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    memory_limit: Option<u64>,
+    cpu_time_limit: Option<Duration>,
+    open_files_limit: Option<u64>,
+    #[cfg(target_os = "linux")]
+    cgroup_parent: Option<PathBuf>,
+}
+
+impl ResourceLimits {
+    /// Create new [`ResourceLimits`], without any limit
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the memory of the process to `memory_limit` bytes.
+    ///
+    /// Via `setrlimit`, this limits the address space (`RLIMIT_AS`, `RLIMIT_RSS` on `OpenBSD`).
+    /// Note that this includes all shared maps of the process, so leave some room for them.
+    /// If a cgroup is used as well, the cgroup limits the actual memory usage.
+    #[must_use]
+    pub fn with_memory_limit(mut self, memory_limit: u64) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
+
+    /// Limit the memory of the process to `memory_limit_mb` megabytes, see [`Self::with_memory_limit`].
+    #[must_use]
+    pub fn with_memory_limit_mb(self, memory_limit_mb: u64) -> Self {
+        self.with_memory_limit(memory_limit_mb << 20)
+    }
+
+    /// Limit the cpu time of the process (`RLIMIT_CPU`).
+    ///
+    /// This is the total cpu time of one process, not of a single execution.
+    /// Once the limit is hit, the process stores its state and gets respawned.
+    #[must_use]
+    pub fn with_cpu_time_limit(mut self, cpu_time_limit: Duration) -> Self {
+        self.cpu_time_limit = Some(cpu_time_limit);
+        self
+    }
+
+    /// Limit the number of open file descriptors of the process (`RLIMIT_NOFILE`)
+    #[must_use]
+    pub fn with_open_files_limit(mut self, open_files_limit: u64) -> Self {
+        self.open_files_limit = Some(open_files_limit);
+        self
+    }
+
+    /// Put each process into its own cgroup v2, below the (delegated) cgroup at `cgroup_parent`.
+    ///
+    /// The memory limit is then enforced for the actual memory usage, and memory-hungry processes
+    /// get killed by the kernel's OOM killer instead of taking down the whole machine.
+    /// If the cgroup cannot be created, only the `setrlimit` limits are used.
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn with_cgroup_parent<P>(mut self, cgroup_parent: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.cgroup_parent = Some(cgroup_parent.into());
+        self
+    }
+
+    /// The memory limit, in bytes
+    #[must_use]
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+
+    /// The cpu time limit
+    #[must_use]
+    pub fn cpu_time_limit(&self) -> Option<Duration> {
+        self.cpu_time_limit
+    }
+
+    /// The open files limit
+    #[must_use]
+    pub fn open_files_limit(&self) -> Option<u64> {
+        self.open_files_limit
+    }
+
+    /// The parent of the per-process cgroups, if any
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn cgroup_parent(&self) -> Option<&Path> {
+        self.cgroup_parent.as_deref()
+    }
+
+    /// Apply the limits to the current process, using `setrlimit`.
+    ///
+    /// The limits are inherited by all children forked or spawned afterwards.
+    pub fn apply(&self) -> Result<(), Error> {
+        // # Safety
+        // `setrlimit` only reads the `rlimit` structs we pass in.
+        if let Some(memory_limit) = self.memory_limit {
+            let r = rlimit(memory_limit, memory_limit);
+            #[cfg(target_os = "openbsd")]
+            let ret = unsafe { libc::setrlimit(libc::RLIMIT_RSS, &raw const r) };
+            #[cfg(not(target_os = "openbsd"))]
+            let ret = unsafe { libc::setrlimit(libc::RLIMIT_AS, &raw const r) };
+            check_setrlimit(ret, ResourceLimit::Memory, memory_limit)?;
+            MEMORY_LIMIT_ACTIVE.store(true, Ordering::Relaxed);
+        }
+        if let Some(cpu_time_limit) = self.cpu_time_limit {
+            // Round up, a limit of 0 would kill us right away.
+            let secs = cpu_time_limit.as_secs().max(1);
+            let r = rlimit(secs, secs + CPU_TIME_GRACE_SECS);
+            let ret = unsafe { libc::setrlimit(libc::RLIMIT_CPU, &raw const r) };
+            check_setrlimit(ret, ResourceLimit::CpuTime, secs)?;
+            CPU_TIME_LIMIT_ACTIVE.store(true, Ordering::Relaxed);
+        }
+        if let Some(open_files_limit) = self.open_files_limit {
+            let r = rlimit(open_files_limit, open_files_limit);
+            let ret = unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raw const r) };
+            check_setrlimit(ret, ResourceLimit::OpenFiles, open_files_limit)?;
+            OPEN_FILES_LIMIT_ACTIVE.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+// libc::rlim_t is i64 in freebsd and trivial_numeric_casts check will failed
+#[allow(trivial_numeric_casts)] // on 32 bit it does not trigger
+fn rlimit(soft: u64, hard: u64) -> libc::rlimit {
+    libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    }
+}
+
+fn check_setrlimit(ret: libc::c_int, limit: ResourceLimit, value: u64) -> Result<(), Error> {
+    if ret < 0 {
+        Err(Error::last_os_error(format!(
+            "Could not set the {limit} limit to {value}"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// A resource limit that can be hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResourceLimit {
+    /// The memory limit
+    Memory,
+    /// The cpu time limit
+    CpuTime,
+    /// The open files limit
+    OpenFiles,
+}
+
+impl Display for ResourceLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceLimit::Memory => write!(f, "memory"),
+            ResourceLimit::CpuTime => write!(f, "cpu time"),
+            ResourceLimit::OpenFiles => write!(f, "open files"),
+        }
+    }
+}
+
+/// Check if a crash with `signal` was caused by one of the [`ResourceLimits`] applied to this process.
+///
+/// `errno` needs to be read at the very beginning of the signal handler, before anything else can overwrite it.
+/// For the cpu time limit, the kernel sends a dedicated signal. For the memory and open files limits,
+/// this is a heuristic: a failed allocation or `open` commonly ends in an `abort()` with `errno` still set
+/// to `ENOMEM` or `EMFILE`. Other crashes, such as a `NULL` dereference after a failed allocation,
+/// cannot reliably be told apart from regular bugs and are reported as crashes.
+/// The function is signal-safe.
+#[must_use]
+pub fn limit_hit(signal: Signal, errno: i32) -> Option<ResourceLimit> {
+    match signal {
+        Signal::SigXCpu if CPU_TIME_LIMIT_ACTIVE.load(Ordering::Relaxed) => {
+            Some(ResourceLimit::CpuTime)
+        }
+        Signal::SigAbort
+            if errno == libc::ENOMEM && MEMORY_LIMIT_ACTIVE.load(Ordering::Relaxed) =>
+        {
+            Some(ResourceLimit::Memory)
+        }
+        Signal::SigAbort
+            if errno == libc::EMFILE && OPEN_FILES_LIMIT_ACTIVE.load(Ordering::Relaxed) =>
+        {
+            Some(ResourceLimit::OpenFiles)
+        }
+        _ => None,
+    }
+}
+
+/// A cgroup v2 for a single fuzzer client.
+///
+/// The cgroup gets removed again on drop, by the process that created it.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct Cgroup {
+    path: PathBuf,
+    creator: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl Cgroup {
+    /// Create a new cgroup `name` below `parent`, limiting the memory of all processes in it to `memory_limit` bytes.
+    ///
+    /// The `parent` needs to be a cgroup v2 that is writable for the current user, e.g., a delegated `systemd` scope.
+    /// Swap is disabled for the cgroup, so that memory-hungry processes get killed fast.
+    pub fn create<P>(parent: P, name: &str, memory_limit: Option<u64>) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let parent = parent.as_ref();
+        if !parent.join("cgroup.controllers").exists() {
+            return Err(Error::illegal_argument(format!(
+                "{} is not a cgroup v2",
+                parent.display()
+            )));
+        }
+        // Enable the memory controller for our children. This may fail if it's enabled already, or not delegated to us.
+        if let Err(err) = fs::write(parent.join("cgroup.subtree_control"), "+memory") {
+            log::debug!(
+                "Could not enable the memory controller in {}: {err}",
+                parent.display()
+            );
+        }
+
+        let path = parent.join(name);
+        match fs::create_dir(&path) {
+            Ok(()) => (),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+        let cgroup = Self {
+            path,
+            creator: std::process::id(),
+        };
+
+        if let Some(memory_limit) = memory_limit {
+            fs::write(cgroup.path.join("memory.max"), memory_limit.to_string())?;
+            // Not all kernels have swap accounting
+            let _ = fs::write(cgroup.path.join("memory.swap.max"), "0");
+        }
+        Ok(cgroup)
+    }
+
+    /// The path of this cgroup
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the process `pid` into the cgroup at `path`
+    pub fn add_process<P>(path: P, pid: u32) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(path.as_ref().join("cgroup.procs"), pid.to_string())?;
+        Ok(())
+    }
+
+    /// The number of processes in this cgroup the kernel's OOM killer killed so far
+    pub fn oom_kills(&self) -> Result<u64, Error> {
+        let events = fs::read_to_string(self.path.join("memory.events"))?;
+        for line in events.lines() {
+            if let Some(count) = line.strip_prefix("oom_kill ") {
+                return count.trim().parse().map_err(|_| {
+                    Error::illegal_state(format!(
+                        "Invalid oom_kill count in {}",
+                        self.path.display()
+                    ))
+                });
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Forked children inherit the handle, only the creator should clean up.
+        if self.creator == std::process::id() {
+            // This fails as long as processes remain in the cgroup, nothing we can do about it here.
+            let _ = fs::remove_dir(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{ResourceLimit, ResourceLimits, limit_hit};
+    use crate::os::unix_signals::Signal;
+
+    #[test]
+    fn test_resource_limits() {
+        let limits = ResourceLimits::new()
+            .with_memory_limit_mb(2)
+            .with_cpu_time_limit(Duration::from_secs(3600))
+            .with_open_files_limit(4096);
+        assert_eq!(limits.memory_limit(), Some(2 * 1024 * 1024));
+
+        assert_eq!(limit_hit(Signal::SigXCpu, 0), None);
+        assert_eq!(limit_hit(Signal::SigAbort, libc::ENOMEM), None);
+
+        assert_eq!(limit_hit(Signal::SigSegmentationFault, libc::ENOMEM), None);
+    }
+
+    #[test]
+    fn test_apply_resource_limits() {
+        // Applied limits stay for the whole process, so only apply them in a forked child.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0, "fork failed");
+        if pid == 0 {
+            let applied = ResourceLimits::new()
+                .with_cpu_time_limit(Duration::from_secs(3600))
+                .apply()
+                .is_ok();
+            let ok = applied
+                && limit_hit(Signal::SigXCpu, 0) == Some(ResourceLimit::CpuTime)
+                && limit_hit(Signal::SigAbort, libc::ENOMEM).is_none();
+            unsafe { libc::_exit(i32::from(!ok)) };
+        }
+
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &raw mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+}
@@ -260,7 +260,7 @@ use libc::{
 };
 use libc::{
     SIGABRT, SIGALRM, SIGBUS, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGKILL, SIGPIPE, SIGQUIT, SIGSEGV,
-    SIGTERM, SIGTRAP, SIGUSR2, c_int,
+    SIGTERM, SIGTRAP, SIGUSR2, SIGXCPU, c_int,
 };
 pub use libc::{c_void, siginfo_t};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -305,6 +305,8 @@ pub enum Signal {
     SigInterrupt = SIGINT,
     /// `SIGTRAP` signal id
     SigTrap = SIGTRAP,
+    /// `SIGXCPU` signal id
+    SigXCpu = SIGXCPU,
 }
 
 #[cfg(feature = "std")]
@@ -339,6 +341,7 @@ impl TryFrom<&str> for Signal {
             "SIGTERM" => Signal::SigTerm,
             "SIGINT" => Signal::SigInterrupt,
             "SIGTRAP" => Signal::SigTrap,
+            "SIGXCPU" => Signal::SigXCpu,
             _ => return Err(Error::illegal_argument(format!("No signal named {value}"))),
         })
     }
@@ -379,6 +382,7 @@ impl Display for Signal {
             Signal::SigTerm => write!(f, "SIGTERM")?,
             Signal::SigInterrupt => write!(f, "SIGINT")?,
             Signal::SigTrap => write!(f, "SIGTRAP")?,
+            Signal::SigXCpu => write!(f, "SIGXCPU")?,
         }
 
         Ok(())
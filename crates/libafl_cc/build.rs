@@ -8,6 +8,9 @@ use core::str;
     feature = "cmplog-instructions",
     feature = "ctx",
     feature = "dump-cfg",
+    feature = "split-compares",
+    feature = "memlock",
+    feature = "dataflow",
 ))]
 use std::path::PathBuf;
 use std::{env, fs::File, io::Write, path::Path, process::Command};
@@ -33,6 +36,9 @@ const LLVM_VERSION_MIN: u32 = 15;
     feature = "cmplog-instructions",
     feature = "ctx",
     feature = "dump-cfg",
+    feature = "split-compares",
+    feature = "memlock",
+    feature = "dataflow",
 ))]
 fn dll_extension<'a>() -> &'a str {
     if let Ok(vendor) = env::var("CARGO_CFG_TARGET_VENDOR") {
@@ -165,6 +171,9 @@ fn find_llvm_version() -> Option<i32> {
     feature = "cmplog-instructions",
     feature = "ctx",
     feature = "dump-cfg",
+    feature = "split-compares",
+    feature = "memlock",
+    feature = "dataflow",
 ))]
 #[expect(clippy::too_many_arguments)]
 fn build_pass(
@@ -548,6 +557,66 @@ pub const LIBAFL_CC_LLVM_VERSION: Option<usize> = None;
         false,
     );
 
+    #[cfg(feature = "split-compares")]
+    build_pass(
+        bindir_path,
+        out_dir,
+        &cxxflags,
+        &ldflags,
+        src_dir,
+        "compare-transform-pass.cc",
+        None,
+        true,
+    );
+
+    #[cfg(feature = "split-compares")]
+    build_pass(
+        bindir_path,
+        out_dir,
+        &cxxflags,
+        &ldflags,
+        src_dir,
+        "split-switches-pass.cc",
+        None,
+        true,
+    );
+
+    #[cfg(feature = "split-compares")]
+    build_pass(
+        bindir_path,
+        out_dir,
+        &cxxflags,
+        &ldflags,
+        src_dir,
+        "split-compares-pass.cc",
+        None,
+        true,
+    );
+
+    #[cfg(feature = "memlock")]
+    build_pass(
+        bindir_path,
+        out_dir,
+        &cxxflags,
+        &ldflags,
+        src_dir,
+        "memlock-pass.cc",
+        None,
+        true,
+    );
+
+    #[cfg(feature = "dataflow")]
+    build_pass(
+        bindir_path,
+        out_dir,
+        &cxxflags,
+        &ldflags,
+        src_dir,
+        "dataflow-pass.cc",
+        None,
+        true,
+    );
+
     cc::Build::new()
         .file(src_dir.join("no-link-rt.c"))
         .compile("no-link-rt");
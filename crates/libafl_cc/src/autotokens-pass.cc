@@ -143,7 +143,7 @@ PreservedAnalyses AutoTokensPass::run(Module &M, ModuleAnalysisManager &MAM) {
   /* Instrument all the things! */
 
   for (auto &F : M) {
-    if (isIgnoreFunction(&F)) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
 
     /*  Some implementation notes.
      *
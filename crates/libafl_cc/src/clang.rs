@@ -1,9 +1,18 @@
 //! LLVM compiler Wrapper from `LibAFL`
 
 use core::{env, str::FromStr};
-use std::path::{Path, PathBuf};
-
-use crate::{CompilerWrapper, Error, LIB_EXT, LIB_PREFIX, ToolWrapper};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    CompilerWrapper, Error, LIB_EXT, LIB_PREFIX, ToolWrapper,
+    instrument_list::{
+        AFL_ALLOWLIST_ENV, AFL_DENYLIST_ENV, ALLOWLIST_ENV, DENYLIST_ENV, InstrumentList,
+    },
+};
 
 /// The `OUT_DIR` for `LLVM` compiler passes
 pub const OUT_DIR: &str = env!("OUT_DIR");
@@ -20,6 +29,23 @@ fn dll_extension<'a>() -> &'a str {
 
 include!(concat!(env!("OUT_DIR"), "/clang_constants.rs"));
 
+/// Read a list path from `var`, falling back to its AFL++ name
+fn instrument_list_from_env(var: &str, afl_var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .or_else(|| std::env::var_os(afl_var))
+        .filter(|path| !path.is_empty())
+        .map(|path| std::path::absolute(&path).unwrap_or_else(|_| PathBuf::from(path)))
+}
+
+/// Convert an allow or deny list for clang's sancov, into a file owned by this process
+fn write_sancov_list(list_path: &Path, allow: bool) -> Result<PathBuf, Error> {
+    let content = InstrumentList::from_file(list_path)?.to_sancov_list(allow);
+    let kind = if allow { "allowlist" } else { "ignorelist" };
+    let path = std::env::temp_dir().join(format!("libafl-sancov-{kind}-{}.txt", process::id()));
+    fs::write(&path, content).map_err(Error::Io)?;
+    Ok(path)
+}
+
 /// The supported LLVM passes
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LLVMPasses {
@@ -39,6 +65,19 @@ pub enum LLVMPasses {
     Ctx,
     /// Function logging
     FunctionLogging,
+    /// Rewrite `strcmp`, `memcmp` and friends against constant strings into byte-wise compares.
+    ///
+    /// Passes run in the order they are added; add this one before [`LLVMPasses::SplitSwitches`]
+    /// and [`LLVMPasses::SplitCompares`].
+    TransformCompares,
+    /// Split switches on wide integers into nested byte-wise compares
+    SplitSwitches,
+    /// Split wide integer compares against constants into byte-wise compare chains
+    SplitCompares,
+    /// Record call depth and allocation sizes for `libafl_targets`' `memlock` runtime
+    MemLock,
+    /// Record def-use pairs of loads and stores for `libafl_targets`' `dataflow` runtime
+    Dataflow,
 }
 
 impl LLVMPasses {
@@ -65,6 +104,18 @@ impl LLVMPasses {
             LLVMPasses::FunctionLogging => {
                 PathBuf::from(env!("OUT_DIR")).join(format!("function-logging.{}", dll_extension()))
             }
+            LLVMPasses::TransformCompares => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("compare-transform-pass.{}", dll_extension())),
+            LLVMPasses::SplitSwitches => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("split-switches-pass.{}", dll_extension())),
+            LLVMPasses::SplitCompares => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("split-compares-pass.{}", dll_extension())),
+            LLVMPasses::MemLock => {
+                PathBuf::from(env!("OUT_DIR")).join(format!("memlock-pass.{}", dll_extension()))
+            }
+            LLVMPasses::Dataflow => {
+                PathBuf::from(env!("OUT_DIR")).join(format!("dataflow-pass.{}", dll_extension()))
+            }
         }
     }
 }
@@ -98,6 +149,9 @@ pub struct ClangWrapper {
     passes: Vec<LLVMPasses>,
     passes_args: Vec<String>,
     passes_linking_args: Vec<String>,
+    allowlist: Option<PathBuf>,
+    denylist: Option<PathBuf>,
+    sancov_lists: Vec<PathBuf>,
 }
 
 #[expect(clippy::match_same_arms)] // for the linking = false wip for "shared"
@@ -423,6 +477,22 @@ impl ToolWrapper for ClangWrapper {
                 args.push(passes_arg.into());
             }
         }
+        if !self.is_asm
+            && args
+                .iter()
+                .any(|arg| arg.starts_with("-fsanitize-coverage="))
+        {
+            if let Some(allowlist) = &self.allowlist {
+                let list = write_sancov_list(allowlist, true)?;
+                args.push(format!("-fsanitize-coverage-allowlist={}", list.display()));
+                self.sancov_lists.push(list);
+            }
+            if let Some(denylist) = &self.denylist {
+                let list = write_sancov_list(denylist, false)?;
+                args.push(format!("-fsanitize-coverage-ignorelist={}", list.display()));
+                self.sancov_lists.push(list);
+            }
+        }
         if self.linking {
             if self.x_set {
                 args.push("-x".into());
@@ -466,6 +536,23 @@ impl ToolWrapper for ClangWrapper {
     fn is_silent(&self) -> bool {
         self.is_silent
     }
+
+    fn cleanup(&mut self) {
+        for list in self.sancov_lists.drain(..) {
+            let _ = fs::remove_file(list);
+        }
+    }
+
+    fn envs(&self) -> Vec<(String, String)> {
+        let mut envs = vec![];
+        if let Some(allowlist) = &self.allowlist {
+            envs.push((ALLOWLIST_ENV.into(), allowlist.display().to_string()));
+        }
+        if let Some(denylist) = &self.denylist {
+            envs.push((DENYLIST_ENV.into(), denylist.display().to_string()));
+        }
+        envs
+    }
 }
 
 impl CompilerWrapper for ClangWrapper {
@@ -545,6 +632,9 @@ impl ClangWrapper {
             passes: vec![],
             passes_args: vec![],
             passes_linking_args: vec![],
+            allowlist: instrument_list_from_env(ALLOWLIST_ENV, AFL_ALLOWLIST_ENV),
+            denylist: instrument_list_from_env(DENYLIST_ENV, AFL_DENYLIST_ENV),
+            sancov_lists: vec![],
             is_silent: false,
         }
     }
@@ -608,6 +698,34 @@ impl ClangWrapper {
         self.need_libafl_arg = value;
         self
     }
+
+    /// Only instrument the source files and functions in this AFL++-style allowlist.
+    ///
+    /// Honored by all [`LLVMPasses`] and, if enabled, sancov. Defaults to the file in the
+    /// `LIBAFL_ALLOWLIST` (or `AFL_LLVM_ALLOWLIST`) env var.
+    /// See [`InstrumentList`] for the format.
+    pub fn instrument_allowlist<P>(&mut self, path: P) -> &'_ mut Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        self.allowlist = Some(std::path::absolute(path).unwrap_or_else(|_| path.into()));
+        self
+    }
+
+    /// Never instrument the source files and functions in this AFL++-style denylist.
+    ///
+    /// Honored by all [`LLVMPasses`] and, if enabled, sancov. Defaults to the file in the
+    /// `LIBAFL_DENYLIST` (or `AFL_LLVM_DENYLIST`) env var.
+    /// See [`InstrumentList`] for the format.
+    pub fn instrument_denylist<P>(&mut self, path: P) -> &'_ mut Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        self.denylist = Some(std::path::absolute(path).unwrap_or_else(|_| path.into()));
+        self
+    }
 }
 
 #[cfg(test)]
@@ -156,7 +156,7 @@ bool CmpLogRoutines::hookRtns(Module &M) {
 
   /* iterate over all functions, bbs and instruction and add suitable calls */
   for (auto &F : M) {
-    if (isIgnoreFunction(&F)) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
 
     for (auto &BB : F) {
       for (auto &IN : BB) {
@@ -3,6 +3,7 @@
 
 #include <stdio.h>
 #include <stdlib.h>
+#include <string.h>
 
 #include "llvm/Config/llvm-config.h"
 
@@ -28,6 +29,11 @@ constexpr std::nullopt_t None = std::nullopt;
 #include "llvm/Passes/PassPlugin.h"
 #include "llvm/Passes/PassBuilder.h"
 #include "llvm/IR/PassManager.h"
+#include "llvm/Support/GlobPattern.h"
+
+#include <fstream>
+#include <string>
+#include <vector>
 
 #define FATAL(...)                          \
   do {                                      \
@@ -103,4 +109,122 @@ static inline bool isIgnoreFunction(const llvm::Function *F) {
   return false;
 }
 
+/* AFL++ compatible allow and deny lists, read from the files named by the
+   LIBAFL_ALLOWLIST and LIBAFL_DENYLIST environment variables. Each line holds
+   a source file (`src:` or `source:`, also the default) or a function name
+   (`fun:` or `function:`). Entries containing `*`, `?` or `[` are globs, plain
+   source entries match as path suffix. `#` starts a comment line.
+   The semantics are the ones of clang's sancov lists, so that the passes and
+   sancov instrument the same functions: the source is the translation unit, an
+   allowlist needs both a source and a function entry to match (a missing kind
+   matches everything), and a denylist needs either. */
+struct InstrumentList {
+  bool                     loaded = false;
+  std::vector<std::string> sources;
+  std::vector<std::string> functions;
+};
+
+static inline std::string trimInstrumentListEntry(const std::string &s) {
+  size_t start = s.find_first_not_of(" \t\r\n");
+  if (start == std::string::npos) { return ""; }
+  size_t end = s.find_last_not_of(" \t\r\n");
+  return s.substr(start, end - start + 1);
+}
+
+static inline InstrumentList loadInstrumentList(const char *env) {
+  InstrumentList list;
+  const char    *path = getenv(env);
+  if (!path || !*path) { return list; }
+
+  std::ifstream file(path);
+  if (!file.is_open()) { FATAL("Could not open %s=%s\n", env, path); }
+  list.loaded = true;
+
+  static constexpr const char *funPrefixes[] = {"fun:", "function:"};
+  static constexpr const char *srcPrefixes[] = {"src:", "source:"};
+
+  std::string line;
+  while (std::getline(file, line)) {
+    line = trimInstrumentListEntry(line);
+    if (line.empty() || line[0] == '#') { continue; }
+
+    std::vector<std::string> *target = &list.sources;
+    for (auto const &prefix : funPrefixes) {
+      if (line.rfind(prefix, 0) == 0) {
+        target = &list.functions;
+        line = line.substr(strlen(prefix));
+      }
+    }
+    for (auto const &prefix : srcPrefixes) {
+      if (line.rfind(prefix, 0) == 0) { line = line.substr(strlen(prefix)); }
+    }
+
+    line = trimInstrumentListEntry(line);
+    if (!line.empty()) { target->push_back(line); }
+  }
+
+  return list;
+}
+
+static inline bool matchesInstrumentListEntry(const std::string &pattern,
+                                              const std::string &value,
+                                              bool               suffix) {
+  if (pattern.find_first_of("*?[") != std::string::npos) {
+    auto glob = llvm::GlobPattern::create(pattern);
+    if (!glob) {
+      llvm::consumeError(glob.takeError());
+      return false;
+    }
+    return glob->match(value);
+  }
+  if (suffix) {
+    return value.size() >= pattern.size() &&
+           value.compare(value.size() - pattern.size(), std::string::npos,
+                         pattern) == 0;
+  }
+  return value == pattern;
+}
+
+static inline bool matchesAnyInstrumentListEntry(
+    const std::vector<std::string> &entries, const std::string &value,
+    bool suffix) {
+  for (auto const &entry : entries) {
+    if (matchesInstrumentListEntry(entry, value, suffix)) { return true; }
+  }
+  return false;
+}
+
+/* Only checks the allow and deny lists, without the built-in ignore list.
+   Filename is the module source file, as for sancov. */
+static inline bool isInAllowDenyLists(const llvm::Function *F,
+                                      const std::string    &Filename) {
+  static const InstrumentList allowList = loadInstrumentList("LIBAFL_ALLOWLIST");
+  static const InstrumentList denyList = loadInstrumentList("LIBAFL_DENYLIST");
+
+  if (!allowList.loaded && !denyList.loaded) { return true; }
+
+  std::string function = F->getName().str();
+
+  if (denyList.loaded &&
+      (matchesAnyInstrumentListEntry(denyList.functions, function, false) ||
+       matchesAnyInstrumentListEntry(denyList.sources, Filename, true))) {
+    return false;
+  }
+  if (allowList.loaded) {
+    return (allowList.functions.empty() ||
+            matchesAnyInstrumentListEntry(allowList.functions, function,
+                                          false)) &&
+           (allowList.sources.empty() ||
+            matchesAnyInstrumentListEntry(allowList.sources, Filename, true));
+  }
+  return true;
+}
+
+/* Whether a libafl_cc pass should touch this function at all */
+static inline bool isInInstrumentList(const llvm::Function *F,
+                                      const std::string    &Filename) {
+  if (isIgnoreFunction(F)) { return false; }
+  return isInAllowDenyLists(F, Filename);
+}
+
 #endif  // LIBAFL_COMMON_LLVM_H
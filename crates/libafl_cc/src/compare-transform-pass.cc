@@ -0,0 +1,232 @@
+/*
+   LibAFL - Compare transform LLVM pass
+   --------------------------------------------------
+
+   Based on the laf-intel compare-transform pass shipped with AFL++.
+
+   Copyright 2016 laf-intel
+   Copyright 2019-2024 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   Rewrites calls to strcmp, strncmp, strcasecmp, strncasecmp, memcmp and bcmp
+   where one argument is a constant string into a chain of single byte
+   compares, so coverage rewards every matched byte.
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include "common-llvm.h"
+
+#include <string>
+#include <vector>
+
+using namespace llvm;
+
+static cl::opt<unsigned> TransformMaxLen(
+    "transform_compares_max_len",
+    cl::desc("Do not transform compares of constants longer than this"),
+    cl::init(128), cl::NotHidden);
+
+namespace {
+
+class CompareTransform : public PassInfoMixin<CompareTransform> {
+ public:
+  CompareTransform() {
+  }
+
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+
+ private:
+  enum class CmpKind { Str, StrN, Mem };
+
+  struct Candidate {
+    CallInst *Call;
+    CmpKind   Kind;
+    bool      CaseInsensitive;
+  };
+
+  bool transformCall(Module &M, const Candidate &Cand);
+};
+
+}  // namespace
+
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "CompareTransform", "v0.1",
+          /* lambda to insert our pass into the pass pipeline. */
+          [](PassBuilder &PB) {
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL
+#if LLVM_VERSION_MAJOR >= 20
+                   ,
+                   ThinOrFullLTOPhase Phase
+#endif
+                ) { MPM.addPass(CompareTransform()); });
+          }};
+}
+
+static bool getConstantBytes(Value *V, StringRef &Str, bool TrimAtNul) {
+#if LLVM_VERSION_MAJOR >= 16
+  return getConstantStringInfo(V, Str, TrimAtNul);
+#else
+  return getConstantStringInfo(V, Str, 0, TrimAtNul);
+#endif
+}
+
+static uint8_t asciiLower(uint8_t c) {
+  if (c >= 'A' && c <= 'Z') { return c | 0x20; }
+  return c;
+}
+
+PreservedAnalyses CompareTransform::run(Module &M, ModuleAnalysisManager &MAM) {
+  std::vector<Candidate> Candidates;
+
+  for (auto &F : M) {
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        CallInst *CI = dyn_cast<CallInst>(&IN);
+        if (!CI) { continue; }
+
+        Function *Callee = CI->getCalledFunction();
+        if (!Callee || !CI->getType()->isIntegerTy()) { continue; }
+
+        StringRef     Name = Callee->getName();
+        FunctionType *FT = Callee->getFunctionType();
+        unsigned      NumParams = FT->getNumParams();
+
+        Candidate Cand = {CI, CmpKind::Str, false};
+        if (Name == "strcmp") {
+          Cand.Kind = CmpKind::Str;
+        } else if (Name == "strcasecmp") {
+          Cand.Kind = CmpKind::Str;
+          Cand.CaseInsensitive = true;
+        } else if (Name == "strncmp") {
+          Cand.Kind = CmpKind::StrN;
+        } else if (Name == "strncasecmp") {
+          Cand.Kind = CmpKind::StrN;
+          Cand.CaseInsensitive = true;
+        } else if (Name == "memcmp" || Name == "bcmp") {
+          Cand.Kind = CmpKind::Mem;
+        } else {
+          continue;
+        }
+
+        unsigned Expected = Cand.Kind == CmpKind::Str ? 2 : 3;
+        if (NumParams != Expected || !FT->getParamType(0)->isPointerTy() ||
+            !FT->getParamType(1)->isPointerTy()) {
+          continue;
+        }
+        if (Cand.Kind != CmpKind::Str &&
+            !FT->getParamType(2)->isIntegerTy()) {
+          continue;
+        }
+
+        Candidates.push_back(Cand);
+      }
+    }
+  }
+
+  bool Changed = false;
+  for (auto &Cand : Candidates) {
+    Changed |= transformCall(M, Cand);
+  }
+
+  if (!Changed) { return PreservedAnalyses::all(); }
+  return PreservedAnalyses::none();
+}
+
+bool CompareTransform::transformCall(Module &M, const Candidate &Cand) {
+  LLVMContext &C = M.getContext();
+  CallInst    *CI = Cand.Call;
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  IntegerType *RetTy = cast<IntegerType>(CI->getType());
+
+  Value *Str1P = CI->getArgOperand(0);
+  Value *Str2P = CI->getArgOperand(1);
+
+  // memcmp compares past NUL bytes, the string functions stop at them
+  bool      TrimAtNul = Cand.Kind != CmpKind::Mem;
+  StringRef Str1, Str2;
+  bool      IsConst1 = getConstantBytes(Str1P, Str1, TrimAtNul);
+  bool      IsConst2 = getConstantBytes(Str2P, Str2, TrimAtNul);
+
+  // Both constant is left to the optimizer, neither constant gives us nothing
+  // to compare against.
+  if (IsConst1 == IsConst2) { return false; }
+
+  bool      ConstFirst = IsConst1;
+  StringRef ConstStr = IsConst1 ? Str1 : Str2;
+  Value    *VarStr = IsConst1 ? Str2P : Str1P;
+
+  uint64_t Len;
+  if (Cand.Kind == CmpKind::Str) {
+    // include the terminating NUL byte
+    Len = ConstStr.size() + 1;
+  } else {
+    ConstantInt *N = dyn_cast<ConstantInt>(CI->getArgOperand(2));
+    if (!N) { return false; }
+    Len = N->getZExtValue();
+    if (Cand.Kind == CmpKind::StrN) {
+      if (ConstStr.size() + 1 < Len) { Len = ConstStr.size() + 1; }
+    } else if (Len > ConstStr.size()) {
+      return false;
+    }
+  }
+
+  if (Len == 0 || Len > TransformMaxLen) { return false; }
+
+  BasicBlock *BB = CI->getParent();
+  Function   *F = BB->getParent();
+  BasicBlock *End = BB->splitBasicBlock(CI, "cmp_transform_end");
+  BB->getTerminator()->eraseFromParent();
+
+  IRBuilder<> EndIRB(End, End->begin());
+  PHINode    *Result = EndIRB.CreatePHI(RetTy, Len, "cmp_transform_res");
+
+  BasicBlock *Cur = BB;
+  for (uint64_t i = 0; i < Len; i++) {
+    IRBuilder<> IRB(Cur);
+
+    uint8_t ConstByte = i < ConstStr.size() ? (uint8_t)ConstStr[i] : 0;
+    Value  *Ptr = IRB.CreateConstInBoundsGEP1_64(Int8Ty, VarStr, i);
+    Value  *VarByte = IRB.CreateLoad(Int8Ty, Ptr);
+
+    if (Cand.CaseInsensitive) {
+      ConstByte = asciiLower(ConstByte);
+      Value *Off = IRB.CreateSub(VarByte, ConstantInt::get(Int8Ty, 'A'));
+      Value *IsUpper = IRB.CreateICmpULT(Off, ConstantInt::get(Int8Ty, 26));
+      Value *Lower = IRB.CreateOr(VarByte, ConstantInt::get(Int8Ty, 0x20));
+      VarByte = IRB.CreateSelect(IsUpper, Lower, VarByte);
+    }
+
+    Value *ConstVal = ConstantInt::get(RetTy, ConstByte);
+    Value *VarVal = IRB.CreateZExt(VarByte, RetTy);
+    Value *Diff = ConstFirst ? IRB.CreateSub(ConstVal, VarVal)
+                             : IRB.CreateSub(VarVal, ConstVal);
+
+    bool Last = i == Len - 1 || (Cand.Kind != CmpKind::Mem && ConstByte == 0);
+    Result->addIncoming(Diff, Cur);
+    if (Last) {
+      IRB.CreateBr(End);
+      break;
+    }
+
+    BasicBlock *Next = BasicBlock::Create(C, "cmp_transform", F, End);
+    Value      *Ne = IRB.CreateICmpNE(VarByte, ConstantInt::get(Int8Ty, ConstByte));
+    IRB.CreateCondBr(Ne, End, Next);
+    Cur = Next;
+  }
+
+  CI->replaceAllUsesWith(Result);
+  CI->eraseFromParent();
+
+  return true;
+}
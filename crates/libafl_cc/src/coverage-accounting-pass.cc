@@ -257,7 +257,7 @@ PreservedAnalyses AFLCoverage::run(Module &M, ModuleAnalysisManager &MAM) {
       fprintf(stderr, "FUNCTION: %s (%zu)\n", F.getName().str().c_str(),
               F.size());
 
-    // if (!isInInstrumentList(&F)) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
 
     if (F.size() < function_minimum_size) { continue; }
 
@@ -103,7 +103,7 @@ PreservedAnalyses CtxPass::run(Module &M, ModuleAnalysisManager &MAM) {
   for (auto &F : M) {
     int has_calls = 0;
 
-    if (isIgnoreFunction(&F)) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
     if (F.size() < 1) { continue; }
     for (auto &BB : F) {
       BasicBlock::iterator IP = BB.getFirstInsertionPt();
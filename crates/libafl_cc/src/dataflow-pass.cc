@@ -0,0 +1,158 @@
+/*
+   LibAFL - Dataflow LLVM pass
+   --------------------------------------------------
+
+   Reports every instrumented store and load together with its address and a
+   site id, in the spirit of DDFuzz and DatAFLow. The runtime in
+   libafl_targets (feature `dataflow`) remembers the last store site per
+   address and hashes each def-use pair into a separate coverage map, so
+   inputs moving data along new paths are rewarded even when the control
+   flow is unchanged.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include "common-llvm.h"
+
+#include <functional>
+#include <string>
+#include <vector>
+
+#include "llvm/Analysis/ValueTracking.h"
+
+using namespace llvm;
+
+static cl::opt<uint32_t> DataflowRatio(
+    "dataflow_ratio",
+    cl::desc("Percentage of load and store sites to instrument (1-100)"),
+    cl::init(100), cl::NotHidden);
+
+namespace {
+
+class DataflowPass : public PassInfoMixin<DataflowPass> {
+ public:
+  DataflowPass() {
+  }
+
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+
+ private:
+  /* Only memory that may outlive the current frame carries interesting data
+     flow; locals that never escape are promoted to registers anyway, and
+     accesses inserted by other instrumentation are tagged nosanitize. */
+  bool shouldInstrument(Instruction *I, Value *Ptr) {
+    if (I->getMetadata(LLVMContext::MD_nosanitize)) { return false; }
+    if (Ptr->getType()->getPointerAddressSpace() != 0) { return false; }
+
+    const Value *Obj = getUnderlyingObject(Ptr);
+    if (isa<AllocaInst>(Obj)) { return false; }
+    if (auto *GV = dyn_cast<GlobalVariable>(Obj)) {
+      if (GV->isConstant()) { return false; }
+      StringRef Name = GV->getName();
+#if LLVM_VERSION_MAJOR >= 18
+      if (Name.starts_with("__sancov_") || Name.starts_with("libafl_") ||
+          Name.starts_with("__afl_") || Name.starts_with("llvm.")) {
+#else
+      if (Name.startswith("__sancov_") || Name.startswith("libafl_") ||
+          Name.startswith("__afl_") || Name.startswith("llvm.")) {
+#endif
+        return false;
+      }
+    }
+    return true;
+  }
+};
+
+}  // namespace
+
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "DataflowPass", "v0.1",
+          /* lambda to insert our pass into the pass pipeline. */
+          [](PassBuilder &PB) {
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL
+#if LLVM_VERSION_MAJOR >= 20
+                   ,
+                   ThinOrFullLTOPhase Phase
+#endif
+                ) { MPM.addPass(DataflowPass()); });
+          }};
+}
+
+PreservedAnalyses DataflowPass::run(Module &M, ModuleAnalysisManager &MAM) {
+  LLVMContext &C = M.getContext();
+  Type        *VoidTy = Type::getVoidTy(C);
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+  PointerType *i8PtrTy = PointerType::get(Int8Ty, 0);
+
+  if (!DataflowRatio || DataflowRatio > 100) {
+    FATAL("Bad value of the dataflow ratio (must be between 1 and 100)");
+  }
+
+  FunctionCallee storeHook =
+      M.getOrInsertFunction("__libafl_dataflow_store", VoidTy, i8PtrTy, Int32Ty);
+  FunctionCallee loadHook =
+      M.getOrInsertFunction("__libafl_dataflow_load", VoidTy, i8PtrTy, Int32Ty);
+
+  bool changed = false;
+
+  for (auto &F : M) {
+    if (F.isDeclaration()) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
+
+    std::vector<std::pair<Instruction *, Value *>> stores;
+    std::vector<std::pair<Instruction *, Value *>> loads;
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        if (auto *SI = dyn_cast<StoreInst>(&IN)) {
+          Value *Ptr = SI->getPointerOperand();
+          if (shouldInstrument(SI, Ptr)) { stores.push_back({SI, Ptr}); }
+        } else if (auto *LI = dyn_cast<LoadInst>(&IN)) {
+          Value *Ptr = LI->getPointerOperand();
+          if (shouldInstrument(LI, Ptr)) { loads.push_back({LI, Ptr}); }
+        }
+      }
+    }
+
+    // ids are stable across builds, so sampling picks the same sites and
+    // the map stays comparable between builds
+    std::string funcName = F.getName().str();
+    unsigned    siteIdx = 0;
+    auto        nextSite = [&](const char *kind) -> uint32_t {
+      uint32_t id = (uint32_t)std::hash<std::string>{}(
+          funcName + ":" + kind + std::to_string(siteIdx++));
+      // 0 marks memory without a known store
+      return id ? id : 1;
+    };
+
+    for (auto &[I, Ptr] : stores) {
+      uint32_t siteId = nextSite("s");
+      if (siteId % 100 >= DataflowRatio) { continue; }
+      IRBuilder<> IRB(I);
+      IRB.CreateCall(storeHook, {IRB.CreatePointerCast(Ptr, i8PtrTy),
+                                 ConstantInt::get(Int32Ty, siteId)});
+      changed = true;
+    }
+
+    for (auto &[I, Ptr] : loads) {
+      uint32_t siteId = nextSite("l");
+      if (siteId % 100 >= DataflowRatio) { continue; }
+      IRBuilder<> IRB(I);
+      IRB.CreateCall(loadHook, {IRB.CreatePointerCast(Ptr, i8PtrTy),
+                                ConstantInt::get(Int32Ty, siteId)});
+      changed = true;
+    }
+  }
+
+  if (!changed) { return PreservedAnalyses::all(); }
+  return PreservedAnalyses::none();
+}
@@ -99,6 +99,9 @@ PreservedAnalyses DumpCfgPass::run(Module &M, ModuleAnalysisManager &MAM) {
   auto         moduleName = M.getName();
 
   for (auto &F : M) {
+    // keep the CFG in line with what the other passes and sancov instrumented
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
+
     unsigned bb_cnt = 0;
     entry_bb[F.getName()] = &F.getEntryBlock();
     for (auto &BB : F) {
@@ -123,7 +123,7 @@ PreservedAnalyses FunctionLogging::run(Module &M, ModuleAnalysisManager &MAM) {
   for (auto &F : M) {
     int has_calls = 0;
 
-    if (isIgnoreFunction(&F)) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
     if (F.size() < 1) { continue; }
     // instrument the first basic block of this fn
     BasicBlock &entry = F.front();
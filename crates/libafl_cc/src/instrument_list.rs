@@ -0,0 +1,170 @@
+//! AFL++-compatible instrumentation allow and deny lists.
+//!
+//! Each line of a list file names a source file (`src:`, `source:` or no prefix) or a function
+//! (`fun:` or `function:`). Lines starting with `#` are comments. Entries containing `*`, `?` or
+//! `[` are globs, other source entries match as path suffix and other function entries match the
+//! (mangled) symbol name exactly.
+//!
+//! The `libafl_cc` passes follow the semantics of clang's sancov lists, so that both instrument
+//! the same functions: source entries match the translation unit (not the header a function is
+//! defined in), a function is allowed if it matches both a source and a function entry of the
+//! allowlist (a kind without entries matches everything), and denied if it matches any entry of
+//! the denylist.
+
+use std::{fmt::Write, fs, path::Path};
+
+use crate::Error;
+
+/// The env var holding the path of the allowlist, read by [`crate::ClangWrapper`] and the
+/// `libafl_cc` passes
+pub const ALLOWLIST_ENV: &str = "LIBAFL_ALLOWLIST";
+/// The env var holding the path of the denylist, read by [`crate::ClangWrapper`] and the
+/// `libafl_cc` passes
+pub const DENYLIST_ENV: &str = "LIBAFL_DENYLIST";
+/// AFL++ name of the allowlist env var, used if [`ALLOWLIST_ENV`] is not set
+pub const AFL_ALLOWLIST_ENV: &str = "AFL_LLVM_ALLOWLIST";
+/// AFL++ name of the denylist env var, used if [`DENYLIST_ENV`] is not set
+pub const AFL_DENYLIST_ENV: &str = "AFL_LLVM_DENYLIST";
+
+const FUNCTION_PREFIXES: [&str; 2] = ["fun:", "function:"];
+const SOURCE_PREFIXES: [&str; 2] = ["src:", "source:"];
+
+/// A parsed allow or deny list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstrumentList {
+    sources: Vec<String>,
+    functions: Vec<String>,
+}
+
+impl InstrumentList {
+    /// Parse a list in the AFL++ format
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut list = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (entry, is_function) = if let Some(entry) = FUNCTION_PREFIXES
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+            {
+                (entry, true)
+            } else if let Some(entry) = SOURCE_PREFIXES
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+            {
+                (entry, false)
+            } else {
+                (line, false)
+            };
+
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            if is_function {
+                list.functions.push(entry.to_string());
+            } else {
+                list.sources.push(entry.to_string());
+            }
+        }
+        list
+    }
+
+    /// Read and parse a list file
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::parse(&fs::read_to_string(path).map_err(Error::Io)?))
+    }
+
+    /// The source file entries
+    #[must_use]
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// The function entries
+    #[must_use]
+    pub fn functions(&self) -> &[String] {
+        &self.functions
+    }
+
+    /// Returns `true` if the list has no entries
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty() && self.functions.is_empty()
+    }
+
+    /// Convert the list to the special case list format of clang's
+    /// `-fsanitize-coverage-allowlist` and `-fsanitize-coverage-ignorelist`.
+    ///
+    /// Clang only instruments functions matching both a `src` and a `fun` entry of an allowlist,
+    /// so a missing kind is allowed as a whole, as in the `libafl_cc` passes.
+    #[must_use]
+    pub fn to_sancov_list(&self, allow: bool) -> String {
+        let mut out = String::new();
+        for source in &self.sources {
+            if is_glob(source) {
+                writeln!(out, "src:{source}").unwrap();
+            } else {
+                writeln!(out, "src:*{source}").unwrap();
+            }
+        }
+        for function in &self.functions {
+            writeln!(out, "fun:{function}").unwrap();
+        }
+        if allow {
+            if self.sources.is_empty() {
+                out.push_str("src:*\n");
+            }
+            if self.functions.is_empty() {
+                out.push_str("fun:*\n");
+            }
+        }
+        out
+    }
+}
+
+fn is_glob(entry: &str) -> bool {
+    entry.contains(['*', '?', '['])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstrumentList;
+
+    #[test]
+    fn test_parse() {
+        let list = InstrumentList::parse(
+            "# vendored code\n\
+             src: third_party/*\n\
+             source:parser.c\n\
+             lexer.c\n\
+             fun: main\n\
+             function:_Z4testv\n\
+             \n\
+             fun:\n",
+        );
+        assert_eq!(list.sources(), ["third_party/*", "parser.c", "lexer.c"]);
+        assert_eq!(list.functions(), ["main", "_Z4testv"]);
+    }
+
+    #[test]
+    fn test_to_sancov_list() {
+        let list = InstrumentList::parse("src: vendor/*\nparser.c\n");
+        assert_eq!(
+            list.to_sancov_list(true),
+            "src:vendor/*\nsrc:*parser.c\nfun:*\n"
+        );
+        assert_eq!(list.to_sancov_list(false), "src:vendor/*\nsrc:*parser.c\n");
+
+        let list = InstrumentList::parse("fun:main\n");
+        assert_eq!(list.to_sancov_list(true), "fun:main\nsrc:*\n");
+        assert!(InstrumentList::parse("# nothing\n").is_empty());
+    }
+}
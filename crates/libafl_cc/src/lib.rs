@@ -49,6 +49,8 @@ pub mod cfg;
 pub use cfg::{CfgEdge, ControlFlowGraph, EntryBasicBlockInfo, HasWeight};
 pub mod clang;
 pub use clang::{ClangWrapper, LLVMPasses};
+pub mod instrument_list;
+pub use instrument_list::InstrumentList;
 pub mod libtool;
 pub use libtool::LibtoolWrapper;
 
@@ -248,6 +250,14 @@ pub trait ToolWrapper {
     /// Returns `true` if `silence` was called with `true`
     fn is_silent(&self) -> bool;
 
+    /// Environment variables to set for the wrapped tool
+    fn envs(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Remove the temporary files created for the wrapped tool, called at the end of `run`
+    fn cleanup(&mut self) {}
+
     /// Run the tool
     fn run(&mut self) -> Result<Option<i32>, Error> {
         let mut last_status = Ok(None);
@@ -257,7 +267,13 @@ pub trait ToolWrapper {
             self.configurations()?
         };
         for configuration in configurations {
-            let mut args = self.command_for_configuration(configuration)?;
+            let mut args = match self.command_for_configuration(configuration) {
+                Ok(args) => args,
+                Err(e) => {
+                    self.cleanup();
+                    return Err(e);
+                }
+            };
             self.filter(&mut args);
 
             if !self.is_silent() {
@@ -269,7 +285,11 @@ pub trait ToolWrapper {
                 ));
                 continue;
             }
-            let status = match Command::new(&args[0]).args(&args[1..]).status() {
+            let status = match Command::new(&args[0])
+                .args(&args[1..])
+                .envs(self.envs())
+                .status()
+            {
                 Ok(s) => s,
                 Err(e) => {
                     last_status = Err(Error::Io(e));
@@ -281,6 +301,7 @@ pub trait ToolWrapper {
             }
             last_status = Ok(status.code());
         }
+        self.cleanup();
         last_status
     }
 }
@@ -0,0 +1,175 @@
+/*
+   LibAFL - MemLock LLVM pass
+   --------------------------------------------------
+
+   Records the call depth at every function entry and the size of every heap
+   allocation, in the spirit of MemLock (ICSE 2020), so the fuzzer can be
+   guided towards uncontrolled recursion and memory consumption bugs.
+   The runtime lives in libafl_targets (feature `memlock`).
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include "common-llvm.h"
+
+#include <functional>
+#include <string>
+#include <vector>
+
+using namespace llvm;
+
+namespace {
+
+/* How to compute the requested size of an allocation function: the size
+   argument, optionally multiplied with a count argument. */
+struct AllocFn {
+  const char *name;
+  unsigned    size_arg;
+  int         count_arg;
+};
+
+static constexpr AllocFn allocFns[] = {
+    {"malloc", 0, -1},
+    {"valloc", 0, -1},
+    {"pvalloc", 0, -1},
+    {"calloc", 1, 0},
+    {"realloc", 1, -1},
+    {"reallocarray", 2, 1},
+    {"aligned_alloc", 1, -1},
+    {"memalign", 1, -1},
+    {"posix_memalign", 2, -1},
+    // operator new and new[], with and without nothrow and alignment
+    {"_Znwm", 0, -1},
+    {"_Znam", 0, -1},
+    {"_Znwj", 0, -1},
+    {"_Znaj", 0, -1},
+    {"_ZnwmRKSt9nothrow_t", 0, -1},
+    {"_ZnamRKSt9nothrow_t", 0, -1},
+    {"_ZnwmSt11align_val_t", 0, -1},
+    {"_ZnamSt11align_val_t", 0, -1},
+    {"_ZnwmSt11align_val_tRKSt9nothrow_t", 0, -1},
+    {"_ZnamSt11align_val_tRKSt9nothrow_t", 0, -1},
+};
+
+class MemLockPass : public PassInfoMixin<MemLockPass> {
+ public:
+  MemLockPass() {
+  }
+
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+
+ private:
+  const AllocFn *getAllocFn(CallBase *CB) {
+    Function *Callee = CB->getCalledFunction();
+    if (!Callee) { return nullptr; }
+    StringRef Name = Callee->getName();
+    for (auto const &Fn : allocFns) {
+      if (Name == Fn.name) {
+        if (CB->arg_size() <= Fn.size_arg) { return nullptr; }
+        if (Fn.count_arg >= 0 && CB->arg_size() <= (unsigned)Fn.count_arg) {
+          return nullptr;
+        }
+        return &Fn;
+      }
+    }
+    return nullptr;
+  }
+};
+
+}  // namespace
+
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "MemLockPass", "v0.1",
+          /* lambda to insert our pass into the pass pipeline. */
+          [](PassBuilder &PB) {
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL
+#if LLVM_VERSION_MAJOR >= 20
+                   ,
+                   ThinOrFullLTOPhase Phase
+#endif
+                ) { MPM.addPass(MemLockPass()); });
+          }};
+}
+
+PreservedAnalyses MemLockPass::run(Module &M, ModuleAnalysisManager &MAM) {
+  LLVMContext &C = M.getContext();
+  Type        *VoidTy = Type::getVoidTy(C);
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+  IntegerType *Int64Ty = IntegerType::getInt64Ty(C);
+
+  FunctionCallee enterHook =
+      M.getOrInsertFunction("__libafl_memlock_func_enter", VoidTy, Int32Ty);
+  FunctionCallee exitHook =
+      M.getOrInsertFunction("__libafl_memlock_func_exit", VoidTy);
+  FunctionCallee allocHook = M.getOrInsertFunction("__libafl_memlock_alloc",
+                                                   VoidTy, Int32Ty, Int64Ty);
+
+  bool changed = false;
+
+  for (auto &F : M) {
+    if (F.isDeclaration() || F.hasFnAttribute(Attribute::Naked)) { continue; }
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
+
+    std::vector<Instruction *> exits;
+    std::vector<CallBase *>    allocs;
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        if (isa<ReturnInst>(&IN) || isa<ResumeInst>(&IN)) {
+          exits.push_back(&IN);
+        } else if (auto *CB = dyn_cast<CallBase>(&IN)) {
+          if (getAllocFn(CB)) { allocs.push_back(CB); }
+        }
+      }
+    }
+
+    // ids are stable across builds, like the function logging pass
+    std::string funcName = F.getName().str();
+    uint32_t    funcId = (uint32_t)std::hash<std::string>{}(funcName);
+
+    IRBuilder<> EntryIRB(&*F.getEntryBlock().getFirstInsertionPt());
+    EntryIRB.CreateCall(enterHook, {ConstantInt::get(Int32Ty, funcId)});
+
+    for (auto *Exit : exits) {
+      // nothing may sit between a musttail call and its return
+      Instruction *InsertPt = Exit;
+      if (auto *CI = dyn_cast_or_null<CallInst>(Exit->getPrevNode())) {
+        if (CI->isMustTailCall()) { InsertPt = CI; }
+      }
+      IRBuilder<> IRB(InsertPt);
+      IRB.CreateCall(exitHook);
+    }
+
+    unsigned siteIdx = 0;
+    for (auto *CB : allocs) {
+      const AllocFn *Fn = getAllocFn(CB);
+      IRBuilder<>    IRB(CB);
+
+      Value *Size = CB->getArgOperand(Fn->size_arg);
+      if (!Size->getType()->isIntegerTy()) { continue; }
+      Size = IRB.CreateZExtOrTrunc(Size, Int64Ty);
+      if (Fn->count_arg >= 0) {
+        Value *Count = CB->getArgOperand(Fn->count_arg);
+        if (!Count->getType()->isIntegerTy()) { continue; }
+        Size = IRB.CreateMul(Size, IRB.CreateZExtOrTrunc(Count, Int64Ty));
+      }
+
+      uint32_t siteId = (uint32_t)std::hash<std::string>{}(
+          funcName + ":" + std::to_string(siteIdx++));
+      IRB.CreateCall(allocHook, {ConstantInt::get(Int32Ty, siteId), Size});
+    }
+
+    changed = true;
+  }
+
+  if (!changed) { return PreservedAnalyses::all(); }
+  return PreservedAnalyses::none();
+}
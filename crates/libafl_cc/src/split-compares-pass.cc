@@ -0,0 +1,170 @@
+/*
+   LibAFL - Split compares LLVM pass
+   --------------------------------------------------
+
+   Based on the laf-intel split-compares pass shipped with AFL++.
+
+   Copyright 2016 laf-intel
+   Copyright 2019-2024 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   Splits integer compares wider than a byte against a constant into a chain
+   of single byte compares, most significant byte first, so coverage rewards
+   every matched byte of the constant.
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include "common-llvm.h"
+
+#include <vector>
+
+using namespace llvm;
+
+static cl::opt<unsigned> SplitComparesMinBits(
+    "split_compares_min_bits",
+    cl::desc("Only split integer compares at least this wide"), cl::init(16),
+    cl::NotHidden);
+
+namespace {
+
+class SplitCompares : public PassInfoMixin<SplitCompares> {
+ public:
+  SplitCompares() {
+  }
+
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+
+ private:
+  bool splitCompare(ICmpInst *Cmp);
+};
+
+}  // namespace
+
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "SplitCompares", "v0.1",
+          /* lambda to insert our pass into the pass pipeline. */
+          [](PassBuilder &PB) {
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL
+#if LLVM_VERSION_MAJOR >= 20
+                   ,
+                   ThinOrFullLTOPhase Phase
+#endif
+                ) { MPM.addPass(SplitCompares()); });
+          }};
+}
+
+PreservedAnalyses SplitCompares::run(Module &M, ModuleAnalysisManager &MAM) {
+  std::vector<ICmpInst *> Compares;
+
+  for (auto &F : M) {
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        ICmpInst *Cmp = dyn_cast<ICmpInst>(&IN);
+        if (!Cmp) { continue; }
+
+        IntegerType *Ty = dyn_cast<IntegerType>(Cmp->getOperand(0)->getType());
+        if (!Ty) { continue; }
+        unsigned Bits = Ty->getBitWidth();
+        if (Bits < SplitComparesMinBits || Bits <= 8 || Bits % 8 != 0) {
+          continue;
+        }
+
+        // Only compares against a constant carry a value worth solving
+        // byte by byte.
+        bool Const0 = isa<ConstantInt>(Cmp->getOperand(0));
+        bool Const1 = isa<ConstantInt>(Cmp->getOperand(1));
+        if (Const0 == Const1) { continue; }
+
+        Compares.push_back(Cmp);
+      }
+    }
+  }
+
+  bool Changed = false;
+  for (auto *Cmp : Compares) {
+    Changed |= splitCompare(Cmp);
+  }
+
+  if (!Changed) { return PreservedAnalyses::all(); }
+  return PreservedAnalyses::none();
+}
+
+bool SplitCompares::splitCompare(ICmpInst *Cmp) {
+  LLVMContext &C = Cmp->getContext();
+  IntegerType *Int1Ty = IntegerType::getInt1Ty(C);
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  unsigned     Bytes = Cmp->getOperand(0)->getType()->getIntegerBitWidth() / 8;
+
+  CmpInst::Predicate Pred = Cmp->getPredicate();
+  bool               IsEquality = Cmp->isEquality();
+  bool               IsSigned = Cmp->isSigned();
+
+  // Ordered compares are decided by the first differing byte with the strict
+  // predicate, and by the last byte with the original one. Only the most
+  // significant byte carries the sign.
+  CmpInst::Predicate Strict = CmpInst::getStrictPredicate(
+      IsSigned ? ICmpInst::getUnsignedPredicate(Pred) : Pred);
+  CmpInst::Predicate LastPred =
+      IsSigned ? ICmpInst::getUnsignedPredicate(Pred) : Pred;
+
+  Value *Op0 = Cmp->getOperand(0);
+  Value *Op1 = Cmp->getOperand(1);
+
+  BasicBlock *BB = Cmp->getParent();
+  Function   *F = BB->getParent();
+  BasicBlock *End = BB->splitBasicBlock(Cmp, "split_cmp_end");
+  BB->getTerminator()->eraseFromParent();
+
+  IRBuilder<> EndIRB(End, End->begin());
+  PHINode    *Result = EndIRB.CreatePHI(Int1Ty, Bytes, "split_cmp_res");
+
+  BasicBlock *Cur = BB;
+  for (int Byte = Bytes - 1; Byte >= 0; Byte--) {
+    IRBuilder<> IRB(Cur);
+
+    Value *A = IRB.CreateTrunc(
+        Byte ? IRB.CreateLShr(Op0, 8 * Byte) : Op0, Int8Ty);
+    Value *B = IRB.CreateTrunc(
+        Byte ? IRB.CreateLShr(Op1, 8 * Byte) : Op1, Int8Ty);
+
+    bool Top = Byte == (int)Bytes - 1;
+
+    if (Byte == 0) {
+      Result->addIncoming(IRB.CreateICmp(LastPred, A, B), Cur);
+      IRB.CreateBr(End);
+      break;
+    }
+
+    // Once a byte differs the outcome is settled
+    Value *Res;
+    if (IsEquality) {
+      Res = ConstantInt::get(Int1Ty, Pred == CmpInst::ICMP_NE);
+    } else if (Top && IsSigned) {
+      Res = IRB.CreateICmp(ICmpInst::getSignedPredicate(Strict), A, B);
+    } else {
+      Res = IRB.CreateICmp(Strict, A, B);
+    }
+    Result->addIncoming(Res, Cur);
+
+    BasicBlock *Next = BasicBlock::Create(C, "split_cmp", F, End);
+    Value      *Ne = IRB.CreateICmpNE(A, B);
+    IRB.CreateCondBr(Ne, End, Next);
+    Cur = Next;
+  }
+
+  Cmp->replaceAllUsesWith(Result);
+  Cmp->eraseFromParent();
+
+  return true;
+}
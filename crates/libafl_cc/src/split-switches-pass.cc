@@ -0,0 +1,197 @@
+/*
+   LibAFL - Split switches LLVM pass
+   --------------------------------------------------
+
+   Based on the laf-intel split-switches pass shipped with AFL++.
+
+   Copyright 2016 laf-intel
+   Copyright 2019-2024 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   Replaces switches on integers wider than a byte with a tree of single byte
+   compares, most significant byte first, so coverage rewards every matched
+   byte of a case value.
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include "common-llvm.h"
+
+#include <map>
+#include <utility>
+#include <vector>
+
+using namespace llvm;
+
+namespace {
+
+class SplitSwitches : public PassInfoMixin<SplitSwitches> {
+ public:
+  SplitSwitches() {
+  }
+
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+
+ private:
+  typedef std::pair<uint64_t, BasicBlock *> CaseEntry;
+  typedef std::pair<BasicBlock *, BasicBlock *> Edge;
+
+  struct SwitchCtx {
+    Function    *F;
+    Value       *Val;
+    BasicBlock  *Default;
+    IntegerType *Int8Ty;
+    // (predecessor, original successor) pairs, one per emitted edge
+    std::vector<Edge> Edges;
+  };
+
+  void emitNode(SwitchCtx &Ctx, std::vector<CaseEntry> &Cases, int Byte,
+                BasicBlock *Node);
+  bool splitSwitch(SwitchInst *SI);
+};
+
+}  // namespace
+
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "SplitSwitches", "v0.1",
+          /* lambda to insert our pass into the pass pipeline. */
+          [](PassBuilder &PB) {
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL
+#if LLVM_VERSION_MAJOR >= 20
+                   ,
+                   ThinOrFullLTOPhase Phase
+#endif
+                ) { MPM.addPass(SplitSwitches()); });
+          }};
+}
+
+PreservedAnalyses SplitSwitches::run(Module &M, ModuleAnalysisManager &MAM) {
+  std::vector<SwitchInst *> Switches;
+
+  for (auto &F : M) {
+    if (!isInInstrumentList(&F, M.getSourceFileName())) { continue; }
+
+    for (auto &BB : F) {
+      SwitchInst *SI = dyn_cast<SwitchInst>(BB.getTerminator());
+      if (!SI || SI->getNumCases() < 1) { continue; }
+
+      IntegerType *Ty = dyn_cast<IntegerType>(SI->getCondition()->getType());
+      if (!Ty) { continue; }
+      unsigned Bits = Ty->getBitWidth();
+      if (Bits <= 8 || Bits > 64 || Bits % 8 != 0) { continue; }
+
+      Switches.push_back(SI);
+    }
+  }
+
+  bool Changed = false;
+  for (auto *SI : Switches) {
+    Changed |= splitSwitch(SI);
+  }
+
+  if (!Changed) { return PreservedAnalyses::all(); }
+  return PreservedAnalyses::none();
+}
+
+void SplitSwitches::emitNode(SwitchCtx &Ctx, std::vector<CaseEntry> &Cases,
+                             int Byte, BasicBlock *Node) {
+  LLVMContext &C = Node->getContext();
+
+  // group the remaining cases by the value of the current byte
+  std::map<uint8_t, std::vector<CaseEntry>> Groups;
+  for (auto &Case : Cases) {
+    Groups[(uint8_t)(Case.first >> (8 * Byte))].push_back(Case);
+  }
+
+  IRBuilder<> NodeIRB(Node);
+  Value      *Shifted = Byte ? NodeIRB.CreateLShr(Ctx.Val, 8 * Byte) : Ctx.Val;
+  Value      *ByteVal = NodeIRB.CreateTrunc(Shifted, Ctx.Int8Ty);
+
+  BasicBlock *Cur = Node;
+  size_t      Remaining = Groups.size();
+  for (auto &Group : Groups) {
+    Remaining--;
+
+    BasicBlock *Target;
+    if (Byte == 0) {
+      // all bytes matched, the case values are unique
+      Target = Group.second.front().second;
+      Ctx.Edges.push_back(Edge(Cur, Target));
+    } else {
+      Target = BasicBlock::Create(C, "switch_node", Ctx.F, Ctx.Default);
+    }
+
+    BasicBlock *Next;
+    if (Remaining == 0) {
+      Next = Ctx.Default;
+      Ctx.Edges.push_back(Edge(Cur, Next));
+    } else {
+      Next = BasicBlock::Create(C, "switch_chain", Ctx.F, Ctx.Default);
+    }
+
+    IRBuilder<> IRB(Cur);
+    Value      *Eq = IRB.CreateICmpEQ(ByteVal,
+                                      ConstantInt::get(Ctx.Int8Ty, Group.first));
+    IRB.CreateCondBr(Eq, Target, Next);
+
+    if (Byte != 0) { emitNode(Ctx, Group.second, Byte - 1, Target); }
+    Cur = Next;
+  }
+}
+
+bool SplitSwitches::splitSwitch(SwitchInst *SI) {
+  BasicBlock  *OrigBB = SI->getParent();
+  LLVMContext &C = OrigBB->getContext();
+  IntegerType *Ty = cast<IntegerType>(SI->getCondition()->getType());
+
+  SwitchCtx Ctx;
+  Ctx.F = OrigBB->getParent();
+  Ctx.Val = SI->getCondition();
+  Ctx.Default = SI->getDefaultDest();
+  Ctx.Int8Ty = IntegerType::getInt8Ty(C);
+
+  std::vector<CaseEntry> Cases;
+  for (auto &Case : SI->cases()) {
+    Cases.push_back(
+        CaseEntry(Case.getCaseValue()->getZExtValue(), Case.getCaseSuccessor()));
+  }
+
+  // Remember what every successor received from the switch block before the
+  // edges move to the new compare blocks.
+  std::map<PHINode *, Value *> Incoming;
+  for (unsigned i = 0; i < SI->getNumSuccessors(); i++) {
+    for (auto &PN : SI->getSuccessor(i)->phis()) {
+      Incoming[&PN] = PN.getIncomingValueForBlock(OrigBB);
+    }
+  }
+
+  BasicBlock *Root = BasicBlock::Create(C, "switch_node", Ctx.F, Ctx.Default);
+  emitNode(Ctx, Cases, Ty->getBitWidth() / 8 - 1, Root);
+
+  SI->eraseFromParent();
+  BranchInst::Create(Root, OrigBB);
+
+  for (auto &Entry : Incoming) {
+    PHINode *PN = Entry.first;
+    while (PN->getBasicBlockIndex(OrigBB) != -1) {
+      PN->removeIncomingValue(OrigBB, false);
+    }
+  }
+
+  for (auto &E : Ctx.Edges) {
+    for (auto &PN : E.second->phis()) {
+      auto It = Incoming.find(&PN);
+      if (It != Incoming.end()) { PN.addIncoming(It->second, E.first); }
+    }
+  }
+
+  return true;
+}
@@ -111,6 +111,11 @@ pub struct AllocationMetadata {
     pub freed: bool,
     /// If the allocation was done with a size of 0
     pub is_malloc_zero: bool,
+    /// If the allocation has already been reported as a leak by [`Allocator::check_for_leaks`]
+    pub leak_reported: bool,
+    /// If the allocation has already been reported as an unreachable leak by
+    /// [`Allocator::check_for_unreachable_leaks`]
+    pub unreachable_leak_reported: bool,
 }
 
 impl Allocator {
@@ -351,6 +356,8 @@ impl Allocator {
             allocation.freed = false;
             allocation.allocation_site_backtrace = None;
             allocation.release_site_backtrace = None;
+            allocation.leak_reported = false;
+            allocation.unreachable_leak_reported = false;
 
             // Move the allocation from the allocations to the to-be-allocated queues
             self.allocation_queue
@@ -610,12 +617,24 @@ impl Allocator {
         self.base_mapping_addr <= ptr as usize && (ptr as usize) < self.current_mapping_addr
     }
 
-    /// Checks if any of the allocations has not been freed
-    pub fn check_for_leaks(&self) {
-        for metadata in self.allocations.values() {
-            if !metadata.freed
-                && AsanErrors::get_mut_blocking()
-                    .report_error(AsanError::Leak((metadata.address, metadata.clone())))
+    /// Checks if `[start, end)` overlaps the shadow memory or the mappings of the allocations
+    #[inline]
+    #[must_use]
+    pub fn overlaps_own_memory(&self, start: usize, end: usize) -> bool {
+        self.shadow_offset < end && start < self.current_mapping_addr
+    }
+
+    /// Checks if any of the allocations has not been freed.
+    ///
+    /// Each allocation is only reported once, even if it stays alive across runs.
+    pub fn check_for_leaks(&mut self) {
+        for metadata in self.allocations.values_mut() {
+            if metadata.freed || metadata.leak_reported {
+                continue;
+            }
+            metadata.leak_reported = true;
+            if AsanErrors::get_mut_blocking()
+                .report_error(AsanError::Leak((metadata.address, metadata.clone())))
             {
                 unsafe {
                     println!(
@@ -628,6 +647,88 @@ impl Allocator {
         }
     }
 
+    /// Finds all live allocations that are not reachable from the given roots.
+    ///
+    /// Starting at `root_values` and every pointer-aligned word in the readable `root_ranges`,
+    /// each value pointing into a live allocation marks it as reachable, and the allocation
+    /// itself is scanned for further pointers. Returns the addresses of the unmarked allocations.
+    #[must_use]
+    pub fn find_unreachable_allocations(
+        &self,
+        root_ranges: &[(usize, usize)],
+        root_values: &[usize],
+    ) -> Vec<usize> {
+        // live allocations, sorted by address as the map is
+        let live: Vec<(usize, usize)> = self
+            .allocations
+            .iter()
+            .filter(|(_, metadata)| !metadata.freed)
+            .map(|(address, metadata)| (*address, *address + metadata.size))
+            .collect();
+        let mut reachable = vec![false; live.len()];
+        let mut worklist = vec![];
+
+        let mut mark = |value: usize, worklist: &mut Vec<usize>| {
+            let index = live.partition_point(|(start, _)| *start <= value);
+            if index > 0 && value < live[index - 1].1 && !reachable[index - 1] {
+                reachable[index - 1] = true;
+                worklist.push(index - 1);
+            }
+        };
+        for value in root_values {
+            mark(*value, &mut worklist);
+        }
+        // marks each word as it is read, so only the worklist grows with the scanned memory
+        let mut scan = |start: usize, end: usize, worklist: &mut Vec<usize>| {
+            let word = size_of::<usize>();
+            let mut address = start.next_multiple_of(word);
+            while address + word <= end {
+                mark(
+                    unsafe { (address as *const usize).read_volatile() },
+                    worklist,
+                );
+                address += word;
+            }
+        };
+
+        for (start, end) in root_ranges {
+            scan(*start, *end, &mut worklist);
+        }
+        while let Some(index) = worklist.pop() {
+            let (start, end) = live[index];
+            scan(start, end, &mut worklist);
+        }
+
+        live.iter()
+            .zip(reachable)
+            .filter(|(_, reachable)| !reachable)
+            .map(|((start, _), _)| *start)
+            .collect()
+    }
+
+    /// Reports all live allocations that are not reachable from the given roots as leaks.
+    ///
+    /// Each allocation is only reported once, even if it stays alive across runs.
+    /// See [`Allocator::find_unreachable_allocations`].
+    pub fn check_for_unreachable_leaks(
+        &mut self,
+        root_ranges: &[(usize, usize)],
+        root_values: &[usize],
+    ) {
+        for address in self.find_unreachable_allocations(root_ranges, root_values) {
+            let metadata = self.allocations.get_mut(&address).unwrap();
+            if metadata.unreachable_leak_reported {
+                continue;
+            }
+            metadata.unreachable_leak_reported = true;
+            if AsanErrors::get_mut_blocking()
+                .report_error(AsanError::UnreachableLeak((address, metadata.clone())))
+            {
+                panic!("ASAN: Crashing target!");
+            }
+        }
+    }
+
     /// Unpoison all the memory that is currently mapped with read permissions.
     #[cfg(target_vendor = "apple")]
     pub fn unpoison_all_existing_memory(&mut self) {
@@ -959,3 +1060,35 @@ fn check_shadow() {
     let allocation = unsafe { allocator.alloc(0x3c, 0) };
     assert!(allocator.check_shadow(unsafe { allocation.offset(0x3a) }, 2));
 }
+
+#[test]
+fn check_unreachable_allocations() {
+    use frida_gum::Gum;
+    let _gum = Gum::obtain();
+    let mut allocator = Allocator::default();
+    allocator.init();
+
+    let root = unsafe { allocator.alloc(16, 8) };
+    let child = unsafe { allocator.alloc(16, 8) };
+    let leaked = unsafe { allocator.alloc(16, 8) };
+    let freed = unsafe { allocator.alloc(16, 8) };
+    unsafe {
+        // an interior pointer keeps the child alive as well
+        root.cast::<usize>().write(child as usize + 4);
+        allocator.release(freed);
+    }
+
+    let unreachable = allocator.find_unreachable_allocations(&[], &[root as usize]);
+    assert_eq!(unreachable, vec![leaked as usize]);
+
+    let stack_root = [root as usize, leaked as usize];
+    let range = (
+        stack_root.as_ptr() as usize,
+        stack_root.as_ptr() as usize + size_of_val(&stack_root),
+    );
+    assert!(
+        allocator
+            .find_unreachable_allocations(&[range], &[])
+            .is_empty()
+    );
+}
@@ -270,6 +270,7 @@ const ASAN_EH_FRAME_FDE_ADDRESS_OFFSET: u32 = 28;
 /// this helps finding mem errors early.
 pub struct AsanRuntime {
     check_for_leaks_enabled: bool,
+    check_for_unreachable_leaks_enabled: bool,
     current_report_impl: u64,
     allocator: Mutex<Allocator>,
     regs: [usize; ASAN_SAVE_REGISTER_COUNT],
@@ -359,6 +360,9 @@ impl FridaRuntime for AsanRuntime {
         if self.check_for_leaks_enabled {
             self.check_for_leaks();
         }
+        if self.check_for_unreachable_leaks_enabled {
+            self.check_for_unreachable_leaks();
+        }
 
         // # Safety
         // The ptr and length are correct.
@@ -413,6 +417,116 @@ impl AsanRuntime {
         self.allocator_mut().check_for_leaks();
     }
 
+    /// Enable or disable the check for unreachable leaks after each run.
+    ///
+    /// The check scans all writable memory for pointers to live allocations, which
+    /// is costly for large targets, so it can be toggled between runs.
+    pub fn set_check_for_unreachable_leaks(&mut self, enabled: bool) {
+        self.check_for_unreachable_leaks_enabled = enabled;
+    }
+
+    /// Check if the test left allocations behind that are no longer reachable, and report them.
+    ///
+    /// The roots are the registers, the current stack above this frame, and all other readable
+    /// and writable memory, which covers the globals of all modules, TLS, the stacks of other
+    /// threads and memory mapped by the target directly. The memory of the allocator, its
+    /// shadow, and the memory cloaked by Frida are not roots. Registers of the callers still on
+    /// the stack have been spilled to it, so their values act as roots as well.
+    pub fn check_for_unreachable_leaks(&mut self) {
+        let registers = Self::register_roots();
+        let mut stack_var = 0_usize;
+        let stack_address = &raw mut stack_var as usize;
+        unsafe {
+            write_volatile(&raw mut stack_var, stack_address);
+        }
+        let mut roots = vec![];
+
+        let mut allocator = self.allocator_mut();
+        RangeDetails::enumerate_with_prot(
+            PageProtection::ReadWrite,
+            &mut |range: &RangeDetails| {
+                let start = range.memory_range().base_address().0 as usize;
+                let end = start + range.memory_range().size();
+                if (start..end).contains(&stack_address) {
+                    // the stack below this frame only holds stale values, that would hide leaks
+                    roots.push((stack_address, end));
+                } else if unsafe { frida_gum_sys::gum_cloak_has_range_containing(start as u64) }
+                    == 0
+                    && !allocator.overlaps_own_memory(start, end)
+                {
+                    roots.push((start, end));
+                }
+                true
+            },
+        );
+
+        allocator.check_for_unreachable_leaks(&roots, &registers);
+    }
+
+    /// The values of the general purpose registers, as roots for the leak check
+    #[expect(clippy::inline_always)] // the registers have to be read in the frame of the check
+    #[inline(always)]
+    fn register_roots() -> Vec<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let mut regs = [0_usize; 16];
+            unsafe {
+                core::arch::asm!(
+                    "mov [{0}], rax",
+                    "mov [{0} + 0x08], rbx",
+                    "mov [{0} + 0x10], rcx",
+                    "mov [{0} + 0x18], rdx",
+                    "mov [{0} + 0x20], rsi",
+                    "mov [{0} + 0x28], rdi",
+                    "mov [{0} + 0x30], rbp",
+                    "mov [{0} + 0x38], rsp",
+                    "mov [{0} + 0x40], r8",
+                    "mov [{0} + 0x48], r9",
+                    "mov [{0} + 0x50], r10",
+                    "mov [{0} + 0x58], r11",
+                    "mov [{0} + 0x60], r12",
+                    "mov [{0} + 0x68], r13",
+                    "mov [{0} + 0x70], r14",
+                    "mov [{0} + 0x78], r15",
+                    in(reg) regs.as_mut_ptr(),
+                    options(nostack, preserves_flags),
+                );
+            }
+            regs.to_vec()
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            let mut regs = [0_usize; 32];
+            unsafe {
+                core::arch::asm!(
+                    "stp x0, x1, [{0}]",
+                    "stp x2, x3, [{0}, #0x10]",
+                    "stp x4, x5, [{0}, #0x20]",
+                    "stp x6, x7, [{0}, #0x30]",
+                    "stp x8, x9, [{0}, #0x40]",
+                    "stp x10, x11, [{0}, #0x50]",
+                    "stp x12, x13, [{0}, #0x60]",
+                    "stp x14, x15, [{0}, #0x70]",
+                    "stp x16, x17, [{0}, #0x80]",
+                    "stp x18, x19, [{0}, #0x90]",
+                    "stp x20, x21, [{0}, #0xa0]",
+                    "stp x22, x23, [{0}, #0xb0]",
+                    "stp x24, x25, [{0}, #0xc0]",
+                    "stp x26, x27, [{0}, #0xd0]",
+                    "stp x28, x29, [{0}, #0xe0]",
+                    "str x30, [{0}, #0xf0]",
+                    in(reg) regs.as_mut_ptr(),
+                    options(nostack, preserves_flags),
+                );
+            }
+            regs.to_vec()
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            Vec::new()
+        }
+    }
+
     /// Returns the `AsanErrors` from the recent run.
     /// Will block if some other thread holds on to the `ASAN_ERRORS` Mutex.
     pub fn errors(&mut self) -> MutexGuard<'static, AsanErrors> {
@@ -3399,6 +3513,7 @@ impl Default for AsanRuntime {
     fn default() -> Self {
         Self {
             check_for_leaks_enabled: false,
+            check_for_unreachable_leaks_enabled: false,
             current_report_impl: 0,
             allocator: Mutex::new(Allocator::default()),
             regs: [0; ASAN_SAVE_REGISTER_COUNT],
@@ -69,6 +69,7 @@ pub(crate) enum AsanError {
         ),
     ),
     Leak((usize, AllocationMetadata)),
+    UnreachableLeak((usize, AllocationMetadata)),
     StackOobRead(
         (
             [usize; ASAN_SAVE_REGISTER_COUNT],
@@ -100,6 +101,7 @@ impl AsanError {
             AsanError::ReadAfterFree(_) => "heap use-after-free read",
             AsanError::Unknown(_) => "heap unknown",
             AsanError::Leak(_) => "memory-leak",
+            AsanError::UnreachableLeak(_) => "unreachable memory-leak",
             AsanError::StackOobRead(_) => "stack out-of-bounds read",
             AsanError::StackOobWrite(_) => "stack out-of-bounds write",
             AsanError::BadFuncArgRead(_) => "function arg resulting in bad read",
@@ -434,7 +436,7 @@ impl AsanErrors {
                 output.reset().unwrap();
                 backtrace_printer.print_trace(backtrace, output).unwrap();
             }
-            AsanError::Leak((ptr, metadata)) => {
+            AsanError::Leak((ptr, metadata)) | AsanError::UnreachableLeak((ptr, metadata)) => {
                 writeln!(output, " of {ptr:#016x}").unwrap();
                 output.reset().unwrap();
 
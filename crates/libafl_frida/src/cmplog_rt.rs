@@ -5,6 +5,12 @@
 //! This allows the fuzzer to potentially solve the compares, if a compare value is directly
 //! related to the input.
 //! Read the [`RedQueen`](https://www.ndss-symposium.org/ndss-paper/redqueen-fuzzing-with-input-to-state-correspondence/) paper for the general concepts.
+//!
+//! On `x86_64`, `rep cmps`, `pcmpistri`-style string compares and vectorized byte compares
+//! (as found in hand-optimized `memcmp` and `strcmp` implementations) are logged as routine
+//! operands, and `ucomisd`-style floating-point compares are logged as floating-point values.
+//! Additionally, the number of matching bits or bytes of each compare is recorded in a
+//! progress map, see [`CmpLogRuntime::progress_map_mut_ptr`].
 
 use alloc::rc::Rc;
 #[cfg(target_arch = "aarch64")]
@@ -30,6 +36,8 @@ use iced_x86::{
     OpKind, Register,
 };
 use libafl::Error;
+#[cfg(target_arch = "x86_64")]
+use libafl_targets::cmps::{__libafl_targets_cmplog_fp, __libafl_targets_cmplog_routines_len};
 use libafl_targets::{CMPLOG_MAP_W, cmps::__libafl_targets_cmplog_instructions};
 use rangemap::RangeMap;
 
@@ -48,9 +56,102 @@ pub enum SpecialCmpLogCase {
 }
 
 #[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
-/// Speciial `CmpLog` Cases for `aarch64`
+/// Speciial `CmpLog` Cases for `x86_64`
 #[derive(Debug)]
-pub enum SpecialCmpLogCase {}
+pub enum SpecialCmpLogCase {
+    /// `rep cmps` over elements of the given size, comparing the strings at `rsi` and `rdi`
+    RepCmps(u8),
+    /// A string or vector compare over the given number of bytes, like `pcmpistri` or `pcmpeqb`
+    Vector(u8),
+    /// A floating-point compare of the given size, like `ucomisd`
+    Float(u8),
+}
+
+/// The size of the partial-match progress map, see [`CmpLogRuntime::progress_map_mut_ptr`]
+pub const CMPLOG_PROGRESS_MAP_SIZE: usize = 65536;
+
+/// The maximum number of leading bytes of a string compare counted as progress
+#[cfg(target_arch = "x86_64")]
+const CMPLOG_PROGRESS_MAX_BYTES: usize = 32;
+
+/// The partial-match progress of each compare site
+static mut CMPLOG_PROGRESS_MAP: [u8; CMPLOG_PROGRESS_MAP_SIZE] = [0; CMPLOG_PROGRESS_MAP_SIZE];
+
+/// Record the progress of the compare at `retaddr`, keeping the best value of this run
+fn record_progress(retaddr: u64, matched: usize) {
+    let slot = ((retaddr >> 4) ^ (retaddr << 8)) as usize & (CMPLOG_PROGRESS_MAP_SIZE - 1);
+    let matched = matched.min(usize::from(u8::MAX)) as u8;
+    unsafe {
+        let entry = (&raw mut CMPLOG_PROGRESS_MAP).cast::<u8>().add(slot);
+        *entry = (*entry).max(matched);
+    }
+}
+
+/// The number of equal bits in the lowest `size` bytes of both operands
+fn matching_bits(op1: u64, op2: u64, size: u8) -> usize {
+    let mask = if size >= 8 {
+        u64::MAX
+    } else {
+        (1 << (u32::from(size) * 8)) - 1
+    };
+    (!(op1 ^ op2) & mask).count_ones() as usize
+}
+
+/// A string or vector compare, handled by [`CmpLogRuntime::populate_special`]
+#[cfg(target_arch = "x86_64")]
+const SPECIAL_KIND_BYTES: u64 = 0;
+/// A floating-point compare, handled by [`CmpLogRuntime::populate_special`]
+#[cfg(target_arch = "x86_64")]
+const SPECIAL_KIND_FLOAT: u64 = 1;
+
+/// The stack frame used by the special compare handling, on top of the saved registers:
+/// shadow space, two 32 byte operand copies, two operand pointers and the length
+#[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+const SPECIAL_FRAME_HEADER: i64 = 0x80;
+#[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+const SPECIAL_FRAME_OPERANDS: i64 = 0x20;
+#[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+const SPECIAL_FRAME_POINTERS: i64 = 0x60;
+
+/// The vector registers preserved around the special compare handling
+#[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+const XMM_REGISTERS: [Register; 16] = [
+    Register::XMM0,
+    Register::XMM1,
+    Register::XMM2,
+    Register::XMM3,
+    Register::XMM4,
+    Register::XMM5,
+    Register::XMM6,
+    Register::XMM7,
+    Register::XMM8,
+    Register::XMM9,
+    Register::XMM10,
+    Register::XMM11,
+    Register::XMM12,
+    Register::XMM13,
+    Register::XMM14,
+    Register::XMM15,
+];
+#[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+const YMM_REGISTERS: [Register; 16] = [
+    Register::YMM0,
+    Register::YMM1,
+    Register::YMM2,
+    Register::YMM3,
+    Register::YMM4,
+    Register::YMM5,
+    Register::YMM6,
+    Register::YMM7,
+    Register::YMM8,
+    Register::YMM9,
+    Register::YMM10,
+    Register::YMM11,
+    Register::YMM12,
+    Register::YMM13,
+    Register::YMM14,
+    Register::YMM15,
+];
 
 #[cfg(target_arch = "aarch64")]
 use yaxpeax_arm::armv8::a64::{InstDecoder, Opcode, Operand, ShiftStyle};
@@ -94,6 +195,8 @@ pub enum CmplogOperandType {
     Imm(u64),
     /// A memory operand
     Mem(Register, Register, i64, u32, MemorySize), // base, index, disp, scale, mem_size
+    /// An `xmm` or `ymm` register
+    Vector(Register),
 }
 
 /// `Frida`-based binary-only innstrumentation that logs compares to the fuzzer
@@ -175,6 +278,7 @@ impl CmpLogRuntime {
         unsafe {
             __libafl_targets_cmplog_instructions(k as usize, 8, op1, op2);
         }
+        record_progress(retaddr, matching_bits(op1, op2, 8));
     }
 
     #[cfg(target_arch = "x86_64")]
@@ -190,6 +294,72 @@ impl CmpLogRuntime {
         unsafe {
             __libafl_targets_cmplog_instructions(k as usize, size, op1, op2);
         }
+        record_progress(retaddr, matching_bits(op1, op2, size));
+    }
+
+    /// Log a string, vector or floating-point compare.
+    ///
+    /// `frame` points to the two operand pointers followed by the length of the compare in bytes.
+    #[cfg(target_arch = "x86_64")]
+    extern "C" fn populate_special(kind: u64, frame: *const u64, retaddr: u64) {
+        let (ptr1, ptr2, len) = unsafe {
+            (
+                *frame as *const u8,
+                *frame.add(1) as *const u8,
+                *frame.add(2) as usize,
+            )
+        };
+        if len == 0 {
+            return;
+        }
+
+        let mut k = (retaddr >> 4) ^ (retaddr << 8);
+
+        k &= (CMPLOG_MAP_W as u64) - 1;
+
+        if kind == SPECIAL_KIND_FLOAT {
+            let (op1, op2) = unsafe {
+                if len == 4 {
+                    (
+                        u64::from(ptr1.cast::<u32>().read_unaligned()),
+                        u64::from(ptr2.cast::<u32>().read_unaligned()),
+                    )
+                } else {
+                    (
+                        ptr1.cast::<u64>().read_unaligned(),
+                        ptr2.cast::<u64>().read_unaligned(),
+                    )
+                }
+            };
+            unsafe {
+                __libafl_targets_cmplog_fp(k as usize, len as u8, op1, op2);
+            }
+            record_progress(retaddr, matching_bits(op1, op2, len as u8));
+        } else {
+            unsafe {
+                __libafl_targets_cmplog_routines_len(k as usize, ptr1, ptr2, len);
+            }
+            // Only read up to the first mismatch, the compare itself does not read further either
+            let mut matched = 0;
+            while matched < len.min(CMPLOG_PROGRESS_MAX_BYTES)
+                && unsafe { *ptr1.add(matched) == *ptr2.add(matched) }
+            {
+                matched += 1;
+            }
+            record_progress(retaddr, matched);
+        }
+    }
+
+    /// Retrieve the partial-match progress map pointer.
+    ///
+    /// Each compare site stores the highest number of matching operand bits, or matching
+    /// leading bytes for string compares, of the current run in a slot of this map.
+    /// Observe it with a map observer of size [`CMPLOG_PROGRESS_MAP_SIZE`] and a
+    /// `MaxMapFeedback` to reward inputs that get closer to solving a compare.
+    #[must_use]
+    #[expect(clippy::unused_self)]
+    pub fn progress_map_mut_ptr(&mut self) -> *mut u8 {
+        (&raw mut CMPLOG_PROGRESS_MAP).cast::<u8>()
     }
 
     /// Generate the instrumentation blobs for the current arch.
@@ -412,8 +582,13 @@ impl CmpLogRuntime {
         op1: &CmplogOperandType, //first operand of the comparsion
         op2: &CmplogOperandType, //second operand of the comparsion
         _shift: &Option<SpecialCmpLogCase>,
-        _special_case: &Option<SpecialCmpLogCase>,
+        special_case: &Option<SpecialCmpLogCase>,
     ) {
+        if let Some(special_case) = special_case {
+            self.emit_special_comparison_handling(address, output, op1, op2, special_case);
+            return;
+        }
+
         let writer = output.writer();
 
         writer.put_bytes(&self.save_registers.clone().unwrap());
@@ -443,7 +618,7 @@ impl CmpLogRuntime {
         }
         #[cfg(unix)]
         {
-            arg_reg_1 = Register::DL;
+            arg_reg_1 = Register::DIL;
             arg_reg_2 = Register::RSI;
             arg_reg_3 = Register::RDX;
             arg_reg_4 = Register::RCX;
@@ -538,6 +713,9 @@ impl CmpLogRuntime {
                 CmplogOperandType::Imm(imm) => {
                     insts.push(Instruction::with1(Code::Pushq_imm32, *imm as i32).unwrap());
                 }
+                CmplogOperandType::Vector(_) => {
+                    unreachable!("Vector operands are only used by special compares")
+                }
             }
         }
 
@@ -553,6 +731,243 @@ impl CmpLogRuntime {
         writer.put_bytes(&self.restore_registers.clone().unwrap());
     }
 
+    /// Emit the instrumentation code for string, vector and floating-point compares.
+    ///
+    /// Both operands are passed by pointer: memory operands directly, vector registers
+    /// through a copy on the stack. Since the populate function may clobber any vector
+    /// register, all of them are preserved around the call. The flags are preserved as well,
+    /// as most of these compares (`pcmpeqb`, `cmpss`, ...) do not write them.
+    #[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+    #[expect(clippy::too_many_lines)]
+    fn emit_special_comparison_handling(
+        &self,
+        address: u64,
+        output: &StalkerOutput,
+        op1: &CmplogOperandType,
+        op2: &CmplogOperandType,
+        special_case: &SpecialCmpLogCase,
+    ) {
+        let writer = output.writer();
+
+        writer.put_pushfx();
+        writer.put_bytes(&self.save_registers.clone().unwrap());
+
+        let arg_reg_1;
+        let arg_reg_2;
+        let arg_reg_3;
+
+        #[cfg(windows)]
+        {
+            arg_reg_1 = Register::RCX;
+            arg_reg_2 = Register::RDX;
+            arg_reg_3 = Register::R8;
+        }
+        #[cfg(unix)]
+        {
+            arg_reg_1 = Register::RDI;
+            arg_reg_2 = Register::RSI;
+            arg_reg_3 = Register::RDX;
+        }
+
+        // the populate function (or the libc it calls) may use AVX even for an SSE compare,
+        // so save the full ymm registers whenever the CPU supports them
+        let wide = std::arch::is_x86_feature_detected!("avx");
+        let (vector_registers, vector_width, store_code, load_code) = if wide {
+            (
+                YMM_REGISTERS,
+                32,
+                Code::VEX_Vmovdqu_ymmm256_ymm,
+                Code::VEX_Vmovdqu_ymm_ymmm256,
+            )
+        } else {
+            (
+                XMM_REGISTERS,
+                16,
+                Code::Movdqu_xmmm128_xmm,
+                Code::Movdqu_xmm_xmmm128,
+            )
+        };
+        let frame_size = SPECIAL_FRAME_HEADER + 16 * vector_width;
+        let stack = |disp: i64| MemoryOperand::with_base_displ(Register::RSP, disp);
+
+        let mut insts =
+            vec![Instruction::with2(Code::Lea_r64_m, Register::RSP, stack(-frame_size)).unwrap()];
+        for (reg_num, reg) in (0_i64..).zip(vector_registers) {
+            insts.push(
+                Instruction::with2(
+                    store_code,
+                    stack(SPECIAL_FRAME_HEADER + reg_num * vector_width),
+                    reg,
+                )
+                .unwrap(),
+            );
+        }
+
+        let kind = match special_case {
+            SpecialCmpLogCase::RepCmps(size) => {
+                // the strings are at rsi and rdi, rcx holds the number of elements
+                insts.push(
+                    Instruction::with2(
+                        Code::Mov_rm64_r64,
+                        stack(SPECIAL_FRAME_POINTERS),
+                        Register::RSI,
+                    )
+                    .unwrap(),
+                );
+                insts.push(
+                    Instruction::with2(
+                        Code::Mov_rm64_r64,
+                        stack(SPECIAL_FRAME_POINTERS + 8),
+                        Register::RDI,
+                    )
+                    .unwrap(),
+                );
+                insts.push(
+                    Instruction::with2(Code::Mov_r64_rm64, Register::RAX, Register::RCX).unwrap(),
+                );
+                insts.push(
+                    Instruction::with2(Code::Shl_rm64_imm8, Register::RAX, size.trailing_zeros())
+                        .unwrap(),
+                );
+                insts.push(
+                    Instruction::with2(
+                        Code::Mov_rm64_r64,
+                        stack(SPECIAL_FRAME_POINTERS + 16),
+                        Register::RAX,
+                    )
+                    .unwrap(),
+                );
+                SPECIAL_KIND_BYTES
+            }
+            SpecialCmpLogCase::Vector(size) | SpecialCmpLogCase::Float(size) => {
+                // copy the vector registers first, this clobbers no general purpose register
+                for (op_num, op) in (0_i64..).zip([op1, op2]) {
+                    if let CmplogOperandType::Vector(reg) = op {
+                        let code = if reg.is_ymm() {
+                            Code::VEX_Vmovdqu_ymmm256_ymm
+                        } else {
+                            Code::Movdqu_xmmm128_xmm
+                        };
+                        insts.push(
+                            Instruction::with2(
+                                code,
+                                stack(SPECIAL_FRAME_OPERANDS + op_num * 0x20),
+                                *reg,
+                            )
+                            .unwrap(),
+                        );
+                    }
+                }
+                // memory operands next, rax still holds its original value here
+                for (op_num, op) in (0_i64..).zip([op1, op2]) {
+                    if let CmplogOperandType::Mem(reg_base, reg_index, disp, scale, _) = op {
+                        if *reg_base == Register::RIP {
+                            // in case of RIP, disp is an absolute address already calculated by iced
+                            insts.push(
+                                Instruction::with2(Code::Mov_r64_imm64, Register::RAX, *disp)
+                                    .unwrap(),
+                            );
+                        } else {
+                            let mut disp_adjusted = *disp;
+                            if *reg_base == Register::RSP {
+                                // 0x40 is an amount of bytes used by pushfq and save_registers()
+                                disp_adjusted += 0x40 + frame_size;
+                            }
+                            insts.push(
+                                Instruction::with2(
+                                    Code::Lea_r64_m,
+                                    Register::RAX,
+                                    MemoryOperand::with_base_index_scale_displ_size(
+                                        *reg_base,
+                                        *reg_index,
+                                        *scale,
+                                        disp_adjusted,
+                                        1,
+                                    ),
+                                )
+                                .unwrap(),
+                            );
+                        }
+                        insts.push(
+                            Instruction::with2(
+                                Code::Mov_rm64_r64,
+                                stack(SPECIAL_FRAME_POINTERS + op_num * 8),
+                                Register::RAX,
+                            )
+                            .unwrap(),
+                        );
+                    }
+                }
+                for (op_num, op) in (0_i64..).zip([op1, op2]) {
+                    if let CmplogOperandType::Vector(_) = op {
+                        insts.push(
+                            Instruction::with2(
+                                Code::Lea_r64_m,
+                                Register::RAX,
+                                stack(SPECIAL_FRAME_OPERANDS + op_num * 0x20),
+                            )
+                            .unwrap(),
+                        );
+                        insts.push(
+                            Instruction::with2(
+                                Code::Mov_rm64_r64,
+                                stack(SPECIAL_FRAME_POINTERS + op_num * 8),
+                                Register::RAX,
+                            )
+                            .unwrap(),
+                        );
+                    }
+                }
+                insts.push(
+                    Instruction::with2(
+                        Code::Mov_rm64_imm32,
+                        stack(SPECIAL_FRAME_POINTERS + 16),
+                        u32::from(*size),
+                    )
+                    .unwrap(),
+                );
+                if matches!(special_case, SpecialCmpLogCase::Float(_)) {
+                    SPECIAL_KIND_FLOAT
+                } else {
+                    SPECIAL_KIND_BYTES
+                }
+            }
+        };
+
+        insts.push(Instruction::with2(Code::Mov_r64_imm64, arg_reg_1, kind).unwrap());
+        insts.push(
+            Instruction::with2(Code::Lea_r64_m, arg_reg_2, stack(SPECIAL_FRAME_POINTERS)).unwrap(),
+        );
+        insts.push(Instruction::with2(Code::Mov_r64_imm64, arg_reg_3, address).unwrap());
+        let block = InstructionBlock::new(&insts, 0);
+        let block = BlockEncoder::encode(64, block, DecoderOptions::NONE).unwrap();
+        writer.put_bytes(block.code_buffer.as_slice());
+        writer.put_call_address(
+            (CmpLogRuntime::populate_special as usize)
+                .try_into()
+                .unwrap(),
+        );
+
+        let mut insts = vec![];
+        for (reg_num, reg) in (0_i64..).zip(vector_registers) {
+            insts.push(
+                Instruction::with2(
+                    load_code,
+                    reg,
+                    stack(SPECIAL_FRAME_HEADER + reg_num * vector_width),
+                )
+                .unwrap(),
+            );
+        }
+        insts.push(Instruction::with2(Code::Lea_r64_m, Register::RSP, stack(frame_size)).unwrap());
+        let block = InstructionBlock::new(&insts, 0);
+        let block = BlockEncoder::encode(64, block, DecoderOptions::NONE).unwrap();
+        writer.put_bytes(block.code_buffer.as_slice());
+
+        writer.put_bytes(&self.restore_registers.clone().unwrap());
+        writer.put_popfx();
+    }
+
     /// Emit the instrumentation code which is responsible for operands value extraction and cmplog map population
     #[cfg(all(feature = "cmplog", target_arch = "aarch64"))]
     #[expect(clippy::too_many_lines)]
@@ -658,6 +1073,11 @@ impl CmpLogRuntime {
         }
         let mut instruction = Instruction::default();
         decoder.decode_out(&mut instruction);
+
+        if let Some(special) = Self::cmplog_special_instruction(&instruction) {
+            return Some(special);
+        }
+
         match instruction.mnemonic() {
             iced_x86::Mnemonic::Cmp | iced_x86::Mnemonic::Sub => {} // continue
             _ => return None,
@@ -743,6 +1163,105 @@ impl CmpLogRuntime {
         Some((op1, op2, None, None))
     }
 
+    /// Check if the instruction is a string, vector or floating-point compare
+    #[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
+    fn cmplog_special_instruction(
+        instruction: &Instruction,
+    ) -> Option<(
+        CmplogOperandType,
+        CmplogOperandType,
+        Option<SpecialCmpLogCase>,
+        Option<SpecialCmpLogCase>,
+    )> {
+        use iced_x86::Mnemonic;
+
+        let rep_cmps_size = match instruction.code() {
+            Code::Cmpsb_m8_m8 => Some(1),
+            Code::Cmpsw_m16_m16 => Some(2),
+            Code::Cmpsd_m32_m32 => Some(4),
+            Code::Cmpsq_m64_m64 => Some(8),
+            _ => None,
+        };
+        if let Some(size) = rep_cmps_size {
+            // a single cmps compares one element, which is not worth the overhead
+            if !instruction.has_repe_prefix() && !instruction.has_repne_prefix() {
+                return None;
+            }
+            return Some((
+                CmplogOperandType::Reg(Register::RSI),
+                CmplogOperandType::Reg(Register::RDI),
+                None,
+                Some(SpecialCmpLogCase::RepCmps(size)),
+            ));
+        }
+
+        // the VEX forms with a separate destination compare the second and third operand
+        let (special_case, first_op) = match instruction.mnemonic() {
+            Mnemonic::Ucomiss
+            | Mnemonic::Comiss
+            | Mnemonic::Vucomiss
+            | Mnemonic::Vcomiss
+            | Mnemonic::Cmpss => (SpecialCmpLogCase::Float(4), 0),
+            Mnemonic::Ucomisd
+            | Mnemonic::Comisd
+            | Mnemonic::Vucomisd
+            | Mnemonic::Vcomisd
+            | Mnemonic::Cmpsd => (SpecialCmpLogCase::Float(8), 0),
+            Mnemonic::Vcmpss => (SpecialCmpLogCase::Float(4), 1),
+            Mnemonic::Vcmpsd => (SpecialCmpLogCase::Float(8), 1),
+            Mnemonic::Pcmpistri
+            | Mnemonic::Pcmpistrm
+            | Mnemonic::Pcmpestri
+            | Mnemonic::Pcmpestrm
+            | Mnemonic::Vpcmpistri
+            | Mnemonic::Vpcmpistrm
+            | Mnemonic::Vpcmpestri
+            | Mnemonic::Vpcmpestrm
+            | Mnemonic::Pcmpeqb => (SpecialCmpLogCase::Vector(16), 0),
+            Mnemonic::Vpcmpeqb => {
+                let size = if instruction.op1_kind() == OpKind::Register
+                    && instruction.op1_register().is_ymm()
+                {
+                    32
+                } else {
+                    16
+                };
+                (SpecialCmpLogCase::Vector(size), 1)
+            }
+            _ => return None,
+        };
+
+        // we don't support rip related reference with index register yet
+        if instruction.memory_base() == Register::RIP
+            && instruction.memory_index() != Register::None
+        {
+            return None;
+        }
+
+        let operand = |op_num: u32| match instruction.op_kind(op_num) {
+            OpKind::Register => {
+                let reg = instruction.op_register(op_num);
+                (reg.is_xmm() || reg.is_ymm()).then_some(CmplogOperandType::Vector(reg))
+            }
+            OpKind::Memory =>
+            {
+                #[expect(clippy::cast_possible_wrap)]
+                Some(CmplogOperandType::Mem(
+                    instruction.memory_base(),
+                    instruction.memory_index(),
+                    instruction.memory_displacement64() as i64,
+                    instruction.memory_index_scale(),
+                    instruction.memory_size(),
+                ))
+            }
+            _ => None,
+        };
+        let op1 = operand(first_op)?;
+        let op2 = operand(first_op + 1)?;
+
+        Some((op1, op2, None, Some(special_case)))
+    }
+
     #[cfg(all(feature = "cmplog", target_arch = "aarch64"))]
     #[expect(clippy::similar_names, clippy::type_complexity)]
     #[inline]
@@ -877,3 +1396,173 @@ impl Default for CmpLogRuntime {
         Self::new()
     }
 }
+
+#[cfg(all(test, feature = "cmplog", target_arch = "x86_64"))]
+mod tests {
+    use iced_x86::{Decoder, DecoderOptions, Register};
+
+    use super::{CmpLogRuntime, CmplogOperandType, SpecialCmpLogCase, matching_bits};
+
+    fn special(bytes: &[u8]) -> Option<(CmplogOperandType, CmplogOperandType, SpecialCmpLogCase)> {
+        let instruction = Decoder::with_ip(64, bytes, 0, DecoderOptions::NONE).decode();
+        CmpLogRuntime::cmplog_special_instruction(&instruction)
+            .map(|(op1, op2, _, special_case)| (op1, op2, special_case.unwrap()))
+    }
+
+    #[test]
+    fn test_special_instructions() {
+        // repe cmpsb
+        let (op1, op2, special_case) = special(&[0xf3, 0xa6]).unwrap();
+        assert!(matches!(special_case, SpecialCmpLogCase::RepCmps(1)));
+        assert!(matches!(op1, CmplogOperandType::Reg(Register::RSI)));
+        assert!(matches!(op2, CmplogOperandType::Reg(Register::RDI)));
+        // a single cmpsb is skipped
+        assert!(special(&[0xa6]).is_none());
+
+        // ucomisd xmm0, xmm1
+        let (op1, op2, special_case) = special(&[0x66, 0x0f, 0x2e, 0xc1]).unwrap();
+        assert!(matches!(special_case, SpecialCmpLogCase::Float(8)));
+        assert!(matches!(op1, CmplogOperandType::Vector(Register::XMM0)));
+        assert!(matches!(op2, CmplogOperandType::Vector(Register::XMM1)));
+
+        // ucomiss xmm0, [rdi]
+        let (_, op2, special_case) = special(&[0x0f, 0x2e, 0x07]).unwrap();
+        assert!(matches!(special_case, SpecialCmpLogCase::Float(4)));
+        assert!(matches!(
+            op2,
+            CmplogOperandType::Mem(Register::RDI, Register::None, 0, _, _)
+        ));
+
+        // pcmpistri xmm0, xmm1, 0xc
+        let (_, _, special_case) = special(&[0x66, 0x0f, 0x3a, 0x63, 0xc1, 0x0c]).unwrap();
+        assert!(matches!(special_case, SpecialCmpLogCase::Vector(16)));
+
+        // vpcmpeqb ymm0, ymm0, ymm1 compares the second and third operand
+        let (op1, op2, special_case) = special(&[0xc5, 0xfd, 0x74, 0xc1]).unwrap();
+        assert!(matches!(special_case, SpecialCmpLogCase::Vector(32)));
+        assert!(matches!(op1, CmplogOperandType::Vector(Register::YMM0)));
+        assert!(matches!(op2, CmplogOperandType::Vector(Register::YMM1)));
+
+        // cmp rax, rcx is handled by the regular path
+        assert!(special(&[0x48, 0x39, 0xc8]).is_none());
+    }
+
+    #[test]
+    fn test_matching_bits() {
+        assert_eq!(matching_bits(0x1234, 0x1234, 2), 16);
+        assert_eq!(matching_bits(0xff, 0x00, 1), 0);
+        // bits above the compare size are ignored
+        assert_eq!(matching_bits(0xffff_0001, 0x0000_0001, 2), 16);
+        assert_eq!(matching_bits(0, 1, 8), 63);
+        assert_eq!(matching_bits(u64::MAX, u64::MAX, 8), 64);
+    }
+
+    // Compares `a` and `b`, then the 16 bytes at `v1` and `v2` with `pcmpeqb`, which keeps the
+    // flags of the first compare. Returns the `sete` result in bit 0 and the byte mask above it.
+    #[cfg(target_os = "linux")]
+    core::arch::global_asm!(
+        ".globl cmplog_stalker_test",
+        "cmplog_stalker_test:",
+        "movdqu xmm0, [rdx]",
+        "movdqu xmm1, [rcx]",
+        "cmp rdi, rsi",
+        "pcmpeqb xmm0, xmm1",
+        "sete al",
+        "movzx eax, al",
+        "pmovmskb edx, xmm0",
+        "shl edx, 1",
+        "or eax, edx",
+        "ret",
+        ".globl cmplog_stalker_test_end",
+        "cmplog_stalker_test_end:",
+    );
+
+    #[cfg(target_os = "linux")]
+    unsafe extern "C" {
+        fn cmplog_stalker_test(a: u64, b: u64, v1: *const u8, v2: *const u8) -> u64;
+        static cmplog_stalker_test_end: u8;
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_special_compare_under_stalker() {
+        use alloc::rc::Rc;
+        use core::cell::Cell;
+
+        use frida_gum::{
+            Gum,
+            stalker::{NoneEventSink, Stalker, Transformer},
+        };
+        use yaxpeax_x86::long_mode::InstDecoder;
+
+        use super::{CMPLOG_PROGRESS_MAP, CMPLOG_PROGRESS_MAP_SIZE};
+
+        let gum = Gum::obtain();
+        let mut rt = CmpLogRuntime::new();
+        rt.generate_instrumentation_blobs();
+
+        let start = cmplog_stalker_test as usize as u64;
+        let end = (&raw const cmplog_stalker_test_end) as u64;
+        let special_site = Rc::new(Cell::new(0));
+        let site = Rc::clone(&special_site);
+        let transformer = Transformer::from_callback(&gum, move |basic_block, output| {
+            for instruction in basic_block {
+                let instr = instruction.instr();
+                let address = instr.address();
+                if (start..end).contains(&address)
+                    && let Some((op1, op2, shift, special_case)) =
+                        CmpLogRuntime::cmplog_is_interesting_instruction(
+                            InstDecoder::default(),
+                            address,
+                            instr,
+                        )
+                {
+                    if special_case.is_some() {
+                        site.set(address);
+                    }
+                    rt.emit_comparison_handling(
+                        address,
+                        &output,
+                        &op1,
+                        &op2,
+                        &shift,
+                        &special_case,
+                    );
+                }
+                instruction.keep();
+            }
+        });
+
+        let v1 = [0x41_u8; 16];
+        let mut v2 = v1;
+        v2[0] = 0;
+
+        let mut stalker = Stalker::new(&gum);
+        stalker.follow_me::<NoneEventSink>(&transformer, None);
+        let target: unsafe extern "C" fn(u64, u64, *const u8, *const u8) -> u64 =
+            core::hint::black_box(cmplog_stalker_test);
+        let (equal, unequal) = unsafe {
+            (
+                target(1, 1, v1.as_ptr(), v1.as_ptr()),
+                target(1, 2, v1.as_ptr(), v2.as_ptr()),
+            )
+        };
+        stalker.unfollow_me();
+
+        assert_ne!(special_site.get(), 0, "pcmpeqb was not instrumented");
+        // the flags of `cmp` and the result of `pcmpeqb` survive the instrumentation
+        assert_eq!(equal, (0xffff << 1) | 1);
+        assert_eq!(unequal, 0xfffe << 1);
+
+        // both runs were logged, the first one matching all 16 bytes
+        let retaddr = special_site.get();
+        let slot = ((retaddr >> 4) ^ (retaddr << 8)) as usize & (CMPLOG_PROGRESS_MAP_SIZE - 1);
+        let matched = unsafe {
+            (&raw const CMPLOG_PROGRESS_MAP)
+                .cast::<u8>()
+                .add(slot)
+                .read()
+        };
+        assert_eq!(matched, 16);
+    }
+}
@@ -0,0 +1,508 @@
+//! Binary-only context-sensitive edge coverage.
+//!
+//! The [`CtxCoverageRuntime`] hashes the calling context into every edge, like `sancov_ctx` does
+//! for source targets. The context is the XOR of the call sites on the stack: each instrumented
+//! call saves the current context on a shadow stack and mixes its call site into it, each
+//! instrumented return restores the context saved by its call.
+//!
+//! The shadow stack entries are tagged with the stack pointer at the call, so returns through
+//! code outside the instrumented ranges, `longjmp` and exceptions unwind it correctly the next
+//! time an instrumented function returns.
+
+use alloc::rc::Rc;
+use core::{cell::RefCell, marker::PhantomPinned, pin::Pin};
+
+use dynasmrt::{DynasmApi, DynasmLabelApi, dynasm};
+use frida_gum::{ModuleMap, instruction_writer::InstructionWriter, stalker::StalkerOutput};
+use frida_gum_sys::Insn;
+use libafl_bolts::hash_std;
+use rangemap::RangeMap;
+#[cfg(target_arch = "aarch64")]
+use yaxpeax_arm::armv8::a64::{InstDecoder, Opcode};
+#[cfg(target_arch = "x86_64")]
+use yaxpeax_x86::amd64::{InstDecoder, Opcode};
+
+#[cfg(target_arch = "aarch64")]
+use crate::utils::disas_count;
+#[cfg(target_arch = "x86_64")]
+use crate::utils::frida_to_cs;
+use crate::{coverage_rt::MAP_SIZE, helper::FridaRuntime};
+
+/// The maximum call depth tracked, deeper calls do not change the context
+pub const CTX_STACK_SIZE: usize = 1024;
+
+#[derive(Debug)]
+struct CtxCoverageRuntimeInner {
+    map: [u8; MAP_SIZE],
+    previous_pc: u64,
+    ctx: u64,
+    depth: u64,
+    /// The stack pointer and the saved context of each active call
+    stack: [[u64; 2]; CTX_STACK_SIZE],
+    _pinned: PhantomPinned,
+}
+
+/// An instruction changing the calling context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtxInstruction {
+    /// A call, entering a new context
+    Call,
+    /// A return, leaving the current context
+    Return,
+}
+
+/// Frida binary-only context-sensitive coverage
+#[derive(Debug)]
+pub struct CtxCoverageRuntime(Pin<Rc<RefCell<CtxCoverageRuntimeInner>>>);
+
+impl Default for CtxCoverageRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FridaRuntime for CtxCoverageRuntime {
+    /// Initialize the coverage runtime
+    /// The struct MUST NOT be moved after this function is called, as the generated assembly references it
+    fn init(
+        &mut self,
+        _gum: &frida_gum::Gum,
+        _ranges: &RangeMap<u64, (u16, String)>,
+        _module_map: &Rc<ModuleMap>,
+    ) {
+    }
+
+    fn deinit(&mut self, _gum: &frida_gum::Gum) {}
+
+    fn pre_exec(&mut self, _input_bytes: &[u8]) -> Result<(), libafl::Error> {
+        // The harness is entered by a call from outside the instrumented ranges
+        let mut inner = self.0.borrow_mut();
+        inner.previous_pc = 0;
+        inner.ctx = 0;
+        inner.depth = 0;
+        Ok(())
+    }
+
+    fn post_exec(&mut self, _input_bytes: &[u8]) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
+
+impl CtxCoverageRuntime {
+    /// Create a new context-sensitive coverage runtime
+    #[allow(clippy::large_stack_arrays)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Rc::pin(RefCell::new(CtxCoverageRuntimeInner {
+            map: [0_u8; MAP_SIZE],
+            previous_pc: 0,
+            ctx: 0,
+            depth: 0,
+            stack: [[0; 2]; CTX_STACK_SIZE],
+            _pinned: PhantomPinned,
+        })))
+    }
+
+    /// Retrieve the coverage map pointer
+    pub fn map_mut_ptr(&mut self) -> *mut u8 {
+        self.0.borrow_mut().map.as_mut_ptr()
+    }
+
+    /// Check if `instr` is a call or a return
+    #[cfg(target_arch = "x86_64")]
+    #[must_use]
+    pub fn ctx_instruction(decoder: InstDecoder, instr: &Insn) -> Option<CtxInstruction> {
+        match frida_to_cs(decoder, instr).ok()?.opcode() {
+            Opcode::CALL => Some(CtxInstruction::Call),
+            Opcode::RETURN => Some(CtxInstruction::Return),
+            _ => None,
+        }
+    }
+
+    /// Check if `instr` is a call or a return
+    #[cfg(target_arch = "aarch64")]
+    #[must_use]
+    pub fn ctx_instruction(decoder: InstDecoder, instr: &Insn) -> Option<CtxInstruction> {
+        match disas_count(&decoder, instr.bytes(), 1).first()?.opcode {
+            Opcode::BL
+            | Opcode::BLR
+            | Opcode::BLRAA
+            | Opcode::BLRAAZ
+            | Opcode::BLRAB
+            | Opcode::BLRABZ => Some(CtxInstruction::Call),
+            Opcode::RET | Opcode::RETAA | Opcode::RETAB => Some(CtxInstruction::Return),
+            _ => None,
+        }
+    }
+
+    /// Write inline instrumentation for context-sensitive edge coverage
+    #[cfg(target_arch = "aarch64")]
+    #[expect(clippy::cast_possible_wrap)]
+    pub fn generate_inline_code(&mut self, h64: u64) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let prev_loc_ptr = &raw mut borrow.previous_pc;
+        let ctx_ptr = &raw mut borrow.ctx;
+        let map_addr_ptr = &raw mut borrow.map;
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::aarch64::Aarch64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch aarch64
+            // Store the context
+            ;   b >start
+
+            ;   stp x16, x17, [sp, -0x90]!
+            ; start:
+
+            // Load the previous_pc
+            ;   ldr x17, >previous_loc
+            ;   ldr x17, [x17]
+
+            // Calculate the edge id in the current calling context
+            ;   ldr x16, >loc
+            ;   eor x16, x17, x16
+            ;   ldr x17, >ctx
+            ;   ldr x17, [x17]
+            ;   eor x16, x17, x16
+
+            // Load the map byte address
+            ;   ldr x17, >map_addr
+            ;   add x16, x17, x16
+
+            // Update the map byte
+            ;   ldrb w17, [x16]
+            ;   add w17, w17, #1
+            ;   add x17, x17, x17, lsr #8
+            ;   strb w17, [x16]
+
+            // Update the previous_pc value
+            ;   ldr x16, >loc_shr
+            ;   ldr x17, >previous_loc
+            ;   str x16, [x17]
+
+            // Restore the context
+            ;   ldp x16, x17, [sp], #0x90
+
+            // Skip the data
+            ;   b >end
+
+            ;map_addr:
+            ;.i64 map_addr_ptr as i64
+            ;previous_loc:
+            ;.i64 prev_loc_ptr as i64
+            ;ctx:
+            ;.i64 ctx_ptr as i64
+            ;loc:
+            ;.i64 h64 as i64
+            ;loc_shr:
+            ;.i64 (h64 >> 1) as i64
+            ;end:
+        );
+        let ops_vec = ops.finalize().unwrap();
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Write inline instrumentation for context-sensitive edge coverage
+    #[cfg(target_arch = "x86_64")]
+    #[expect(clippy::cast_possible_wrap)]
+    pub fn generate_inline_code(&mut self, h64: u64) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let prev_loc_ptr = &raw mut borrow.previous_pc;
+        let ctx_ptr = &raw mut borrow.ctx;
+        let map_addr_ptr = &raw mut borrow.map;
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch x64
+            // Store the context
+            ; mov    QWORD [rsp-0x88], rax
+            ; lahf
+            ; mov    QWORD [rsp-0x90], rax
+            ; mov    QWORD [rsp-0x98], rbx
+
+            // Load the previous_pc
+            ; mov rax, QWORD prev_loc_ptr as _
+            ; mov rax, QWORD [rax]
+
+            // Calculate the edge id in the current calling context
+            ; mov rbx, QWORD h64 as i64
+            ; xor rax, rbx
+            ; mov rbx, QWORD ctx_ptr as _
+            ; xor rax, QWORD [rbx]
+
+            // Load the map byte address
+            ; mov rbx, QWORD map_addr_ptr as _
+            ; add rax, rbx
+
+            // Update the map byte
+            ; mov bl, BYTE [rax]
+            ; add bl,0x1
+            ; adc bl,0x0
+            ; mov BYTE [rax],bl
+
+            // Update the previous_pc value
+            ; mov rax, QWORD prev_loc_ptr as _
+            ; mov rbx, QWORD (h64 >> 1) as i64
+            ; mov QWORD [rax], rbx
+
+            // Restore the context
+            ; mov    rbx, QWORD [rsp-0x98]
+            ; mov    rax, QWORD [rsp-0x90]
+            ; sahf
+            ; mov    rax, QWORD [rsp-0x88]
+        );
+        let ops_vec = ops.finalize().unwrap();
+
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Write inline code entering the context of the call site `site`.
+    ///
+    /// Flags are not preserved across calls, so they are not saved.
+    #[cfg(target_arch = "aarch64")]
+    #[expect(clippy::cast_possible_wrap)]
+    pub fn generate_call_code(&mut self, site: u64) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let ctx_ptr = &raw mut borrow.ctx;
+        let depth_ptr = &raw mut borrow.depth;
+        let stack_ptr = &raw mut borrow.stack;
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::aarch64::Aarch64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch aarch64
+            // Store the context
+            ;   stp x16, x17, [sp, -0x90]!
+            ;   stp x14, x15, [sp, -0x10]!
+
+            // Push the stack pointer and the current context, if the shadow stack has room
+            ;   ldr x16, >depth
+            ;   ldr x17, [x16]
+            ;   cmp x17, CTX_STACK_SIZE as u32
+            ;   b.hs >full
+            ;   add x15, x17, #1
+            ;   str x15, [x16]
+            ;   ldr x16, >stack
+            ;   add x16, x16, x17, lsl #4
+            ;   add x15, sp, #0xa0
+            ;   ldr x14, >ctx
+            ;   ldr x14, [x14]
+            ;   stp x15, x14, [x16]
+            ; full:
+
+            // Mix the call site into the context
+            ;   ldr x16, >ctx
+            ;   ldr x17, [x16]
+            ;   ldr x15, >site
+            ;   eor x17, x17, x15
+            ;   str x17, [x16]
+
+            // Restore the context
+            ;   ldp x14, x15, [sp], #0x10
+            ;   ldp x16, x17, [sp], #0x90
+
+            // Skip the data
+            ;   b >end
+
+            ;depth:
+            ;.i64 depth_ptr as i64
+            ;stack:
+            ;.i64 stack_ptr as i64
+            ;ctx:
+            ;.i64 ctx_ptr as i64
+            ;site:
+            ;.i64 site as i64
+            ;end:
+        );
+        let ops_vec = ops.finalize().unwrap();
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Write inline code entering the context of the call site `site`.
+    ///
+    /// Flags are not preserved across calls, so they are not saved.
+    #[cfg(target_arch = "x86_64")]
+    #[expect(clippy::cast_possible_wrap)]
+    pub fn generate_call_code(&mut self, site: u64) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let ctx_ptr = &raw mut borrow.ctx;
+        let depth_ptr = &raw mut borrow.depth;
+        let stack_ptr = &raw mut borrow.stack;
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch x64
+            // Store the context
+            ; mov    QWORD [rsp-0x88], rax
+            ; mov    QWORD [rsp-0x90], rbx
+            ; mov    QWORD [rsp-0x98], rcx
+
+            // Push the stack pointer and the current context, if the shadow stack has room
+            ; mov rax, QWORD depth_ptr as _
+            ; mov rbx, QWORD [rax]
+            ; cmp rbx, CTX_STACK_SIZE as i32
+            ; jae >full
+            ; inc QWORD [rax]
+            ; shl rbx, 4
+            ; mov rcx, QWORD stack_ptr as _
+            ; add rcx, rbx
+            ; mov QWORD [rcx], rsp
+            ; mov rax, QWORD ctx_ptr as _
+            ; mov rbx, QWORD [rax]
+            ; mov QWORD [rcx + 8], rbx
+            ; full:
+
+            // Mix the call site into the context
+            ; mov rax, QWORD ctx_ptr as _
+            ; mov rbx, QWORD site as i64
+            ; xor QWORD [rax], rbx
+
+            // Restore the context
+            ; mov    rcx, QWORD [rsp-0x98]
+            ; mov    rbx, QWORD [rsp-0x90]
+            ; mov    rax, QWORD [rsp-0x88]
+        );
+        let ops_vec = ops.finalize().unwrap();
+
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Write inline code restoring the context saved by the call this return returns from.
+    ///
+    /// Flags are not preserved across returns, so they are not saved.
+    #[cfg(target_arch = "aarch64")]
+    pub fn generate_return_code(&mut self) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let ctx_ptr = &raw mut borrow.ctx;
+        let depth_ptr = &raw mut borrow.depth;
+        let stack_ptr = &raw mut borrow.stack;
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::aarch64::Aarch64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch aarch64
+            // Store the context
+            ;   stp x16, x17, [sp, -0x90]!
+            ;   stp x14, x15, [sp, -0x10]!
+            ;   stp x12, x13, [sp, -0x10]!
+
+            // The stack pointer is the same as at the matching call
+            ;   add x13, sp, #0xb0
+            ;   ldr x12, >depth
+            ;   ldr x14, >stack
+
+            // Pop all entries of this and deeper frames, the last one holds our context
+            ; pop_entry:
+            ;   ldr x16, [x12]
+            ;   cbz x16, >done
+            ;   sub x16, x16, #1
+            ;   add x17, x14, x16, lsl #4
+            ;   ldp x15, x17, [x17]
+            ;   cmp x15, x13
+            ;   b.hi >done
+            ;   ldr x15, >ctx
+            ;   str x17, [x15]
+            ;   str x16, [x12]
+            ;   b <pop_entry
+            ; done:
+
+            // Restore the context
+            ;   ldp x12, x13, [sp], #0x10
+            ;   ldp x14, x15, [sp], #0x10
+            ;   ldp x16, x17, [sp], #0x90
+
+            // Skip the data
+            ;   b >end
+
+            ;depth:
+            ;.i64 depth_ptr as i64
+            ;stack:
+            ;.i64 stack_ptr as i64
+            ;ctx:
+            ;.i64 ctx_ptr as i64
+            ;end:
+        );
+        let ops_vec = ops.finalize().unwrap();
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Write inline code restoring the context saved by the call this return returns from.
+    ///
+    /// Flags are not preserved across returns, so they are not saved.
+    #[cfg(target_arch = "x86_64")]
+    pub fn generate_return_code(&mut self) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let ctx_ptr = &raw mut borrow.ctx;
+        let depth_ptr = &raw mut borrow.depth;
+        let stack_ptr = &raw mut borrow.stack;
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch x64
+            // Store the context
+            ; mov    QWORD [rsp-0x88], rax
+            ; mov    QWORD [rsp-0x90], rbx
+            ; mov    QWORD [rsp-0x98], rcx
+            ; mov    QWORD [rsp-0xa0], rdx
+            ; mov    QWORD [rsp-0xa8], r8
+
+            // The stack pointer at the matching call, before the return address was pushed
+            ; lea rdx, [rsp + 8]
+            ; mov rax, QWORD depth_ptr as _
+            ; mov rcx, QWORD stack_ptr as _
+            ; mov r8, QWORD ctx_ptr as _
+
+            // Pop all entries of this and deeper frames, the last one holds our context
+            ; pop_entry:
+            ; mov rbx, QWORD [rax]
+            ; test rbx, rbx
+            ; jz >done
+            ; dec rbx
+            ; shl rbx, 4
+            ; cmp QWORD [rcx + rbx], rdx
+            ; ja >done
+            ; mov rbx, QWORD [rcx + rbx + 8]
+            ; mov QWORD [r8], rbx
+            ; dec QWORD [rax]
+            ; jmp <pop_entry
+            ; done:
+
+            // Restore the context
+            ; mov    r8, QWORD [rsp-0xa8]
+            ; mov    rdx, QWORD [rsp-0xa0]
+            ; mov    rcx, QWORD [rsp-0x98]
+            ; mov    rbx, QWORD [rsp-0x90]
+            ; mov    rax, QWORD [rsp-0x88]
+        );
+        let ops_vec = ops.finalize().unwrap();
+
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Emits context-sensitive coverage mapping into the current basic block.
+    #[inline]
+    pub fn emit_coverage_mapping(&mut self, address: u64, output: &StalkerOutput) {
+        let h64 = hash_std(&address.to_le_bytes());
+        let writer = output.writer();
+
+        // Re-use the registers spilt by a long branch, see `CoverageRuntime::emit_coverage_mapping`
+        #[cfg(target_arch = "aarch64")]
+        {
+            let pc = writer.pc();
+            writer.reset(pc - 4);
+        }
+
+        let code = self.generate_inline_code(h64 & (MAP_SIZE as u64 - 1));
+        writer.put_bytes(&code);
+    }
+
+    /// Emits the context update for the call or return at `address` before it.
+    #[inline]
+    pub fn emit_context_update(
+        &mut self,
+        address: u64,
+        kind: CtxInstruction,
+        output: &StalkerOutput,
+    ) {
+        let code = match kind {
+            CtxInstruction::Call => {
+                // The call site is hashed differently than blocks, so it does not cancel out
+                let site = hash_std(&(!address).to_le_bytes());
+                self.generate_call_code(site & (MAP_SIZE as u64 - 1))
+            }
+            CtxInstruction::Return => self.generate_return_code(),
+        };
+        output.writer().put_bytes(&code);
+    }
+}
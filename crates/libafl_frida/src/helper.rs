@@ -37,6 +37,8 @@ use yaxpeax_x86::protected_mode::InstDecoder;
 #[cfg(feature = "cmplog")]
 use crate::cmplog_rt::CmpLogRuntime;
 use crate::{asan::asan_rt::AsanRuntime, coverage_rt::CoverageRuntime, drcov_rt::DrCovRuntime};
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+use crate::{ctx_rt::CtxCoverageRuntime, ngram_rt::NgramCoverageRuntime};
 
 /// The Runtime trait
 pub trait FridaRuntime: 'static + Debug + core::any::Any {
@@ -275,6 +277,43 @@ pub enum SkipRange {
     },
 }
 
+/// The kind of coverage recorded by the coverage runtime of a [`FridaInstrumentationHelper`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageFlavor {
+    /// Edge coverage, recorded by [`CoverageRuntime`]
+    Edges,
+    /// N-gram coverage, recorded by [`NgramCoverageRuntime`]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    Ngram,
+    /// Context-sensitive edge coverage, recorded by [`CtxCoverageRuntime`]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    Context,
+}
+
+impl CoverageFlavor {
+    /// The flavors of the coverage runtimes in `runtimes`
+    #[must_use]
+    pub fn of_runtimes<RT: FridaRuntimeTuple>(runtimes: &RT) -> Vec<Self> {
+        let mut flavors = Vec::new();
+        if runtimes.match_first_type::<CoverageRuntime>().is_some() {
+            flavors.push(Self::Edges);
+        }
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        {
+            if runtimes
+                .match_first_type::<NgramCoverageRuntime>()
+                .is_some()
+            {
+                flavors.push(Self::Ngram);
+            }
+            if runtimes.match_first_type::<CtxCoverageRuntime>().is_some() {
+                flavors.push(Self::Context);
+            }
+        }
+        flavors
+    }
+}
+
 /// Builder for [`FridaInstrumentationHelper`]
 pub struct FridaInstrumentationHelperBuilder {
     stalker_enabled: bool,
@@ -283,6 +322,7 @@ pub struct FridaInstrumentationHelperBuilder {
     instrument_module_predicate: Option<Box<dyn FnMut(&Module) -> bool>>,
     skip_module_predicate: Box<dyn FnMut(&Module) -> bool>,
     skip_ranges: Vec<SkipRange>,
+    coverage_flavor: Option<CoverageFlavor>,
 }
 
 impl FridaInstrumentationHelperBuilder {
@@ -411,7 +451,29 @@ impl FridaInstrumentationHelperBuilder {
         self
     }
 
+    /// Require the coverage runtime of `flavor` in the runtimes passed to
+    /// [`build`](Self::build).
+    ///
+    /// Without this, the flavor is the one of whichever coverage runtime is passed, if any.
+    #[must_use]
+    pub fn coverage_flavor(self, flavor: CoverageFlavor) -> Self {
+        Self {
+            coverage_flavor: Some(flavor),
+            ..self
+        }
+    }
+
     /// Build a [`FridaInstrumentationHelper`]
+    ///
+    /// The coverage flavor is selected by the coverage runtime in `runtimes`:
+    /// [`CoverageRuntime`] for edges, or, on `x86_64` and `aarch64`,
+    /// [`crate::ngram_rt::NgramCoverageRuntime`] for N-grams and
+    /// [`crate::ctx_rt::CtxCoverageRuntime`] for context-sensitive edges.
+    ///
+    /// # Panics
+    /// Panics if `runtimes` holds more than one coverage runtime, as they would all write to
+    /// their own map while only one is observed, or if it lacks the runtime of the
+    /// [`coverage_flavor`](Self::coverage_flavor).
     pub fn build<RT: FridaRuntimeTuple>(
         self,
         gum: &Gum,
@@ -423,8 +485,21 @@ impl FridaInstrumentationHelperBuilder {
             mut instrument_module_predicate,
             mut skip_module_predicate,
             skip_ranges,
+            coverage_flavor,
         } = self;
 
+        let flavors = CoverageFlavor::of_runtimes(&runtimes);
+        assert!(
+            flavors.len() <= 1,
+            "Conflicting coverage runtimes {flavors:?}, use only one coverage runtime"
+        );
+        if let Some(flavor) = coverage_flavor {
+            assert!(
+                flavors == [flavor],
+                "The {flavor:?} coverage flavor needs its runtime, got coverage runtimes {flavors:?}"
+            );
+        }
+
         let mut module_filter = Box::new(move |module| {
             if let Some(instrument_module_predicate) = &mut instrument_module_predicate {
                 let skip = skip_module_predicate(&module);
@@ -524,6 +599,7 @@ impl Default for FridaInstrumentationHelperBuilder {
                 range.contains(&(Self::new as usize))
             }),
             skip_ranges: Vec::new(),
+            coverage_flavor: None,
         }
     }
 }
@@ -687,11 +763,26 @@ where
                             output.writer().pc()
                         );
                     }
+                    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+                    if let Some(rt) = runtimes.match_first_type_mut::<NgramCoverageRuntime>() {
+                        rt.emit_coverage_mapping(address, output);
+                    }
+                    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+                    if let Some(rt) = runtimes.match_first_type_mut::<CtxCoverageRuntime>() {
+                        rt.emit_coverage_mapping(address, output);
+                    }
                     if let Some(_rt) = runtimes.match_first_type_mut::<DrCovRuntime>() {
                         basic_block_start = address;
                     }
                 }
 
+                #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+                if let Some(rt) = runtimes.match_first_type_mut::<CtxCoverageRuntime>()
+                    && let Some(kind) = CtxCoverageRuntime::ctx_instruction(decoder, instr)
+                {
+                    rt.emit_context_update(address, kind, output);
+                }
+
                 let res = if let Some(_rt) = runtimes.match_first_type_mut::<AsanRuntime>() {
                     AsanRuntime::asan_is_interesting_instruction(decoder, address, instr)
                 } else {
@@ -861,12 +952,22 @@ where
         self.stalker_enabled
     }
 
-    /// Pointer to coverage map
+    /// Pointer to the map of the coverage runtime, if any
     pub fn map_mut_ptr(&mut self) -> Option<*mut u8> {
-        (*self.runtimes)
-            .borrow_mut()
-            .match_first_type_mut::<CoverageRuntime>()
-            .map(CoverageRuntime::map_mut_ptr)
+        let mut runtimes = (*self.runtimes).borrow_mut();
+        if let Some(rt) = runtimes.match_first_type_mut::<CoverageRuntime>() {
+            return Some(rt.map_mut_ptr());
+        }
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        {
+            if let Some(rt) = runtimes.match_first_type_mut::<NgramCoverageRuntime>() {
+                return Some(rt.map_mut_ptr());
+            }
+            if let Some(rt) = runtimes.match_first_type_mut::<CtxCoverageRuntime>() {
+                return Some(rt.map_mut_ptr());
+            }
+        }
+        None
     }
 
     /// Ranges
@@ -880,3 +981,37 @@ where
         (*self.ranges).borrow_mut()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::CoverageFlavor;
+    use crate::coverage_rt::CoverageRuntime;
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    use crate::{ctx_rt::CtxCoverageRuntime, ngram_rt::NgramCoverageRuntime};
+
+    #[test]
+    fn test_coverage_flavors() {
+        assert!(CoverageFlavor::of_runtimes(&tuple_list!()).is_empty());
+        assert_eq!(
+            CoverageFlavor::of_runtimes(&tuple_list!(CoverageRuntime::new())),
+            [CoverageFlavor::Edges]
+        );
+
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        {
+            assert_eq!(
+                CoverageFlavor::of_runtimes(&tuple_list!(NgramCoverageRuntime::new(4))),
+                [CoverageFlavor::Ngram]
+            );
+            assert_eq!(
+                CoverageFlavor::of_runtimes(&tuple_list!(
+                    CoverageRuntime::new(),
+                    CtxCoverageRuntime::new()
+                )),
+                [CoverageFlavor::Edges, CoverageFlavor::Context]
+            );
+        }
+    }
+}
@@ -56,6 +56,12 @@ pub mod windows_hooks;
 
 pub mod coverage_rt;
 
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub mod ctx_rt;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub mod ngram_rt;
+
 /// Hooking thread lifecycle events. Seems like this is apple-only for now.
 #[cfg(target_vendor = "apple")]
 pub mod pthread_hook;
@@ -63,6 +69,9 @@ pub mod pthread_hook;
 #[cfg(feature = "cmplog")]
 pub mod cmplog_rt;
 
+#[cfg(all(unix, any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub mod persistent;
+
 /// The `LibAFL` frida helper
 pub mod helper;
 
@@ -0,0 +1,247 @@
+//! Binary-only N-gram edge coverage.
+//!
+//! Instead of the edge from the previous block, the [`NgramCoverageRuntime`] records the path
+//! through the last `N` blocks, like `sancov_ngram4` and `sancov_ngram8` do for source targets.
+
+use alloc::rc::Rc;
+use core::{cell::RefCell, marker::PhantomPinned, pin::Pin};
+
+#[cfg(target_arch = "aarch64")]
+use dynasmrt::DynasmLabelApi;
+use dynasmrt::{DynasmApi, dynasm};
+use frida_gum::{ModuleMap, instruction_writer::InstructionWriter, stalker::StalkerOutput};
+use libafl_bolts::hash_std;
+use rangemap::RangeMap;
+
+use crate::{coverage_rt::MAP_SIZE, helper::FridaRuntime};
+
+/// The largest supported N-gram size
+pub const MAX_NGRAM_SIZE: usize = 16;
+
+#[derive(Debug)]
+struct NgramCoverageRuntimeInner {
+    map: [u8; MAP_SIZE],
+    /// The shifted locations of the last `n - 1` blocks, most recent first
+    history: [u64; MAX_NGRAM_SIZE - 1],
+    n: usize,
+    _pinned: PhantomPinned,
+}
+
+/// Frida binary-only N-gram coverage
+#[derive(Debug)]
+pub struct NgramCoverageRuntime(Pin<Rc<RefCell<NgramCoverageRuntimeInner>>>);
+
+impl FridaRuntime for NgramCoverageRuntime {
+    /// Initialize the coverage runtime
+    /// The struct MUST NOT be moved after this function is called, as the generated assembly references it
+    fn init(
+        &mut self,
+        _gum: &frida_gum::Gum,
+        _ranges: &RangeMap<u64, (u16, String)>,
+        _module_map: &Rc<ModuleMap>,
+    ) {
+    }
+
+    fn deinit(&mut self, _gum: &frida_gum::Gum) {}
+
+    fn pre_exec(&mut self, _input_bytes: &[u8]) -> Result<(), libafl::Error> {
+        // Paths must not reach into the previous execution
+        self.0.borrow_mut().history = [0; MAX_NGRAM_SIZE - 1];
+        Ok(())
+    }
+
+    fn post_exec(&mut self, _input_bytes: &[u8]) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
+
+impl NgramCoverageRuntime {
+    /// Create a new N-gram coverage runtime over paths of `n` blocks.
+    ///
+    /// # Panics
+    /// Panics if `n` is not between 2 and [`MAX_NGRAM_SIZE`].
+    #[allow(clippy::large_stack_arrays)]
+    #[must_use]
+    pub fn new(n: usize) -> Self {
+        assert!(
+            (2..=MAX_NGRAM_SIZE).contains(&n),
+            "N-gram size must be between 2 and {MAX_NGRAM_SIZE}"
+        );
+        Self(Rc::pin(RefCell::new(NgramCoverageRuntimeInner {
+            map: [0_u8; MAP_SIZE],
+            history: [0; MAX_NGRAM_SIZE - 1],
+            n,
+            _pinned: PhantomPinned,
+        })))
+    }
+
+    /// Retrieve the coverage map pointer
+    pub fn map_mut_ptr(&mut self) -> *mut u8 {
+        self.0.borrow_mut().map.as_mut_ptr()
+    }
+
+    /// The N-gram size
+    #[must_use]
+    pub fn n(&self) -> usize {
+        self.0.borrow().n
+    }
+
+    /// Write inline instrumentation for N-gram coverage
+    #[cfg(target_arch = "aarch64")]
+    #[expect(clippy::cast_possible_wrap)]
+    pub fn generate_inline_code(&mut self, h64: u64) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let history_ptr = &raw mut borrow.history;
+        let map_addr_ptr = &raw mut borrow.map;
+        let history_len = u32::try_from(borrow.n - 1).unwrap();
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::aarch64::Aarch64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch aarch64
+            // Store the context
+            ;   b >start
+
+            ;   stp x16, x17, [sp, -0x90]!
+            ; start:
+            ;   str x15, [sp, -0x10]!
+
+            // Calculate the N-gram id from the current and the previous locations
+            ;   ldr x16, >loc
+            ;   ldr x17, >history
+        );
+        for i in 0..history_len {
+            dynasm!(ops
+                ;   .arch aarch64
+                ;   ldr x15, [x17, i * 8]
+                ;   eor x16, x16, x15
+            );
+        }
+        dynasm!(ops
+            ;   .arch aarch64
+            // Load the map byte address
+            ;   ldr x17, >map_addr
+            ;   add x16, x17, x16
+
+            // Update the map byte
+            ;   ldrb w17, [x16]
+            ;   add w17, w17, #1
+            ;   add x17, x17, x17, lsr #8
+            ;   strb w17, [x16]
+
+            // Shift the history of previous locations
+            ;   ldr x17, >history
+        );
+        for i in (1..history_len).rev() {
+            dynasm!(ops
+                ;   .arch aarch64
+                ;   ldr x15, [x17, (i - 1) * 8]
+                ;   str x15, [x17, i * 8]
+            );
+        }
+        dynasm!(ops
+            ;   .arch aarch64
+            ;   ldr x15, >loc_shr
+            ;   str x15, [x17]
+
+            // Restore the context
+            ;   ldr x15, [sp], #0x10
+            ;   ldp x16, x17, [sp], #0x90
+
+            // Skip the data
+            ;   b >end
+
+            ;map_addr:
+            ;.i64 map_addr_ptr as i64
+            ;history:
+            ;.i64 history_ptr as i64
+            ;loc:
+            ;.i64 h64 as i64
+            ;loc_shr:
+            ;.i64 (h64 >> 1) as i64
+            ;end:
+        );
+        let ops_vec = ops.finalize().unwrap();
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Write inline instrumentation for N-gram coverage
+    #[cfg(target_arch = "x86_64")]
+    #[expect(clippy::cast_possible_wrap)]
+    pub fn generate_inline_code(&mut self, h64: u64) -> Box<[u8]> {
+        let mut borrow = self.0.borrow_mut();
+        let history_ptr = &raw mut borrow.history;
+        let map_addr_ptr = &raw mut borrow.map;
+        let history_len = i32::try_from(borrow.n - 1).unwrap();
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch x64
+            // Store the context
+            ; mov    QWORD [rsp-0x88], rax
+            ; lahf
+            ; mov    QWORD [rsp-0x90], rax
+            ; mov    QWORD [rsp-0x98], rbx
+
+            // Calculate the N-gram id from the current and the previous locations
+            ; mov rax, QWORD history_ptr as _
+            ; mov rbx, QWORD h64 as i64
+        );
+        for i in 0..history_len {
+            dynasm!(ops
+                ;   .arch x64
+                ; xor rbx, QWORD [rax + i * 8]
+            );
+        }
+        dynasm!(ops
+            ;   .arch x64
+            // Load the map byte address
+            ; mov rax, QWORD map_addr_ptr as _
+            ; add rax, rbx
+
+            // Update the map byte
+            ; mov bl, BYTE [rax]
+            ; add bl,0x1
+            ; adc bl,0x0
+            ; mov BYTE [rax],bl
+
+            // Shift the history of previous locations
+            ; mov rax, QWORD history_ptr as _
+        );
+        for i in (1..history_len).rev() {
+            dynasm!(ops
+                ;   .arch x64
+                ; mov rbx, QWORD [rax + (i - 1) * 8]
+                ; mov QWORD [rax + i * 8], rbx
+            );
+        }
+        dynasm!(ops
+            ;   .arch x64
+            ; mov rbx, QWORD (h64 >> 1) as i64
+            ; mov QWORD [rax], rbx
+
+            // Restore the context
+            ; mov    rbx, QWORD [rsp-0x98]
+            ; mov    rax, QWORD [rsp-0x90]
+            ; sahf
+            ; mov    rax, QWORD [rsp-0x88]
+        );
+        let ops_vec = ops.finalize().unwrap();
+
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Emits N-gram coverage mapping into the current basic block.
+    #[inline]
+    pub fn emit_coverage_mapping(&mut self, address: u64, output: &StalkerOutput) {
+        let h64 = hash_std(&address.to_le_bytes());
+        let writer = output.writer();
+
+        // Re-use the registers spilt by a long branch, see `CoverageRuntime::emit_coverage_mapping`
+        #[cfg(target_arch = "aarch64")]
+        {
+            let pc = writer.pc();
+            writer.reset(pc - 4);
+        }
+
+        let code = self.generate_inline_code(h64 & (MAP_SIZE as u64 - 1));
+        writer.put_bytes(&code);
+    }
+}
@@ -0,0 +1,483 @@
+//! Persistent mode for plain executables.
+//!
+//! Instead of a `LLVMFuzzerTestOneInput`-like harness, a [`FridaPersistentEntry`] hooks a
+//! function of the target, found by symbol or module offset. Once the target reaches it, the
+//! hook hands control to the fuzzer, which calls the function again for every input through a
+//! [`PersistentHarness`]. The input is passed in a chosen argument and can also be served to
+//! `read` and `fread` calls on a given file descriptor.
+//!
+//! At entry, the argument registers and the stack above the entry are snapshotted, and both
+//! are restored before each run, so every run starts from the same state.
+//! Only integer and pointer arguments, up to six of them, are supported.
+
+use core::{ffi::c_void, fmt, ptr};
+
+use frida_gum::{
+    Gum, Module, NativePointer, PageProtection, Process, RangeDetails, interceptor::Interceptor,
+};
+use libafl::{Error, executors::ExitKind};
+
+/// The number of arguments restored and passed to the entry function
+const PERSISTENT_ARG_COUNT: usize = 6;
+
+type EntryFunc = extern "C" fn(usize, usize, usize, usize, usize, usize) -> usize;
+
+/// The location of the function used as the persistent loop
+#[derive(Debug, Clone)]
+pub enum PersistentEntryPoint {
+    /// An exported symbol, in the given module or in any module
+    Symbol {
+        /// The module exporting the symbol, or `None` to search all modules
+        module: Option<String>,
+        /// The symbol name
+        name: String,
+    },
+    /// An offset from the base of the given module
+    Offset {
+        /// The module name
+        module: String,
+        /// The offset of the function in the module
+        offset: usize,
+    },
+    /// An absolute address, e.g. of a function linked into the fuzzer
+    Address(usize),
+}
+
+impl PersistentEntryPoint {
+    /// Resolve the address of the entry function
+    fn resolve(&self, gum: &Gum) -> Result<NativePointer, Error> {
+        let process = Process::obtain(gum);
+        let find_module = |module: &str| {
+            process
+                .find_module_by_name(module)
+                .ok_or_else(|| Error::illegal_argument(format!("Could not find module {module}")))
+        };
+        match self {
+            Self::Symbol { module: None, name } => Module::find_global_export_by_name(name)
+                .ok_or_else(|| Error::illegal_argument(format!("Could not find symbol {name}"))),
+            Self::Symbol {
+                module: Some(module),
+                name,
+            } => find_module(module)?
+                .find_export_by_name(name)
+                .ok_or_else(|| {
+                    Error::illegal_argument(format!("Could not find symbol {name} in {module}"))
+                }),
+            Self::Offset { module, offset } => {
+                let base = find_module(module)?.range().base_address().0 as usize;
+                Ok(NativePointer((base + offset) as *mut c_void))
+            }
+            Self::Address(address) => Ok(NativePointer(*address as *mut c_void)),
+        }
+    }
+}
+
+/// A hook turning a function of the target into the persistent loop
+#[derive(Debug)]
+pub struct FridaPersistentEntry {
+    entry: PersistentEntryPoint,
+    input_arg: Option<usize>,
+    len_arg: Option<usize>,
+    input_fd: Option<i32>,
+}
+
+impl FridaPersistentEntry {
+    /// Create a new persistent entry hook for the given function
+    #[must_use]
+    pub fn new(entry: PersistentEntryPoint) -> Self {
+        Self {
+            entry,
+            input_arg: None,
+            len_arg: None,
+            input_fd: None,
+        }
+    }
+
+    /// Pass a pointer to the input in the argument `input_arg`, and optionally its length in
+    /// the argument `len_arg`. Arguments are counted from 0.
+    ///
+    /// The input must not be modified by the target.
+    ///
+    /// # Panics
+    /// Panics if an argument index is out of range.
+    #[must_use]
+    pub fn input_argument(mut self, input_arg: usize, len_arg: Option<usize>) -> Self {
+        assert!(
+            input_arg < PERSISTENT_ARG_COUNT
+                && len_arg.is_none_or(|len_arg| len_arg < PERSISTENT_ARG_COUNT),
+            "Only the first {PERSISTENT_ARG_COUNT} arguments can be set"
+        );
+        self.input_arg = Some(input_arg);
+        self.len_arg = len_arg;
+        self
+    }
+
+    /// Serve the input to `read` and `fread` calls on the file descriptor `fd`
+    #[must_use]
+    pub fn input_fd(mut self, fd: i32) -> Self {
+        self.input_fd = Some(fd);
+        self
+    }
+
+    /// Install the hook. Once the target calls the entry function, `fuzz` is called with a
+    /// [`PersistentHarness`] running the entry function for each input. When `fuzz` returns,
+    /// the entry function runs with its original arguments and the target continues normally.
+    ///
+    /// The hook stays installed for the lifetime of the process.
+    ///
+    /// # Safety
+    /// The entry point must be a function taking integer or pointer arguments only.
+    pub unsafe fn install<F>(self, gum: &Gum, fuzz: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut PersistentHarness) + 'static,
+    {
+        let entry = self.entry.resolve(gum)?;
+        log::info!("Hooking persistent entry {:?} = {:?}", self.entry, entry.0);
+
+        let inner = Box::into_raw(Box::new(PersistentInner {
+            entry: unsafe { core::mem::transmute::<*mut c_void, EntryFunc>(entry.0) },
+            input_arg: self.input_arg,
+            len_arg: self.len_arg,
+            input_fd: self.input_fd,
+            fuzz: Some(Box::new(fuzz)),
+            input: (ptr::null(), 0),
+            input_offset: 0,
+            running: false,
+        }));
+
+        let mut interceptor = Interceptor::obtain(gum);
+        if let Err(err) = interceptor.replace(
+            entry,
+            NativePointer(replacement_entry as *mut c_void),
+            NativePointer(inner.cast()),
+        ) {
+            drop(unsafe { Box::from_raw(inner) });
+            return Err(Error::unknown(format!(
+                "Failed to hook the persistent entry: {err:?}"
+            )));
+        }
+
+        if self.input_fd.is_some() {
+            let mut hooked = vec![entry];
+            for (name, replacement) in [
+                ("read", replacement_read as *mut c_void),
+                ("fread", replacement_fread as *mut c_void),
+            ] {
+                let result = Module::find_global_export_by_name(name)
+                    .ok_or_else(|| Error::unknown(format!("Could not find {name}")))
+                    .and_then(|target| {
+                        interceptor
+                            .replace(
+                                target,
+                                NativePointer(replacement),
+                                NativePointer(inner.cast()),
+                            )
+                            .map(|_| target)
+                            .map_err(|err| {
+                                Error::unknown(format!("Failed to hook {name}: {err:?}"))
+                            })
+                    });
+                match result {
+                    Ok(target) => hooked.push(target),
+                    Err(err) => {
+                        // without the input, the target would run on whatever it reads itself
+                        for target in hooked {
+                            interceptor.revert(target);
+                        }
+                        drop(unsafe { Box::from_raw(inner) });
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct PersistentInner {
+    entry: EntryFunc,
+    input_arg: Option<usize>,
+    len_arg: Option<usize>,
+    input_fd: Option<i32>,
+    fuzz: Option<Box<dyn FnOnce(&mut PersistentHarness)>>,
+    /// The current input, valid while the entry function runs
+    input: (*const u8, usize),
+    /// The read position of the input through the file descriptor
+    input_offset: usize,
+    /// If the fuzzer is currently running the entry function
+    running: bool,
+}
+
+impl PersistentInner {
+    /// The part of the input not yet read through the file descriptor
+    fn remaining_input(&self) -> &[u8] {
+        if self.input.0.is_null() {
+            return &[];
+        }
+        let input = unsafe { core::slice::from_raw_parts(self.input.0, self.input.1) };
+        &input[self.input_offset.min(input.len())..]
+    }
+}
+
+/// Runs the hooked entry function of the target for an input
+pub struct PersistentHarness {
+    inner: *mut PersistentInner,
+    args: [usize; PERSISTENT_ARG_COUNT],
+    /// The lowest address of the snapshotted stack
+    stack_start: usize,
+    stack: Vec<u8>,
+}
+
+impl fmt::Debug for PersistentHarness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistentHarness")
+            .field("args", &self.args)
+            .field("stack_start", &self.stack_start)
+            .field("stack_size", &self.stack.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PersistentHarness {
+    /// Snapshot the arguments and the stack from `sp` up to the end of its mapping
+    fn new(inner: *mut PersistentInner, args: [usize; PERSISTENT_ARG_COUNT], sp: usize) -> Self {
+        let mut stack_end = 0;
+        RangeDetails::enumerate_with_prot(
+            PageProtection::ReadWrite,
+            &mut |range: &RangeDetails| {
+                let start = range.memory_range().base_address().0 as usize;
+                let end = start + range.memory_range().size();
+                if start <= sp && sp < end {
+                    stack_end = end;
+                    return false;
+                }
+                true
+            },
+        );
+        assert_ne!(stack_end, 0, "Couldn't find the stack mapping at {sp:#x}");
+
+        let stack =
+            unsafe { core::slice::from_raw_parts(sp as *const u8, stack_end - sp) }.to_vec();
+        log::info!(
+            "Persistent entry reached, snapshotted {:#x} bytes of stack",
+            stack.len()
+        );
+
+        Self {
+            inner,
+            args,
+            stack_start: sp,
+            stack,
+        }
+    }
+
+    /// Restore the snapshot and run the entry function with the given input
+    pub fn run(&mut self, input: &[u8]) -> ExitKind {
+        self.run_with(Some(input));
+        ExitKind::Ok
+    }
+
+    /// Restore the snapshot and run the entry function, returning its return value
+    fn run_with(&mut self, input: Option<&[u8]>) -> usize {
+        let inner = unsafe { &mut *self.inner };
+        let mut args = self.args;
+        if let Some(input) = input {
+            if let Some(input_arg) = inner.input_arg {
+                args[input_arg] = input.as_ptr() as usize;
+            }
+            if let Some(len_arg) = inner.len_arg {
+                args[len_arg] = input.len();
+            }
+            inner.input = (input.as_ptr(), input.len());
+        } else {
+            inner.input = (ptr::null(), 0);
+        }
+        inner.input_offset = 0;
+
+        // The stack above the entry belongs to the callers of the entry function,
+        // all frames of the fuzzer are below it.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.stack.as_ptr(),
+                self.stack_start as *mut u8,
+                self.stack.len(),
+            );
+        }
+
+        inner.running = input.is_some();
+        let entry = inner.entry;
+
+        // The `read` hooks access the state while the entry function runs
+        let ret = entry(args[0], args[1], args[2], args[3], args[4], args[5]);
+
+        let inner = unsafe { &mut *self.inner };
+        inner.running = false;
+        inner.input = (ptr::null(), 0);
+        ret
+    }
+}
+
+/// The replacement of the entry function, running the fuzzer on its first call
+extern "C" fn replacement_entry(
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> usize {
+    let args = [arg0, arg1, arg2, arg3, arg4, arg5];
+    let invocation = Interceptor::current_invocation();
+    let inner = invocation
+        .replacement_data()
+        .unwrap()
+        .0
+        .cast::<PersistentInner>();
+
+    let fuzz = unsafe { (*inner).fuzz.take() };
+    let Some(fuzz) = fuzz else {
+        // Fuzzing is over, or this is a call from within a run
+        return unsafe { ((*inner).entry)(arg0, arg1, arg2, arg3, arg4, arg5) };
+    };
+
+    #[cfg(target_arch = "x86_64")]
+    let sp = invocation.cpu_context().rsp() as usize;
+    #[cfg(target_arch = "aarch64")]
+    let sp = invocation.cpu_context().sp() as usize;
+
+    let mut harness = PersistentHarness::new(inner, args, sp);
+    fuzz(&mut harness);
+
+    log::info!("Fuzzing finished, resuming the target");
+    harness.run_with(None)
+}
+
+/// Serve the input to `read` on the input file descriptor
+unsafe extern "C" fn replacement_read(fd: i32, buf: *mut c_void, count: usize) -> isize {
+    let invocation = Interceptor::current_invocation();
+    let inner = unsafe {
+        &mut *invocation
+            .replacement_data()
+            .unwrap()
+            .0
+            .cast::<PersistentInner>()
+    };
+    if !inner.running || inner.input_fd != Some(fd) {
+        return unsafe { libc::read(fd, buf, count) };
+    }
+
+    let remaining = inner.remaining_input();
+    let len = remaining.len().min(count);
+    unsafe {
+        ptr::copy_nonoverlapping(remaining.as_ptr(), buf.cast(), len);
+    }
+    inner.input_offset += len;
+    len.try_into().unwrap()
+}
+
+/// Serve the input to `fread` on a stream of the input file descriptor
+unsafe extern "C" fn replacement_fread(
+    buf: *mut c_void,
+    size: usize,
+    nmemb: usize,
+    stream: *mut libc::FILE,
+) -> usize {
+    let invocation = Interceptor::current_invocation();
+    let inner = unsafe {
+        &mut *invocation
+            .replacement_data()
+            .unwrap()
+            .0
+            .cast::<PersistentInner>()
+    };
+    if !inner.running || size == 0 || inner.input_fd != Some(unsafe { libc::fileno(stream) }) {
+        return unsafe { libc::fread(buf, size, nmemb, stream) };
+    }
+
+    // only whole items are read, like from a file
+    let remaining = inner.remaining_input();
+    let items = (remaining.len() / size).min(nmemb);
+    unsafe {
+        ptr::copy_nonoverlapping(remaining.as_ptr(), buf.cast(), items * size);
+    }
+    inner.input_offset += items * size;
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::c_void;
+    use std::{fs::File, os::fd::IntoRawFd, sync::Mutex};
+
+    use frida_gum::Gum;
+    use libafl::executors::ExitKind;
+
+    use super::{FridaPersistentEntry, PersistentEntryPoint};
+
+    /// The counter value and the input seen by each run of [`persistent_target`]
+    static SEEN: Mutex<Vec<(u64, Vec<u8>)>> = Mutex::new(Vec::new());
+
+    /// Reads its input in two parts, through `read` and `fread`, and counts its runs in a
+    /// variable on the stack of the caller
+    #[inline(never)]
+    extern "C" fn persistent_target(fd: i32, stream: *mut libc::FILE, counter: *mut u64) -> usize {
+        let counter = unsafe { &mut *counter };
+        let seen = *counter;
+        *counter += 1;
+
+        let mut input = vec![0_u8; 64];
+        let head = unsafe { libc::read(fd, input.as_mut_ptr().cast::<c_void>(), 2) };
+        let head = usize::try_from(head).unwrap_or(0);
+        let tail = unsafe {
+            libc::fread(
+                input[head..].as_mut_ptr().cast::<c_void>(),
+                1,
+                input.len() - head,
+                stream,
+            )
+        };
+        input.truncate(head + tail);
+
+        SEEN.lock().unwrap().push((seen, input.clone()));
+        input.len()
+    }
+
+    #[test]
+    fn test_persistent_read_input() {
+        let gum = Gum::obtain();
+        let fd = File::open("/dev/null").unwrap().into_raw_fd();
+        let stream = unsafe { libc::fdopen(fd, c"r".as_ptr()) };
+        assert!(!stream.is_null());
+
+        let entry =
+            FridaPersistentEntry::new(PersistentEntryPoint::Address(persistent_target as usize))
+                .input_fd(fd);
+        unsafe {
+            entry.install(&gum, |harness| {
+                assert_eq!(harness.run(b"hello world"), ExitKind::Ok);
+                assert_eq!(harness.run(b"a"), ExitKind::Ok);
+            })
+        }
+        .unwrap();
+
+        let mut counter = 0_u64;
+        let target: extern "C" fn(i32, *mut libc::FILE, *mut u64) -> usize =
+            core::hint::black_box(persistent_target);
+        // the first call runs the fuzzer, then the target resumes on the real file
+        assert_eq!(target(fd, stream, &raw mut counter), 0);
+
+        // the stack is restored before every run, so each one sees the counter at 0
+        assert_eq!(counter, 1);
+        assert_eq!(
+            *SEEN.lock().unwrap(),
+            [
+                (0, b"hello world".to_vec()),
+                (0, b"a".to_vec()),
+                (0, Vec::new()),
+            ]
+        );
+
+        unsafe { libc::fclose(stream) };
+    }
+}
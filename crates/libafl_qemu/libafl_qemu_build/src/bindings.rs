@@ -51,6 +51,7 @@ const WRAPPER_HEADER: &str = r#"
 
 #include "migration/vmstate.h"
 #include "migration/savevm.h"
+#include "migration/snapshot.h"
 #include "hw/core/sysemu-cpu-ops.h"
 #include "exec/address-spaces.h"
 #include "exec/target_page.h"
@@ -132,6 +133,7 @@ pub fn generate(
         .allowlist_var("mmap_next_start")
         .allowlist_var("guest_base")
         .allowlist_var("exec_path")
+        .allowlist_var("ram_size")
         .allowlist_type("target_ulong")
         .allowlist_type("target_long")
         .allowlist_type("CPUState")
@@ -173,6 +175,7 @@ pub fn generate(
         .allowlist_function("vm_start")
         .allowlist_function("qemu_main_loop")
         .allowlist_function("qemu_cleanup")
+        .allowlist_function("delete_snapshot")
         .blocklist_function("main_loop_wait") // bindgen issue #1313
         .blocklist_type("siginfo_t")
         .raw_line("use libc::siginfo_t;")
@@ -7,6 +7,7 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 #[allow(unused)]
 use pyo3::prelude::*;
 pub use strum_macros::EnumIter;
+use strum_macros::EnumString;
 pub use syscall_numbers::aarch64::*;
 
 use crate::{CallingConvention, GuestAddr, QemuRWError, QemuRWErrorKind, sync_exit::ExitArgs};
@@ -55,6 +56,47 @@ pub enum Regs {
     Pstate = 33,
 }
 
+/// The SIMD&FP registers, numbered like gdb does
+///
+/// They are wider than [`GuestReg`], read them with [`crate::CPU::read_reg_bytes`].
+#[derive(IntoPrimitive, TryFromPrimitive, Debug, Copy, Clone, EnumIter, EnumString)]
+#[strum(serialize_all = "lowercase")]
+#[repr(i32)]
+pub enum VecRegs {
+    V0 = 34,
+    V1 = 35,
+    V2 = 36,
+    V3 = 37,
+    V4 = 38,
+    V5 = 39,
+    V6 = 40,
+    V7 = 41,
+    V8 = 42,
+    V9 = 43,
+    V10 = 44,
+    V11 = 45,
+    V12 = 46,
+    V13 = 47,
+    V14 = 48,
+    V15 = 49,
+    V16 = 50,
+    V17 = 51,
+    V18 = 52,
+    V19 = 53,
+    V20 = 54,
+    V21 = 55,
+    V22 = 56,
+    V23 = 57,
+    V24 = 58,
+    V25 = 59,
+    V26 = 60,
+    V27 = 61,
+    V28 = 62,
+    V29 = 63,
+    V30 = 64,
+    V31 = 65,
+}
+
 static EXIT_ARCH_REGS: OnceLock<EnumMap<ExitArgs, Regs>> = OnceLock::new();
 
 pub fn get_exit_arch_regs() -> &'static EnumMap<ExitArgs, Regs> {
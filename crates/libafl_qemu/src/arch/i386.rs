@@ -6,6 +6,7 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 pub use strum_macros::EnumIter;
+use strum_macros::EnumString;
 pub use syscall_numbers::x86::*;
 
 use crate::{CallingConvention, GuestAddr, QemuRWError, QemuRWErrorKind, sync_exit::ExitArgs};
@@ -30,6 +31,23 @@ pub enum Regs {
     Eflags = 9,
 }
 
+/// The SSE registers, numbered like gdb does
+///
+/// They are wider than [`GuestReg`], read them with [`crate::CPU::read_reg_bytes`].
+#[derive(IntoPrimitive, TryFromPrimitive, Debug, Copy, Clone, EnumIter, EnumString)]
+#[strum(serialize_all = "lowercase")]
+#[repr(i32)]
+pub enum VecRegs {
+    Xmm0 = 32,
+    Xmm1 = 33,
+    Xmm2 = 34,
+    Xmm3 = 35,
+    Xmm4 = 36,
+    Xmm5 = 37,
+    Xmm6 = 38,
+    Xmm7 = 39,
+}
+
 static EXIT_ARCH_REGS: OnceLock<EnumMap<ExitArgs, Regs>> = OnceLock::new();
 
 pub fn get_exit_arch_regs() -> &'static EnumMap<ExitArgs, Regs> {
@@ -4,6 +4,7 @@ use capstone::arch::BuildsCapstone;
 use enum_map::{EnumMap, enum_map};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 pub use strum_macros::EnumIter;
+use strum_macros::EnumString;
 pub use syscall_numbers::x86_64::*;
 
 use crate::{CallingConvention, GuestAddr, QemuRWError, QemuRWErrorKind, sync_exit::ExitArgs};
@@ -36,6 +37,31 @@ pub enum Regs {
     Rflags = 17,
 }
 
+/// The SSE registers, numbered like gdb does
+///
+/// They are wider than [`GuestReg`], read them with [`crate::CPU::read_reg_bytes`].
+#[derive(IntoPrimitive, TryFromPrimitive, Debug, Copy, Clone, EnumIter, EnumString)]
+#[strum(serialize_all = "lowercase")]
+#[repr(i32)]
+pub enum VecRegs {
+    Xmm0 = 40,
+    Xmm1 = 41,
+    Xmm2 = 42,
+    Xmm3 = 43,
+    Xmm4 = 44,
+    Xmm5 = 45,
+    Xmm6 = 46,
+    Xmm7 = 47,
+    Xmm8 = 48,
+    Xmm9 = 49,
+    Xmm10 = 50,
+    Xmm11 = 51,
+    Xmm12 = 52,
+    Xmm13 = 53,
+    Xmm14 = 54,
+    Xmm15 = 55,
+}
+
 static EXIT_ARCH_REGS: OnceLock<EnumMap<ExitArgs, Regs>> = OnceLock::new();
 
 pub fn get_exit_arch_regs() -> &'static EnumMap<ExitArgs, Regs> {
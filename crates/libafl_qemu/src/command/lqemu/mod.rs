@@ -97,8 +97,20 @@ pub enum NativeExitKind {
     Crash = libvharness_sys::LibaflQemuEndStatus_LIBAFL_QEMU_END_CRASH.0 as u64, // Crash reported in the VM
 }
 
+/// Marks a save as a checkpoint: with this value as second argument, the first argument is the
+/// number of input bytes the target consumed so far.
+/// A plain save, as issued by existing harnesses, leaves both arguments alone.
+pub const SAVE_CHECKPOINT_MAGIC: GuestReg = 0x4c51_4350;
+
+/// Save the VM before fuzzing starts, to restore it after each run.
+///
+/// Once fuzzing started, if the driver has a [`crate::SnapshotTree`], a checkpoint save (see
+/// [`SAVE_CHECKPOINT_MAGIC`]) takes a child snapshot instead, after the target consumed
+/// `input_consumed` bytes of the input.
 #[derive(Debug, Clone)]
-pub struct SaveCommand;
+pub struct SaveCommand {
+    input_consumed: Option<GuestReg>,
+}
 impl<C, CM, ET, I, IS, S, SM> IsCommand<C, CM, GenericEmulatorDriver<IS>, ET, I, S, SM>
     for SaveCommand
 where
@@ -117,6 +129,14 @@ where
         _ret_reg: Option<Regs>,
     ) -> Result<Option<EmulatorDriverResult<C>>, EmulatorDriverError> {
         let qemu = emu.qemu();
+
+        #[cfg(feature = "systemmode")]
+        if let Some(input_consumed) = self.input_consumed {
+            if emu.driver().snapshot_id().is_some() && emu.checkpoint(input_consumed as usize) {
+                return Ok(None);
+            }
+        }
+
         let snapshot_id = emu.snapshot_manager_mut().save(qemu);
 
         emu.driver_mut()
@@ -490,7 +510,10 @@ impl AddressAllowCommand {
 
 impl Display for SaveCommand {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Save VM")
+        match self.input_consumed {
+            Some(input_consumed) => write!(f, "Save VM ({input_consumed} input bytes consumed)"),
+            None => write!(f, "Save VM"),
+        }
     }
 }
 
@@ -531,6 +554,13 @@ impl Display for PageAllowCommand {
     }
 }
 
+impl SaveCommand {
+    #[must_use]
+    pub fn new(input_consumed: Option<GuestReg>) -> Self {
+        Self { input_consumed }
+    }
+}
+
 impl StartCommand {
     #[must_use]
     pub fn new(input_location: InputLocation) -> Self {
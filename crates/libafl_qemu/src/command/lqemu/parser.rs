@@ -10,8 +10,8 @@ use libafl_qemu_sys::{GuestAddr, GuestVirtAddr};
 use libc::c_uint;
 
 use super::{
-    AddressAllowCommand, EndCommand, LoadCommand, LqprintfCommand, NativeExitKind, SaveCommand,
-    StartCommand, TestCommand, VersionCommand,
+    AddressAllowCommand, EndCommand, LoadCommand, LqprintfCommand, NativeExitKind,
+    SAVE_CHECKPOINT_MAGIC, SaveCommand, StartCommand, TestCommand, VersionCommand,
 };
 use crate::{
     GenericEmulatorDriver, GuestReg, InputLocation, InputSetter, IsSnapshotManager, Qemu,
@@ -126,10 +126,17 @@ where
     const COMMAND_ID: c_uint = libvharness_sys::LibaflQemuCommand_LIBAFL_QEMU_COMMAND_SAVE.0;
 
     fn parse(
-        _qemu: Qemu,
-        _arch_regs_map: &'static EnumMap<ExitArgs, Regs>,
+        qemu: Qemu,
+        arch_regs_map: &'static EnumMap<ExitArgs, Regs>,
     ) -> Result<Self::OutputCommand, CommandError> {
-        Ok(SaveCommand)
+        let magic: GuestReg = qemu.read_reg(arch_regs_map[ExitArgs::Arg2])?;
+        let input_consumed = if magic == SAVE_CHECKPOINT_MAGIC {
+            Some(qemu.read_reg(arch_regs_map[ExitArgs::Arg1])?)
+        } else {
+            None
+        };
+
+        Ok(SaveCommand::new(input_consumed))
     }
 }
 
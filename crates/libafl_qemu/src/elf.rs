@@ -2,7 +2,12 @@
 
 use std::{fs::File, io::Read, ops::Range, path::Path, str};
 
-use goblin::elf::{Elf, header::ET_DYN};
+use goblin::elf::{
+    Elf,
+    header::ET_DYN,
+    section_header::SHN_UNDEF,
+    sym::{STB_GLOBAL, STB_WEAK},
+};
 use libafl::Error;
 use libafl_qemu_sys::GuestAddr;
 
@@ -52,28 +57,72 @@ impl<'a> EasyElf<'a> {
             if let Some(sym_name) = self.elf.strtab.get_at(sym.st_name)
                 && sym_name == name
             {
-                return if sym.st_value == 0 {
-                    None
-                } else if self.is_pic() {
-                    #[cfg(cpu_target = "arm")]
-                    // Required because of arm interworking addresses aka bit(0) for thumb mode
-                    let addr = (sym.st_value as GuestAddr + load_addr) & !(0x1 as GuestAddr);
-                    #[cfg(not(cpu_target = "arm"))]
-                    let addr = sym.st_value as GuestAddr + load_addr;
-                    Some(addr)
-                } else {
-                    #[cfg(cpu_target = "arm")]
-                    // Required because of arm interworking addresses aka bit(0) for thumb mode
-                    let addr = (sym.st_value as GuestAddr) & !(0x1 as GuestAddr);
-                    #[cfg(not(cpu_target = "arm"))]
-                    let addr = sym.st_value as GuestAddr;
-                    Some(addr)
-                };
+                return self.symbol_addr(sym.st_value, load_addr);
             }
         }
         None
     }
 
+    /// Resolve a symbol exported in the `.dynsym` table, i.e. the functions and variables
+    /// a shared library exports. Unlike the `.symtab`, this table survives stripping.
+    ///
+    /// A library can export several versions of a symbol under the same name, like glibc's
+    /// `memcpy@GLIBC_2.2.5` and `memcpy@@GLIBC_2.14`. The version is not part of the name in the
+    /// `.dynsym`, but kept in the `.gnu.version` table. Like the dynamic linker, this prefers the
+    /// default version, i.e. the one not marked as hidden.
+    #[must_use]
+    pub fn resolve_dynamic_symbol(&self, name: &str, load_addr: GuestAddr) -> Option<GuestAddr> {
+        let mut hidden_version = None;
+        for (idx, sym) in self.elf.dynsyms.iter().enumerate() {
+            // Skip imports, they are defined in some other object
+            if sym.st_shndx == SHN_UNDEF as usize || !matches!(sym.st_bind(), STB_GLOBAL | STB_WEAK)
+            {
+                continue;
+            }
+            if self.elf.dynstrtab.get_at(sym.st_name) != Some(name) {
+                continue;
+            }
+            let hidden = self
+                .elf
+                .versym
+                .as_ref()
+                .and_then(|versym| versym.get_at(idx))
+                .is_some_and(|versym| versym.is_hidden());
+            if !hidden {
+                return self.symbol_addr(sym.st_value, load_addr);
+            }
+            hidden_version = hidden_version.or(Some(sym.st_value));
+        }
+        hidden_version.and_then(|st_value| self.symbol_addr(st_value, load_addr))
+    }
+
+    /// Resolve a symbol in the `.symtab` first, and fall back to the exports in the `.dynsym`
+    #[must_use]
+    pub fn resolve_any_symbol(&self, name: &str, load_addr: GuestAddr) -> Option<GuestAddr> {
+        self.resolve_symbol(name, load_addr)
+            .or_else(|| self.resolve_dynamic_symbol(name, load_addr))
+    }
+
+    fn symbol_addr(&self, st_value: u64, load_addr: GuestAddr) -> Option<GuestAddr> {
+        if st_value == 0 {
+            None
+        } else if self.is_pic() {
+            #[cfg(cpu_target = "arm")]
+            // Required because of arm interworking addresses aka bit(0) for thumb mode
+            let addr = (st_value as GuestAddr + load_addr) & !(0x1 as GuestAddr);
+            #[cfg(not(cpu_target = "arm"))]
+            let addr = st_value as GuestAddr + load_addr;
+            Some(addr)
+        } else {
+            #[cfg(cpu_target = "arm")]
+            // Required because of arm interworking addresses aka bit(0) for thumb mode
+            let addr = (st_value as GuestAddr) & !(0x1 as GuestAddr);
+            #[cfg(not(cpu_target = "arm"))]
+            let addr = st_value as GuestAddr;
+            Some(addr)
+        }
+    }
+
     #[must_use]
     pub fn get_section(&self, name: &str, load_addr: GuestAddr) -> Option<Range<GuestAddr>> {
         for section in &self.elf.section_headers {
@@ -115,3 +164,33 @@ impl<'a> EasyElf<'a> {
         self.elf.header.e_type == ET_DYN
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::EasyElf;
+
+    /// A stripped shared library, see `tests/fixtures/versioned.s` for its source
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/versioned.elf");
+
+    #[test]
+    fn test_resolve_dynamic_symbol() {
+        let elf = EasyElf::from_slice(FIXTURE).unwrap();
+        assert!(elf.is_pic());
+
+        assert_eq!(elf.resolve_dynamic_symbol("exported", 0x1000), Some(0x12f0));
+        // the default version wins over the hidden one
+        assert_eq!(elf.resolve_dynamic_symbol("memcpy", 0x1000), Some(0x12d0));
+        // a hidden version is still found if there is no default one
+        assert_eq!(elf.resolve_dynamic_symbol("legacy", 0x1000), Some(0x12e0));
+
+        // imports, local symbols and version names are not exports
+        assert_eq!(elf.resolve_dynamic_symbol("imported", 0x1000), None);
+        assert_eq!(elf.resolve_dynamic_symbol("internal", 0x1000), None);
+        assert_eq!(elf.resolve_dynamic_symbol("V1", 0x1000), None);
+        assert_eq!(elf.resolve_dynamic_symbol("missing", 0x1000), None);
+
+        // the fixture is stripped, so only the .dynsym knows the symbol
+        assert_eq!(elf.resolve_symbol("exported", 0x1000), None);
+        assert_eq!(elf.resolve_any_symbol("exported", 0x1000), Some(0x12f0));
+    }
+}
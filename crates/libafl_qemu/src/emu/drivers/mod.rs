@@ -9,7 +9,8 @@ use libafl::{executors::ExitKind, inputs::HasTargetBytes, observers::ObserversTu
 use libafl_bolts::os::{CTRL_C_EXIT, unix_signals::Signal};
 
 #[cfg(feature = "systemmode")]
-use crate::PhysMemoryChunk;
+use libafl_bolts::AsSlice;
+
 use crate::{
     Emulator, EmulatorExitError, EmulatorExitResult, InputLocation, IsSnapshotManager, Qemu,
     QemuError, QemuShutdownCause, Regs, SnapshotId, SnapshotManagerCheckError,
@@ -17,6 +18,8 @@ use crate::{
     command::{CommandError, CommandManager, IsCommand},
     modules::EmulatorModuleTuple,
 };
+#[cfg(feature = "systemmode")]
+use crate::{PhysMemoryChunk, SnapshotTree};
 
 #[cfg(not(feature = "nyx"))]
 pub mod lqemu;
@@ -126,8 +129,9 @@ where
         emulator: &mut Emulator<C, CM, Self, ET, I, S, SM>,
         state: &mut S,
         input: &I,
-    ) {
+    ) -> Result<(), EmulatorDriverError> {
         emulator.modules.pre_exec_all(emulator.qemu, state, input);
+        Ok(())
     }
 
     /// Just after returning from user's harness
@@ -186,6 +190,8 @@ pub struct StdEmulatorDriverBuilder<IS> {
     #[cfg(feature = "x86_64")]
     process_only: bool,
     print_commands: bool,
+    #[cfg(feature = "systemmode")]
+    snapshot_tree: Option<SnapshotTree>,
 }
 
 impl<IS> Default for StdEmulatorDriverBuilder<IS>
@@ -201,6 +207,8 @@ where
             #[cfg(feature = "x86_64")]
             process_only: false,
             print_commands: false,
+            #[cfg(feature = "systemmode")]
+            snapshot_tree: None,
         }
     }
 }
@@ -212,6 +220,7 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
         #[cfg(feature = "systemmode")] allow_page_on_start: bool,
         #[cfg(feature = "x86_64")] process_only: bool,
         print_commands: bool,
+        #[cfg(feature = "systemmode")] snapshot_tree: Option<SnapshotTree>,
     ) -> Self {
         Self {
             input_setter,
@@ -221,6 +230,8 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             #[cfg(feature = "x86_64")]
             process_only,
             print_commands,
+            #[cfg(feature = "systemmode")]
+            snapshot_tree,
         }
     }
 
@@ -233,6 +244,8 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             #[cfg(feature = "x86_64")]
             self.process_only,
             self.print_commands,
+            #[cfg(feature = "systemmode")]
+            self.snapshot_tree,
         )
     }
 
@@ -246,6 +259,8 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             #[cfg(feature = "x86_64")]
             self.process_only,
             self.print_commands,
+            #[cfg(feature = "systemmode")]
+            self.snapshot_tree,
         )
     }
 
@@ -258,6 +273,8 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             #[cfg(feature = "x86_64")]
             self.process_only,
             self.print_commands,
+            #[cfg(feature = "systemmode")]
+            self.snapshot_tree,
         )
     }
 
@@ -270,6 +287,8 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             self.allow_page_on_start,
             process_only,
             self.print_commands,
+            #[cfg(feature = "systemmode")]
+            self.snapshot_tree,
         )
     }
 
@@ -283,6 +302,24 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             #[cfg(feature = "x86_64")]
             self.process_only,
             print_commands,
+            #[cfg(feature = "systemmode")]
+            self.snapshot_tree,
+        )
+    }
+
+    /// Take child snapshots in `snapshot_tree` when the target saves the VM after fuzzing started,
+    /// and start each run from the snapshot with the longest matching input prefix.
+    #[cfg(feature = "systemmode")]
+    #[must_use]
+    pub fn snapshot_tree(self, snapshot_tree: SnapshotTree) -> Self {
+        Self::new(
+            self.input_setter,
+            self.hooks_locked,
+            self.allow_page_on_start,
+            #[cfg(feature = "x86_64")]
+            self.process_only,
+            self.print_commands,
+            Some(snapshot_tree),
         )
     }
 
@@ -298,6 +335,10 @@ impl<IS> StdEmulatorDriverBuilder<IS> {
             print_commands: self.print_commands,
             #[cfg(feature = "systemmode")]
             maps: HashMap::new(),
+            #[cfg(feature = "systemmode")]
+            snapshot_tree: self.snapshot_tree,
+            #[cfg(feature = "systemmode")]
+            current_input: Vec::new(),
         }
     }
 }
@@ -316,6 +357,11 @@ pub struct GenericEmulatorDriver<IS> {
     // maps declared by the VM
     #[cfg(feature = "systemmode")]
     maps: HashMap<MapKind, PhysMemoryChunk>,
+    #[cfg(feature = "systemmode")]
+    snapshot_tree: Option<SnapshotTree>,
+    // the target bytes of the current run, to key the snapshot tree
+    #[cfg(feature = "systemmode")]
+    current_input: Vec<u8>,
 }
 
 #[cfg(not(feature = "nyx"))]
@@ -389,6 +435,40 @@ impl<IS> GenericEmulatorDriver<IS> {
     pub fn maps_mut(&mut self) -> &mut HashMap<MapKind, PhysMemoryChunk> {
         &mut self.maps
     }
+
+    #[cfg(feature = "systemmode")]
+    pub fn snapshot_tree(&self) -> Option<&SnapshotTree> {
+        self.snapshot_tree.as_ref()
+    }
+
+    #[cfg(feature = "systemmode")]
+    pub fn snapshot_tree_mut(&mut self) -> Option<&mut SnapshotTree> {
+        self.snapshot_tree.as_mut()
+    }
+}
+
+#[cfg(feature = "systemmode")]
+impl<C, CM, ET, I, IS, S, SM> Emulator<C, CM, GenericEmulatorDriver<IS>, ET, I, S, SM>
+where
+    SM: IsSnapshotManager,
+{
+    /// Take a child snapshot in the driver's [`SnapshotTree`], now that the target consumed
+    /// `input_consumed` bytes of the current input.
+    /// Returns `false` if the driver has no snapshot tree.
+    pub fn checkpoint(&mut self, input_consumed: usize) -> bool {
+        let Some(snapshot_tree) = self.driver.snapshot_tree.as_mut() else {
+            return false;
+        };
+        let current_input = &self.driver.current_input;
+        let prefix = &current_input[..input_consumed.min(current_input.len())];
+        snapshot_tree.checkpoint(
+            self.qemu,
+            &mut self.snapshot_manager,
+            prefix,
+            current_input.len(),
+        );
+        true
+    }
 }
 
 // TODO: replace handlers with generics to permit compile-time customization of handlers
@@ -410,16 +490,43 @@ where
         emulator: &mut Emulator<C, CM, Self, ET, I, S, SM>,
         state: &mut S,
         input: &I,
-    ) {
+    ) -> Result<(), EmulatorDriverError> {
         emulator.modules.pre_exec_all(emulator.qemu, state, input);
 
+        // start from the deepest child snapshot sharing a prefix with the input, if any.
+        // Only snapshots of runs with an input of the same length are candidates.
+        #[cfg(feature = "systemmode")]
+        if let Some(snapshot_tree) = emulator.driver.snapshot_tree.as_mut() {
+            let current_input = &mut emulator.driver.current_input;
+            current_input.clear();
+            current_input.extend_from_slice(input.target_bytes().as_slice());
+
+            let restored = snapshot_tree.restore_deepest(
+                emulator.qemu,
+                &mut emulator.snapshot_manager,
+                current_input,
+            )?;
+
+            // The target is in the middle of the run, it already consumed the prefix and got
+            // the input length, which is the same, back when the snapshot was taken.
+            // Its registers must stay untouched, we only refresh the input buffer: the prefix
+            // is identical, the rest is what the target reads from now on.
+            if restored.is_some() {
+                if let Some(input_location) = emulator.driver.input_setter.input_location() {
+                    unsafe {
+                        input_location.location().write(current_input);
+                    }
+                }
+                return Ok(());
+            }
+        }
+
         // set the input in the target, according the input setter
         // this should be run iif the emulator is "started".
         emulator
             .driver
             .input_setter
             .write_input(emulator.qemu, state, input)
-            .unwrap();
     }
 
     fn post_harness_exec<OT>(
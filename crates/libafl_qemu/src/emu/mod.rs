@@ -33,6 +33,11 @@ pub use drivers::*;
 mod snapshot;
 pub use snapshot::*;
 
+#[cfg(feature = "systemmode")]
+pub mod snapshot_tree;
+#[cfg(feature = "systemmode")]
+pub use snapshot_tree::SnapshotTree;
+
 #[cfg(feature = "usermode")]
 mod usermode;
 #[cfg(feature = "usermode")]
@@ -513,8 +518,8 @@ where
     }
 
     /// Pre exec of Emulator, called before calling to user harness
-    pub fn pre_exec(&mut self, state: &mut S, input: &I) {
-        ED::pre_harness_exec(self, state, input);
+    pub fn pre_exec(&mut self, state: &mut S, input: &I) -> Result<(), EmulatorDriverError> {
+        ED::pre_harness_exec(self, state, input)
     }
 
     /// Post exec of Emulator, called before calling to user harness
@@ -17,6 +17,15 @@ pub trait IsSnapshotManager: Clone + Debug {
         reference_snapshot_id: &SnapshotId,
     ) -> Result<QemuSnapshotCheckResult, SnapshotManagerError>;
 
+    /// Free a snapshot that will not be restored anymore.
+    /// Managers that cannot free snapshots do nothing.
+    fn delete(&mut self, _qemu: Qemu, _snapshot_id: &SnapshotId) {}
+
+    /// The memory used by a snapshot, if the manager knows it
+    fn snapshot_size(&self, _qemu: Qemu, _snapshot_id: &SnapshotId) -> Option<usize> {
+        None
+    }
+
     fn check(
         &self,
         qemu: Qemu,
@@ -0,0 +1,446 @@
+//! A tree of snapshots, keyed by the input prefix that led to them and the input length.
+//!
+//! Stateful targets, like an OS going through a multi-step login, usually consume their input
+//! in several steps, with a sync exit between the steps.
+//! Instead of re-running the same prefix for every input, the harness can take a child snapshot
+//! at such a sync exit with [`SnapshotTree::checkpoint`], and start later inputs sharing that
+//! prefix from there with [`SnapshotTree::restore_deepest`].
+//!
+//! Given to the driver with [`crate::StdEmulatorDriverBuilder::snapshot_tree`], this happens on
+//! its own: a checkpoint save sync exit after fuzzing started takes a child snapshot, with the
+//! number of input bytes the target consumed so far as first argument and
+//! [`crate::command::lqemu::SAVE_CHECKPOINT_MAGIC`] as second argument, and every run starts from
+//! the deepest matching snapshot.
+//!
+//! The target read the length of its input before the snapshot was taken, so a snapshot is only
+//! used for inputs of the same length as the run that took it.
+//!
+//! The tree is bounded by an (estimated) memory budget. When the budget is exceeded, the least
+//! recently used leaves are evicted first, so that the hot prefixes stay available.
+
+use std::collections::BTreeMap;
+
+use hashbrown::HashMap;
+use libafl_bolts::generic_hash_std;
+
+use crate::{IsSnapshotManager, Qemu, SnapshotId, SnapshotManagerError};
+
+/// The default memory budget of a [`SnapshotTree`], 1 GiB
+pub const DEFAULT_SNAPSHOT_TREE_BUDGET: usize = 1 << 30;
+/// The default size estimate of a snapshot, if the snapshot manager cannot tell, 16 MiB
+pub const DEFAULT_SNAPSHOT_SIZE: usize = 16 << 20;
+
+#[derive(Debug, Clone)]
+struct SnapshotNode {
+    snapshot_id: SnapshotId,
+    prefix: Vec<u8>,
+    input_len: usize,
+    parent: Option<u64>,
+    children: usize,
+    last_used: u64,
+    size: usize,
+}
+
+/// A tree of child snapshots, see the [module level documentation](self).
+///
+/// The root snapshot, taken at the start of fuzzing, is not part of the tree, it is never evicted.
+#[derive(Debug, Clone)]
+pub struct SnapshotTree {
+    /// The nodes, keyed by the hash of their input length and prefix
+    nodes: HashMap<u64, SnapshotNode>,
+    /// How many nodes have a prefix of a given length
+    prefix_lens: BTreeMap<usize, usize>,
+    budget: usize,
+    default_snapshot_size: usize,
+    used: usize,
+    clock: u64,
+}
+
+impl Default for SnapshotTree {
+    fn default() -> Self {
+        Self::new(DEFAULT_SNAPSHOT_TREE_BUDGET)
+    }
+}
+
+impl SnapshotTree {
+    /// Create a new, empty, [`SnapshotTree`] using at most `budget` bytes for snapshots
+    #[must_use]
+    pub fn new(budget: usize) -> Self {
+        Self {
+            nodes: HashMap::new(),
+            prefix_lens: BTreeMap::new(),
+            budget,
+            default_snapshot_size: DEFAULT_SNAPSHOT_SIZE,
+            used: 0,
+            clock: 0,
+        }
+    }
+
+    /// The size to account for a snapshot, if [`IsSnapshotManager::snapshot_size`] does not know
+    #[must_use]
+    pub fn with_default_snapshot_size(mut self, size: usize) -> Self {
+        self.default_snapshot_size = size;
+        self
+    }
+
+    /// The number of snapshots in the tree
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// If the tree has no snapshots
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The estimated memory used by the snapshots in the tree
+    #[must_use]
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn key(prefix: &[u8], input_len: usize) -> u64 {
+        generic_hash_std(&(input_len, prefix))
+    }
+
+    /// Get the node for exactly this `prefix` of an input of `input_len` bytes
+    fn get(&self, prefix: &[u8], input_len: usize) -> Option<u64> {
+        let key = Self::key(prefix, input_len);
+        self.nodes
+            .get(&key)
+            .is_some_and(|node| node.input_len == input_len && node.prefix == prefix)
+            .then_some(key)
+    }
+
+    /// Find the node with the longest prefix of `input`, not longer than `max_len`,
+    /// taken during a run with an input of `input_len` bytes
+    fn find_deepest(&self, input: &[u8], max_len: usize, input_len: usize) -> Option<u64> {
+        let max_len = max_len.min(input.len());
+        self.prefix_lens
+            .range(..=max_len)
+            .rev()
+            .find_map(|(&len, _)| self.get(&input[..len], input_len))
+    }
+
+    /// Get the snapshot with the longest prefix of `input`, taken during a run with an input of
+    /// the same length, and the length of that prefix.
+    /// The target already consumed the prefix in this snapshot, it should continue with the rest.
+    pub fn lookup(&mut self, input: &[u8]) -> Option<(SnapshotId, usize)> {
+        let key = self.find_deepest(input, input.len(), input.len())?;
+        let now = self.tick();
+        let node = self.nodes.get_mut(&key).unwrap();
+        node.last_used = now;
+        Some((node.snapshot_id, node.prefix.len()))
+    }
+
+    /// Check if there is a snapshot for exactly this `prefix` of an input of `input_len` bytes
+    #[must_use]
+    pub fn contains(&self, prefix: &[u8], input_len: usize) -> bool {
+        self.get(prefix, input_len).is_some()
+    }
+
+    /// Add the snapshot `snapshot_id`, taken after the target consumed `prefix` of an input of
+    /// `input_len` bytes, using `size` bytes.
+    /// Returns the snapshots evicted to stay within the budget, they should be deleted.
+    /// The new snapshot itself is never evicted right away.
+    pub fn insert(
+        &mut self,
+        prefix: &[u8],
+        input_len: usize,
+        snapshot_id: SnapshotId,
+        size: usize,
+    ) -> Vec<SnapshotId> {
+        let key = Self::key(prefix, input_len);
+        let parent = if prefix.is_empty() {
+            None
+        } else {
+            self.find_deepest(prefix, prefix.len() - 1, input_len)
+        };
+        let now = self.tick();
+
+        // Same prefix, replace the old snapshot. Its children now descend from the new one.
+        let mut evicted = Vec::new();
+        let children = match self.nodes.remove(&key) {
+            Some(old) => {
+                self.remove_accounting(&old);
+                evicted.push(old.snapshot_id);
+                old.children
+            }
+            None => 0,
+        };
+        self.nodes.insert(
+            key,
+            SnapshotNode {
+                snapshot_id,
+                prefix: prefix.to_vec(),
+                input_len,
+                parent,
+                children,
+                last_used: now,
+                size,
+            },
+        );
+        *self.prefix_lens.entry(prefix.len()).or_default() += 1;
+        self.used += size;
+        if let Some(parent) = parent {
+            self.nodes.get_mut(&parent).unwrap().children += 1;
+        }
+
+        evicted.extend(self.evict(key));
+        evicted
+    }
+
+    fn remove_accounting(&mut self, node: &SnapshotNode) {
+        self.used -= node.size;
+        let len = node.prefix.len();
+        if let Some(count) = self.prefix_lens.get_mut(&len) {
+            *count -= 1;
+            if *count == 0 {
+                self.prefix_lens.remove(&len);
+            }
+        }
+        if let Some(parent) = node.parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            parent.children -= 1;
+        }
+    }
+
+    /// Evict the least recently used leaves, except for `keep`, until we are within budget
+    fn evict(&mut self, keep: u64) -> Vec<SnapshotId> {
+        let mut evicted = Vec::new();
+        while self.used > self.budget {
+            let Some(victim) = self
+                .nodes
+                .iter()
+                .filter(|&(&key, node)| key != keep && node.children == 0)
+                .min_by_key(|(_, node)| node.last_used)
+                .map(|(&key, _)| key)
+            else {
+                break;
+            };
+            let node = self.nodes.remove(&victim).unwrap();
+            self.remove_accounting(&node);
+            evicted.push(node.snapshot_id);
+        }
+        evicted
+    }
+
+    /// Take a child snapshot now, after the target consumed `prefix` of its input of `input_len`
+    /// bytes. Nothing happens if there already is a snapshot for this prefix and length.
+    pub fn checkpoint<SM>(
+        &mut self,
+        qemu: Qemu,
+        snapshot_manager: &mut SM,
+        prefix: &[u8],
+        input_len: usize,
+    ) where
+        SM: IsSnapshotManager,
+    {
+        if self.contains(prefix, input_len) {
+            return;
+        }
+        let snapshot_id = snapshot_manager.save(qemu);
+        let size = snapshot_manager
+            .snapshot_size(qemu, &snapshot_id)
+            .unwrap_or(self.default_snapshot_size);
+        for evicted in self.insert(prefix, input_len, snapshot_id, size) {
+            log::debug!("Evicting snapshot {}", evicted.inner());
+            snapshot_manager.delete(qemu, &evicted);
+        }
+    }
+
+    /// Restore the snapshot with the longest prefix of `input`, taken during a run with an input
+    /// of the same length, and return the prefix length.
+    /// Returns `Ok(None)` without restoring anything if no snapshot matches, the caller should
+    /// restore the root snapshot then.
+    pub fn restore_deepest<SM>(
+        &mut self,
+        qemu: Qemu,
+        snapshot_manager: &mut SM,
+        input: &[u8],
+    ) -> Result<Option<usize>, SnapshotManagerError>
+    where
+        SM: IsSnapshotManager,
+    {
+        let Some((snapshot_id, prefix_len)) = self.lookup(input) else {
+            return Ok(None);
+        };
+        snapshot_manager.restore(qemu, &snapshot_id)?;
+        Ok(Some(prefix_len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+
+    use super::SnapshotTree;
+    use crate::{
+        IsSnapshotManager, Qemu, QemuSnapshotCheckResult, SnapshotId, SnapshotManagerError,
+    };
+
+    #[test]
+    fn test_snapshot_tree_lookup() {
+        let mut tree = SnapshotTree::new(usize::MAX);
+        let login = SnapshotId::gen_unique_id();
+        let login_ls = SnapshotId::gen_unique_id();
+        assert!(tree.insert(b"login", 12, login, 1).is_empty());
+        assert!(tree.insert(b"login;ls", 12, login_ls, 1).is_empty());
+
+        assert_eq!(tree.lookup(b"login;ls -la"), Some((login_ls, 8)));
+        assert_eq!(tree.lookup(b"login;cat xy"), Some((login, 5)));
+        assert_eq!(tree.lookup(b"logout;ls -l"), None);
+        // Same prefix, but the target was told another length
+        assert_eq!(tree.lookup(b"login;ls"), None);
+        assert!(tree.contains(b"login", 12));
+        assert!(!tree.contains(b"login", 8));
+        assert!(!tree.contains(b"login;", 12));
+    }
+
+    #[test]
+    fn test_snapshot_tree_eviction() {
+        let mut tree = SnapshotTree::new(3);
+        let a = SnapshotId::gen_unique_id();
+        let ab = SnapshotId::gen_unique_id();
+        let c = SnapshotId::gen_unique_id();
+        let d = SnapshotId::gen_unique_id();
+        assert!(tree.insert(b"a", 3, a, 1).is_empty());
+        assert!(tree.insert(b"ab", 3, ab, 1).is_empty());
+        assert!(tree.insert(b"c", 3, c, 1).is_empty());
+
+        // `a` has a child, `ab` is the oldest leaf, but `c` got used more recently
+        assert!(tree.lookup(b"abc").is_some());
+        assert!(tree.lookup(b"cde").is_some());
+        assert_eq!(tree.insert(b"d", 3, d, 1), [ab]);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.used(), 3);
+
+        // Now `a` is a leaf again, and the least recently used one
+        let e = SnapshotId::gen_unique_id();
+        assert_eq!(tree.insert(b"e", 3, e, 1), [a]);
+        assert_eq!(tree.lookup(b"abc"), None);
+    }
+
+    /// The state of a guest reading its input in steps: the input length it got at the start of
+    /// the run, and the bytes it consumed so far.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    struct Guest {
+        input_len: usize,
+        consumed: Vec<u8>,
+    }
+
+    /// A snapshot manager saving and restoring the [`Guest`] state
+    #[derive(Debug, Clone, Default)]
+    struct GuestSnapshotManager {
+        guest: Guest,
+        snapshots: HashMap<SnapshotId, Guest>,
+    }
+
+    impl IsSnapshotManager for GuestSnapshotManager {
+        fn save(&mut self, _qemu: Qemu) -> SnapshotId {
+            let id = SnapshotId::gen_unique_id();
+            self.snapshots.insert(id, self.guest.clone());
+            id
+        }
+
+        fn restore(
+            &mut self,
+            _qemu: Qemu,
+            snapshot_id: &SnapshotId,
+        ) -> Result<(), SnapshotManagerError> {
+            self.guest = self
+                .snapshots
+                .get(snapshot_id)
+                .ok_or(SnapshotManagerError::SnapshotIdNotFound(*snapshot_id))?
+                .clone();
+            Ok(())
+        }
+
+        fn do_check(
+            &self,
+            _qemu: Qemu,
+            _reference_snapshot_id: &SnapshotId,
+        ) -> Result<QemuSnapshotCheckResult, SnapshotManagerError> {
+            Ok(QemuSnapshotCheckResult::default())
+        }
+
+        fn delete(&mut self, _qemu: Qemu, snapshot_id: &SnapshotId) {
+            self.snapshots.remove(snapshot_id);
+        }
+    }
+
+    /// Run `input` the way the driver does: resume from the deepest snapshot, or start over, then
+    /// consume the input in `;` separated steps, checkpointing after each step.
+    fn run(tree: &mut SnapshotTree, sm: &mut GuestSnapshotManager, input: &[u8]) -> Guest {
+        // The snapshot manager is a mock, `Qemu` is never used
+        let qemu = unsafe { Qemu::get_unchecked() };
+        let resumed_at = tree.restore_deepest(qemu, sm, input).unwrap();
+        if resumed_at.is_none() {
+            sm.guest = Guest {
+                input_len: input.len(),
+                consumed: Vec::new(),
+            };
+        }
+        let resumed = sm.guest.clone();
+
+        let start = resumed_at.unwrap_or(0);
+        assert_eq!(start, sm.guest.consumed.len());
+        for step in input[start..].split_inclusive(|&b| b == b';') {
+            sm.guest.consumed.extend_from_slice(step);
+            let consumed = sm.guest.consumed.len();
+            tree.checkpoint(qemu, sm, &input[..consumed], input.len());
+        }
+        assert_eq!(sm.guest.consumed, input);
+        resumed
+    }
+
+    #[test]
+    fn test_snapshot_tree_resume() {
+        let mut tree = SnapshotTree::new(usize::MAX);
+        let mut sm = GuestSnapshotManager::default();
+
+        assert_eq!(
+            run(&mut tree, &mut sm, b"login;ls;id"),
+            Guest {
+                input_len: 11,
+                consumed: Vec::new(),
+            }
+        );
+        // Same length, resumes after the shared prefix
+        assert_eq!(
+            run(&mut tree, &mut sm, b"login;ls;df"),
+            Guest {
+                input_len: 11,
+                consumed: b"login;ls;".to_vec(),
+            }
+        );
+        assert_eq!(
+            run(&mut tree, &mut sm, b"login;cat;x"),
+            Guest {
+                input_len: 11,
+                consumed: b"login;".to_vec(),
+            }
+        );
+        // Another length, the guest would see the wrong length in any of the snapshots
+        assert_eq!(
+            run(&mut tree, &mut sm, b"login;ls;pwd"),
+            Guest {
+                input_len: 12,
+                consumed: Vec::new(),
+            }
+        );
+        assert_eq!(
+            run(&mut tree, &mut sm, b"login;ls;cwd"),
+            Guest {
+                input_len: 12,
+                consumed: b"login;ls;".to_vec(),
+            }
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use libafl_qemu_sys::GuestPhysAddr;
 
 use crate::{
@@ -89,6 +89,20 @@ impl IsSnapshotManager for SnapshotManager {
             SnapshotManager::Fast(fast_sm) => fast_sm.do_check(qemu, reference_snapshot_id),
         }
     }
+
+    fn delete(&mut self, qemu: Qemu, snapshot_id: &SnapshotId) {
+        match self {
+            SnapshotManager::Qemu(qemu_sm) => qemu_sm.delete(qemu, snapshot_id),
+            SnapshotManager::Fast(fast_sm) => fast_sm.delete(qemu, snapshot_id),
+        }
+    }
+
+    fn snapshot_size(&self, qemu: Qemu, snapshot_id: &SnapshotId) -> Option<usize> {
+        match self {
+            SnapshotManager::Qemu(qemu_sm) => qemu_sm.snapshot_size(qemu, snapshot_id),
+            SnapshotManager::Fast(fast_sm) => fast_sm.snapshot_size(qemu, snapshot_id),
+        }
+    }
 }
 
 pub type FastSnapshotPtr = *mut libafl_qemu_sys::SyxSnapshot;
@@ -121,6 +135,7 @@ impl FastSnapshotManager {
 #[derive(Debug, Clone)]
 pub struct QemuSnapshotManager {
     is_sync: bool,
+    snapshots: HashSet<SnapshotId>,
 }
 
 impl Default for QemuSnapshotManager {
@@ -132,7 +147,10 @@ impl Default for QemuSnapshotManager {
 impl QemuSnapshotManager {
     #[must_use]
     pub fn new(is_sync: bool) -> Self {
-        Self { is_sync }
+        Self {
+            is_sync,
+            snapshots: HashSet::new(),
+        }
     }
 
     #[must_use]
@@ -148,6 +166,7 @@ impl IsSnapshotManager for QemuSnapshotManager {
             self.snapshot_id_to_name(&snapshot_id).as_str(),
             self.is_sync,
         );
+        self.snapshots.insert(snapshot_id);
         snapshot_id
     }
 
@@ -168,6 +187,21 @@ impl IsSnapshotManager for QemuSnapshotManager {
         // We consider the qemu implementation to be 'ideal' for now.
         Ok(QemuSnapshotCheckResult::default())
     }
+
+    fn delete(&mut self, qemu: Qemu, snapshot_id: &SnapshotId) {
+        if self.snapshots.remove(snapshot_id)
+            && !qemu.delete_snapshot(self.snapshot_id_to_name(snapshot_id).as_str())
+        {
+            log::warn!("Could not delete snapshot {}", snapshot_id.inner());
+        }
+    }
+
+    /// QEMU snapshots store the whole guest RAM, the device state is negligible next to it.
+    fn snapshot_size(&self, qemu: Qemu, snapshot_id: &SnapshotId) -> Option<usize> {
+        self.snapshots
+            .contains(snapshot_id)
+            .then(|| qemu.ram_size() as usize)
+    }
 }
 
 impl IsSnapshotManager for FastSnapshotManager {
@@ -206,6 +240,22 @@ impl IsSnapshotManager for FastSnapshotManager {
 
         unsafe { Ok(qemu.check_fast_snapshot(fast_snapshot_ptr)) }
     }
+
+    fn delete(&mut self, qemu: Qemu, snapshot_id: &SnapshotId) {
+        if let Some(fast_snapshot_ptr) = self.snapshots.remove(snapshot_id) {
+            unsafe {
+                qemu.free_fast_snapshot(fast_snapshot_ptr);
+            }
+        }
+    }
+
+    /// A fast snapshot keeps a copy of the whole guest RAM, plus the pages dirtied since.
+    /// The dirty pages are bounded by the time between restores, so only the copy is counted.
+    fn snapshot_size(&self, qemu: Qemu, snapshot_id: &SnapshotId) -> Option<usize> {
+        self.snapshots
+            .contains_key(snapshot_id)
+            .then(|| qemu.ram_size() as usize)
+    }
 }
 
 impl<C, CM, ED, ET, I, S, SM> Emulator<C, CM, ED, ET, I, S, SM> {
@@ -321,7 +321,8 @@ where
 
         self.inner
             .exposed_executor_state_mut()
-            .pre_exec(state, input);
+            .pre_exec(state, input)
+            .map_err(|e| Error::illegal_state(format!("Could not prepare the run: {e:?}")))?;
 
         let mut exit_kind = self.inner.run_target(fuzzer, state, mgr, input)?;
 
@@ -482,7 +483,10 @@ where
             self.first_exec = false;
         }
 
-        self.inner.exposed_executor_state.pre_exec(state, input);
+        self.inner
+            .exposed_executor_state
+            .pre_exec(state, input)
+            .map_err(|e| Error::illegal_state(format!("Could not prepare the run: {e:?}")))?;
 
         let mut exit_kind = self.inner.run_target(fuzzer, state, mgr, input)?;
 
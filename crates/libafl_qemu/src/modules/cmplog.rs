@@ -7,7 +7,8 @@ use libafl_qemu_sys::GuestAddr;
 pub use libafl_targets::{
     CMPLOG_MAP_H, CMPLOG_MAP_PTR, CMPLOG_MAP_SIZE, CMPLOG_MAP_W, CmpLogMap, CmpLogObserver,
     cmps::{
-        __libafl_targets_cmplog_instructions, __libafl_targets_cmplog_routines, CMPLOG_ENABLED,
+        __libafl_targets_cmplog_fp, __libafl_targets_cmplog_instructions,
+        __libafl_targets_cmplog_jump_table, __libafl_targets_cmplog_routines, CMPLOG_ENABLED,
     },
 };
 use serde::{Deserialize, Serialize};
@@ -458,3 +459,633 @@ impl HasAddressFilter for CmpLogRoutinesModule {
         &mut NopPageFilter
     }
 }
+
+/// Max number of instructions scanned past a bounds check to find the indirect jump of a jump table
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+const JUMP_TABLE_LOOKAHEAD: usize = 8;
+
+/// A floating-point compare operand
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FpOperand {
+    /// A floating-point or vector register, by gdb register number
+    Reg(i32),
+    /// A value at a fixed guest address, e.g. a `rip`-relative constant
+    Mem(GuestAddr),
+    /// The constant `0.0`
+    Zero,
+}
+
+/// A compare found by [`CmpLogDisasmModule`], passed to the instruction hook
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DisasmCmp {
+    /// A floating-point compare of `size` bytes
+    Fp {
+        k: usize,
+        size: u8,
+        lhs: FpOperand,
+        rhs: FpOperand,
+    },
+    /// The bounds check in front of an indirect jump through a table of `entries` entries
+    JumpTable {
+        k: usize,
+        reg: i32,
+        mask: u64,
+        entries: u64,
+    },
+}
+
+/// A disassembled instruction
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+#[derive(Debug)]
+struct DisasmInsn {
+    addr: GuestAddr,
+    len: usize,
+    mnemonic: String,
+    op_str: String,
+    is_branch: bool,
+}
+
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+impl DisasmInsn {
+    fn next(&self) -> GuestAddr {
+        self.addr + self.len as GuestAddr
+    }
+
+    fn operands(&self) -> Vec<&str> {
+        self.op_str.split(", ").map(str::trim).collect()
+    }
+}
+
+/// Logs the compares the TCG cmp hooks of [`CmpLogModule`] do not see, found by disassembling
+/// the translated blocks:
+/// - floating-point compares (SSE `ucomis*`/`comis*` on x86, `fcmp`/`fcmpe` on `AArch64`),
+///   logged as [`libafl::observers::CmpValues::F32`] and [`libafl::observers::CmpValues::F64`];
+/// - bounds checks in front of an indirect jump through a jump table, as emitted for a `switch`,
+///   logged as [`libafl::observers::CmpValues::JumpTable`].
+///
+/// With the `cmplog_extended_instrumentation` feature of `libafl_targets`, both also go to the
+/// AFL++ style map, for [`libafl::mutators::AflppRedQueen`].
+///
+/// Floating-point operands are read either from registers, through the gdb register interface,
+/// or from fixed addresses (`rip`-relative and absolute constants).
+/// Compares against any other memory operand are not logged.
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+#[derive(Debug)]
+pub struct CmpLogDisasmModule {
+    address_filter: StdAddressFilter,
+    cs: Capstone,
+    /// The compares with an installed hook. They are boxed, as the hooks point to them.
+    cmps: HashMap<GuestAddr, Box<DisasmCmp>>,
+}
+
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+impl CmpLogDisasmModule {
+    #[must_use]
+    pub fn new(address_filter: StdAddressFilter) -> Self {
+        Self {
+            address_filter,
+            cs: capstone().detail(true).build().unwrap(),
+            cmps: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn must_instrument(&self, addr: GuestAddr) -> bool {
+        self.address_filter.allowed(&addr)
+    }
+
+    /// # Safety
+    /// `data` must point to a [`DisasmCmp`] owned by the module.
+    unsafe extern "C" fn on_cmp(data: u64, _pc: GuestAddr) {
+        unsafe {
+            if CMPLOG_ENABLED == 0 {
+                return;
+            }
+        }
+
+        let qemu = Qemu::get().unwrap();
+        let cmp = unsafe { &*(data as *const DisasmCmp) };
+
+        match *cmp {
+            DisasmCmp::Fp { k, size, lhs, rhs } => {
+                let (Some(v0), Some(v1)) = (
+                    Self::read_fp_operand(qemu, lhs, size),
+                    Self::read_fp_operand(qemu, rhs, size),
+                ) else {
+                    return;
+                };
+                unsafe {
+                    __libafl_targets_cmplog_fp(k, size, v0, v1);
+                }
+            }
+            DisasmCmp::JumpTable {
+                k,
+                reg,
+                mask,
+                entries,
+            } => {
+                let Ok(index) = qemu.read_reg(reg) else {
+                    return;
+                };
+                unsafe {
+                    __libafl_targets_cmplog_jump_table(k, u64::from(index) & mask, entries);
+                }
+            }
+        }
+    }
+
+    /// Reads the raw bits of a floating-point operand of `size` bytes
+    fn read_fp_operand(qemu: Qemu, operand: FpOperand, size: u8) -> Option<u64> {
+        let mut bytes = [0; 8];
+        let size = usize::from(size);
+        match operand {
+            FpOperand::Reg(reg) => {
+                let val = qemu.current_cpu()?.read_reg_bytes(reg).ok()?;
+                bytes[..size].copy_from_slice(&val[..size]);
+            }
+            FpOperand::Mem(addr) => {
+                qemu.read_mem(addr, &mut bytes[..size]).ok()?;
+            }
+            FpOperand::Zero => {}
+        }
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    fn disasm(&self, qemu: Qemu, addr: GuestAddr) -> Option<DisasmInsn> {
+        let code = unsafe { std::slice::from_raw_parts(qemu.g2h(addr), 16) };
+        let insns = self.cs.disasm_count(code, addr.into(), 1).ok()?;
+        let insn = insns.first()?;
+        let detail: InsnDetail = self.cs.insn_detail(insn).ok()?;
+        let is_branch = detail.groups().iter().any(|group| {
+            matches!(
+                u32::from(group.0),
+                capstone::InsnGroupType::CS_GRP_JUMP
+                    | capstone::InsnGroupType::CS_GRP_CALL
+                    | capstone::InsnGroupType::CS_GRP_RET
+                    | capstone::InsnGroupType::CS_GRP_INVALID
+                    | capstone::InsnGroupType::CS_GRP_IRET
+                    | capstone::InsnGroupType::CS_GRP_PRIVILEGE
+            )
+        });
+        Some(DisasmInsn {
+            addr,
+            len: insn.bytes().len(),
+            mnemonic: insn.mnemonic()?.to_string(),
+            op_str: insn.op_str().unwrap_or_default().to_string(),
+            is_branch,
+        })
+    }
+
+    /// Checks if an indirect jump follows `addr` before any other branch
+    fn reaches_indirect_jump(&self, qemu: Qemu, mut addr: GuestAddr) -> bool {
+        for _ in 0..JUMP_TABLE_LOOKAHEAD {
+            let Some(insn) = self.disasm(qemu, addr) else {
+                return false;
+            };
+            if insn.is_branch {
+                return is_indirect_jump(&insn);
+            }
+            addr = insn.next();
+        }
+        false
+    }
+
+    fn add_cmp_hook(&mut self, qemu: Qemu, addr: GuestAddr, cmp: DisasmCmp) {
+        if let hashbrown::hash_map::Entry::Vacant(entry) = self.cmps.entry(addr) {
+            let cmp = entry.insert(Box::new(cmp));
+            qemu.hooks()
+                .add_instruction_hooks(&raw const **cmp as u64, addr, Self::on_cmp, false);
+        }
+    }
+
+    #[allow(clippy::needless_pass_by_value)] // no longer a problem with nightly
+    fn gen_blocks_cmps<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) -> Option<u64>
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let h = emulator_modules.get_mut::<Self>()?;
+        if !h.must_instrument(pc) {
+            return None;
+        }
+
+        let mut prev: Option<DisasmInsn> = None;
+        let mut addr = pc;
+        while let Some(insn) = h.disasm(qemu, addr) {
+            let k = (hash_64_fast(insn.addr.into()) as usize) & (CMPLOG_MAP_W - 1);
+
+            if let Some((size, lhs, rhs)) = fp_compare(&insn) {
+                h.add_cmp_hook(qemu, insn.addr, DisasmCmp::Fp { k, size, lhs, rhs });
+            }
+
+            if insn.is_branch {
+                if let Some(cmp) = prev
+                    && let Some((reg, mask, entries)) = jump_table_bounds_check(&cmp, &insn)
+                    && h.reaches_indirect_jump(qemu, insn.next())
+                {
+                    let k = (hash_64_fast(cmp.addr.into()) as usize) & (CMPLOG_MAP_W - 1);
+                    h.add_cmp_hook(
+                        qemu,
+                        cmp.addr,
+                        DisasmCmp::JumpTable {
+                            k,
+                            reg,
+                            mask,
+                            entries,
+                        },
+                    );
+                }
+                break;
+            }
+
+            addr = insn.next();
+            prev = Some(insn);
+        }
+
+        None
+    }
+}
+
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+impl Default for CmpLogDisasmModule {
+    fn default() -> Self {
+        Self::new(StdAddressFilter::default())
+    }
+}
+
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+impl<I, S> EmulatorModule<I, S> for CmpLogDisasmModule
+where
+    I: Unpin,
+    S: Unpin,
+{
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        emulator_modules.blocks(
+            Hook::Function(Self::gen_blocks_cmps::<ET, I, S>),
+            Hook::Empty,
+            Hook::Empty,
+        );
+    }
+}
+
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+impl HasAddressFilter for CmpLogDisasmModule {
+    type AddressFilter = StdAddressFilter;
+
+    fn address_filter(&self) -> &Self::AddressFilter {
+        &self.address_filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::AddressFilter {
+        &mut self.address_filter
+    }
+}
+
+/// Parses an immediate, as printed by capstone (`5`, `0x1f`, `#0x1f`)
+#[cfg(all(
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+fn parse_imm(op: &str) -> Option<u64> {
+    let op = op.trim_start_matches('#');
+    if let Some(hex) = op.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        op.parse().ok()
+    }
+}
+
+/// Maps a general purpose register name to its register number and the mask of its width
+#[cfg(all(feature = "usermode", cpu_target = "x86_64"))]
+fn gpr(name: &str) -> Option<(i32, u64)> {
+    use crate::Regs;
+
+    let (reg, mask) = match name {
+        "rax" => (Regs::Rax, u64::MAX),
+        "rbx" => (Regs::Rbx, u64::MAX),
+        "rcx" => (Regs::Rcx, u64::MAX),
+        "rdx" => (Regs::Rdx, u64::MAX),
+        "rsi" => (Regs::Rsi, u64::MAX),
+        "rdi" => (Regs::Rdi, u64::MAX),
+        "rbp" => (Regs::Rbp, u64::MAX),
+        "eax" => (Regs::Rax, u64::from(u32::MAX)),
+        "ebx" => (Regs::Rbx, u64::from(u32::MAX)),
+        "ecx" => (Regs::Rcx, u64::from(u32::MAX)),
+        "edx" => (Regs::Rdx, u64::from(u32::MAX)),
+        "esi" => (Regs::Rsi, u64::from(u32::MAX)),
+        "edi" => (Regs::Rdi, u64::from(u32::MAX)),
+        "ebp" => (Regs::Rbp, u64::from(u32::MAX)),
+        _ => {
+            // r8 - r15, and their 32 bit halves r8d - r15d
+            let (num, mask) = match name.strip_suffix('d') {
+                Some(num) => (num, u64::from(u32::MAX)),
+                None => (name, u64::MAX),
+            };
+            let num: i32 = num.strip_prefix('r')?.parse().ok()?;
+            return (8..16).contains(&num).then_some((num, mask));
+        }
+    };
+    Some((reg.into(), mask))
+}
+
+/// Maps a general purpose register name to its register number and the mask of its width
+#[cfg(all(feature = "usermode", cpu_target = "i386"))]
+fn gpr(name: &str) -> Option<(i32, u64)> {
+    use crate::Regs;
+
+    let reg = match name {
+        "eax" => Regs::Eax,
+        "ebx" => Regs::Ebx,
+        "ecx" => Regs::Ecx,
+        "edx" => Regs::Edx,
+        "esi" => Regs::Esi,
+        "edi" => Regs::Edi,
+        "ebp" => Regs::Ebp,
+        _ => return None,
+    };
+    Some((reg.into(), u64::from(u32::MAX)))
+}
+
+/// Maps a general purpose register name to its register number and the mask of its width
+#[cfg(all(feature = "usermode", cpu_target = "aarch64"))]
+fn gpr(name: &str) -> Option<(i32, u64)> {
+    let (num, mask) = if let Some(num) = name.strip_prefix('x') {
+        (num, u64::MAX)
+    } else {
+        (name.strip_prefix('w')?, u64::from(u32::MAX))
+    };
+    let num: i32 = num.parse().ok()?;
+    (0..=30).contains(&num).then_some((num, mask))
+}
+
+/// Parses a fixed address memory operand, such as `qword ptr [rip + 0x2f0e]`
+#[cfg(all(feature = "usermode", any(cpu_target = "x86_64", cpu_target = "i386")))]
+fn fixed_mem_operand(op: &str, next: GuestAddr) -> Option<GuestAddr> {
+    let inner = op.split_once('[')?.1.strip_suffix(']')?;
+    if let Some(disp) = inner.strip_prefix("rip + ") {
+        Some(next.wrapping_add(parse_imm(disp)? as GuestAddr))
+    } else if let Some(disp) = inner.strip_prefix("rip - ") {
+        Some(next.wrapping_sub(parse_imm(disp)? as GuestAddr))
+    } else {
+        parse_imm(inner).map(|addr| addr as GuestAddr)
+    }
+}
+
+/// Recognizes a scalar SSE compare, returning its size and operands
+#[cfg(all(feature = "usermode", any(cpu_target = "x86_64", cpu_target = "i386")))]
+fn fp_compare(insn: &DisasmInsn) -> Option<(u8, FpOperand, FpOperand)> {
+    use crate::VecRegs;
+
+    let size = match insn.mnemonic.trim_start_matches('v') {
+        "ucomiss" | "comiss" => 4,
+        "ucomisd" | "comisd" => 8,
+        _ => return None,
+    };
+    let parse = |op: &str| {
+        if op.starts_with("xmm") {
+            Some(FpOperand::Reg(op.parse::<VecRegs>().ok()?.into()))
+        } else {
+            fixed_mem_operand(op, insn.next()).map(FpOperand::Mem)
+        }
+    };
+    let ops = insn.operands();
+    let [lhs, rhs] = ops.as_slice() else {
+        return None;
+    };
+    Some((size, parse(lhs)?, parse(rhs)?))
+}
+
+/// Recognizes a scalar `fcmp`/`fcmpe`, returning its size and operands
+#[cfg(all(feature = "usermode", cpu_target = "aarch64"))]
+fn fp_compare(insn: &DisasmInsn) -> Option<(u8, FpOperand, FpOperand)> {
+    use crate::VecRegs;
+
+    if insn.mnemonic != "fcmp" && insn.mnemonic != "fcmpe" {
+        return None;
+    }
+    let ops = insn.operands();
+    let [lhs, rhs] = ops.as_slice() else {
+        return None;
+    };
+    // half precision compares are not logged
+    let size = if lhs.starts_with('s') {
+        4
+    } else if lhs.starts_with('d') {
+        8
+    } else {
+        return None;
+    };
+    let parse = |op: &str| {
+        if op.starts_with('#') {
+            Some(FpOperand::Zero)
+        } else {
+            // `s0` and `d0` are the low bits of `v0`
+            let vreg = format!("v{}", op.get(1..)?);
+            Some(FpOperand::Reg(vreg.parse::<VecRegs>().ok()?.into()))
+        }
+    };
+    Some((size, parse(lhs)?, parse(rhs)?))
+}
+
+/// Recognizes a `cmp reg, imm` followed by an unsigned "above" branch, the bounds check compilers
+/// emit in front of a jump table. Returns the index register, its mask and the number of entries.
+#[cfg(all(feature = "usermode", any(cpu_target = "x86_64", cpu_target = "i386")))]
+fn jump_table_bounds_check(cmp: &DisasmInsn, branch: &DisasmInsn) -> Option<(i32, u64, u64)> {
+    if cmp.mnemonic != "cmp" {
+        return None;
+    }
+    let ops = cmp.operands();
+    let [reg, imm] = ops.as_slice() else {
+        return None;
+    };
+    let (reg, mask) = gpr(reg)?;
+    let imm = parse_imm(imm)?;
+    let entries = match branch.mnemonic.as_str() {
+        "ja" => imm.checked_add(1)?,
+        "jae" | "jnb" => imm,
+        _ => return None,
+    };
+    Some((reg, mask, entries))
+}
+
+/// Recognizes a `cmp reg, #imm` followed by an unsigned "higher" branch, the bounds check
+/// compilers emit in front of a jump table. Returns the index register, its mask and the number
+/// of entries.
+#[cfg(all(feature = "usermode", cpu_target = "aarch64"))]
+fn jump_table_bounds_check(cmp: &DisasmInsn, branch: &DisasmInsn) -> Option<(i32, u64, u64)> {
+    if cmp.mnemonic != "cmp" {
+        return None;
+    }
+    let ops = cmp.operands();
+    let [reg, imm] = ops.as_slice() else {
+        return None;
+    };
+    let (reg, mask) = gpr(reg)?;
+    let imm = parse_imm(imm)?;
+    let entries = match branch.mnemonic.as_str() {
+        "b.hi" => imm.checked_add(1)?,
+        "b.hs" | "b.cs" => imm,
+        _ => return None,
+    };
+    Some((reg, mask, entries))
+}
+
+/// An indirect `jmp`, either through a register or a memory operand
+#[cfg(all(feature = "usermode", any(cpu_target = "x86_64", cpu_target = "i386")))]
+fn is_indirect_jump(insn: &DisasmInsn) -> bool {
+    matches!(insn.mnemonic.as_str(), "jmp" | "notrack jmp") && parse_imm(&insn.op_str).is_none()
+}
+
+/// An indirect `br`
+#[cfg(all(feature = "usermode", cpu_target = "aarch64"))]
+fn is_indirect_jump(insn: &DisasmInsn) -> bool {
+    insn.mnemonic == "br"
+}
+
+#[cfg(all(
+    test,
+    feature = "usermode",
+    any(cpu_target = "x86_64", cpu_target = "i386", cpu_target = "aarch64")
+))]
+mod tests {
+    use super::parse_imm;
+    #[cfg(any(cpu_target = "x86_64", cpu_target = "aarch64"))]
+    use super::{DisasmInsn, FpOperand, fp_compare, jump_table_bounds_check};
+
+    #[cfg(any(cpu_target = "x86_64", cpu_target = "aarch64"))]
+    fn insn(mnemonic: &str, op_str: &str) -> DisasmInsn {
+        DisasmInsn {
+            addr: 0x1000,
+            len: 4,
+            mnemonic: mnemonic.to_string(),
+            op_str: op_str.to_string(),
+            is_branch: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_imm() {
+        assert_eq!(parse_imm("5"), Some(5));
+        assert_eq!(parse_imm("0x1f"), Some(0x1f));
+        assert_eq!(parse_imm("#0x1f"), Some(0x1f));
+        assert_eq!(parse_imm("rax"), None);
+    }
+
+    #[cfg(cpu_target = "x86_64")]
+    #[test]
+    fn test_jump_table_bounds_check() {
+        use crate::Regs;
+
+        let cmp = insn("cmp", "edi, 0x1f");
+        assert_eq!(
+            jump_table_bounds_check(&cmp, &insn("ja", "0x2000")),
+            Some((Regs::Rdi.into(), u64::from(u32::MAX), 0x20))
+        );
+        assert_eq!(
+            jump_table_bounds_check(&cmp, &insn("jae", "0x2000")),
+            Some((Regs::Rdi.into(), u64::from(u32::MAX), 0x1f))
+        );
+        assert_eq!(jump_table_bounds_check(&cmp, &insn("jne", "0x2000")), None);
+        assert_eq!(
+            jump_table_bounds_check(&insn("cmp", "r9, 4"), &insn("ja", "0x2000")),
+            Some((9, u64::MAX, 5))
+        );
+    }
+
+    #[cfg(cpu_target = "x86_64")]
+    #[test]
+    fn test_fp_compare() {
+        use crate::VecRegs;
+
+        assert_eq!(
+            fp_compare(&insn("ucomisd", "xmm0, xmm15")),
+            Some((
+                8,
+                FpOperand::Reg(VecRegs::Xmm0.into()),
+                FpOperand::Reg(VecRegs::Xmm15.into())
+            ))
+        );
+        // gdb numbers the SSE registers after the x87 ones
+        assert_eq!(i32::from(VecRegs::Xmm0), 40);
+        assert_eq!(
+            fp_compare(&insn("vcomiss", "xmm1, dword ptr [rip + 0x10]")),
+            Some((4, FpOperand::Reg(41), FpOperand::Mem(0x1014)))
+        );
+        assert_eq!(fp_compare(&insn("ucomisd", "xmm16, xmm0")), None);
+    }
+
+    #[cfg(cpu_target = "aarch64")]
+    #[test]
+    fn test_fp_compare() {
+        use crate::VecRegs;
+
+        assert_eq!(
+            fp_compare(&insn("fcmp", "d0, #0.0")),
+            Some((8, FpOperand::Reg(VecRegs::V0.into()), FpOperand::Zero))
+        );
+        assert_eq!(i32::from(VecRegs::V0), 34);
+        assert_eq!(
+            fp_compare(&insn("fcmpe", "s31, s2")),
+            Some((4, FpOperand::Reg(65), FpOperand::Reg(36)))
+        );
+    }
+
+    #[cfg(cpu_target = "aarch64")]
+    #[test]
+    fn test_jump_table_bounds_check() {
+        let cmp = insn("cmp", "w8, #0x1f");
+        assert_eq!(
+            jump_table_bounds_check(&cmp, &insn("b.hi", "#0x2000")),
+            Some((8, u64::from(u32::MAX), 0x20))
+        );
+        assert_eq!(
+            jump_table_bounds_check(&cmp, &insn("b.ne", "#0x2000")),
+            None
+        );
+    }
+}
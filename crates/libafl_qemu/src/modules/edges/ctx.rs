@@ -0,0 +1,236 @@
+use libafl::{HasMetadata, inputs::Input};
+use libafl_bolts::hash_64_fast;
+use libafl_qemu_sys::GuestAddr;
+
+use super::{
+    EdgeCoverageVariant,
+    helpers::{
+        call_ctx, gen_hashed_block_ids, set_call_ctx, trace_block_transition_ctx_hitcount,
+        trace_block_transition_ctx_single,
+    },
+};
+use crate::{
+    EmulatorModules, Hook, Qemu,
+    modules::{
+        AddressFilter, EdgeCoverageModule, EdgeCoverageModuleBuilder, EmulatorModuleTuple,
+        PageFilter,
+        calls::CallTraceCollector,
+        utils::filters::{StdAddressFilter, StdPageFilter},
+    },
+};
+
+/// Context-sensitive edge coverage, like the `ctx` instrumentation of `libafl_cc`.
+///
+/// The map index of each edge is mixed with a hash of the current call stack.
+/// The call stack is maintained by a [`CallContextCollector`], which has to be added to a
+/// [`crate::modules::CallTracerModule`] next to this module, otherwise this is classic edge
+/// coverage. There is no JIT version of this variant.
+#[derive(Debug)]
+pub struct EdgeCoverageCtxVariant;
+
+pub type StdEdgeCoverageCtxModule =
+    EdgeCoverageModule<StdAddressFilter, StdPageFilter, EdgeCoverageCtxVariant, false, 0>;
+pub type StdEdgeCoverageCtxModuleBuilder = EdgeCoverageModuleBuilder<
+    StdAddressFilter,
+    StdPageFilter,
+    EdgeCoverageCtxVariant,
+    false,
+    false,
+    0,
+>;
+
+impl<AF, PF, const IS_CONST_MAP: bool, const MAP_SIZE: usize>
+    EdgeCoverageVariant<AF, PF, IS_CONST_MAP, MAP_SIZE> for EdgeCoverageCtxVariant
+{
+    const DO_SIDE_EFFECTS: bool = false;
+
+    fn fn_hitcount<ET, I, S>(&mut self, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        AF: AddressFilter,
+        ET: EmulatorModuleTuple<I, S>,
+        PF: PageFilter,
+        I: Unpin,
+        S: HasMetadata + Unpin,
+    {
+        emulator_modules.blocks(
+            Hook::Function(gen_hashed_block_ids::<AF, ET, PF, I, S, Self, IS_CONST_MAP, MAP_SIZE>),
+            Hook::Empty,
+            Hook::Raw(trace_block_transition_ctx_hitcount),
+        );
+    }
+
+    fn fn_no_hitcount<ET, I, S>(&mut self, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        AF: AddressFilter,
+        ET: EmulatorModuleTuple<I, S>,
+        PF: PageFilter,
+        I: Unpin,
+        S: HasMetadata + Unpin,
+    {
+        emulator_modules.blocks(
+            Hook::Function(gen_hashed_block_ids::<AF, ET, PF, I, S, Self, IS_CONST_MAP, MAP_SIZE>),
+            Hook::Empty,
+            Hook::Raw(trace_block_transition_ctx_single),
+        );
+    }
+}
+
+impl Default for StdEdgeCoverageCtxModuleBuilder {
+    fn default() -> Self {
+        Self {
+            variant: EdgeCoverageCtxVariant,
+            address_filter: StdAddressFilter::default(),
+            page_filter: StdPageFilter::default(),
+            use_hitcounts: true,
+            use_jit: false,
+        }
+    }
+}
+
+impl StdEdgeCoverageCtxModule {
+    #[must_use]
+    pub fn builder() -> StdEdgeCoverageCtxModuleBuilder {
+        EdgeCoverageModuleBuilder::default()
+    }
+}
+
+/// Maintains the calling context used by the [`EdgeCoverageCtxVariant`].
+///
+/// Each call mixes the call site into the context, each return restores the context of the
+/// matching call. Returns skipping frames, like `longjmp`, unwind all the skipped calls.
+// TODO support multiple threads with a thread local call stack
+#[derive(Debug, Default)]
+pub struct CallContextCollector {
+    /// The return address and the context before each call
+    stack: Vec<(GuestAddr, u64)>,
+}
+
+impl CallContextCollector {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(clippy::unnecessary_cast)] // dependent on the target instruction size
+    fn call(&mut self, pc: GuestAddr, call_len: usize) {
+        let ctx = call_ctx();
+        self.stack.push((pc + call_len as GuestAddr, ctx));
+        set_call_ctx(ctx ^ hash_64_fast(pc as u64));
+    }
+
+    fn ret(&mut self, ret_addr: GuestAddr) {
+        if let Some(pos) = self
+            .stack
+            .iter()
+            .rposition(|&(return_address, _)| return_address == ret_addr)
+        {
+            set_call_ctx(self.stack[pos].1);
+            self.stack.truncate(pos);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        set_call_ctx(0);
+    }
+}
+
+impl CallTraceCollector for CallContextCollector {
+    fn on_call<ET, I, S>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+        call_len: usize,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        self.call(pc, call_len);
+    }
+
+    fn on_ret<ET, I, S>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        _pc: GuestAddr,
+        ret_addr: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        self.ret(ret_addr);
+    }
+
+    fn pre_exec<I>(&mut self, _qemu: Qemu, _input: &I)
+    where
+        I: Input,
+    {
+        self.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::helpers::{call_ctx, ctx_hash},
+        CallContextCollector,
+    };
+
+    #[test]
+    fn test_call_context_collector() {
+        let mut collector = CallContextCollector::new();
+        collector.reset();
+
+        collector.call(0x1000, 5);
+        let outer = call_ctx();
+        assert_ne!(outer, 0);
+        collector.call(0x2000, 5);
+        let inner = call_ctx();
+        assert_ne!(inner, outer);
+
+        // A return restores the context of its call
+        collector.ret(0x2005);
+        assert_eq!(call_ctx(), outer);
+
+        // An unknown return address does not change the context
+        collector.ret(0x3000);
+        assert_eq!(call_ctx(), outer);
+
+        // Returning past a frame, like longjmp, unwinds the skipped calls
+        collector.call(0x2000, 5);
+        collector.ret(0x1005);
+        assert_eq!(call_ctx(), 0);
+        assert!(collector.stack.is_empty());
+
+        collector.call(0x1000, 5);
+        collector.reset();
+        assert_eq!(call_ctx(), 0);
+        assert!(collector.stack.is_empty());
+    }
+
+    #[test]
+    fn test_ctx_hash() {
+        let mut collector = CallContextCollector::new();
+
+        // Run the same callee from two different call sites
+        let mut run = |call_site| {
+            collector.reset();
+            ctx_hash(1 << 8);
+            collector.call(call_site, 5);
+            let callee = ctx_hash(2 << 8);
+            collector.ret(call_site + 5);
+            let after = ctx_hash(3 << 8);
+            (callee, after)
+        };
+        let (callee_a, after_a) = run(0x1000);
+        let (callee_b, after_b) = run(0x2000);
+
+        // The edge into the callee depends on the call site, the one after the return does not
+        assert_ne!(callee_a, callee_b);
+        assert_eq!(after_a, after_b);
+        assert_eq!(run(0x1000), (callee_a, after_a));
+    }
+}
@@ -5,11 +5,17 @@ pub use generators::{gen_hashed_block_ids, gen_hashed_edge_ids, gen_unique_edge_
 use hashbrown::HashMap;
 use libafl_qemu_sys::GuestAddr;
 use serde::{Deserialize, Serialize};
+pub(super) use tracers::reset_ngram_history;
 /// Tracers, responsible for propagating an ID in a map.
 pub use tracers::{
-    trace_block_transition_hitcount, trace_block_transition_single, trace_edge_hitcount,
-    trace_edge_hitcount_ptr, trace_edge_single, trace_edge_single_ptr,
+    MAX_NGRAM_SIZE, call_ctx, set_call_ctx, trace_block_transition_ctx_hitcount,
+    trace_block_transition_ctx_single, trace_block_transition_hitcount,
+    trace_block_transition_ngram_hitcount, trace_block_transition_ngram_single,
+    trace_block_transition_single, trace_edge_hitcount, trace_edge_hitcount_ptr, trace_edge_single,
+    trace_edge_single_ptr,
 };
+#[cfg(test)]
+pub(super) use tracers::{ctx_hash, ngram_hash};
 
 // Constants used for variable-length maps
 
@@ -289,13 +295,69 @@ mod generators {
 }
 
 mod tracers {
-    use std::cell::UnsafeCell;
+    use std::cell::{Cell, UnsafeCell};
 
     use libafl_targets::EDGES_MAP;
 
     use super::{LIBAFL_QEMU_EDGES_MAP_MASK_MAX, LIBAFL_QEMU_EDGES_MAP_PTR};
 
+    /// The largest `N` supported by the n-gram tracers
+    pub const MAX_NGRAM_SIZE: usize = 16;
+
     thread_local!(static PREV_LOC : UnsafeCell<u64> = const { UnsafeCell::new(0) });
+    thread_local!(static NGRAM_HISTORY : UnsafeCell<[u64; MAX_NGRAM_SIZE]> = const { UnsafeCell::new([0; MAX_NGRAM_SIZE]) });
+    thread_local!(static CALL_CTX : Cell<u64> = const { Cell::new(0) });
+
+    /// The calling context of the current thread, mixed into the map index by the ctx tracers
+    #[must_use]
+    pub fn call_ctx() -> u64 {
+        CALL_CTX.with(Cell::get)
+    }
+
+    /// Set the calling context of the current thread, usually from the calls hooks
+    pub fn set_call_ctx(ctx: u64) {
+        CALL_CTX.with(|call_ctx| call_ctx.set(ctx));
+    }
+
+    /// Forget the blocks of the previous run, so each run starts with the same n-gram history
+    pub(super) fn reset_ngram_history() {
+        NGRAM_HISTORY.with(|history| {
+            // # Safety
+            // The history is thread local, and no reference to it outlives this closure.
+            unsafe { *history.get() = [0; MAX_NGRAM_SIZE] };
+        });
+    }
+
+    /// Hash block `id` together with the previous block and the calling context,
+    /// and make it the previous block.
+    pub(super) fn ctx_hash(id: u64) -> u64 {
+        PREV_LOC.with(|prev_loc| {
+            // # Safety
+            // The previous location is thread local, and no reference to it outlives this closure.
+            let prev_loc = unsafe { &mut *prev_loc.get() };
+            let hash = *prev_loc ^ id ^ call_ctx();
+            *prev_loc = id.overflowing_shr(1).0;
+            hash
+        })
+    }
+
+    /// Hash block `id` together with the last `N - 1` blocks, and push it to the history.
+    pub(super) fn ngram_hash<const N: usize>(id: u64) -> u64 {
+        const {
+            assert!(N >= 2, "An n-gram needs at least 2 blocks.");
+            assert!(N <= MAX_NGRAM_SIZE, "The n-gram size is too large.");
+        };
+
+        NGRAM_HISTORY.with(|history| {
+            // # Safety
+            // The history is thread local, and no reference to it outlives this closure.
+            let history = unsafe { &mut *history.get() };
+            let prev = history[..N - 1].iter().fold(0, |acc, &prev| acc ^ prev);
+            history.copy_within(..N - 2, 1);
+            history[0] = id.overflowing_shr(1).0;
+            prev ^ id
+        })
+    }
 
     /// # Safety
     ///
@@ -366,4 +428,54 @@ mod tracers {
             });
         }
     }
+
+    /// # Safety
+    ///
+    /// Dereferences the global `NGRAM_HISTORY` variable. May not be called concurrently.
+    pub unsafe extern "C" fn trace_block_transition_ngram_hitcount<const N: usize>(
+        _: *const (),
+        id: u64,
+    ) {
+        unsafe {
+            let x = (ngram_hash::<N>(id) as usize) & LIBAFL_QEMU_EDGES_MAP_MASK_MAX;
+            let entry = LIBAFL_QEMU_EDGES_MAP_PTR.add(x);
+            *entry = (*entry).wrapping_add(1);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Dereferences the global `NGRAM_HISTORY` variable. May not be called concurrently.
+    pub unsafe extern "C" fn trace_block_transition_ngram_single<const N: usize>(
+        _: *const (),
+        id: u64,
+    ) {
+        unsafe {
+            let x = (ngram_hash::<N>(id) as usize) & LIBAFL_QEMU_EDGES_MAP_MASK_MAX;
+            let entry = LIBAFL_QEMU_EDGES_MAP_PTR.add(x);
+            *entry = 1;
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Dereferences the global `PREV_LOC` variable. May not be called concurrently.
+    pub unsafe extern "C" fn trace_block_transition_ctx_hitcount(_: *const (), id: u64) {
+        unsafe {
+            let x = (ctx_hash(id) as usize) & LIBAFL_QEMU_EDGES_MAP_MASK_MAX;
+            let entry = LIBAFL_QEMU_EDGES_MAP_PTR.add(x);
+            *entry = (*entry).wrapping_add(1);
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Dereferences the global `PREV_LOC` variable. May not be called concurrently.
+    pub unsafe extern "C" fn trace_block_transition_ctx_single(_: *const (), id: u64) {
+        unsafe {
+            let x = (ctx_hash(id) as usize) & LIBAFL_QEMU_EDGES_MAP_MASK_MAX;
+            let entry = LIBAFL_QEMU_EDGES_MAP_PTR.add(x);
+            *entry = 1;
+        }
+    }
 }
@@ -13,6 +13,7 @@ use crate::{
 };
 
 mod helpers;
+pub use helpers::MAX_NGRAM_SIZE;
 use helpers::{
     LIBAFL_QEMU_EDGES_MAP_ALLOCATED_SIZE, LIBAFL_QEMU_EDGES_MAP_MASK_MAX,
     LIBAFL_QEMU_EDGES_MAP_PTR, LIBAFL_QEMU_EDGES_MAP_SIZE_PTR,
@@ -32,6 +33,19 @@ pub mod child;
 pub use child::{
     EdgeCoverageChildVariant, StdEdgeCoverageChildModule, StdEdgeCoverageChildModuleBuilder,
 };
+
+pub mod ngram;
+pub use ngram::{
+    EdgeCoverageNgramVariant, StdEdgeCoverageNgramModule, StdEdgeCoverageNgramModuleBuilder,
+};
+
+#[cfg(not(cpu_target = "hexagon"))]
+pub mod ctx;
+#[cfg(not(cpu_target = "hexagon"))]
+pub use ctx::{
+    CallContextCollector, EdgeCoverageCtxVariant, StdEdgeCoverageCtxModule,
+    StdEdgeCoverageCtxModuleBuilder,
+};
 use libafl::observers::ConstLenMapObserver;
 
 use super::utils::filters::HasAddressFilter;
@@ -96,6 +110,9 @@ trait EdgeCoverageVariant<AF, PF, const IS_CONST_MAP: bool, const MAP_SIZE: usiz
     {
         panic!("Func no hitcount is not supported.")
     }
+
+    /// Reset the tracing state of the previous run, if the variant keeps any.
+    fn pre_exec(&mut self) {}
 }
 
 #[derive(Debug)]
@@ -362,6 +379,18 @@ where
             self.variant.fn_no_hitcount(emulator_modules);
         }
     }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.variant.pre_exec();
+    }
 }
 
 impl<AF, PF, V, const IS_CONST_MAP: bool, const MAP_SIZE: usize> HasAddressFilter
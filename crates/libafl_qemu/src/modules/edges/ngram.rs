@@ -0,0 +1,140 @@
+use libafl::HasMetadata;
+
+use super::{
+    EdgeCoverageVariant,
+    helpers::{
+        gen_hashed_block_ids, reset_ngram_history, trace_block_transition_ngram_hitcount,
+        trace_block_transition_ngram_single,
+    },
+};
+use crate::{
+    EmulatorModules, Hook,
+    modules::{
+        AddressFilter, EdgeCoverageModule, EdgeCoverageModuleBuilder, EmulatorModuleTuple,
+        PageFilter,
+        utils::filters::{StdAddressFilter, StdPageFilter},
+    },
+};
+
+/// Edge coverage over the last `N` blocks instead of the last 2, like the `ngram` instrumentation
+/// of AFL++.
+///
+/// `N` must be between 2 and [`super::MAX_NGRAM_SIZE`]. There is no JIT version of this variant.
+#[derive(Debug)]
+pub struct EdgeCoverageNgramVariant<const N: usize>;
+
+pub type StdEdgeCoverageNgramModule<const N: usize> =
+    EdgeCoverageModule<StdAddressFilter, StdPageFilter, EdgeCoverageNgramVariant<N>, false, 0>;
+pub type StdEdgeCoverageNgramModuleBuilder<const N: usize> = EdgeCoverageModuleBuilder<
+    StdAddressFilter,
+    StdPageFilter,
+    EdgeCoverageNgramVariant<N>,
+    false,
+    false,
+    0,
+>;
+
+impl<AF, PF, const IS_CONST_MAP: bool, const MAP_SIZE: usize, const N: usize>
+    EdgeCoverageVariant<AF, PF, IS_CONST_MAP, MAP_SIZE> for EdgeCoverageNgramVariant<N>
+{
+    const DO_SIDE_EFFECTS: bool = false;
+
+    fn fn_hitcount<ET, I, S>(&mut self, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        AF: AddressFilter,
+        ET: EmulatorModuleTuple<I, S>,
+        PF: PageFilter,
+        I: Unpin,
+        S: HasMetadata + Unpin,
+    {
+        emulator_modules.blocks(
+            Hook::Function(gen_hashed_block_ids::<AF, ET, PF, I, S, Self, IS_CONST_MAP, MAP_SIZE>),
+            Hook::Empty,
+            Hook::Raw(trace_block_transition_ngram_hitcount::<N>),
+        );
+    }
+
+    fn fn_no_hitcount<ET, I, S>(&mut self, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        AF: AddressFilter,
+        ET: EmulatorModuleTuple<I, S>,
+        PF: PageFilter,
+        I: Unpin,
+        S: HasMetadata + Unpin,
+    {
+        emulator_modules.blocks(
+            Hook::Function(gen_hashed_block_ids::<AF, ET, PF, I, S, Self, IS_CONST_MAP, MAP_SIZE>),
+            Hook::Empty,
+            Hook::Raw(trace_block_transition_ngram_single::<N>),
+        );
+    }
+
+    fn pre_exec(&mut self) {
+        reset_ngram_history();
+    }
+}
+
+impl<const N: usize> Default for StdEdgeCoverageNgramModuleBuilder<N> {
+    fn default() -> Self {
+        Self {
+            variant: EdgeCoverageNgramVariant,
+            address_filter: StdAddressFilter::default(),
+            page_filter: StdPageFilter::default(),
+            use_hitcounts: true,
+            use_jit: false,
+        }
+    }
+}
+
+impl<const N: usize> StdEdgeCoverageNgramModule<N> {
+    #[must_use]
+    pub fn builder() -> StdEdgeCoverageNgramModuleBuilder<N> {
+        EdgeCoverageModuleBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::helpers::{ngram_hash, reset_ngram_history};
+
+    #[test]
+    fn test_ngram_hash() {
+        // Fill the history
+        for id in [1, 2, 3, 4] {
+            ngram_hash::<4>(id << 8);
+        }
+        let a = ngram_hash::<4>(5 << 8);
+
+        // The same last 4 blocks map to the same index
+        for id in [9, 2, 3, 4] {
+            ngram_hash::<4>(id << 8);
+        }
+        assert_eq!(ngram_hash::<4>(5 << 8), a);
+
+        // A different path to the same edge does not
+        for id in [1, 2, 7, 4] {
+            ngram_hash::<4>(id << 8);
+        }
+        assert_ne!(ngram_hash::<4>(5 << 8), a);
+    }
+
+    #[test]
+    fn test_ngram_reset() {
+        reset_ngram_history();
+        let first: Vec<u64> = [1, 2, 3]
+            .iter()
+            .map(|id| ngram_hash::<4>(id << 8))
+            .collect();
+
+        // The blocks of the previous run do not leak into the next one
+        for id in [7, 8, 9] {
+            ngram_hash::<4>(id << 8);
+        }
+        reset_ngram_history();
+        let second: Vec<u64> = [1, 2, 3]
+            .iter()
+            .map(|id| ngram_hash::<4>(id << 8))
+            .collect();
+        assert_eq!(first, second);
+    }
+}
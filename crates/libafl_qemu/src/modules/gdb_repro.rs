@@ -0,0 +1,635 @@
+//! Reproduce a solution under the QEMU gdbstub, stopped where it matters.
+//!
+//! Instead of re-running a crash by hand under `qemu -g`, build the same emulator as the fuzzer,
+//! with a [`GdbReproModule`] added to the modules and the QEMU arguments passed through
+//! [`GdbRepro::qemu_args`], and hand the harness to [`GdbRepro::run`]. The reproduction runs in
+//! two phases:
+//! - the *trace* phase runs the solution once and counts the executed blocks, to find the block
+//!   the run crashed in, or the block with the chosen [`ReproStop::Block`] count. It then writes
+//!   a gdb script breaking there and starts the current binary again for the second phase.
+//! - the *debug* phase, started with the gdbstub listening, runs the solution again. Attaching
+//!   with `gdb -x <script>` stops at the start of the block, with the exact same state as in the
+//!   fuzzer. Continuing from a crashing block gets to the faulting instruction in usermode, where
+//!   gdb reports the signal.
+//!
+//! Both phases restore the snapshot taken right after [`Emulator::first_exec`] before running the
+//! solution, so that they replay it from the same state.
+//!
+//! gdb counts breakpoint hits from the start of QEMU, including the loader, the libc init and the
+//! setup of the harness before the solution runs. The [`GdbReproModule`] counts the executions of
+//! each block from the start of QEMU as well, so the `ignore` count of the script matches.
+//!
+//! In usermode, the trace phase runs in a forked child, so that the crash does not take down the
+//! reproducer, and is killed after [`GdbRepro::timeout`]. QEMU waits for gdb to attach before
+//! running the guest. In systemmode, the
+//! crash has to be detected by the harness (as for the fuzzer), and QEMU is started with `-S`.
+#![allow(clippy::needless_pass_by_value)] // default compiler complains about Option<&mut T> otherwise
+#![allow(clippy::unnecessary_cast)]
+
+use core::{fmt::Write, ptr::NonNull, time::Duration};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use hashbrown::HashMap;
+use libafl::{executors::ExitKind, inputs::Input};
+use libafl_bolts::Error;
+use libafl_qemu_sys::GuestAddr;
+
+#[cfg(feature = "systemmode")]
+use crate::modules::utils::filters::{HasPageFilter, NOP_PAGE_FILTER, NopPageFilter};
+use crate::{
+    Emulator, EmulatorDriver, Qemu,
+    command::CommandManager,
+    emu::{EmulatorModules, IsSnapshotManager, SnapshotId},
+    modules::{
+        EmulatorModule, EmulatorModuleTuple,
+        utils::filters::{HasAddressFilter, NOP_ADDRESS_FILTER, NopAddressFilter},
+    },
+    qemu::Hook,
+};
+
+/// The environment variable marking the debug phase of a [`GdbRepro`]
+pub const GDB_REPRO_ENV: &str = "LIBAFL_QEMU_GDB_REPRO";
+
+/// The default port of the gdbstub
+pub const DEFAULT_GDB_REPRO_PORT: u16 = 1234;
+
+/// The default time the trace phase may run for
+pub const DEFAULT_GDB_REPRO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the reproduction stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReproStop {
+    /// In the block the solution crashed (or timed out) in
+    Crash,
+    /// In the n-th executed block, counting from 1
+    Block(u64),
+}
+
+/// A point in the execution of a solution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReproStopPoint {
+    /// The start of the block
+    pub pc: GuestAddr,
+    /// How often the block was executed since QEMU started, including this time
+    pub hits: u64,
+    /// How many blocks the solution executed, including this one
+    pub block: u64,
+}
+
+/// The result of [`GdbRepro::run`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GdbReproPhase {
+    /// The stop point was found, the gdb script written, and the debug phase has run to the end
+    Traced {
+        /// Where gdb stops
+        stop: ReproStopPoint,
+        /// The gdb script breaking at `stop`
+        script: PathBuf,
+    },
+    /// The solution ran with the gdbstub listening, and returned
+    Debugged(ExitKind),
+}
+
+/// The trace of a run, kept in shared memory to survive a crashing child
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct ReproTrace {
+    blocks: u64,
+    last_pc: u64,
+    last_hits: u64,
+    stop_pc: u64,
+    stop_hits: u64,
+    stop_reached: bool,
+    finished: bool,
+    exit_ok: bool,
+}
+
+/// Counts the executed blocks of a reproduction run, see [`GdbRepro`]
+#[derive(Debug)]
+pub struct GdbReproModule {
+    stop: ReproStop,
+    hits: HashMap<GuestAddr, u64>,
+    trace: NonNull<ReproTrace>,
+}
+
+impl GdbReproModule {
+    /// Creates a new [`GdbReproModule`], stopping at `stop`
+    ///
+    /// # Errors
+    /// If the shared memory for the trace cannot be mapped
+    pub fn new(stop: ReproStop) -> Result<Self, Error> {
+        let trace = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                size_of::<ReproTrace>(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if trace == libc::MAP_FAILED {
+            return Err(Error::last_os_error("Could not map the reproduction trace"));
+        }
+        let trace = NonNull::new(trace.cast::<ReproTrace>()).unwrap();
+        unsafe { trace.write(ReproTrace::default()) };
+        Ok(Self {
+            stop,
+            hits: HashMap::new(),
+            trace,
+        })
+    }
+
+    fn trace(&self) -> ReproTrace {
+        unsafe { self.trace.read_volatile() }
+    }
+
+    /// Starts a new trace. The hits of each block are kept, as gdb counts them from QEMU start.
+    fn reset(&mut self) {
+        unsafe { self.trace.write_volatile(ReproTrace::default()) };
+    }
+
+    fn finish(&mut self, exit_kind: ExitKind) {
+        let trace = unsafe { self.trace.as_mut() };
+        trace.finished = true;
+        trace.exit_ok = exit_kind == ExitKind::Ok;
+    }
+
+    fn on_block(&mut self, pc: GuestAddr) {
+        let hits = self.hits.entry(pc).or_default();
+        *hits += 1;
+
+        let trace = unsafe { self.trace.as_mut() };
+        trace.blocks += 1;
+        trace.last_pc = pc as u64;
+        trace.last_hits = *hits;
+        if self.stop == ReproStop::Block(trace.blocks) {
+            trace.stop_pc = pc as u64;
+            trace.stop_hits = *hits;
+            trace.stop_reached = true;
+        }
+    }
+
+    /// The point to stop at, from the trace of the last run
+    fn stop_point(&self) -> Result<ReproStopPoint, Error> {
+        let trace = self.trace();
+        match self.stop {
+            ReproStop::Crash => {
+                if trace.finished && trace.exit_ok {
+                    return Err(Error::illegal_argument("The solution does not crash"));
+                }
+                if trace.blocks == 0 {
+                    return Err(Error::illegal_state(
+                        "The solution did not execute any block",
+                    ));
+                }
+                Ok(ReproStopPoint {
+                    pc: trace.last_pc as GuestAddr,
+                    hits: trace.last_hits,
+                    block: trace.blocks,
+                })
+            }
+            ReproStop::Block(block) => {
+                if !trace.stop_reached {
+                    return Err(Error::illegal_argument(format!(
+                        "The solution executes only {} blocks, not {block}",
+                        trace.blocks
+                    )));
+                }
+                Ok(ReproStopPoint {
+                    pc: trace.stop_pc as GuestAddr,
+                    hits: trace.stop_hits,
+                    block,
+                })
+            }
+        }
+    }
+}
+
+impl Drop for GdbReproModule {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.trace.as_ptr().cast(), size_of::<ReproTrace>());
+        }
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for GdbReproModule
+where
+    I: Unpin,
+    S: Unpin,
+{
+    fn post_qemu_init<ET>(&mut self, _qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        emulator_modules.blocks(
+            Hook::Function(gen_block_repro::<ET, I, S>),
+            Hook::Empty,
+            Hook::Function(exec_block_repro::<ET, I, S>),
+        );
+    }
+}
+
+impl HasAddressFilter for GdbReproModule {
+    type AddressFilter = NopAddressFilter;
+
+    fn address_filter(&self) -> &Self::AddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::AddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+#[cfg(feature = "systemmode")]
+impl HasPageFilter for GdbReproModule {
+    type PageFilter = NopPageFilter;
+
+    fn page_filter(&self) -> &Self::PageFilter {
+        &NopPageFilter
+    }
+
+    fn page_filter_mut(&mut self) -> &mut Self::PageFilter {
+        unsafe { (&raw mut NOP_PAGE_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+fn gen_block_repro<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    Some(pc as u64)
+}
+
+fn exec_block_repro<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    if let Some(h) = emulator_modules.get_mut::<GdbReproModule>() {
+        h.on_block(id as GuestAddr);
+    }
+}
+
+/// Reproduces a solution under the QEMU gdbstub, see the [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct GdbRepro {
+    solution: PathBuf,
+    port: u16,
+    script: Option<PathBuf>,
+    timeout: Duration,
+}
+
+impl GdbRepro {
+    /// Creates a new [`GdbRepro`] for the solution at `solution`
+    #[must_use]
+    pub fn new<P: AsRef<Path>>(solution: P) -> Self {
+        Self {
+            solution: solution.as_ref().to_path_buf(),
+            port: DEFAULT_GDB_REPRO_PORT,
+            script: None,
+            timeout: DEFAULT_GDB_REPRO_TIMEOUT,
+        }
+    }
+
+    /// The port the gdbstub listens on
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Where to write the gdb script, next to the solution with a `.gdb` extension by default
+    #[must_use]
+    pub fn script<P: AsRef<Path>>(mut self, script: P) -> Self {
+        self.script = Some(script.as_ref().to_path_buf());
+        self
+    }
+
+    /// How long the trace phase may run before it is killed and the last block is taken as the
+    /// stop point, as for a timeout in the fuzzer. Only used in usermode.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// If this process is the debug phase, started by the trace phase
+    #[must_use]
+    pub fn is_debug_phase(&self) -> bool {
+        env::var_os(GDB_REPRO_ENV).is_some()
+    }
+
+    /// The QEMU arguments for this phase: `args`, with the gdbstub enabled in the debug phase
+    pub fn qemu_args<A, T>(&self, args: A) -> Vec<String>
+    where
+        A: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut args: Vec<String> = args.into_iter().map(Into::into).collect();
+        if self.is_debug_phase() {
+            // QEMU options have to go in front of the target binary in usermode
+            let at = 1.min(args.len());
+            #[cfg(feature = "usermode")]
+            args.splice(at..at, ["-g".to_string(), self.port.to_string()]);
+            #[cfg(feature = "systemmode")]
+            args.splice(
+                at..at,
+                [
+                    "-S".to_string(),
+                    "-gdb".to_string(),
+                    format!("tcp::{}", self.port),
+                ],
+            );
+        }
+        args
+    }
+
+    fn script_path(&self) -> PathBuf {
+        self.script
+            .clone()
+            .unwrap_or_else(|| self.solution.with_extension("gdb"))
+    }
+
+    /// The gdb script connecting to the gdbstub and breaking at `stop`
+    #[must_use]
+    pub fn gdb_script(&self, stop: &ReproStopPoint) -> String {
+        let mut script = String::new();
+        writeln!(script, "set pagination off").unwrap();
+        writeln!(script, "target remote localhost:{}", self.port).unwrap();
+        writeln!(script, "break *{:#x}", stop.pc).unwrap();
+        if stop.hits > 1 {
+            writeln!(script, "ignore $bpnum {}", stop.hits - 1).unwrap();
+        }
+        writeln!(script, "continue").unwrap();
+        script
+    }
+
+    /// Runs the reproduction.
+    ///
+    /// The emulator has to be set up as for the fuzzer, with a [`GdbReproModule`] in its modules,
+    /// and `harness` is the harness of the fuzzer. In the trace phase, this only returns once the
+    /// debug phase is over.
+    ///
+    /// # Errors
+    /// If the solution cannot be loaded, does not reach the stop point, or the debug phase cannot
+    /// be started
+    pub fn run<C, CM, ED, ET, H, I, S, SM>(
+        &self,
+        emulator: &mut Emulator<C, CM, ED, ET, I, S, SM>,
+        state: &mut S,
+        harness: &mut H,
+    ) -> Result<GdbReproPhase, Error>
+    where
+        C: Clone,
+        CM: CommandManager<C, ED, ET, I, S, SM, Commands = C>,
+        ED: EmulatorDriver<C, CM, ET, I, S, SM>,
+        ET: EmulatorModuleTuple<I, S>,
+        H: FnMut(&mut Emulator<C, CM, ED, ET, I, S, SM>, &mut S, &I) -> ExitKind,
+        I: Input + Unpin,
+        S: Unpin,
+        SM: IsSnapshotManager,
+    {
+        let input = I::from_file(&self.solution)?;
+        emulator
+            .modules_mut()
+            .get_mut::<GdbReproModule>()
+            .ok_or_else(|| Error::key_not_found("GdbReproModule is not in the emulator modules"))?
+            .reset();
+        emulator.first_exec(state);
+        let qemu = emulator.qemu();
+        let snapshot = emulator.snapshot_manager_mut().save(qemu);
+
+        if self.is_debug_phase() {
+            log::info!(
+                "Running {} with gdbstub on port {}",
+                self.solution.display(),
+                self.port
+            );
+            Self::restore(emulator, &snapshot)?;
+            emulator
+                .pre_exec(state, &input)
+                .map_err(|e| Error::illegal_state(format!("Could not prepare the run: {e:?}")))?;
+            return Ok(GdbReproPhase::Debugged(harness(emulator, state, &input)));
+        }
+
+        Self::restore(emulator, &snapshot)?;
+        self.trace(emulator, state, harness, &input)?;
+        let stop = emulator
+            .modules()
+            .get::<GdbReproModule>()
+            .unwrap()
+            .stop_point()?;
+
+        let script = self.script_path();
+        fs::write(&script, self.gdb_script(&stop))?;
+        log::info!(
+            "Stopping in block {} ({:#x}, hit {} times), attach with `gdb -x {}`",
+            stop.block,
+            stop.pc,
+            stop.hits,
+            script.display()
+        );
+
+        let status = Command::new(env::current_exe()?)
+            .args(env::args_os().skip(1))
+            .env(GDB_REPRO_ENV, &script)
+            .status()?;
+        log::info!("Debug phase exited with {status}");
+
+        Ok(GdbReproPhase::Traced { stop, script })
+    }
+
+    fn restore<C, CM, ED, ET, I, S, SM>(
+        emulator: &mut Emulator<C, CM, ED, ET, I, S, SM>,
+        snapshot: &SnapshotId,
+    ) -> Result<(), Error>
+    where
+        SM: IsSnapshotManager,
+    {
+        let qemu = emulator.qemu();
+        emulator
+            .snapshot_manager_mut()
+            .restore(qemu, snapshot)
+            .map_err(|e| Error::illegal_state(format!("Could not restore the snapshot: {e:?}")))
+    }
+
+    /// Runs the solution once, recording the trace in the [`GdbReproModule`].
+    /// Forks in usermode, as a crashing guest takes the whole process down.
+    #[allow(clippy::unused_self)] // the timeout is only used in usermode
+    fn trace<C, CM, ED, ET, H, I, S, SM>(
+        &self,
+        emulator: &mut Emulator<C, CM, ED, ET, I, S, SM>,
+        state: &mut S,
+        harness: &mut H,
+        input: &I,
+    ) -> Result<(), Error>
+    where
+        C: Clone,
+        CM: CommandManager<C, ED, ET, I, S, SM, Commands = C>,
+        ED: EmulatorDriver<C, CM, ET, I, S, SM>,
+        ET: EmulatorModuleTuple<I, S>,
+        H: FnMut(&mut Emulator<C, CM, ED, ET, I, S, SM>, &mut S, &I) -> ExitKind,
+        I: Unpin,
+        S: Unpin,
+    {
+        #[cfg(feature = "usermode")]
+        {
+            use libafl_bolts::os::{ForkResult, fork};
+
+            match unsafe { fork()? } {
+                ForkResult::Parent(child) => {
+                    if child.wait_timeout(self.timeout).is_none() {
+                        log::warn!(
+                            "The solution still runs after {:?}, killing the trace",
+                            self.timeout
+                        );
+                        child.kill();
+                    }
+                }
+                ForkResult::Child => {
+                    if let Err(e) = emulator.pre_exec(state, input) {
+                        log::error!("Could not prepare the run: {e:?}");
+                        unsafe { libc::_exit(1) };
+                    }
+                    let exit_kind = harness(emulator, state, input);
+                    if let Some(h) = emulator.modules_mut().get_mut::<GdbReproModule>() {
+                        h.finish(exit_kind);
+                    }
+                    unsafe { libc::_exit(0) };
+                }
+            }
+        }
+
+        #[cfg(feature = "systemmode")]
+        {
+            emulator
+                .pre_exec(state, input)
+                .map_err(|e| Error::illegal_state(format!("Could not prepare the run: {e:?}")))?;
+            let exit_kind = harness(emulator, state, input);
+            if let Some(h) = emulator.modules_mut().get_mut::<GdbReproModule>() {
+                h.finish(exit_kind);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::executors::ExitKind;
+
+    use super::{GdbRepro, GdbReproModule, ReproStop, ReproStopPoint};
+
+    #[test]
+    fn test_gdb_script() {
+        let repro = GdbRepro::new("crashes/id_0").port(4321);
+        let script = repro.gdb_script(&ReproStopPoint {
+            pc: 0x401000,
+            hits: 3,
+            block: 1337,
+        });
+        assert_eq!(
+            script,
+            "set pagination off\n\
+             target remote localhost:4321\n\
+             break *0x401000\n\
+             ignore $bpnum 2\n\
+             continue\n"
+        );
+        assert_eq!(
+            repro.script_path(),
+            std::path::PathBuf::from("crashes/id_0.gdb")
+        );
+    }
+
+    #[test]
+    fn test_stop_at_crash() {
+        let mut module = GdbReproModule::new(ReproStop::Crash).unwrap();
+        assert!(module.stop_point().is_err());
+
+        for pc in [0x1000, 0x2000, 0x1000, 0x3000, 0x1000] {
+            module.on_block(pc);
+        }
+        // A crash (or a killed trace) never finishes
+        assert_eq!(
+            module.stop_point().unwrap(),
+            ReproStopPoint {
+                pc: 0x1000,
+                hits: 3,
+                block: 5,
+            }
+        );
+
+        module.finish(ExitKind::Crash);
+        assert_eq!(module.stop_point().unwrap().block, 5);
+
+        module.finish(ExitKind::Ok);
+        assert!(module.stop_point().is_err());
+
+        // The hits before the solution ran count, as they do for gdb
+        module.reset();
+        assert!(module.stop_point().is_err());
+        module.on_block(0x1000);
+        assert_eq!(
+            module.stop_point().unwrap(),
+            ReproStopPoint {
+                pc: 0x1000,
+                hits: 4,
+                block: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stop_at_block() {
+        let mut module = GdbReproModule::new(ReproStop::Block(4)).unwrap();
+        for pc in [0x1000, 0x2000, 0x1000] {
+            module.on_block(pc);
+        }
+        assert!(module.stop_point().is_err());
+
+        for pc in [0x2000, 0x1000, 0x3000] {
+            module.on_block(pc);
+        }
+        module.finish(ExitKind::Ok);
+        assert_eq!(
+            module.stop_point().unwrap(),
+            ReproStopPoint {
+                pc: 0x2000,
+                hits: 2,
+                block: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_trace_phase_args() {
+        let repro = GdbRepro::new("crashes/id_0").timeout(std::time::Duration::from_secs(1));
+        assert!(!repro.is_debug_phase());
+        assert_eq!(repro.timeout, std::time::Duration::from_secs(1));
+        assert_eq!(
+            repro.qemu_args(["qemu", "./target", "@@"]),
+            vec!["qemu", "./target", "@@"]
+        );
+    }
+}
@@ -1,20 +1,31 @@
 //! Logger Module
 //!
-//! It's a simple module logging selected events to the logger with the `info` level.
+//! It's a simple module tracing selected events of each execution.
+//! By default, the events get logged to the logger with the `info` level.
+//! If a trace file is set, they get written to it in the [`libafl_targets::exec_trace`] format instead,
+//! which can be read back with an [`ExecTraceReader`](libafl_targets::exec_trace::ExecTraceReader).
 //! It must be built through [`LoggerModuleBuilder`].
 
-use std::fmt::Debug;
+use std::{fmt::Debug, fs::File, path::PathBuf};
 
-use libafl_qemu_sys::TCGTemp;
+#[cfg(not(cpu_target = "hexagon"))]
+use capstone::prelude::*;
+use libafl::{executors::ExitKind, observers::ObserversTuple};
+use libafl_qemu_sys::{CPUArchStatePtr, TCGTemp};
+use libafl_targets::exec_trace::{ExecTraceEvent, ExecTraceWriter};
 
+#[cfg(feature = "usermode")]
+use crate::qemu::SyscallHookResult;
 use crate::{
     EmulatorModules, GuestAddr, Hook, MemAccessInfo,
     modules::{
         EmulatorModule, EmulatorModuleTuple,
-        utils::filters::{AddressFilter, NopAddressFilter, NopPageFilter},
+        utils::filters::{AddressFilter, NopAddressFilter, NopPageFilter, PageFilter},
     },
     qemu::Qemu,
 };
+#[cfg(not(cpu_target = "hexagon"))]
+use crate::{capstone, qemu::ArchExtras};
 
 /// A builder for [`LoggerModule`].
 ///
@@ -31,6 +42,7 @@ pub struct LoggerModuleBuilder<AF, PF> {
     edges: bool,
     blocks: bool,
     instruction: Option<Vec<GuestAddr>>,
+    trace_file: Option<PathBuf>,
 
     addr_filter: AF,
     page_filter: PF,
@@ -49,6 +61,7 @@ impl Default for LoggerModuleBuilder<NopAddressFilter, NopPageFilter> {
             edges: false,
             blocks: false,
             instruction: None,
+            trace_file: None,
             addr_filter: NopAddressFilter,
             page_filter: NopPageFilter,
         }
@@ -56,6 +69,7 @@ impl Default for LoggerModuleBuilder<NopAddressFilter, NopPageFilter> {
 }
 
 impl<AF, PF> LoggerModuleBuilder<AF, PF> {
+    /// Log call and return instructions
     #[must_use]
     pub fn calls(mut self, calls: bool) -> Self {
         self.calls = calls;
@@ -63,6 +77,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log comparisons, with their operands
     #[must_use]
     pub fn cmps(mut self, cmps: bool) -> Self {
         self.cmps = cmps;
@@ -70,6 +85,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log memory reads
     #[must_use]
     pub fn reads(mut self, reads: bool) -> Self {
         self.reads = reads;
@@ -77,6 +93,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log memory writes
     #[must_use]
     pub fn writes(mut self, writes: bool) -> Self {
         self.writes = writes;
@@ -84,6 +101,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log the creation of new threads
     #[must_use]
     pub fn threads(mut self, threads: bool) -> Self {
         self.threads = threads;
@@ -91,6 +109,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log syscalls and their return values (usermode only)
     #[must_use]
     pub fn syscalls(mut self, syscalls: bool) -> Self {
         self.syscalls = syscalls;
@@ -98,6 +117,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log custom (backdoor) instructions
     #[must_use]
     pub fn custom_insns(mut self, custom_insns: bool) -> Self {
         self.custom_insns = custom_insns;
@@ -105,6 +125,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log taken edges
     #[must_use]
     pub fn edges(mut self, edges: bool) -> Self {
         self.edges = edges;
@@ -112,6 +133,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log executed blocks
     #[must_use]
     pub fn blocks(mut self, blocks: bool) -> Self {
         self.blocks = blocks;
@@ -119,6 +141,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Log each execution of the instruction at the given address. Can be called multiple times.
     #[must_use]
     pub fn instruction(mut self, instruction: GuestAddr) -> Self {
         let instructions = if let Some(insns) = &mut self.instruction {
@@ -134,6 +157,19 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
         self
     }
 
+    /// Write the events to a binary execution trace at `trace_file`, instead of logging them.
+    ///
+    /// The file is created (or truncated) before the first execution.
+    #[must_use]
+    pub fn trace_file<P>(mut self, trace_file: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.trace_file = Some(trace_file.into());
+
+        self
+    }
+
     #[must_use]
     pub fn addr_filter<AF2>(self, addr_filter: AF2) -> LoggerModuleBuilder<AF2, PF> {
         LoggerModuleBuilder {
@@ -147,6 +183,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
             edges: self.edges,
             blocks: self.blocks,
             instruction: self.instruction,
+            trace_file: self.trace_file,
             addr_filter,
             page_filter: self.page_filter,
         }
@@ -165,6 +202,7 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
             edges: self.edges,
             blocks: self.blocks,
             instruction: self.instruction,
+            trace_file: self.trace_file,
             addr_filter: self.addr_filter,
             page_filter,
         }
@@ -173,6 +211,8 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
     #[must_use]
     pub fn build(self) -> LoggerModule<AF, PF> {
         LoggerModule {
+            #[cfg(not(cpu_target = "hexagon"))]
+            cs: self.calls.then(|| capstone().detail(true).build().unwrap()),
             calls: self.calls,
             cmps: self.cmps,
             reads: self.reads,
@@ -183,6 +223,11 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
             edges: self.edges,
             blocks: self.blocks,
             instruction: self.instruction,
+            trace_file: self.trace_file,
+            trace: None,
+            exec_index: 0,
+            edge_ids: Vec::new(),
+            cmp_ids: Vec::new(),
             addr_filter: self.addr_filter,
             page_filter: self.page_filter,
         }
@@ -190,10 +235,9 @@ impl<AF, PF> LoggerModuleBuilder<AF, PF> {
 }
 
 /// Module used to log events in QEMU.
-/// It basically logs whatever goes through QEMU's hooks.
+/// It basically logs whatever goes through QEMU's hooks, either to the logger or to a binary execution trace.
 /// It can be configured through [`LoggerModuleBuilder`].
 #[derive(Debug)]
-#[expect(dead_code)]
 #[expect(clippy::struct_excessive_bools)]
 pub struct LoggerModule<AF, PF> {
     calls: bool,
@@ -206,8 +250,19 @@ pub struct LoggerModule<AF, PF> {
     edges: bool,
     blocks: bool,
     instruction: Option<Vec<GuestAddr>>,
+    trace_file: Option<PathBuf>,
+
+    #[cfg(not(cpu_target = "hexagon"))]
+    cs: Option<Capstone>,
+    trace: Option<ExecTraceWriter<File>>,
+    exec_index: u64,
+    /// The (source, destination) of each instrumented edge, indexed by the edge id
+    edge_ids: Vec<(GuestAddr, GuestAddr)>,
+    /// The (pc, size) of each instrumented comparison, indexed by the comparison id
+    cmp_ids: Vec<(GuestAddr, usize)>,
 
     addr_filter: AF,
+    #[cfg_attr(feature = "usermode", allow(dead_code))]
     page_filter: PF,
 }
 
@@ -218,65 +273,480 @@ impl LoggerModule<NopAddressFilter, NopPageFilter> {
     }
 }
 
-#[expect(clippy::unnecessary_wraps)]
-fn gen_logger_rw<ET, I, S, const IS_WRITE: bool>(
-    _qemu: Qemu,
-    _emulator_modules: &mut EmulatorModules<ET, I, S>,
-    _state: Option<&mut S>,
-    pc: GuestAddr,
-    _addr: *mut TCGTemp,
-    info: MemAccessInfo,
-) -> Option<u64>
+impl<AF, PF> LoggerModule<AF, PF>
 where
-    ET: EmulatorModuleTuple<I, S>,
-    I: Unpin,
-    S: Unpin,
+    AF: AddressFilter,
+    PF: PageFilter,
 {
-    let kind = if IS_WRITE { "write" } else { "read" };
+    #[cfg(feature = "usermode")]
+    #[must_use]
+    fn must_instrument(&self, _qemu: Qemu, addr: GuestAddr) -> bool {
+        self.addr_filter.allowed(&addr)
+    }
 
-    let size = info.size();
-    log::info!("[PC {pc:#x}] generator {kind} of {size} bytes");
+    #[cfg(feature = "systemmode")]
+    #[must_use]
+    fn must_instrument(&self, qemu: Qemu, addr: GuestAddr) -> bool {
+        let paging_id = qemu.current_cpu().and_then(|cpu| cpu.current_paging_id());
+        self.addr_filter.allowed(&addr)
+            && paging_id.is_none_or(|paging_id| self.page_filter.allowed(&paging_id))
+    }
 
-    Some(0)
-}
+    /// Write an event to the trace, or to the logger if no trace file is set
+    fn record(&mut self, event: &ExecTraceEvent) {
+        if let Some(trace) = &mut self.trace {
+            if let Err(err) = trace.write(event) {
+                log::error!("Failed to write to the execution trace: {err}");
+            }
+        } else {
+            log::info!("{event}");
+        }
+    }
 
-fn exec_logger_rw<ET, I, S, const IS_WRITE: bool, const N: usize>(
-    qemu: Qemu,
-    emulator_modules: &mut EmulatorModules<ET, I, S>,
-    state: Option<&mut S>,
-    id: u64,
-    pc: GuestAddr,
-    addr: GuestAddr,
-) where
-    ET: EmulatorModuleTuple<I, S>,
-    I: Unpin,
-    S: Unpin,
-{
-    exec_logger_rw_n::<ET, I, S, IS_WRITE>(qemu, emulator_modules, state, id, pc, addr, N);
-}
+    /// Record an event from within a hook
+    fn record_from<ET, I, S>(
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        event: &ExecTraceEvent,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        if let Some(module) = emulator_modules.get_mut::<Self>() {
+            module.record(event);
+        }
+    }
 
-fn exec_logger_rw_n<ET, I, S, const IS_WRITE: bool>(
-    _qemu: Qemu,
-    _emulator_modules: &mut EmulatorModules<ET, I, S>,
-    _state: Option<&mut S>,
-    _id: u64,
-    pc: GuestAddr,
-    addr: GuestAddr,
-    size: usize,
-) where
-    ET: EmulatorModuleTuple<I, S>,
-    I: Unpin,
-    S: Unpin,
-{
-    let kind = if IS_WRITE { "write" } else { "read" };
+    fn gen_rw<ET, I, S, const IS_WRITE: bool>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+        _addr: *mut TCGTemp,
+        _info: MemAccessInfo,
+    ) -> Option<u64>
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let module = emulator_modules.get::<Self>()?;
+        module.must_instrument(qemu, pc).then_some(0)
+    }
+
+    fn exec_rw<ET, I, S, const IS_WRITE: bool, const N: usize>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        state: Option<&mut S>,
+        id: u64,
+        pc: GuestAddr,
+        addr: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        Self::exec_rw_n::<ET, I, S, IS_WRITE>(qemu, emulator_modules, state, id, pc, addr, N);
+    }
+
+    fn exec_rw_n<ET, I, S, const IS_WRITE: bool>(
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        _id: u64,
+        pc: GuestAddr,
+        addr: GuestAddr,
+        size: usize,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let (pc, addr, size) = (u64::from(pc), u64::from(addr), size as u64);
+        let event = if IS_WRITE {
+            ExecTraceEvent::Write { pc, addr, size }
+        } else {
+            ExecTraceEvent::Read { pc, addr, size }
+        };
+        Self::record_from(emulator_modules, &event);
+    }
+
+    fn gen_block<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) -> Option<u64>
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let module = emulator_modules.get::<Self>()?;
+        // The block id is its address
+        module.must_instrument(qemu, pc).then(|| u64::from(pc))
+    }
+
+    fn exec_block<ET, I, S>(
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        id: u64,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        Self::record_from(emulator_modules, &ExecTraceEvent::Block { pc: id });
+    }
+
+    fn gen_edge<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        src: GuestAddr,
+        dest: GuestAddr,
+    ) -> Option<u64>
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let module = emulator_modules.get_mut::<Self>()?;
+        if !module.must_instrument(qemu, src) && !module.must_instrument(qemu, dest) {
+            return None;
+        }
+        module.edge_ids.push((src, dest));
+        Some(module.edge_ids.len() as u64 - 1)
+    }
+
+    fn exec_edge<ET, I, S>(
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        id: u64,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let Some(module) = emulator_modules.get_mut::<Self>() else {
+            return;
+        };
+        let edge = usize::try_from(id)
+            .ok()
+            .and_then(|id| module.edge_ids.get(id).copied());
+        if let Some((src, dest)) = edge {
+            module.record(&ExecTraceEvent::Edge {
+                src: u64::from(src),
+                dst: u64::from(dest),
+            });
+        }
+    }
+
+    fn gen_cmp<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+        size: usize,
+    ) -> Option<u64>
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let module = emulator_modules.get_mut::<Self>()?;
+        if !module.must_instrument(qemu, pc) {
+            return None;
+        }
+        module.cmp_ids.push((pc, size));
+        Some(module.cmp_ids.len() as u64 - 1)
+    }
 
-    log::info!("[PC {pc:#x}] exec {kind} of {size} bytes @addr {addr:#x}");
+    fn exec_cmp<ET, I, S, SZ>(
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        id: u64,
+        v0: SZ,
+        v1: SZ,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+        SZ: Into<u64>,
+    {
+        let Some(module) = emulator_modules.get_mut::<Self>() else {
+            return;
+        };
+        let cmp = usize::try_from(id)
+            .ok()
+            .and_then(|id| module.cmp_ids.get(id).copied());
+        if let Some((pc, size)) = cmp {
+            module.record(&ExecTraceEvent::Cmp {
+                pc: u64::from(pc),
+                size: size as u64,
+                v0: v0.into(),
+                v1: v1.into(),
+            });
+        }
+    }
+
+    /// Disassemble each new block, and hook the call and return instructions in it
+    #[cfg(not(cpu_target = "hexagon"))]
+    fn gen_blocks_calls<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) -> Option<u64>
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let mut call_addrs: Vec<(GuestAddr, usize)> = Vec::new();
+        let mut ret_addrs: Vec<GuestAddr> = Vec::new();
+
+        {
+            let module = emulator_modules.get_mut::<Self>()?;
+            if !module.must_instrument(qemu, pc) {
+                return None;
+            }
+            let cs = module.cs.as_mut()?;
+
+            #[cfg(cpu_target = "arm")]
+            cs.set_mode(if pc & 1 == 1 {
+                arch::arm::ArchMode::Thumb.into()
+            } else {
+                arch::arm::ArchMode::Arm.into()
+            })
+            .unwrap();
+
+            #[allow(unused_mut)] // cfg dependent
+            let mut code = {
+                #[cfg(feature = "usermode")]
+                unsafe {
+                    std::slice::from_raw_parts(qemu.g2h(pc), 512)
+                }
+                #[cfg(feature = "systemmode")]
+                &mut [0; 512]
+            };
+            #[cfg(feature = "systemmode")]
+            if let Err(err) = qemu.read_mem(pc, code) {
+                log::error!("Logger: failed to read mem at pc {pc:#x}: {err:?}");
+                return None;
+            }
+
+            let mut iaddr = pc;
+
+            'disasm: while let Ok(insns) = cs.disasm_count(code, iaddr.into(), 1) {
+                let Some(insn) = insns.first() else {
+                    break;
+                };
+                let insn_detail: InsnDetail = cs.insn_detail(insn).unwrap();
+                for detail in insn_detail.groups() {
+                    match u32::from(detail.0) {
+                        capstone::InsnGroupType::CS_GRP_CALL => {
+                            call_addrs.push((insn.address() as GuestAddr, insn.bytes().len()));
+                        }
+                        capstone::InsnGroupType::CS_GRP_RET => {
+                            ret_addrs.push(insn.address() as GuestAddr);
+                            break 'disasm;
+                        }
+                        capstone::InsnGroupType::CS_GRP_INVALID
+                        | capstone::InsnGroupType::CS_GRP_JUMP
+                        | capstone::InsnGroupType::CS_GRP_IRET
+                        | capstone::InsnGroupType::CS_GRP_PRIVILEGE => {
+                            break 'disasm;
+                        }
+                        _ => {}
+                    }
+                }
+
+                iaddr += insn.bytes().len() as GuestAddr;
+
+                #[cfg(feature = "usermode")]
+                {
+                    code = unsafe { std::slice::from_raw_parts(qemu.g2h(iaddr), 512) };
+                }
+                #[cfg(feature = "systemmode")]
+                if let Err(err) = qemu.read_mem(iaddr, code) {
+                    log::error!("Logger: failed to read mem at pc {iaddr:#x}: {err:?}");
+                    break;
+                }
+            }
+        }
+
+        for (call_addr, call_len) in call_addrs {
+            let on_call = Box::new(
+                move |_qemu: Qemu,
+                      emulator_modules: &mut EmulatorModules<ET, I, S>,
+                      _state: Option<&mut S>,
+                      pc: GuestAddr| {
+                    Self::record_from(
+                        emulator_modules,
+                        &ExecTraceEvent::Call {
+                            pc: u64::from(pc),
+                            return_address: u64::from(pc) + call_len as u64,
+                        },
+                    );
+                },
+            );
+            emulator_modules.instruction_closure(call_addr, on_call, false);
+        }
+
+        for ret_addr in ret_addrs {
+            emulator_modules.instruction_function(ret_addr, Self::on_ret, false);
+        }
+
+        None
+    }
+
+    #[cfg(not(cpu_target = "hexagon"))]
+    fn on_ret<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let target = qemu.read_return_address().map_or(0, u64::from);
+        Self::record_from(
+            emulator_modules,
+            &ExecTraceEvent::Ret {
+                pc: u64::from(pc),
+                target,
+            },
+        );
+    }
+
+    #[expect(clippy::too_many_arguments)]
+    #[cfg(feature = "usermode")]
+    fn on_pre_syscall<ET, I, S>(
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        sys_num: i32,
+        a0: GuestAddr,
+        a1: GuestAddr,
+        a2: GuestAddr,
+        a3: GuestAddr,
+        a4: GuestAddr,
+        a5: GuestAddr,
+        a6: GuestAddr,
+        a7: GuestAddr,
+    ) -> SyscallHookResult
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        Self::record_from(
+            emulator_modules,
+            &ExecTraceEvent::Syscall {
+                num: u64::from(sys_num.cast_unsigned()),
+                args: [a0, a1, a2, a3, a4, a5, a6, a7].map(u64::from),
+            },
+        );
+        SyscallHookResult::Run
+    }
+
+    #[expect(clippy::too_many_arguments)]
+    #[cfg(feature = "usermode")]
+    fn on_post_syscall<ET, I, S>(
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        result: GuestAddr,
+        sys_num: i32,
+        _a0: GuestAddr,
+        _a1: GuestAddr,
+        _a2: GuestAddr,
+        _a3: GuestAddr,
+        _a4: GuestAddr,
+        _a5: GuestAddr,
+        _a6: GuestAddr,
+        _a7: GuestAddr,
+    ) -> GuestAddr
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        Self::record_from(
+            emulator_modules,
+            &ExecTraceEvent::SyscallRet {
+                num: u64::from(sys_num.cast_unsigned()),
+                result: u64::from(result),
+            },
+        );
+        result
+    }
+
+    fn on_new_thread<ET, I, S>(
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        _env: CPUArchStatePtr,
+        tid: u32,
+    ) -> bool
+    where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        Self::record_from(
+            emulator_modules,
+            &ExecTraceEvent::NewThread {
+                tid: u64::from(tid),
+            },
+        );
+        true
+    }
+
+    fn on_custom_insn<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        _cpu: CPUArchStatePtr,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let Some(module) = emulator_modules.get_mut::<Self>() else {
+            return;
+        };
+        if module.must_instrument(qemu, pc) {
+            module.record(&ExecTraceEvent::CustomInsn { pc: u64::from(pc) });
+        }
+    }
+
+    fn on_instruction<ET, I, S>(
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        Self::record_from(
+            emulator_modules,
+            &ExecTraceEvent::Instruction { pc: u64::from(pc) },
+        );
+    }
 }
 
 impl<AF, I, PF, S> EmulatorModule<I, S> for LoggerModule<AF, PF>
 where
     AF: AddressFilter,
-    PF: Debug + 'static,
+    PF: PageFilter,
     I: Unpin,
     S: Unpin,
 {
@@ -288,58 +758,144 @@ where
     ) where
         ET: EmulatorModuleTuple<I, S>,
     {
+        if let Some(trace_file) = &self.trace_file {
+            let file = File::create(trace_file).unwrap_or_else(|err| {
+                panic!(
+                    "Failed to create the execution trace {}: {err}",
+                    trace_file.display()
+                )
+            });
+            self.trace = Some(ExecTraceWriter::new(file).unwrap());
+        }
+
         if self.reads {
             emulator_modules.reads(
-                Hook::Function(gen_logger_rw::<ET, I, S, false>),
-                Hook::Function(exec_logger_rw::<ET, I, S, false, 1>),
-                Hook::Function(exec_logger_rw::<ET, I, S, false, 2>),
-                Hook::Function(exec_logger_rw::<ET, I, S, false, 4>),
-                Hook::Function(exec_logger_rw::<ET, I, S, false, 8>),
-                Hook::Function(exec_logger_rw_n::<ET, I, S, false>),
+                Hook::Function(Self::gen_rw::<ET, I, S, false>),
+                Hook::Function(Self::exec_rw::<ET, I, S, false, 1>),
+                Hook::Function(Self::exec_rw::<ET, I, S, false, 2>),
+                Hook::Function(Self::exec_rw::<ET, I, S, false, 4>),
+                Hook::Function(Self::exec_rw::<ET, I, S, false, 8>),
+                Hook::Function(Self::exec_rw_n::<ET, I, S, false>),
             );
         }
 
         if self.writes {
             emulator_modules.writes(
-                Hook::Function(gen_logger_rw::<ET, I, S, true>),
-                Hook::Function(exec_logger_rw::<ET, I, S, true, 1>),
-                Hook::Function(exec_logger_rw::<ET, I, S, true, 2>),
-                Hook::Function(exec_logger_rw::<ET, I, S, true, 4>),
-                Hook::Function(exec_logger_rw::<ET, I, S, true, 8>),
-                Hook::Function(exec_logger_rw_n::<ET, I, S, true>),
+                Hook::Function(Self::gen_rw::<ET, I, S, true>),
+                Hook::Function(Self::exec_rw::<ET, I, S, true, 1>),
+                Hook::Function(Self::exec_rw::<ET, I, S, true, 2>),
+                Hook::Function(Self::exec_rw::<ET, I, S, true, 4>),
+                Hook::Function(Self::exec_rw::<ET, I, S, true, 8>),
+                Hook::Function(Self::exec_rw_n::<ET, I, S, true>),
             );
         }
 
         if self.calls {
-            todo!()
+            #[cfg(not(cpu_target = "hexagon"))]
+            emulator_modules.blocks(
+                Hook::Function(Self::gen_blocks_calls::<ET, I, S>),
+                Hook::Empty,
+                Hook::Empty,
+            );
+            #[cfg(cpu_target = "hexagon")]
+            log::warn!("Logging calls is not supported on hexagon");
         }
 
         if self.blocks {
-            todo!()
+            emulator_modules.blocks(
+                Hook::Function(Self::gen_block::<ET, I, S>),
+                Hook::Empty,
+                Hook::Function(Self::exec_block::<ET, I, S>),
+            );
         }
 
         if self.cmps {
-            todo!()
+            emulator_modules.cmps(
+                Hook::Function(Self::gen_cmp::<ET, I, S>),
+                Hook::Function(Self::exec_cmp::<ET, I, S, u8>),
+                Hook::Function(Self::exec_cmp::<ET, I, S, u16>),
+                Hook::Function(Self::exec_cmp::<ET, I, S, u32>),
+                Hook::Function(Self::exec_cmp::<ET, I, S, u64>),
+            );
         }
 
         if self.custom_insns {
-            todo!()
+            emulator_modules.backdoor_function(Self::on_custom_insn::<ET, I, S>);
         }
 
         if self.edges {
-            todo!()
+            emulator_modules.edges(
+                Hook::Function(Self::gen_edge::<ET, I, S>),
+                Hook::Function(Self::exec_edge::<ET, I, S>),
+            );
         }
 
         if self.syscalls {
-            todo!()
+            #[cfg(feature = "usermode")]
+            {
+                emulator_modules.pre_syscalls(Hook::Function(Self::on_pre_syscall::<ET, I, S>));
+                emulator_modules.post_syscalls(Hook::Function(Self::on_post_syscall::<ET, I, S>));
+            }
+            #[cfg(feature = "systemmode")]
+            log::warn!("Logging syscalls is only supported in usermode");
         }
 
         if self.threads {
-            todo!()
+            emulator_modules.thread_creation_function(Self::on_new_thread::<ET, I, S>);
         }
 
-        if self.instruction.is_some() {
-            todo!()
+        if let Some(instructions) = &self.instruction {
+            for &instruction in instructions {
+                emulator_modules.instruction_function(
+                    instruction,
+                    Self::on_instruction::<ET, I, S>,
+                    true,
+                );
+            }
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.record(&ExecTraceEvent::ExecStart {
+            index: self.exec_index,
+        });
+        self.exec_index += 1;
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+        _observers: &mut OT,
+        exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<I, S>,
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.record(&ExecTraceEvent::ExecEnd {
+            exit_kind: format!("{exit_kind:?}"),
+        });
+        if let Some(trace) = &mut self.trace {
+            if let Err(err) = trace.flush() {
+                log::error!("Failed to flush the execution trace: {err}");
+            }
+        }
+    }
+
+    unsafe fn on_crash(&mut self) {
+        // Keep the events up to the crash
+        if let Some(trace) = &mut self.trace {
+            let _ = trace.flush();
         }
     }
 }
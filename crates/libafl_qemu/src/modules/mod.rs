@@ -21,11 +21,14 @@ pub mod systemmode;
 pub use systemmode::*;
 
 pub mod edges;
+#[cfg(not(cpu_target = "hexagon"))]
+pub use edges::{CallContextCollector, StdEdgeCoverageCtxModule, StdEdgeCoverageCtxModuleBuilder};
 pub use edges::{
     EdgeCoverageModule, EdgeCoverageModuleBuilder, StdEdgeCoverageChildModule,
     StdEdgeCoverageChildModuleBuilder, StdEdgeCoverageClassicModule,
     StdEdgeCoverageClassicModuleBuilder, StdEdgeCoverageFullModule,
     StdEdgeCoverageFullModuleBuilder, StdEdgeCoverageModule, StdEdgeCoverageModuleBuilder,
+    StdEdgeCoverageNgramModule, StdEdgeCoverageNgramModuleBuilder,
 };
 
 #[cfg(not(cpu_target = "hexagon"))]
@@ -43,6 +46,9 @@ pub mod drcov;
 #[cfg(not(cpu_target = "hexagon"))]
 pub use drcov::{DrCovMetadata, DrCovModule, DrCovModuleBuilder};
 
+pub mod gdb_repro;
+pub use gdb_repro::{GdbRepro, GdbReproModule, GdbReproPhase, ReproStop};
+
 pub mod logger;
 pub use logger::LoggerModule;
 
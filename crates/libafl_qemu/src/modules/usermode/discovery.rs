@@ -0,0 +1,917 @@
+//! Find the persistent-loop harness of a usermode binary automatically.
+//!
+//! Fuzzing a binary in persistent mode needs the function that consumes the input, the address
+//! it returns to, and where it gets the input from. Instead of digging for these by hand, run the
+//! binary once on a sample input with the [`HarnessDiscoveryModule`], and a
+//! [`crate::modules::CallTracerModule`] with a [`HarnessDiscoveryCollector`]:
+//! - `read` syscalls returning the start of the sample mark input buffers
+//! - each called function gets its arguments checked for a pointer to the sample, and its length,
+//!   or for the length returned by `read` if it consumes a buffer
+//! - reads of the input during a call mark the function as consuming the input
+//!
+//! Of the functions consuming the input and returning, the one executing the most blocks wins,
+//! functions getting the input as argument are preferred over functions reading a buffer.
+//! The resulting [`HarnessConfig`] can be saved with [`HarnessConfig::to_file`], and turned into
+//! a harness for the [`crate::QemuExecutor`] in a fresh QEMU with [`HarnessConfig::harness`].
+#![allow(clippy::needless_pass_by_value)] // default compiler complains about Option<&mut T> otherwise
+#![allow(clippy::unnecessary_cast)]
+use std::{fs, path::Path};
+
+use libafl::{executors::ExitKind, inputs::HasTargetBytes};
+use libafl_bolts::{AsSlice, Error};
+use libafl_qemu_sys::{GuestAddr, MmapPerms};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ArchExtras, Emulator, GuestReg, Qemu, QemuExitError, QemuExitReason, QemuShutdownCause, Regs,
+    SYS_pread64, SYS_read,
+    emu::EmulatorModules,
+    modules::{
+        EmulatorModule, EmulatorModuleTuple,
+        calls::CallTraceCollector,
+        utils::filters::{HasAddressFilter, NOP_ADDRESS_FILTER, NopAddressFilter},
+    },
+    qemu::{Hook, MemAccessInfo},
+    sys::TCGTemp,
+};
+
+/// How many arguments of each called function are checked for the input
+pub const DISCOVERY_MAX_ARGS: u8 = 4;
+/// The default size of the input buffer mapped by the harness, 1 MiB
+pub const DEFAULT_MAX_INPUT_SIZE: usize = 1_048_576;
+/// How many bytes of the sample have to match to recognize the input
+const DISCOVERY_COMPARE_LEN: usize = 64;
+
+/// Where the harness function gets its input from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HarnessInput {
+    /// A pointer to the input is passed as argument `ptr_arg`, its length as `len_arg`
+    Argument {
+        /// The index of the pointer argument
+        ptr_arg: u8,
+        /// The index of the length argument, if there is one
+        len_arg: Option<u8>,
+    },
+    /// The input is read from a fixed buffer, filled before the call
+    Buffer {
+        /// The address of the buffer
+        addr: GuestAddr,
+        /// The size of the buffer
+        capacity: usize,
+        /// Where the function gets the length of the input in the buffer from, if it does
+        len: Option<HarnessLength>,
+    },
+}
+
+/// Where the harness function gets the length of a [`HarnessInput::Buffer`] from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HarnessLength {
+    /// The length is passed as argument
+    Argument(u8),
+    /// The length is an integer of `size` bytes, in guest byte order, stored at `addr`
+    Memory {
+        /// The address of the integer
+        addr: GuestAddr,
+        /// The size of the integer, up to 8 bytes
+        size: u8,
+    },
+}
+
+/// A persistent-loop harness, as found by the [`HarnessDiscoveryModule`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarnessConfig {
+    /// The function consuming the input
+    pub entry: GuestAddr,
+    /// The address the function returned to during discovery
+    pub return_address: GuestAddr,
+    /// Where the function gets its input from
+    pub input: HarnessInput,
+    /// The size of the input buffer mapped for [`HarnessInput::Argument`]
+    pub max_input_size: usize,
+}
+
+impl HarnessConfig {
+    /// Load a config saved by [`HarnessConfig::to_file`]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        toml::from_str(&fs::read_to_string(path)?).map_err(|e| {
+            Error::serialize(format!(
+                "Failed to deserialize harness config at {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Save the config as toml
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let content = toml::to_string(self)
+            .map_err(|e| Error::serialize(format!("Failed to serialize harness config: {e}")))?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Run to the entry of the harness function and set up the input buffer.
+    ///
+    /// This has to be a fresh QEMU, not the one used for discovery. The returned closure can be
+    /// passed to the [`crate::QemuExecutor`] as is.
+    /// Runs that cannot be set up, or that stop anywhere but at the return address, get logged
+    /// and reported as [`ExitKind::Crash`].
+    #[allow(clippy::type_complexity)]
+    pub fn harness<C, CM, ED, ET, I, S, SM>(
+        &self,
+        qemu: Qemu,
+    ) -> Result<impl FnMut(&mut Emulator<C, CM, ED, ET, I, S, SM>, &mut S, &I) -> ExitKind, Error>
+    where
+        I: HasTargetBytes,
+    {
+        let harness = DiscoveredHarness::init(qemu, self)?;
+        Ok(
+            move |emulator: &mut Emulator<C, CM, ED, ET, I, S, SM>, _state: &mut S, input: &I| {
+                harness
+                    .run(emulator.qemu(), input.target_bytes().as_slice())
+                    .unwrap_or_else(|e| {
+                        log::error!("Harness run failed: {e}");
+                        ExitKind::Crash
+                    })
+            },
+        )
+    }
+}
+
+/// Encode `value` as an integer of `size` bytes in guest byte order
+fn guest_bytes(value: u64, size: usize) -> Vec<u8> {
+    let size = size.min(8);
+    if cfg!(any(feature = "be", cpu_target = "ppc")) {
+        value.to_be_bytes()[8 - size..].to_vec()
+    } else {
+        value.to_le_bytes()[..size].to_vec()
+    }
+}
+
+/// A write done by [`DiscoveredHarness::reset`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ResetStep {
+    Memory(GuestAddr, Vec<u8>),
+    Pc(GuestAddr),
+    Sp(GuestAddr),
+    ReturnAddress(GuestAddr),
+    Argument(u8, GuestReg),
+}
+
+/// The state of a [`HarnessConfig`] at the entry of the harness function, restored for each run
+#[derive(Debug, Clone, Copy)]
+struct DiscoveredHarness {
+    entry: GuestAddr,
+    stack_ptr: GuestAddr,
+    return_address: GuestAddr,
+    input: HarnessInput,
+    input_addr: GuestAddr,
+    capacity: usize,
+}
+
+impl DiscoveredHarness {
+    fn init(qemu: Qemu, config: &HarnessConfig) -> Result<Self, Error> {
+        qemu.entry_break(config.entry);
+        log::info!("Harness entry @ {:#x}", config.entry);
+
+        let stack_ptr: GuestAddr = qemu
+            .read_reg(Regs::Sp)
+            .map_err(|e| Error::unknown(format!("Failed to read stack pointer: {e:?}")))?;
+        let return_address: GuestAddr = qemu
+            .read_return_address()
+            .map_err(|e| Error::unknown(format!("Failed to read return address: {e:?}")))?;
+        if return_address != config.return_address {
+            log::warn!(
+                "Harness returns to {return_address:#x}, discovery saw {:#x}",
+                config.return_address
+            );
+        }
+        qemu.set_breakpoint(return_address);
+
+        let (input_addr, capacity) = match config.input {
+            HarnessInput::Argument { .. } => (
+                qemu.map_private(0, config.max_input_size, MmapPerms::ReadWrite)
+                    .map_err(|e| Error::unknown(format!("Failed to map input buffer: {e:}")))?,
+                config.max_input_size,
+            ),
+            HarnessInput::Buffer { addr, capacity, .. } => (addr, capacity),
+        };
+
+        Ok(Self {
+            entry: config.entry,
+            stack_ptr,
+            return_address,
+            input: config.input,
+            input_addr,
+            capacity,
+        })
+    }
+
+    fn run(&self, qemu: Qemu, input: &[u8]) -> Result<ExitKind, Error> {
+        self.reset(qemu, input)?;
+        let result = unsafe { qemu.run() };
+        if let Ok(QemuExitReason::End(QemuShutdownCause::HostSignal(signal))) = &result {
+            signal.handle();
+        }
+        self.exit_kind(result)
+    }
+
+    fn exit_kind(&self, result: Result<QemuExitReason, QemuExitError>) -> Result<ExitKind, Error> {
+        match result {
+            Ok(QemuExitReason::Breakpoint(addr)) if addr == self.return_address => Ok(ExitKind::Ok),
+            Ok(QemuExitReason::Crash) | Err(QemuExitError::UnexpectedExit) => Ok(ExitKind::Crash),
+            Ok(QemuExitReason::Timeout) => Ok(ExitKind::Timeout),
+            result => Err(Error::unknown(format!(
+                "Unexpected QEMU exit in the harness: {result:?}"
+            ))),
+        }
+    }
+
+    fn reset(&self, qemu: Qemu, input: &[u8]) -> Result<(), Error> {
+        for step in self.reset_steps(input) {
+            match step {
+                ResetStep::Memory(addr, bytes) => qemu.write_mem(addr, &bytes).map_err(|e| {
+                    Error::unknown(format!("Failed to write to memory@{addr:#x}: {e:?}"))
+                }),
+                ResetStep::Pc(pc) => qemu
+                    .write_reg(Regs::Pc, pc)
+                    .map_err(|e| Error::unknown(format!("Failed to write PC: {e:?}"))),
+                ResetStep::Sp(sp) => qemu
+                    .write_reg(Regs::Sp, sp)
+                    .map_err(|e| Error::unknown(format!("Failed to write SP: {e:?}"))),
+                ResetStep::ReturnAddress(ret) => qemu
+                    .write_return_address(ret)
+                    .map_err(|e| Error::unknown(format!("Failed to write return address: {e:?}"))),
+                ResetStep::Argument(idx, val) => qemu
+                    .write_function_argument(idx, val)
+                    .map_err(|e| Error::unknown(format!("Failed to write argument {idx}: {e:?}"))),
+            }?;
+        }
+        Ok(())
+    }
+
+    /// The writes setting up a run on `input`, the stack pointer comes before the arguments
+    fn reset_steps(&self, input: &[u8]) -> Vec<ResetStep> {
+        let input = &input[..input.len().min(self.capacity)];
+        let len = input.len() as GuestReg;
+        let mut steps = vec![
+            ResetStep::Memory(self.input_addr, input.to_vec()),
+            ResetStep::Pc(self.entry),
+            ResetStep::Sp(self.stack_ptr),
+            ResetStep::ReturnAddress(self.return_address),
+        ];
+
+        match self.input {
+            HarnessInput::Argument { ptr_arg, len_arg } => {
+                steps.push(ResetStep::Argument(ptr_arg, self.input_addr as GuestReg));
+                if let Some(len_arg) = len_arg {
+                    steps.push(ResetStep::Argument(len_arg, len));
+                }
+            }
+            HarnessInput::Buffer { len, .. } => match len {
+                Some(HarnessLength::Argument(idx)) => {
+                    steps.push(ResetStep::Argument(idx, input.len() as GuestReg));
+                }
+                Some(HarnessLength::Memory { addr, size }) => {
+                    steps.push(ResetStep::Memory(
+                        addr,
+                        guest_bytes(input.len() as u64, size as usize),
+                    ));
+                }
+                None => {}
+            },
+        }
+        steps
+    }
+}
+
+/// A function call in progress during discovery
+#[derive(Debug, Clone)]
+struct Frame {
+    return_address: GuestAddr,
+    /// The first block of the callee, once it executed
+    entry: Option<GuestAddr>,
+    /// The block counter at the entry
+    entered_at: u64,
+    /// How the callee gets the input, and where the input is, if it does
+    input: Option<(HarnessInput, GuestAddr, usize)>,
+    consumed: bool,
+}
+
+/// Find the harness function of the target, see the [module level documentation](self).
+#[derive(Debug)]
+pub struct HarnessDiscoveryModule {
+    sample: Vec<u8>,
+    max_input_size: usize,
+    frames: Vec<Frame>,
+    /// The return address of a call whose callee did not execute yet
+    pending_call: Option<GuestAddr>,
+    blocks: u64,
+    /// Buffers `read` filled with the sample: address, capacity, bytes read, and the block
+    /// counter then
+    read_buffers: Vec<(GuestAddr, usize, usize, u64)>,
+    /// The best harness so far, with its priority and the blocks it executed
+    best: Option<(bool, u64, HarnessConfig)>,
+}
+
+impl HarnessDiscoveryModule {
+    /// Create a new [`HarnessDiscoveryModule`], the target has to consume `sample` during discovery
+    #[must_use]
+    pub fn new<B: Into<Vec<u8>>>(sample: B) -> Self {
+        let sample = sample.into();
+        assert!(!sample.is_empty(), "The discovery sample must not be empty");
+        Self {
+            sample,
+            max_input_size: DEFAULT_MAX_INPUT_SIZE,
+            frames: Vec::new(),
+            pending_call: None,
+            blocks: 0,
+            read_buffers: Vec::new(),
+            best: None,
+        }
+    }
+
+    /// The size of the input buffer the harness maps, defaults to [`DEFAULT_MAX_INPUT_SIZE`]
+    #[must_use]
+    pub fn max_input_size(mut self, max_input_size: usize) -> Self {
+        self.max_input_size = max_input_size;
+        self
+    }
+
+    /// The harness found so far, usually called after the target exited
+    pub fn config(&self) -> Result<&HarnessConfig, Error> {
+        self.best
+            .as_ref()
+            .map(|(_, _, config)| config)
+            .ok_or_else(|| Error::empty_optional("No function consuming the input returned"))
+    }
+
+    fn compare_len(&self) -> usize {
+        self.sample.len().min(DISCOVERY_COMPARE_LEN)
+    }
+
+    /// Check the arguments of a function at its entry for the input
+    fn match_arguments(
+        &self,
+        qemu: Qemu,
+        args: &[GuestReg],
+    ) -> Option<(HarnessInput, GuestAddr, usize)> {
+        let mut buf = vec![0; self.compare_len()];
+        let ptr_arg = args.iter().position(|&arg| {
+            arg != 0
+                && qemu.read_mem(arg as GuestAddr, &mut buf).is_ok()
+                && buf == self.sample[..buf.len()]
+        })?;
+        let len_arg = args
+            .iter()
+            .enumerate()
+            .position(|(idx, &arg)| idx != ptr_arg && arg as usize == self.sample.len());
+
+        Some((
+            HarnessInput::Argument {
+                ptr_arg: ptr_arg as u8,
+                len_arg: len_arg.map(|idx| idx as u8),
+            },
+            args[ptr_arg] as GuestAddr,
+            self.sample.len(),
+        ))
+    }
+
+    /// Check if a function entered now can read the input from a buffer filled before,
+    /// and if it gets the number of bytes read as argument
+    fn match_buffer(&self, args: &[GuestReg]) -> Option<(HarnessInput, GuestAddr, usize)> {
+        let &(addr, capacity, read, _) = self
+            .read_buffers
+            .iter()
+            .rev()
+            .find(|&&(_, _, _, filled_at)| filled_at < self.blocks)?;
+        let len = args
+            .iter()
+            .position(|&arg| arg as usize == read)
+            .map(|idx| HarnessLength::Argument(idx as u8));
+        Some((
+            HarnessInput::Buffer {
+                addr,
+                capacity,
+                len,
+            },
+            addr,
+            capacity.min(self.sample.len()),
+        ))
+    }
+
+    fn on_block(&mut self, qemu: Qemu, pc: GuestAddr) {
+        self.blocks += 1;
+        if self.pending_call.is_none() {
+            return;
+        }
+        let args: Vec<GuestReg> = (0..DISCOVERY_MAX_ARGS)
+            .map(|idx| qemu.read_function_argument(idx).unwrap_or_default())
+            .collect();
+        let input = self
+            .match_arguments(qemu, &args)
+            .or_else(|| self.match_buffer(&args));
+        self.on_entry(pc, input);
+    }
+
+    /// The callee of the pending call executes its first block at `pc`
+    fn on_entry(&mut self, pc: GuestAddr, input: Option<(HarnessInput, GuestAddr, usize)>) {
+        let Some(return_address) = self.pending_call.take() else {
+            return;
+        };
+        self.frames.push(Frame {
+            return_address,
+            entry: Some(pc),
+            entered_at: self.blocks,
+            input,
+            consumed: false,
+        });
+    }
+
+    fn on_read(&mut self, addr: GuestAddr, size: usize) {
+        for frame in &mut self.frames {
+            if let Some((_, start, len)) = frame.input
+                && addr < start + len as GuestAddr
+                && start < addr + size as GuestAddr
+            {
+                frame.consumed = true;
+            }
+        }
+    }
+
+    fn on_ret(&mut self, ret_addr: GuestAddr) {
+        let Some(pos) = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.return_address == ret_addr)
+        else {
+            return;
+        };
+        // Frames above `pos` got skipped, e.g. by `longjmp`, they did not return
+        self.frames.truncate(pos + 1);
+        let frame = self.frames.pop().unwrap();
+
+        let (Some(entry), Some((input, _, _)), true) = (frame.entry, frame.input, frame.consumed)
+        else {
+            return;
+        };
+        let priority = matches!(input, HarnessInput::Argument { .. });
+        let executed = self.blocks - frame.entered_at;
+        if self
+            .best
+            .as_ref()
+            .is_some_and(|&(best_priority, best_executed, _)| {
+                (best_priority, best_executed) >= (priority, executed)
+            })
+        {
+            return;
+        }
+        log::debug!("Harness candidate {entry:#x}, {executed} blocks, input {input:?}");
+        self.best = Some((
+            priority,
+            executed,
+            HarnessConfig {
+                entry,
+                return_address: frame.return_address,
+                input,
+                max_input_size: self.max_input_size,
+            },
+        ));
+    }
+
+    fn on_input_read(&mut self, qemu: Qemu, buf: GuestAddr, capacity: usize, read: usize) {
+        let len = read.min(self.compare_len());
+        let mut data = vec![0; len];
+        if len > 0 && qemu.read_mem(buf, &mut data).is_ok() && data == self.sample[..len] {
+            log::debug!("Input read to {buf:#x}");
+            self.read_buffers.push((buf, capacity, read, self.blocks));
+        }
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for HarnessDiscoveryModule
+where
+    I: Unpin,
+    S: Unpin,
+{
+    fn post_qemu_init<ET>(&mut self, _qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        emulator_modules.blocks(
+            Hook::Function(gen_discovery::<ET, I, S>),
+            Hook::Empty,
+            Hook::Function(exec_block_discovery::<ET, I, S>),
+        );
+        emulator_modules.reads(
+            Hook::Function(gen_read_discovery::<ET, I, S>),
+            Hook::Function(trace_read_discovery::<ET, I, S, 1>),
+            Hook::Function(trace_read_discovery::<ET, I, S, 2>),
+            Hook::Function(trace_read_discovery::<ET, I, S, 4>),
+            Hook::Function(trace_read_discovery::<ET, I, S, 8>),
+            Hook::Function(trace_read_n_discovery::<ET, I, S>),
+        );
+        emulator_modules.post_syscalls(Hook::Function(syscall_discovery::<ET, I, S>));
+    }
+}
+
+impl HasAddressFilter for HarnessDiscoveryModule {
+    type AddressFilter = NopAddressFilter;
+
+    fn address_filter(&self) -> &Self::AddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::AddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+/// Tells the [`HarnessDiscoveryModule`] about calls and returns
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HarnessDiscoveryCollector;
+
+impl CallTraceCollector for HarnessDiscoveryCollector {
+    fn on_call<ET, I, S>(
+        &mut self,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+        call_len: usize,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        if let Some(h) = emulator_modules.get_mut::<HarnessDiscoveryModule>() {
+            h.pending_call = Some(pc + call_len as GuestAddr);
+        }
+    }
+
+    fn on_ret<ET, I, S>(
+        &mut self,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        _pc: GuestAddr,
+        ret_addr: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        if let Some(h) = emulator_modules.get_mut::<HarnessDiscoveryModule>() {
+            h.on_ret(ret_addr);
+        }
+    }
+}
+
+fn gen_discovery<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    Some(pc as u64)
+}
+
+fn exec_block_discovery<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules
+        .get_mut::<HarnessDiscoveryModule>()
+        .unwrap();
+    h.on_block(qemu, id as GuestAddr);
+}
+
+fn gen_read_discovery<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+    _addr: *mut TCGTemp,
+    _info: MemAccessInfo,
+) -> Option<u64>
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    Some(pc as u64)
+}
+
+fn trace_read_discovery<ET, I, S, const N: usize>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    _pc: GuestAddr,
+    addr: GuestAddr,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules
+        .get_mut::<HarnessDiscoveryModule>()
+        .unwrap();
+    h.on_read(addr, N);
+}
+
+fn trace_read_n_discovery<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    _pc: GuestAddr,
+    addr: GuestAddr,
+    size: usize,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules
+        .get_mut::<HarnessDiscoveryModule>()
+        .unwrap();
+    h.on_read(addr, size);
+}
+
+#[expect(clippy::too_many_arguments)]
+fn syscall_discovery<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    result: GuestAddr,
+    sys_num: i32,
+    _a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> GuestAddr
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let sys_num = i64::from(sys_num);
+    // Errors are small negative numbers
+    let failed = result > GuestAddr::MAX - 4096;
+    if (sys_num == SYS_read || sys_num == SYS_pread64) && !failed {
+        let h = emulator_modules
+            .get_mut::<HarnessDiscoveryModule>()
+            .unwrap();
+        h.on_input_read(qemu, a1, a2 as usize, result as usize);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::executors::ExitKind;
+
+    use libafl_qemu_sys::GuestAddr;
+
+    use super::{
+        DEFAULT_MAX_INPUT_SIZE, DiscoveredHarness, HarnessConfig, HarnessDiscoveryModule,
+        HarnessInput, HarnessLength, ResetStep, guest_bytes,
+    };
+    use crate::{QemuExitError, QemuExitReason};
+
+    const ARGUMENT: HarnessInput = HarnessInput::Argument {
+        ptr_arg: 0,
+        len_arg: Some(1),
+    };
+    const BUFFER: HarnessInput = HarnessInput::Buffer {
+        addr: 0x9000,
+        capacity: 64,
+        len: None,
+    };
+
+    /// Call a function returning to `return_address`, which finds the input at 0x9000
+    fn call(
+        discovery: &mut HarnessDiscoveryModule,
+        return_address: GuestAddr,
+        entry: GuestAddr,
+        input: Option<HarnessInput>,
+    ) {
+        discovery.pending_call = Some(return_address);
+        discovery.blocks += 1;
+        discovery.on_entry(entry, input.map(|input| (input, 0x9000, 6)));
+    }
+
+    fn harness(input: HarnessInput) -> DiscoveredHarness {
+        DiscoveredHarness {
+            entry: 0x1000,
+            stack_ptr: 0x7ff0,
+            return_address: 0x2000,
+            input,
+            input_addr: 0x9000,
+            capacity: 4,
+        }
+    }
+
+    #[test]
+    fn test_harness_config_toml() {
+        let config = HarnessConfig {
+            entry: 0x1234,
+            return_address: 0x5678,
+            input: HarnessInput::Argument {
+                ptr_arg: 0,
+                len_arg: Some(1),
+            },
+            max_input_size: 4096,
+        };
+        let content = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<HarnessConfig>(&content).unwrap(), config);
+    }
+
+    #[test]
+    fn test_discovered_harness_reset() {
+        let args = harness(HarnessInput::Argument {
+            ptr_arg: 0,
+            len_arg: Some(1),
+        });
+        assert_eq!(
+            args.reset_steps(b"abcdef"),
+            [
+                ResetStep::Memory(0x9000, b"abcd".to_vec()),
+                ResetStep::Pc(0x1000),
+                ResetStep::Sp(0x7ff0),
+                ResetStep::ReturnAddress(0x2000),
+                ResetStep::Argument(0, 0x9000),
+                ResetStep::Argument(1, 4),
+            ]
+        );
+
+        let buffer = harness(HarnessInput::Buffer {
+            addr: 0x9000,
+            capacity: 4,
+            len: Some(HarnessLength::Memory {
+                addr: 0x8000,
+                size: 4,
+            }),
+        });
+        let steps = buffer.reset_steps(b"ab");
+        assert_eq!(steps[0], ResetStep::Memory(0x9000, b"ab".to_vec()));
+        assert_eq!(steps[4], ResetStep::Memory(0x8000, guest_bytes(2, 4)));
+        assert_eq!(steps.len(), 5);
+
+        let buffer = harness(HarnessInput::Buffer {
+            addr: 0x9000,
+            capacity: 4,
+            len: Some(HarnessLength::Argument(2)),
+        });
+        assert_eq!(buffer.reset_steps(b"abc")[4], ResetStep::Argument(2, 3));
+    }
+
+    #[test]
+    fn test_discovered_harness_run() {
+        let harness = harness(HarnessInput::Buffer {
+            addr: 0x9000,
+            capacity: 4,
+            len: None,
+        });
+        assert!(matches!(
+            harness.exit_kind(Ok(QemuExitReason::Breakpoint(0x2000))),
+            Ok(ExitKind::Ok)
+        ));
+        assert!(matches!(
+            harness.exit_kind(Err(QemuExitError::UnexpectedExit)),
+            Ok(ExitKind::Crash)
+        ));
+        assert!(matches!(
+            harness.exit_kind(Ok(QemuExitReason::Timeout)),
+            Ok(ExitKind::Timeout)
+        ));
+        // Stopping anywhere else is an error, not a run
+        assert!(
+            harness
+                .exit_kind(Ok(QemuExitReason::Breakpoint(0x3000)))
+                .is_err()
+        );
+        assert!(harness.exit_kind(Ok(QemuExitReason::SyncExit)).is_err());
+    }
+
+    #[test]
+    fn test_discovery_candidates() {
+        let mut discovery = HarnessDiscoveryModule::new(*b"sample");
+        assert!(discovery.config().is_err());
+
+        // main reads the input to a buffer, and runs a long loop around the parser
+        call(&mut discovery, 0x100, 0x1000, Some(BUFFER));
+        discovery.on_read(0x9000, 1);
+        call(&mut discovery, 0x200, 0x2000, Some(ARGUMENT));
+        discovery.blocks += 10;
+        discovery.on_read(0x9004, 4);
+        discovery.on_ret(0x200);
+        assert_eq!(discovery.config().unwrap().entry, 0x2000);
+        discovery.blocks += 100;
+        discovery.on_ret(0x100);
+        // Functions getting the input as argument win over functions reading a buffer
+        assert_eq!(discovery.config().unwrap().entry, 0x2000);
+
+        // Functions not reading the input are no candidates, reads past the input do not count
+        call(&mut discovery, 0x300, 0x3000, Some(ARGUMENT));
+        discovery.blocks += 100;
+        discovery.on_read(0x9006, 2);
+        discovery.on_ret(0x300);
+        assert_eq!(discovery.config().unwrap().entry, 0x2000);
+
+        // Of the same priority, the function executing the most blocks wins
+        call(&mut discovery, 0x400, 0x4000, Some(ARGUMENT));
+        discovery.blocks += 20;
+        discovery.on_read(0x9000, 8);
+        discovery.on_ret(0x400);
+        assert_eq!(
+            discovery.config().unwrap(),
+            &HarnessConfig {
+                entry: 0x4000,
+                return_address: 0x400,
+                input: ARGUMENT,
+                max_input_size: DEFAULT_MAX_INPUT_SIZE,
+            }
+        );
+
+        // Functions skipped by a longjmp never return
+        call(&mut discovery, 0x500, 0x5000, None);
+        call(&mut discovery, 0x600, 0x6000, Some(ARGUMENT));
+        discovery.blocks += 50;
+        discovery.on_read(0x9000, 1);
+        discovery.on_ret(0x500);
+        assert!(discovery.frames.is_empty());
+        assert_eq!(discovery.config().unwrap().entry, 0x4000);
+    }
+
+    #[test]
+    fn test_discovery_match_buffer() {
+        let mut discovery = HarnessDiscoveryModule::new(*b"sample");
+        assert!(discovery.match_buffer(&[0x9000, 6]).is_none());
+
+        // read 6 bytes of the sample to a buffer of 64
+        discovery.blocks = 3;
+        discovery.read_buffers.push((0x9000, 64, 6, 3));
+        // a function entered in the same block did not see the buffer filled
+        assert!(discovery.match_buffer(&[0, 6]).is_none());
+
+        discovery.blocks = 4;
+        assert_eq!(
+            discovery.match_buffer(&[0, 6, 0, 0]),
+            Some((
+                HarnessInput::Buffer {
+                    addr: 0x9000,
+                    capacity: 64,
+                    len: Some(HarnessLength::Argument(1)),
+                },
+                0x9000,
+                6
+            ))
+        );
+        assert_eq!(
+            discovery.match_buffer(&[0, 0, 0, 0]),
+            Some((BUFFER, 0x9000, 6))
+        );
+
+        // the most recent buffer wins, and the input is never larger than it
+        discovery.read_buffers.push((0xa000, 4, 4, 4));
+        discovery.blocks = 5;
+        assert_eq!(
+            discovery.match_buffer(&[4, 0, 0, 0]),
+            Some((
+                HarnessInput::Buffer {
+                    addr: 0xa000,
+                    capacity: 4,
+                    len: Some(HarnessLength::Argument(0)),
+                },
+                0xa000,
+                4
+            ))
+        );
+    }
+
+    #[test]
+    fn test_guest_bytes() {
+        let bytes = guest_bytes(0x0102, 2);
+        if cfg!(any(feature = "be", cpu_target = "ppc")) {
+            assert_eq!(bytes, [1, 2]);
+        } else {
+            assert_eq!(bytes, [2, 1]);
+        }
+        assert_eq!(guest_bytes(5, 16).len(), 8);
+    }
+}
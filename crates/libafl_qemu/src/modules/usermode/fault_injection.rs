@@ -0,0 +1,536 @@
+//! Make syscalls and libc calls of the target fail, driven by the fuzz input.
+//!
+//! Every call of a hooked syscall or function is a *fault site hit*. The `n`-th hit looks at the
+//! `n`-th byte of the fault schedule, which is taken from the input by a [`FaultScheduleSource`]:
+//! - if the high bit of the byte is clear, or there is no such byte, the call runs as usual.
+//! - if the high bit is set, the call fails, and the low 7 bits select one of the configured
+//!   errno values (syscalls) or return values (functions).
+//!
+//! Syscalls are skipped and return `-errno`, just like the kernel would.
+//! Functions run normally, but their return value is replaced once they return to their caller,
+//! so that any allocation does not leak into later executions when used with snapshots.
+use core::fmt::Debug;
+use std::path::Path;
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    Error,
+    inputs::{HasTargetBytes, Keyed, MultipartInput},
+};
+use libafl_bolts::AsSlice;
+use libafl_qemu_sys::GuestAddr;
+
+use crate::{
+    InputLocation, Qemu, Regs,
+    elf::EasyElf,
+    emu::{EmulatorDriverError, EmulatorModules, InputSetter},
+    get_exit_arch_regs,
+    modules::{
+        EmulatorModule, EmulatorModuleTuple,
+        utils::filters::{HasAddressFilter, NOP_ADDRESS_FILTER, NopAddressFilter},
+    },
+    qemu::{ArchExtras, Hook, SyscallHookResult},
+    sync_exit::ExitArgs,
+};
+
+/// The bit of a schedule byte telling whether the corresponding fault site hit should fail
+pub const FAULT_BIT: u8 = 0x80;
+
+/// Extracts the fault schedule from an input
+pub trait FaultScheduleSource<I>: 'static + Debug {
+    /// Fill `schedule` with the schedule bytes of the `input`.
+    /// `schedule` is empty when this gets called.
+    fn fault_schedule(&self, input: &I, schedule: &mut Vec<u8>);
+}
+
+/// Use the last `len` bytes of the input as fault schedule.
+///
+/// The schedule is not meant for the target. Write the input with a [`TrailingBytesInputSetter`],
+/// or cut the schedule off with [`TrailingBytesSchedule::target_bytes`] in the harness.
+#[derive(Debug, Clone, Copy)]
+pub struct TrailingBytesSchedule {
+    len: usize,
+}
+
+impl TrailingBytesSchedule {
+    /// Use the last `len` bytes of the input as fault schedule
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self { len }
+    }
+
+    /// The bytes of the input without the schedule, i.e. the bytes meant for the target
+    #[must_use]
+    pub fn target_bytes<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
+        &bytes[..bytes.len().saturating_sub(self.len)]
+    }
+}
+
+/// An [`InputSetter`] passing the input without the [`TrailingBytesSchedule`] to the wrapped setter
+#[derive(Debug, Clone)]
+pub struct TrailingBytesInputSetter<IS> {
+    inner: IS,
+    schedule: TrailingBytesSchedule,
+}
+
+impl<IS> TrailingBytesInputSetter<IS> {
+    /// Wrap `inner`, cutting off the schedule bytes of `schedule` before the input is written
+    #[must_use]
+    pub fn new(inner: IS, schedule: TrailingBytesSchedule) -> Self {
+        Self { inner, schedule }
+    }
+}
+
+impl<I, IS, S> InputSetter<I, S> for TrailingBytesInputSetter<IS>
+where
+    I: HasTargetBytes + From<Vec<u8>>,
+    IS: InputSetter<I, S>,
+{
+    fn write_input(
+        &mut self,
+        qemu: Qemu,
+        state: &mut S,
+        input: &I,
+    ) -> Result<(), EmulatorDriverError> {
+        let bytes = input.target_bytes();
+        let input = I::from(self.schedule.target_bytes(bytes.as_slice()).to_vec());
+        self.inner.write_input(qemu, state, &input)
+    }
+
+    fn set_input_location(&mut self, location: InputLocation) -> Result<(), EmulatorDriverError> {
+        self.inner.set_input_location(location)
+    }
+
+    fn input_location(&self) -> Option<&InputLocation> {
+        self.inner.input_location()
+    }
+}
+
+impl<I> FaultScheduleSource<I> for TrailingBytesSchedule
+where
+    I: HasTargetBytes,
+{
+    fn fault_schedule(&self, input: &I, schedule: &mut Vec<u8>) {
+        let bytes = input.target_bytes();
+        let bytes = bytes.as_slice();
+        schedule.extend_from_slice(&bytes[bytes.len().saturating_sub(self.len)..]);
+    }
+}
+
+/// Use the parts of a [`MultipartInput`] with the given key as fault schedule.
+/// If the key appears multiple times, the parts are concatenated.
+#[derive(Debug, Clone)]
+pub struct MultipartSchedule {
+    key: String,
+}
+
+impl MultipartSchedule {
+    /// Use the parts with `key` as fault schedule
+    #[must_use]
+    pub fn new<K: Into<String>>(key: K) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+impl<I> FaultScheduleSource<MultipartInput<I, String>> for MultipartSchedule
+where
+    I: HasTargetBytes,
+{
+    fn fault_schedule(&self, input: &MultipartInput<I, String>, schedule: &mut Vec<u8>) {
+        for (_, part) in input.with_key(&self.key) {
+            schedule.extend_from_slice(part.target_bytes().as_slice());
+        }
+    }
+}
+
+/// A fault injected during the last execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectedFault {
+    /// The syscall `num` was skipped, returning `-errno`
+    Syscall {
+        /// The syscall number
+        num: i32,
+        /// The errno returned to the target
+        errno: i32,
+    },
+    /// The function at `addr` returned `value` to `return_address`
+    Function {
+        /// The entry of the function
+        addr: GuestAddr,
+        /// Where the function returned to
+        return_address: GuestAddr,
+        /// The value returned to the target
+        value: GuestAddr,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct FaultyFunction {
+    name: String,
+    values: Vec<GuestAddr>,
+}
+
+/// Make selected syscalls and libc functions fail according to a schedule taken from the input.
+///
+/// Use it together with the [`crate::modules::SnapshotModule`], failing calls may leave
+/// the target in a state that should not leak into the next execution.
+#[derive(Debug)]
+pub struct FaultInjectionModule<FS> {
+    source: FS,
+    syscalls: HashMap<i32, Vec<i32>>,
+    functions: Vec<FaultyFunction>,
+    /// Resolved function entries, indexing into `functions`
+    hooked_functions: HashMap<GuestAddr, usize>,
+    hooked_returns: HashSet<GuestAddr>,
+    /// Return addresses of faulty calls in flight, with the value to return
+    pending_returns: Vec<(GuestAddr, GuestAddr, GuestAddr)>,
+    schedule: Vec<u8>,
+    hits: usize,
+    injected: Vec<InjectedFault>,
+}
+
+impl<FS> FaultInjectionModule<FS> {
+    /// Create a new [`FaultInjectionModule`], taking the schedule from `source`.
+    /// Add syscalls and functions to fail with [`Self::syscall`] and [`Self::function`].
+    #[must_use]
+    pub fn new(source: FS) -> Self {
+        Self {
+            source,
+            syscalls: HashMap::new(),
+            functions: Vec::new(),
+            hooked_functions: HashMap::new(),
+            hooked_returns: HashSet::new(),
+            pending_returns: Vec::new(),
+            schedule: Vec::new(),
+            hits: 0,
+            injected: Vec::new(),
+        }
+    }
+
+    /// Make the syscall `num` fail with one of `errnos`, for example
+    /// `.syscall(SYS_read as i32, [libc::EIO, libc::EINTR])`.
+    #[must_use]
+    pub fn syscall<E: IntoIterator<Item = i32>>(mut self, num: i32, errnos: E) -> Self {
+        self.syscalls.entry(num).or_default().extend(errnos);
+        self
+    }
+
+    /// Make the function `name` return one of `values`, for example `.function("malloc", [0])`.
+    /// The function is looked up in the symbols and exports of all mapped files in [`EmulatorModule::first_exec`],
+    /// which panics if it is not found.
+    #[must_use]
+    pub fn function<V: IntoIterator<Item = GuestAddr>>(mut self, name: &str, values: V) -> Self {
+        self.functions.push(FaultyFunction {
+            name: name.to_string(),
+            values: values.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Make the common allocation functions of libc return `NULL`
+    #[must_use]
+    pub fn libc_allocations(self) -> Self {
+        ["malloc", "calloc", "realloc", "strdup"]
+            .into_iter()
+            .fold(self, |module, name| module.function(name, [0]))
+    }
+
+    /// The faults injected during the last execution, in order
+    #[must_use]
+    pub fn injected(&self) -> &[InjectedFault] {
+        &self.injected
+    }
+
+    /// The number of fault site hits during the last execution.
+    /// A schedule longer than this has no effect.
+    #[must_use]
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Account for a fault site hit, and return the index of the value to fail with, if any
+    fn next_fault(&mut self, choices: usize) -> Option<usize> {
+        let byte = self.schedule.get(self.hits).copied();
+        self.hits += 1;
+        match byte {
+            Some(byte) if byte & FAULT_BIT != 0 && choices > 0 => {
+                Some(usize::from(byte & !FAULT_BIT) % choices)
+            }
+            _ => None,
+        }
+    }
+
+    fn on_function_entry<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        FS: FaultScheduleSource<I>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let module = emulator_modules.get_mut::<Self>().unwrap();
+        let Some(&idx) = module.hooked_functions.get(&pc) else {
+            return;
+        };
+        let Some(choice) = module.next_fault(module.functions[idx].values.len()) else {
+            return;
+        };
+        let value = module.functions[idx].values[choice];
+        let Ok(return_address) = qemu.read_return_address() else {
+            return;
+        };
+        module.pending_returns.push((return_address, pc, value));
+
+        if module.hooked_returns.insert(return_address) {
+            emulator_modules.instruction_function(
+                return_address,
+                Self::on_function_return::<ET, I, S>,
+                true,
+            );
+        }
+    }
+
+    fn on_function_return<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        FS: FaultScheduleSource<I>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let module = emulator_modules.get_mut::<Self>().unwrap();
+        let Some(pos) = module
+            .pending_returns
+            .iter()
+            .rposition(|&(return_address, _, _)| return_address == pc)
+        else {
+            return;
+        };
+        let (return_address, addr, value) = module.pending_returns.remove(pos);
+        let ret_reg: Regs = get_exit_arch_regs()[ExitArgs::Ret];
+        if qemu.write_reg(ret_reg, value).is_ok() {
+            log::debug!("Fault injection: function {addr:#x} returns {value:#x}");
+            module.injected.push(InjectedFault::Function {
+                addr,
+                return_address,
+                value,
+            });
+        }
+    }
+}
+
+impl<FS, I, S> EmulatorModule<I, S> for FaultInjectionModule<FS>
+where
+    FS: FaultScheduleSource<I>,
+    I: Unpin,
+    S: Unpin,
+{
+    fn post_qemu_init<ET>(&mut self, _qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if !self.syscalls.is_empty() {
+            emulator_modules.pre_syscalls(Hook::Function(syscall_hook::<ET, FS, I, S>));
+        }
+    }
+
+    fn first_exec<ET>(
+        &mut self,
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        let mut files: Vec<(String, GuestAddr)> = Vec::new();
+        for region in qemu.mappings() {
+            if let Some(path) = region.path() {
+                // skip [heap], [vdso] and friends
+                if !path.is_empty()
+                    && !path.starts_with('[')
+                    && !files.iter().any(|(name, _)| name == path)
+                {
+                    files.push((path.to_string(), region.start() as GuestAddr));
+                }
+            }
+        }
+
+        for (idx, function) in self.functions.iter().enumerate() {
+            let mut addrs = Vec::new();
+            for (file, off) in &files {
+                match find_function(qemu, file, &function.name, *off) {
+                    Ok(addr) => addrs.push(addr),
+                    Err(err) => log::debug!("Fault injection: {err}"),
+                }
+            }
+            // A configured fault site that does not exist would silently never fail
+            assert!(
+                !addrs.is_empty(),
+                "Fault injection: function {} not found in any mapped file",
+                function.name
+            );
+            for addr in addrs {
+                log::info!(
+                    "Fault injection: function {} found at {addr:#x}",
+                    function.name
+                );
+                if self.hooked_functions.insert(addr, idx).is_none() {
+                    emulator_modules.instruction_function(
+                        addr,
+                        Self::on_function_entry::<ET, I, S>,
+                        true,
+                    );
+                }
+            }
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.schedule.clear();
+        self.source.fault_schedule(input, &mut self.schedule);
+        self.hits = 0;
+        self.injected.clear();
+        self.pending_returns.clear();
+    }
+}
+
+impl<FS> HasAddressFilter for FaultInjectionModule<FS> {
+    type AddressFilter = NopAddressFilter;
+
+    fn address_filter(&self) -> &Self::AddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::AddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+#[expect(clippy::too_many_arguments, clippy::cast_sign_loss)]
+#[allow(clippy::needless_pass_by_value)] // no longer a problem with nightly
+fn syscall_hook<ET, FS, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    syscall: i32,
+    _x0: GuestAddr,
+    _x1: GuestAddr,
+    _x2: GuestAddr,
+    _x3: GuestAddr,
+    _x4: GuestAddr,
+    _x5: GuestAddr,
+    _x6: GuestAddr,
+    _x7: GuestAddr,
+) -> SyscallHookResult
+where
+    ET: EmulatorModuleTuple<I, S>,
+    FS: FaultScheduleSource<I>,
+    I: Unpin,
+    S: Unpin,
+{
+    let module = emulator_modules
+        .get_mut::<FaultInjectionModule<FS>>()
+        .unwrap();
+    let Some(choices) = module.syscalls.get(&syscall).map(Vec::len) else {
+        return SyscallHookResult::Run;
+    };
+    let Some(choice) = module.next_fault(choices) else {
+        return SyscallHookResult::Run;
+    };
+    let errno = module.syscalls[&syscall][choice];
+    log::debug!("Fault injection: syscall {syscall} fails with errno {errno}");
+    module.injected.push(InjectedFault::Syscall {
+        num: syscall,
+        errno,
+    });
+    // Sign-extends to the guest register width, `-errno` is how the kernel reports errors
+    SyscallHookResult::Skip(errno.wrapping_neg() as GuestAddr)
+}
+
+fn find_function(
+    qemu: Qemu,
+    file: &str,
+    function: &str,
+    loadaddr: GuestAddr,
+) -> Result<GuestAddr, Error> {
+    if !Path::new(file).is_file() {
+        return Err(Error::illegal_argument(format!("{file} is not a file")));
+    }
+    let mut elf_buffer = Vec::new();
+    let elf = EasyElf::from_file(file, &mut elf_buffer)?;
+    let offset = if loadaddr > 0 {
+        loadaddr
+    } else {
+        qemu.load_addr()
+    };
+    elf.resolve_any_symbol(function, offset)
+        .ok_or_else(|| Error::key_not_found(format!("Symbol {function} not found in {file}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::inputs::{BytesInput, HasTargetBytes};
+    use libafl_bolts::AsSlice;
+
+    use super::{
+        FAULT_BIT, FaultInjectionModule, FaultScheduleSource, MultipartSchedule,
+        TrailingBytesSchedule,
+    };
+
+    #[test]
+    fn test_schedule_decoding() {
+        let mut module = FaultInjectionModule::new(TrailingBytesSchedule::new(3))
+            .syscall(0, [1, 2])
+            .function("malloc", [0]);
+
+        let input = BytesInput::new(b"AAAA\x00\x81\x80".to_vec());
+        FaultScheduleSource::<BytesInput>::fault_schedule(
+            &module.source,
+            &input,
+            &mut module.schedule,
+        );
+        assert_eq!(module.schedule, [0, 1 | FAULT_BIT, FAULT_BIT]);
+        assert_eq!(
+            TrailingBytesSchedule::new(3).target_bytes(input.target_bytes().as_slice()),
+            b"AAAA"
+        );
+        assert!(
+            TrailingBytesSchedule::new(8)
+                .target_bytes(input.target_bytes().as_slice())
+                .is_empty()
+        );
+
+        assert_eq!(module.next_fault(2), None);
+        assert_eq!(module.next_fault(2), Some(1));
+        assert_eq!(module.next_fault(1), Some(0));
+        // Past the end of the schedule, everything runs normally
+        assert_eq!(module.next_fault(2), None);
+        assert_eq!(module.hits(), 4);
+    }
+
+    #[test]
+    fn test_multipart_schedule() {
+        let input = libafl::inputs::MultipartInput::from(vec![
+            ("data".to_string(), BytesInput::new(b"data".to_vec())),
+            ("faults".to_string(), BytesInput::new(vec![FAULT_BIT])),
+            ("faults".to_string(), BytesInput::new(vec![0])),
+        ]);
+        let mut schedule = Vec::new();
+        MultipartSchedule::new("faults").fault_schedule(&input, &mut schedule);
+        assert_eq!(schedule, [FAULT_BIT, 0]);
+    }
+}
@@ -1,9 +1,6 @@
 //! Detect injection vulnerabilities
 
 /*
- * TODOs:
- *  - read in export addresses of shared libraries to resolve functions
- *
  * Maybe:
  *  - return code analysis support (not needed currently)
  *  - regex support (not needed currently)
@@ -13,13 +10,19 @@
 
 use std::{ffi::CStr, fmt::Display, fs, os::raw::c_char, path::Path};
 
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use libafl::Error;
 use libafl_qemu_sys::GuestAddr;
+#[cfg(not(cpu_target = "hexagon"))]
+use libafl_qemu_sys::MmapPerms;
 use serde::{Deserialize, Serialize};
 
 #[cfg(not(cpu_target = "hexagon"))]
 use crate::SYS_execve;
+#[cfg(not(any(cpu_target = "arm", cpu_target = "riscv32", cpu_target = "hexagon")))]
+use crate::SYS_mmap;
+#[cfg(any(cpu_target = "arm", cpu_target = "mips", cpu_target = "riscv32"))]
+use crate::SYS_mmap2;
 use crate::{
     CallingConvention, Qemu,
     elf::EasyElf,
@@ -157,6 +160,10 @@ pub struct InjectionModule {
     pub tokens: Vec<String>,
     definitions: HashMap<String, InjectionDefinition>,
     matches_list: Vec<Matches>,
+    /// The mapped files, with their load address, we already looked for functions to hook.
+    /// A library unloaded and loaded again at another address is scanned again.
+    scanned_libs: HashSet<(String, GuestAddr)>,
+    hooked_addrs: HashSet<GuestAddr>,
 }
 
 impl InjectionModule {
@@ -211,9 +218,107 @@ impl InjectionModule {
             tokens,
             definitions,
             matches_list,
+            scanned_libs: HashSet::new(),
+            hooked_addrs: HashSet::new(),
         })
     }
 
+    /// Look for the functions to hook in all files mapped since the last call,
+    /// and return the new hooks as `(address, matches id, parameter)`.
+    fn find_new_hooks(&mut self, qemu: Qemu) -> Vec<(GuestAddr, usize, u8)> {
+        let first_scan = self.scanned_libs.is_empty();
+        let mut libs: Vec<LibInfo> = Vec::new();
+
+        for region in qemu.mappings() {
+            if let Some(path) = region.path().map(ToOwned::to_owned) {
+                // skip [heap], [vdso] and friends
+                if !path.is_empty() && !path.starts_with('[') {
+                    LibInfo::add_unique(
+                        &mut libs,
+                        LibInfo {
+                            name: path.clone(),
+                            off: region.start() as GuestAddr,
+                        },
+                    );
+                }
+            }
+        }
+        // Only the first mapping of each file is its load address, so filter after collecting them all
+        libs.retain(|lib| self.scanned_libs.insert((lib.name.clone(), lib.off)));
+        if libs.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hooks = Vec::new();
+        for matches in &self.matches_list {
+            let id = matches.id;
+            let lib_name = &matches.lib_name;
+
+            for (name, func_definition) in &self.definitions[lib_name].functions {
+                let hook_addrs = if name.to_lowercase().starts_with(&"0x".to_string()) {
+                    if !first_scan {
+                        continue;
+                    }
+                    let func_pc = u64::from_str_radix(&name[2..], 16)
+                        .map_err(|e| {
+                            Error::illegal_argument(format!(
+                            "Failed to parse hex string {name} from definition for {lib_name}: {e}"
+                        ))
+                        })
+                        .unwrap() as GuestAddr;
+                    log::info!("Injections: Hooking hardcoded function {func_pc:#x}");
+                    vec![func_pc]
+                } else {
+                    libs.iter()
+                        .filter_map(|lib| match find_function(qemu, &lib.name, name, lib.off) {
+                            Ok(func_pc) => func_pc,
+                            Err(e) => {
+                                log::debug!("Injections: Could not parse {}: {e}", lib.name);
+                                None
+                            }
+                        })
+                        .inspect(|&func_pc| {
+                            log::info!("Injections: Function {name} found at {func_pc:#x}");
+                        })
+                        .collect()
+                };
+
+                if hook_addrs.is_empty() && first_scan {
+                    log::warn!(
+                        "Injections: Function not found for {lib_name}: {name}, it may still be loaded later",
+                    );
+                }
+
+                let param = func_definition.param;
+                for hook_addr in hook_addrs {
+                    if self.hooked_addrs.insert(hook_addr) {
+                        hooks.push((hook_addr, id, param));
+                    }
+                }
+            }
+        }
+        hooks
+    }
+
+    fn install_hooks<ET, I, S>(
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        hooks: Vec<(GuestAddr, usize, u8)>,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        for (hook_addr, id, param) in hooks {
+            emulator_modules.instructions(
+                hook_addr,
+                Hook::Closure(Box::new(move |qemu, hooks, _state, _guest_addr| {
+                    Self::on_call_check(qemu, hooks, id, param);
+                })),
+                true,
+            );
+        }
+    }
+
     fn on_call_check<ET, I, S>(
         qemu: Qemu,
         emulator_modules: &mut EmulatorModules<ET, I, S>,
@@ -273,6 +378,8 @@ where
         ET: EmulatorModuleTuple<I, S>,
     {
         emulator_modules.pre_syscalls(Hook::Function(syscall_hook::<ET, I, S>));
+        #[cfg(not(cpu_target = "hexagon"))]
+        emulator_modules.post_syscalls(Hook::Function(mmap_hook::<ET, I, S>));
     }
 
     fn first_exec<ET>(
@@ -283,64 +390,8 @@ where
     ) where
         ET: EmulatorModuleTuple<I, S>,
     {
-        let mut libs: Vec<LibInfo> = Vec::new();
-
-        for region in qemu.mappings() {
-            if let Some(path) = region.path().map(ToOwned::to_owned) {
-                // skip [heap], [vdso] and friends
-                if !path.is_empty() && !path.starts_with('[') {
-                    LibInfo::add_unique(
-                        &mut libs,
-                        LibInfo {
-                            name: path.clone(),
-                            off: region.start() as GuestAddr,
-                        },
-                    );
-                }
-            }
-        }
-
-        for matches in &self.matches_list {
-            let id = matches.id;
-            let lib_name = &matches.lib_name;
-
-            for (name, func_definition) in &self.definitions[lib_name].functions {
-                let hook_addrs = if name.to_lowercase().starts_with(&"0x".to_string()) {
-                    let func_pc = u64::from_str_radix(&name[2..], 16)
-                        .map_err(|e| {
-                            Error::illegal_argument(format!(
-                            "Failed to parse hex string {name} from definition for {lib_name}: {e}"
-                        ))
-                        })
-                        .unwrap() as GuestAddr;
-                    log::info!("Injections: Hooking hardcoded function {func_pc:#x}");
-                    vec![func_pc]
-                } else {
-                    libs.iter()
-                        .filter_map(|lib| find_function(qemu, &lib.name, name, lib.off).unwrap())
-                        .inspect(|&func_pc| {
-                            log::info!("Injections: Function {name} found at {func_pc:#x}");
-                        })
-                        .collect()
-                };
-
-                if hook_addrs.is_empty() {
-                    log::warn!("Injections: Function not found for {lib_name}: {name}",);
-                }
-
-                let param = func_definition.param;
-
-                for hook_addr in hook_addrs {
-                    emulator_modules.instructions(
-                        hook_addr,
-                        Hook::Closure(Box::new(move |qemu, hooks, _state, _guest_addr| {
-                            Self::on_call_check(qemu, hooks, id, param);
-                        })),
-                        true,
-                    );
-                }
-            }
-        }
+        let hooks = self.find_new_hooks(qemu);
+        Self::install_hooks(emulator_modules, hooks);
     }
 }
 
@@ -437,6 +488,60 @@ where
     }
 }
 
+/// Libraries loaded through `dlopen` get mapped after the first execution.
+/// Whenever the target maps executable memory, look for new files to hook.
+#[cfg(not(cpu_target = "hexagon"))]
+#[expect(clippy::too_many_arguments)]
+#[allow(clippy::needless_pass_by_value)] // no longer a problem with nightly
+fn mmap_hook<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    result: GuestAddr,
+    syscall: i32,
+    _x0: GuestAddr,
+    _x1: GuestAddr,
+    x2: GuestAddr,
+    _x3: GuestAddr,
+    _x4: GuestAddr,
+    _x5: GuestAddr,
+    _x6: GuestAddr,
+    _x7: GuestAddr,
+) -> GuestAddr
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    #[cfg(not(any(cpu_target = "arm", cpu_target = "mips", cpu_target = "riscv32")))]
+    let is_mmap = i64::from(syscall) == SYS_mmap;
+    #[cfg(cpu_target = "mips")]
+    let is_mmap = i64::from(syscall) == SYS_mmap || i64::from(syscall) == SYS_mmap2;
+    #[cfg(any(cpu_target = "arm", cpu_target = "riscv32"))]
+    let is_mmap = i64::from(syscall) == SYS_mmap2;
+
+    // `MAP_FAILED` is -1, other errors are close to it
+    let failed = result > GuestAddr::MAX - 4096;
+    let executable = MmapPerms::try_from(x2 as i32).is_ok_and(|prot| {
+        matches!(
+            prot,
+            MmapPerms::Execute
+                | MmapPerms::ReadExecute
+                | MmapPerms::WriteExecute
+                | MmapPerms::ReadWriteExecute
+        )
+    });
+    if is_mmap && !failed && executable {
+        let module = emulator_modules.get_mut::<InjectionModule>().unwrap();
+        // Nothing got hooked yet, `first_exec` will scan everything
+        if !module.scanned_libs.is_empty() {
+            let hooks = module.find_new_hooks(qemu);
+            InjectionModule::install_hooks(emulator_modules, hooks);
+        }
+    }
+    result
+}
+
 fn find_function(
     qemu: Qemu,
     file: &str,
@@ -450,7 +555,7 @@ fn find_function(
     } else {
         qemu.load_addr()
     };
-    Ok(elf.resolve_symbol(function, offset))
+    Ok(elf.resolve_any_symbol(function, offset))
 }
 
 fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
@@ -17,6 +17,32 @@ pub use asan_host::AsanHostModule;
 pub mod asan_guest;
 #[cfg(not(cpu_target = "hexagon"))]
 pub use asan_guest::AsanGuestModule;
+
+#[cfg(not(cpu_target = "hexagon"))]
+pub mod msan;
+#[cfg(not(cpu_target = "hexagon"))]
+pub use msan::{MsanCheck, MsanError, MsanModule, MsanStackCollector};
+
+#[cfg(all(feature = "fault_injection", not(cpu_target = "hexagon")))]
+pub mod fault_injection;
+#[cfg(all(feature = "fault_injection", not(cpu_target = "hexagon")))]
+pub use fault_injection::{
+    FaultInjectionModule, FaultScheduleSource, InjectedFault, MultipartSchedule,
+    TrailingBytesInputSetter, TrailingBytesSchedule,
+};
+
+#[cfg(all(feature = "vfs", not(cpu_target = "hexagon")))]
+pub mod vfs;
+#[cfg(all(feature = "vfs", not(cpu_target = "hexagon")))]
+pub use vfs::{VfsContent, VfsInput, VfsModule};
+
+#[cfg(all(feature = "harness_discovery", not(cpu_target = "hexagon")))]
+pub mod discovery;
+#[cfg(all(feature = "harness_discovery", not(cpu_target = "hexagon")))]
+pub use discovery::{
+    HarnessConfig, HarnessDiscoveryCollector, HarnessDiscoveryModule, HarnessInput, HarnessLength,
+};
+
 pub mod redirect_stdin;
 pub use redirect_stdin::*;
 
@@ -0,0 +1,964 @@
+//! Detect the use of uninitialized memory, like `MemorySanitizer`, but for binary-only targets.
+//!
+//! The [`MsanModule`] keeps a shadow with one *undefined* bit per guest byte:
+//! - `malloc` and `realloc` mark the memory they return as undefined, `calloc` and `free` as defined.
+//! - [`MsanStackCollector`], added to a [`crate::modules::CallTracerModule`], marks the stack
+//!   below the stack pointer as undefined on each call, and keeps the call stack for reports.
+//! - every store marks the stored bytes as defined.
+//!
+//! Without taint tracking through registers, we cannot follow undefined values like `MSan` does.
+//! By default, each load of undefined bytes is reported, see [`MsanCheck`] for the alternative.
+//! Syscalls writing undefined bytes to the outside world are always reported.
+//!
+//! Reports set the [`ExitKind`] to [`ExitKind::Crash`] and get logged, with the backtrace of the
+//! [`MsanStackCollector`], if there is one.
+#![allow(clippy::needless_pass_by_value)] // default compiler complains about Option<&mut T> otherwise, and this is used extensively.
+#![allow(clippy::unnecessary_cast)]
+
+use core::fmt::{self, Display};
+use std::{collections::VecDeque, path::Path};
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{executors::ExitKind, observers::ObserversTuple};
+use libafl_qemu_sys::GuestAddr;
+
+use crate::{
+    CallingConvention, Qemu, Regs, SYS_pwrite64, SYS_sendto, SYS_write,
+    elf::EasyElf,
+    emu::EmulatorModules,
+    get_exit_arch_regs,
+    modules::{
+        AddressFilter, EmulatorModule, EmulatorModuleTuple,
+        calls::CallTraceCollector,
+        utils::filters::{HasAddressFilter, StdAddressFilter},
+    },
+    qemu::{ArchExtras, Hook, MemAccessInfo, SyscallHookResult},
+    sync_exit::ExitArgs,
+    sys::TCGTemp,
+};
+
+/// The granularity of the shadow
+pub const MSAN_PAGE_SIZE: usize = 4096;
+/// How many undefined loads [`MsanCheck::Use`] remembers
+pub const MSAN_RECENT_LOADS: usize = 16;
+/// The default stack area marked undefined on each call by the [`MsanStackCollector`]
+pub const DEFAULT_MSAN_STACK_WINDOW: usize = 256;
+
+const MSAN_PAGE_MASK: GuestAddr = !(MSAN_PAGE_SIZE as GuestAddr - 1);
+
+/// When to report undefined memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MsanCheck {
+    /// Report each load of undefined bytes. Precise, but copying uninitialized padding is reported too.
+    #[default]
+    Load,
+    /// Report once a recently loaded undefined value is compared or used as an address.
+    ///
+    /// Values are matched, not tracked: any defined value equal to a recent undefined load gets
+    /// reported as well, and uses after arithmetic are missed. Only use this to triage.
+    Use,
+}
+
+/// A use of undefined memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MsanError {
+    /// `size` bytes loaded from `addr`, containing undefined bytes
+    Load {
+        /// The instruction doing the load
+        pc: GuestAddr,
+        /// The first undefined byte
+        addr: GuestAddr,
+        /// The size of the load
+        size: usize,
+    },
+    /// An undefined value, loaded at `load_pc`, decided a branch
+    Branch {
+        /// The comparison
+        pc: GuestAddr,
+        /// The load of the undefined value
+        load_pc: GuestAddr,
+        /// The undefined value
+        value: u64,
+    },
+    /// An undefined value, loaded at `load_pc`, was used as an address
+    Address {
+        /// The memory access
+        pc: GuestAddr,
+        /// The load of the undefined value
+        load_pc: GuestAddr,
+        /// The accessed address
+        addr: GuestAddr,
+    },
+    /// A syscall got passed a buffer with undefined bytes
+    Syscall {
+        /// The syscall number
+        num: i32,
+        /// The first undefined byte
+        addr: GuestAddr,
+    },
+}
+
+impl Display for MsanError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MsanError::Load { pc, addr, size } => write!(
+                fmt,
+                "Use of uninitialized value: {size} bytes load at {addr:#x} (pc {pc:#x})"
+            ),
+            MsanError::Branch { pc, load_pc, value } => write!(
+                fmt,
+                "Conditional jump depends on uninitialized value {value:#x} at pc {pc:#x}, loaded at pc {load_pc:#x}"
+            ),
+            MsanError::Address { pc, load_pc, addr } => write!(
+                fmt,
+                "Uninitialized value used as address {addr:#x} at pc {pc:#x}, loaded at pc {load_pc:#x}"
+            ),
+            MsanError::Syscall { num, addr } => {
+                write!(fmt, "Syscall {num} uses uninitialized bytes at {addr:#x}")
+            }
+        }
+    }
+}
+
+/// The allocation functions we hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AllocFn {
+    Malloc,
+    Calloc,
+    Realloc,
+    Free,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingAlloc {
+    return_address: GuestAddr,
+    kind: AllocFn,
+    size: usize,
+    old: GuestAddr,
+}
+
+/// Sparse shadow memory, one bit per byte, set if the byte is undefined.
+/// Pages that are not in the map are defined.
+#[derive(Debug, Clone, Default)]
+struct Shadow {
+    pages: HashMap<GuestAddr, Box<[u8; MSAN_PAGE_SIZE / 8]>>,
+}
+
+impl Shadow {
+    /// Call `f` with the page, and the in-page range, for each page of `[addr, addr + len)`
+    fn for_each_page<F>(addr: GuestAddr, len: usize, mut f: F)
+    where
+        F: FnMut(GuestAddr, usize, usize),
+    {
+        let mut addr = addr;
+        let end = addr.saturating_add(len as GuestAddr);
+        while addr < end {
+            let page = addr & MSAN_PAGE_MASK;
+            let start = (addr - page) as usize;
+            let stop = ((end - page) as usize).min(MSAN_PAGE_SIZE);
+            f(page, start, stop);
+            addr = page.saturating_add(MSAN_PAGE_SIZE as GuestAddr);
+            if addr == 0 {
+                break;
+            }
+        }
+    }
+
+    fn set(&mut self, addr: GuestAddr, len: usize, undefined: bool) {
+        Self::for_each_page(addr, len, |page, start, stop| {
+            let bits = if undefined {
+                self.pages
+                    .entry(page)
+                    .or_insert_with(|| Box::new([0; MSAN_PAGE_SIZE / 8]))
+            } else if let Some(bits) = self.pages.get_mut(&page) {
+                bits
+            } else {
+                return;
+            };
+            let mut i = start;
+            while i < stop {
+                if i % 8 == 0 && i + 8 <= stop {
+                    bits[i / 8] = if undefined { 0xff } else { 0 };
+                    i += 8;
+                } else {
+                    if undefined {
+                        bits[i / 8] |= 1 << (i % 8);
+                    } else {
+                        bits[i / 8] &= !(1 << (i % 8));
+                    }
+                    i += 1;
+                }
+            }
+        });
+    }
+
+    fn set_undefined(&mut self, addr: GuestAddr, len: usize) {
+        self.set(addr, len, true);
+    }
+
+    fn set_defined(&mut self, addr: GuestAddr, len: usize) {
+        self.set(addr, len, false);
+    }
+
+    /// The first undefined byte in `[addr, addr + len)`
+    fn first_undefined(&self, addr: GuestAddr, len: usize) -> Option<GuestAddr> {
+        let mut found = None;
+        Self::for_each_page(addr, len, |page, start, stop| {
+            if found.is_some() {
+                return;
+            }
+            if let Some(bits) = self.pages.get(&page) {
+                found = (start..stop)
+                    .find(|i| bits[i / 8] & (1 << (i % 8)) != 0)
+                    .map(|i| page + i as GuestAddr);
+            }
+        });
+        found
+    }
+
+    /// Copy the definedness of `[src, src + len)` to the non-overlapping `[dst, dst + len)`,
+    /// in chunks that stay within one source and one destination page
+    fn copy(&mut self, src: GuestAddr, dst: GuestAddr, len: usize) {
+        let mut done = 0;
+        while done < len {
+            let src = src + done as GuestAddr;
+            let dst = dst + done as GuestAddr;
+            let src_off = (src & !MSAN_PAGE_MASK) as usize;
+            let dst_off = (dst & !MSAN_PAGE_MASK) as usize;
+            let chunk = (len - done)
+                .min(MSAN_PAGE_SIZE - src_off)
+                .min(MSAN_PAGE_SIZE - dst_off);
+            done += chunk;
+
+            let Some(src_bits) = self.pages.get(&(src & MSAN_PAGE_MASK)).cloned() else {
+                self.set_defined(dst, chunk);
+                continue;
+            };
+            let dst_bits = self
+                .pages
+                .entry(dst & MSAN_PAGE_MASK)
+                .or_insert_with(|| Box::new([0; MSAN_PAGE_SIZE / 8]));
+            let mut i = 0;
+            while i < chunk {
+                let (s, d) = (src_off + i, dst_off + i);
+                if s % 8 == 0 && d % 8 == 0 && i + 8 <= chunk {
+                    // Both sides are byte aligned, copy whole shadow bytes at once
+                    let n = (chunk - i) / 8;
+                    dst_bits[d / 8..d / 8 + n].copy_from_slice(&src_bits[s / 8..s / 8 + n]);
+                    i += n * 8;
+                } else {
+                    if src_bits[s / 8] & (1 << (s % 8)) == 0 {
+                        dst_bits[d / 8] &= !(1 << (d % 8));
+                    } else {
+                        dst_bits[d / 8] |= 1 << (d % 8);
+                    }
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Report uses of uninitialized memory, see the [module level documentation](self).
+#[derive(Debug)]
+pub struct MsanModule {
+    filter: StdAddressFilter,
+    check: MsanCheck,
+    shadow: Shadow,
+    allocs: HashMap<GuestAddr, usize>,
+    /// The shadow and allocations when fuzzing started, restored before each run
+    saved: Option<(Shadow, HashMap<GuestAddr, usize>)>,
+    alloc_fns: HashMap<GuestAddr, AllocFn>,
+    hooked_returns: HashSet<GuestAddr>,
+    pending: Vec<PendingAlloc>,
+    /// `(pc, value)` of the last undefined loads, for [`MsanCheck::Use`]
+    recent: VecDeque<(GuestAddr, u64)>,
+    /// The return addresses of the current calls, kept by the [`MsanStackCollector`]
+    callstack: Vec<GuestAddr>,
+    errors: Vec<MsanError>,
+}
+
+impl MsanModule {
+    /// Create a new [`MsanModule`], checking loads in code allowed by `filter`
+    #[must_use]
+    pub fn new(filter: StdAddressFilter) -> Self {
+        Self {
+            filter,
+            check: MsanCheck::default(),
+            shadow: Shadow::default(),
+            allocs: HashMap::new(),
+            saved: None,
+            alloc_fns: HashMap::new(),
+            hooked_returns: HashSet::new(),
+            pending: Vec::new(),
+            recent: VecDeque::with_capacity(MSAN_RECENT_LOADS),
+            callstack: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Set when to report undefined memory, defaults to [`MsanCheck::Load`]
+    #[must_use]
+    pub fn check(mut self, check: MsanCheck) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// The uses of undefined memory found in the last run
+    #[must_use]
+    pub fn errors(&self) -> &[MsanError] {
+        &self.errors
+    }
+
+    #[must_use]
+    pub fn must_instrument(&self, addr: GuestAddr) -> bool {
+        self.filter.allowed(&addr)
+    }
+
+    /// Mark `[addr, addr + len)` as undefined
+    pub fn poison(&mut self, addr: GuestAddr, len: usize) {
+        self.shadow.set_undefined(addr, len);
+    }
+
+    /// Mark `[addr, addr + len)` as defined
+    pub fn unpoison(&mut self, addr: GuestAddr, len: usize) {
+        self.shadow.set_defined(addr, len);
+    }
+
+    fn report(&mut self, error: MsanError) {
+        if self.errors.is_empty() {
+            let backtrace: String = self
+                .callstack
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, addr)| format!("\n\t#{i} {addr:#x}"))
+                .collect();
+            log::error!("QEMU-MSan: {error}{backtrace}");
+        }
+        self.errors.push(error);
+    }
+
+    fn remember_load(&mut self, qemu: Qemu, pc: GuestAddr, addr: GuestAddr, size: usize) {
+        let mut buf = [0_u8; 8];
+        let size = size.min(buf.len());
+        if qemu.read_mem(addr, &mut buf[..size]).is_err() {
+            return;
+        }
+        self.remember(pc, u64::from_le_bytes(buf));
+    }
+
+    fn remember(&mut self, pc: GuestAddr, value: u64) {
+        if self.recent.len() == MSAN_RECENT_LOADS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((pc, value));
+    }
+
+    fn recent_load(&self, value: u64) -> Option<GuestAddr> {
+        self.recent
+            .iter()
+            .rev()
+            .find(|&&(_, recent)| recent == value)
+            .map(|&(pc, _)| pc)
+    }
+
+    /// Check a load, returns if the loaded value should be remembered for [`MsanCheck::Use`]
+    fn read(&mut self, pc: GuestAddr, addr: GuestAddr, size: usize) -> bool {
+        if self.check == MsanCheck::Use
+            && let Some(load_pc) = self.recent_load(addr as u64)
+        {
+            self.report(MsanError::Address { pc, load_pc, addr });
+        }
+        let Some(undefined) = self.shadow.first_undefined(addr, size) else {
+            return false;
+        };
+        match self.check {
+            MsanCheck::Load => {
+                self.report(MsanError::Load {
+                    pc,
+                    addr: undefined,
+                    size,
+                });
+                false
+            }
+            MsanCheck::Use => true,
+        }
+    }
+
+    fn cmp(&mut self, pc: GuestAddr, v0: u64, v1: u64) {
+        if let Some(load_pc) = self.recent_load(v0).or_else(|| self.recent_load(v1)) {
+            let value = if self.recent_load(v0).is_some() {
+                v0
+            } else {
+                v1
+            };
+            self.report(MsanError::Branch { pc, load_pc, value });
+            // Report each undefined value once
+            self.recent.retain(|&(_, recent)| recent != value);
+        }
+    }
+
+    fn on_alloc_entry<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let arg = |idx| {
+            qemu.read_function_argument_with_cc(idx, CallingConvention::Default)
+                .unwrap_or_default()
+        };
+        let h = emulator_modules.get_mut::<Self>().unwrap();
+        let Some(&kind) = h.alloc_fns.get(&pc) else {
+            return;
+        };
+        let (size, old) = match kind {
+            AllocFn::Malloc => (arg(0) as usize, 0),
+            AllocFn::Calloc => ((arg(0) as usize).saturating_mul(arg(1) as usize), 0),
+            AllocFn::Realloc => (arg(1) as usize, arg(0)),
+            AllocFn::Free => {
+                let ptr = arg(0);
+                if let Some(size) = h.allocs.remove(&ptr) {
+                    h.shadow.set_defined(ptr, size);
+                }
+                return;
+            }
+        };
+        let Ok(return_address) = qemu.read_return_address() else {
+            return;
+        };
+        h.pending.push(PendingAlloc {
+            return_address,
+            kind,
+            size,
+            old,
+        });
+        if h.hooked_returns.insert(return_address) {
+            emulator_modules.instruction_function(
+                return_address,
+                Self::on_alloc_return::<ET, I, S>,
+                true,
+            );
+        }
+    }
+
+    fn on_alloc_return<ET, I, S>(
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let h = emulator_modules.get_mut::<Self>().unwrap();
+        let Some(pos) = h
+            .pending
+            .iter()
+            .rposition(|pending| pending.return_address == pc)
+        else {
+            return;
+        };
+        let pending = h.pending.remove(pos);
+        let ret_reg: Regs = get_exit_arch_regs()[ExitArgs::Ret];
+        let Ok(ptr) = qemu.read_reg(ret_reg) else {
+            return;
+        };
+        if ptr == 0 {
+            return;
+        }
+        let ptr = ptr as GuestAddr;
+        match pending.kind {
+            AllocFn::Malloc => h.shadow.set_undefined(ptr, pending.size),
+            AllocFn::Calloc => h.shadow.set_defined(ptr, pending.size),
+            AllocFn::Realloc => {
+                let old_size = if pending.old == 0 {
+                    0
+                } else {
+                    h.allocs.remove(&pending.old).unwrap_or(0)
+                };
+                let kept = old_size.min(pending.size);
+                if pending.old != ptr {
+                    h.shadow.copy(pending.old, ptr, kept);
+                    h.shadow.set_defined(pending.old, old_size);
+                }
+                h.shadow
+                    .set_undefined(ptr + kept as GuestAddr, pending.size - kept);
+            }
+            AllocFn::Free => unreachable!(),
+        }
+        h.allocs.insert(ptr, pending.size);
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for MsanModule
+where
+    I: Unpin,
+    S: Unpin,
+{
+    fn post_qemu_init<ET>(&mut self, _qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        emulator_modules.pre_syscalls(Hook::Function(syscall_msan::<ET, I, S>));
+    }
+
+    fn first_exec<ET>(
+        &mut self,
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        let mut files: Vec<(String, GuestAddr)> = Vec::new();
+        for region in qemu.mappings() {
+            if let Some(path) = region.path() {
+                // skip [heap], [vdso] and friends
+                if !path.is_empty()
+                    && !path.starts_with('[')
+                    && !files.iter().any(|(name, _)| name == path)
+                {
+                    files.push((path.clone(), region.start() as GuestAddr));
+                }
+            }
+        }
+
+        for (name, kind) in [
+            ("malloc", AllocFn::Malloc),
+            ("calloc", AllocFn::Calloc),
+            ("realloc", AllocFn::Realloc),
+            ("free", AllocFn::Free),
+        ] {
+            for (file, off) in &files {
+                if !Path::new(file).is_file() {
+                    continue;
+                }
+                let mut elf_buffer = Vec::new();
+                let Ok(elf) = EasyElf::from_file(file, &mut elf_buffer) else {
+                    continue;
+                };
+                if let Some(addr) = elf.resolve_any_symbol(name, *off)
+                    && self.alloc_fns.insert(addr, kind).is_none()
+                {
+                    log::info!("MSan: hooking {name} at {addr:#x}");
+                    emulator_modules.instruction_function(
+                        addr,
+                        Self::on_alloc_entry::<ET, I, S>,
+                        true,
+                    );
+                }
+            }
+        }
+
+        emulator_modules.reads(
+            Hook::Function(gen_read_msan::<ET, I, S>),
+            Hook::Function(trace_read_msan::<ET, I, S, 1>),
+            Hook::Function(trace_read_msan::<ET, I, S, 2>),
+            Hook::Function(trace_read_msan::<ET, I, S, 4>),
+            Hook::Function(trace_read_msan::<ET, I, S, 8>),
+            Hook::Function(trace_read_n_msan::<ET, I, S>),
+        );
+        // Stores define memory anywhere, also in code we do not check
+        emulator_modules.writes(
+            Hook::Function(gen_write_msan::<ET, I, S>),
+            Hook::Function(trace_write_msan::<ET, I, S, 1>),
+            Hook::Function(trace_write_msan::<ET, I, S, 2>),
+            Hook::Function(trace_write_msan::<ET, I, S, 4>),
+            Hook::Function(trace_write_msan::<ET, I, S, 8>),
+            Hook::Function(trace_write_n_msan::<ET, I, S>),
+        );
+        if self.check == MsanCheck::Use {
+            emulator_modules.cmps(
+                Hook::Function(gen_cmp_msan::<ET, I, S>),
+                Hook::Function(trace_cmp_msan::<ET, I, S, u8>),
+                Hook::Function(trace_cmp_msan::<ET, I, S, u16>),
+                Hook::Function(trace_cmp_msan::<ET, I, S, u32>),
+                Hook::Function(trace_cmp_msan::<ET, I, S, u64>),
+            );
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        match &self.saved {
+            Some((shadow, allocs)) => {
+                self.shadow.clone_from(shadow);
+                self.allocs.clone_from(allocs);
+            }
+            None => self.saved = Some((self.shadow.clone(), self.allocs.clone())),
+        }
+        self.pending.clear();
+        self.recent.clear();
+        self.callstack.clear();
+        self.errors.clear();
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+        _observers: &mut OT,
+        exit_kind: &mut ExitKind,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        OT: ObserversTuple<I, S>,
+    {
+        if !self.errors.is_empty() {
+            *exit_kind = ExitKind::Crash;
+        }
+    }
+}
+
+impl HasAddressFilter for MsanModule {
+    type AddressFilter = StdAddressFilter;
+    fn address_filter(&self) -> &Self::AddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::AddressFilter {
+        &mut self.filter
+    }
+}
+
+/// Marks the stack below the stack pointer as undefined before each call, so that reads of
+/// uninitialized locals get reported by the [`MsanModule`].
+/// It also keeps the call stack, printed with each report.
+#[derive(Debug, Clone, Copy)]
+pub struct MsanStackCollector {
+    window: usize,
+}
+
+impl Default for MsanStackCollector {
+    fn default() -> Self {
+        Self::new(DEFAULT_MSAN_STACK_WINDOW)
+    }
+}
+
+impl MsanStackCollector {
+    /// Mark `window` bytes below the stack pointer as undefined before each call
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        Self { window }
+    }
+}
+
+impl CallTraceCollector for MsanStackCollector {
+    fn on_call<ET, I, S>(
+        &mut self,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+        call_len: usize,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        let Some(qemu) = Qemu::get() else {
+            return;
+        };
+        let Ok(sp) = qemu.read_reg(Regs::Sp) else {
+            return;
+        };
+        let sp = sp as GuestAddr;
+        if let Some(h) = emulator_modules.get_mut::<MsanModule>() {
+            let window = self.window.min(sp as usize);
+            h.shadow.set_undefined(sp - window as GuestAddr, window);
+            h.callstack.push(pc + call_len as GuestAddr);
+        }
+    }
+
+    fn on_ret<ET, I, S>(
+        &mut self,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: Option<&mut S>,
+        _pc: GuestAddr,
+        ret_addr: GuestAddr,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+        I: Unpin,
+        S: Unpin,
+    {
+        if let Some(h) = emulator_modules.get_mut::<MsanModule>() {
+            // Unwind up to the frame we return to, longjmp skips some returns
+            while let Some(ret) = h.callstack.pop() {
+                if ret == ret_addr {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub fn gen_read_msan<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+    _addr: *mut TCGTemp,
+    _info: MemAccessInfo,
+) -> Option<u64>
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules.get::<MsanModule>().unwrap();
+    h.must_instrument(pc).then_some(pc.into())
+}
+
+pub fn gen_write_msan<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+    _addr: *mut TCGTemp,
+    _info: MemAccessInfo,
+) -> Option<u64>
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    Some(pc.into())
+}
+
+pub fn trace_read_msan<ET, I, S, const N: usize>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+    _pc: GuestAddr,
+    addr: GuestAddr,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules.get_mut::<MsanModule>().unwrap();
+    if h.read(id as GuestAddr, addr, N) {
+        h.remember_load(qemu, id as GuestAddr, addr, N);
+    }
+}
+
+pub fn trace_read_n_msan<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+    _pc: GuestAddr,
+    addr: GuestAddr,
+    size: usize,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules.get_mut::<MsanModule>().unwrap();
+    if h.read(id as GuestAddr, addr, size) {
+        h.remember_load(qemu, id as GuestAddr, addr, size);
+    }
+}
+
+pub fn trace_write_msan<ET, I, S, const N: usize>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    _pc: GuestAddr,
+    addr: GuestAddr,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules.get_mut::<MsanModule>().unwrap();
+    h.shadow.set_defined(addr, N);
+}
+
+pub fn trace_write_n_msan<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    _pc: GuestAddr,
+    addr: GuestAddr,
+    size: usize,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules.get_mut::<MsanModule>().unwrap();
+    h.shadow.set_defined(addr, size);
+}
+
+pub fn gen_cmp_msan<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+    _size: usize,
+) -> Option<u64>
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    let h = emulator_modules.get::<MsanModule>().unwrap();
+    h.must_instrument(pc).then_some(pc.into())
+}
+
+pub fn trace_cmp_msan<ET, I, S, SZ>(
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+    v0: SZ,
+    v1: SZ,
+) where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+    SZ: Into<u64>,
+{
+    let h = emulator_modules.get_mut::<MsanModule>().unwrap();
+    if !h.recent.is_empty() {
+        h.cmp(id as GuestAddr, v0.into(), v1.into());
+    }
+}
+
+#[expect(non_upper_case_globals, clippy::too_many_arguments)]
+fn syscall_msan<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    sys_num: i32,
+    _a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin,
+    S: Unpin,
+{
+    // Buffers leaving the process
+    if let SYS_write | SYS_pwrite64 | SYS_sendto = i64::from(sys_num) {
+        let h = emulator_modules.get_mut::<MsanModule>().unwrap();
+        if let Some(addr) = h.shadow.first_undefined(a1, a2 as usize) {
+            h.report(MsanError::Syscall { num: sys_num, addr });
+        }
+    }
+    SyscallHookResult::Run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MSAN_PAGE_SIZE, MsanCheck, MsanError, MsanModule, Shadow};
+    use crate::modules::utils::filters::StdAddressFilter;
+
+    #[test]
+    fn test_shadow() {
+        let mut shadow = Shadow::default();
+        assert_eq!(shadow.first_undefined(0x1000, 0x100), None);
+
+        // Spanning a page boundary
+        let start = (2 * MSAN_PAGE_SIZE - 10) as _;
+        shadow.set_undefined(start, 20);
+        assert_eq!(shadow.first_undefined(0x1000, 0x10000), Some(start));
+        assert_eq!(shadow.first_undefined(start + 20, 8), None);
+
+        // A store in the middle
+        shadow.set_defined(start, 13);
+        assert_eq!(shadow.first_undefined(start, 20), Some(start + 13));
+
+        shadow.copy(start + 13, 0x10, 4);
+        assert_eq!(shadow.first_undefined(0, 0x100), Some(0x10));
+
+        shadow.set_defined(0, 0x10000);
+        assert_eq!(shadow.first_undefined(0, 0x10000), None);
+    }
+
+    #[test]
+    fn test_shadow_copy() {
+        let mut shadow = Shadow::default();
+        let page = MSAN_PAGE_SIZE as _;
+        shadow.set_undefined(page + 3, 2);
+        shadow.set_undefined(page + 64, 9);
+
+        // Unaligned destination, crossing into the next page
+        let dst = 3 * page - 5;
+        shadow.copy(page, dst, 80);
+        assert_eq!(shadow.first_undefined(dst, 3), None);
+        assert_eq!(shadow.first_undefined(dst, 80), Some(dst + 3));
+        assert_eq!(shadow.first_undefined(dst + 5, 59), None);
+        assert_eq!(shadow.first_undefined(dst + 64, 16), Some(dst + 64));
+        assert_eq!(shadow.first_undefined(dst + 73, 7), None);
+
+        // Both sides aligned, whole shadow bytes get copied
+        shadow.copy(page, 7 * page, 80);
+        assert_eq!(shadow.first_undefined(7 * page, 80), Some(7 * page + 3));
+        assert_eq!(shadow.first_undefined(7 * page + 73, 7), None);
+
+        // Aligned, from a defined page, clears the destination
+        shadow.copy(5 * page, dst - 3, 80);
+        assert_eq!(shadow.first_undefined(dst - 3, 80), None);
+    }
+
+    #[test]
+    fn test_msan_undefined_use() {
+        let mut msan = MsanModule::new(StdAddressFilter::default());
+        msan.poison(0x1000, 16);
+        msan.unpoison(0x1000, 4);
+
+        // A defined load is fine, loading the undefined bytes is reported
+        assert!(!msan.read(0x400, 0x1000, 4));
+        assert!(msan.errors().is_empty());
+        assert!(!msan.read(0x404, 0x1002, 4));
+        assert_eq!(
+            msan.errors(),
+            [MsanError::Load {
+                pc: 0x404,
+                addr: 0x1004,
+                size: 4
+            }]
+        );
+
+        // Loads are only remembered, until the value decides a branch
+        let mut msan = MsanModule::new(StdAddressFilter::default()).check(MsanCheck::Use);
+        msan.poison(0x1000, 16);
+        assert!(msan.read(0x400, 0x1008, 8));
+        assert!(msan.errors().is_empty());
+        msan.remember(0x400, 0x1234);
+        msan.cmp(0x408, 0x1234, 0);
+        assert_eq!(
+            msan.errors(),
+            [MsanError::Branch {
+                pc: 0x408,
+                load_pc: 0x400,
+                value: 0x1234
+            }]
+        );
+    }
+}
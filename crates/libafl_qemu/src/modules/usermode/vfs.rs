@@ -0,0 +1,712 @@
+//! An in-memory virtual filesystem for usermode targets
+//!
+//! Files opened by the target whose path matches one of the configured patterns are served from
+//! host `memfd`s instead of the host filesystem.
+//! Since QEMU usermode passes file descriptors through to the host, the guest gets a real
+//! descriptor of the `memfd`: `read`, `pread64`, `lseek`, `fstat`, `mmap` and `close` just work,
+//! while writes only ever change the in-memory copy.
+//! Path based syscalls (`open`, `openat`, `openat2`, the `stat` family, `statx` and `access`)
+//! are intercepted.
+//! All files are recreated from their original content for each run, so together with the
+//! [`crate::modules::SnapshotModule`] the file state is reset as well.
+#![allow(clippy::needless_pass_by_value)] // default compiler complains about Option<&mut T> otherwise
+use core::ffi::{CStr, c_char};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    mem::MaybeUninit,
+    os::{
+        fd::{AsRawFd, FromRawFd, IntoRawFd},
+        unix::fs::MetadataExt,
+    },
+};
+
+use hashbrown::HashMap;
+use libafl::{
+    Error,
+    inputs::{BytesInput, HasTargetBytes, Keyed, MultipartInput},
+};
+use libafl_bolts::AsSlice;
+use libafl_qemu_sys::GuestAddr;
+
+#[cfg(any(cpu_target = "x86_64", cpu_target = "aarch64", cpu_target = "riscv64"))]
+use crate::SYS_newfstatat;
+use crate::{
+    Qemu, SYS_close, SYS_faccessat, SYS_openat, SYS_openat2, SYS_statx,
+    emu::EmulatorModules,
+    modules::{
+        EmulatorModule, EmulatorModuleTuple,
+        utils::filters::{HasAddressFilter, NOP_ADDRESS_FILTER, NopAddressFilter},
+    },
+    qemu::{Hook, SyscallHookResult},
+};
+#[cfg(not(any(cpu_target = "aarch64", cpu_target = "riscv32", cpu_target = "riscv64")))]
+use crate::{SYS_access, SYS_open};
+#[cfg(any(
+    cpu_target = "arm",
+    cpu_target = "i386",
+    cpu_target = "mips",
+    cpu_target = "ppc"
+))]
+use crate::{SYS_fstatat64, SYS_lstat64, SYS_stat64};
+#[cfg(cpu_target = "x86_64")]
+use crate::{SYS_lstat, SYS_stat};
+
+// Open flags of the guest, these differ from the host on some architectures
+#[cfg(cpu_target = "mips")]
+const GUEST_O_CREAT: GuestAddr = 0x100;
+#[cfg(cpu_target = "mips")]
+const GUEST_O_TRUNC: GuestAddr = 0x200;
+#[cfg(cpu_target = "mips")]
+const GUEST_O_APPEND: GuestAddr = 0x8;
+#[cfg(not(cpu_target = "mips"))]
+const GUEST_O_CREAT: GuestAddr = 0o100;
+#[cfg(not(cpu_target = "mips"))]
+const GUEST_O_TRUNC: GuestAddr = 0o1000;
+#[cfg(not(cpu_target = "mips"))]
+const GUEST_O_APPEND: GuestAddr = 0o2000;
+
+/// The fields we fill in the `struct stat` (or `struct stat64` on 32-bit targets) of the guest
+#[cfg(not(cpu_target = "riscv32"))]
+struct StatLayout {
+    /// The size of the struct
+    len: usize,
+    /// The offset of the 32-bit `st_mode`
+    mode: usize,
+    /// The offset and size of `st_nlink`
+    nlink: (usize, usize),
+    /// The offset of the 64-bit `st_size`
+    size: usize,
+}
+
+#[cfg(cpu_target = "x86_64")]
+const GUEST_STAT_LAYOUT: StatLayout = StatLayout {
+    len: 144,
+    mode: 24,
+    nlink: (16, 8),
+    size: 48,
+};
+#[cfg(any(cpu_target = "aarch64", cpu_target = "riscv64"))]
+const GUEST_STAT_LAYOUT: StatLayout = StatLayout {
+    len: 128,
+    mode: 16,
+    nlink: (20, 4),
+    size: 48,
+};
+// `struct stat64` is packed on i386
+#[cfg(cpu_target = "i386")]
+const GUEST_STAT_LAYOUT: StatLayout = StatLayout {
+    len: 96,
+    mode: 16,
+    nlink: (20, 4),
+    size: 44,
+};
+#[cfg(any(cpu_target = "arm", cpu_target = "ppc"))]
+const GUEST_STAT_LAYOUT: StatLayout = StatLayout {
+    len: 104,
+    mode: 16,
+    nlink: (20, 4),
+    size: 48,
+};
+#[cfg(cpu_target = "mips")]
+const GUEST_STAT_LAYOUT: StatLayout = StatLayout {
+    len: 104,
+    mode: 24,
+    nlink: (28, 4),
+    size: 56,
+};
+
+/// The size of `struct statx`, which is the same for all architectures
+const GUEST_STATX_LEN: usize = 256;
+/// `STATX_TYPE | STATX_MODE | STATX_NLINK | STATX_SIZE`, the fields we fill in
+const GUEST_STATX_MASK: u64 = 0x1 | 0x2 | 0x4 | 0x200;
+
+/// Files the dynamic loader opens before the target runs, never served for patterns with wildcards
+const LOADER_PATHS: [&str; 2] = ["/etc/ld.so.cache", "/etc/ld.so.preload"];
+
+/// An input that can provide the content of virtual files
+pub trait VfsInput {
+    /// Append the bytes for the file backed by the input `part`, or by the whole input if `part`
+    /// is `None`, to `content`. Returns `false` if there is no such part.
+    fn vfs_content(&self, part: Option<&str>, content: &mut Vec<u8>) -> bool;
+}
+
+impl VfsInput for BytesInput {
+    fn vfs_content(&self, part: Option<&str>, content: &mut Vec<u8>) -> bool {
+        if part.is_some() {
+            return false;
+        }
+        content.extend_from_slice(self.target_bytes().as_slice());
+        true
+    }
+}
+
+impl<I> VfsInput for MultipartInput<I, String>
+where
+    I: HasTargetBytes,
+{
+    fn vfs_content(&self, part: Option<&str>, content: &mut Vec<u8>) -> bool {
+        let mut found = false;
+        match part {
+            Some(key) => {
+                for (_, input) in self.with_key(&key.to_string()) {
+                    content.extend_from_slice(input.target_bytes().as_slice());
+                    found = true;
+                }
+            }
+            None => {
+                for (_, input) in self.parts() {
+                    content.extend_from_slice(input.target_bytes().as_slice());
+                    found = true;
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Where the content of a virtual file comes from
+#[derive(Debug, Clone)]
+pub enum VfsContent {
+    /// The same bytes for every run
+    Static(Vec<u8>),
+    /// The whole fuzz input
+    Input,
+    /// The part of a [`MultipartInput`] with this key
+    Part(String),
+    /// A private copy of the host file, if it exists.
+    /// The target may create and write the file without ever touching the host filesystem.
+    Sandbox,
+}
+
+#[derive(Debug, Clone)]
+struct VfsEntry {
+    pattern: String,
+    content: VfsContent,
+}
+
+/// Serve files from memory, see the [module level documentation](self).
+#[derive(Debug)]
+pub struct VfsModule {
+    entries: Vec<VfsEntry>,
+    /// The input bytes of the current run, for [`VfsContent::Input`] and [`VfsContent::Part`]
+    input_contents: HashMap<Option<String>, Vec<u8>>,
+    /// The `memfd` of each path opened during the current run
+    files: HashMap<String, File>,
+    /// Host file descriptors handed out to the guest, and not closed yet,
+    /// with the device and inode of their `memfd` to tell if the number still refers to it
+    guest_fds: HashMap<i32, (u64, u64)>,
+}
+
+impl Default for VfsModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsModule {
+    /// Create a new [`VfsModule`] without any virtual files
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            input_contents: HashMap::new(),
+            files: HashMap::new(),
+            guest_fds: HashMap::new(),
+        }
+    }
+
+    /// Serve paths matching `pattern` from `content`.
+    ///
+    /// Patterns are matched against the path as passed by the target, `*` matches any sequence
+    /// of characters (including `/`), `?` any single character.
+    /// The first matching pattern wins.
+    /// Patterns with wildcards never match the files of the dynamic loader, i.e. its cache and
+    /// shared objects (`*.so` and `*.so.*`), so that a pattern like `*` does not break loading.
+    #[must_use]
+    pub fn file<P: Into<String>>(mut self, pattern: P, content: VfsContent) -> Self {
+        self.entries.push(VfsEntry {
+            pattern: pattern.into(),
+            content,
+        });
+        self
+    }
+
+    /// Serve paths matching `pattern` from the whole fuzz input
+    #[must_use]
+    pub fn input_file<P: Into<String>>(self, pattern: P) -> Self {
+        self.file(pattern, VfsContent::Input)
+    }
+
+    /// Serve paths matching `pattern` from the [`MultipartInput`] part `key`
+    #[must_use]
+    pub fn part_file<P: Into<String>, K: Into<String>>(self, pattern: P, key: K) -> Self {
+        self.file(pattern, VfsContent::Part(key.into()))
+    }
+
+    /// Serve paths matching `pattern` from fixed bytes
+    #[must_use]
+    pub fn static_file<P: Into<String>, C: Into<Vec<u8>>>(self, pattern: P, content: C) -> Self {
+        self.file(pattern, VfsContent::Static(content.into()))
+    }
+
+    /// Keep all writes to paths matching `pattern` in memory
+    #[must_use]
+    pub fn sandbox<P: Into<String>>(self, pattern: P) -> Self {
+        self.file(pattern, VfsContent::Sandbox)
+    }
+
+    fn entry(&self, path: &str) -> Option<&VfsEntry> {
+        let loader_path = is_loader_path(path);
+        self.entries.iter().find(|entry| {
+            if loader_path && entry.pattern.contains(['*', '?']) {
+                return false;
+            }
+            glob_match(entry.pattern.as_bytes(), path.as_bytes())
+        })
+    }
+
+    /// Get the `memfd` for `path`, creating it from the content of its entry the first time in a run.
+    /// Returns `Ok(None)` if the file does not exist and should not be created.
+    fn backing_file(&mut self, path: &str, create: bool) -> Result<Option<&File>, Error> {
+        if !self.files.contains_key(path) {
+            let Some(entry) = self.entry(path) else {
+                return Ok(None);
+            };
+            let content = match &entry.content {
+                VfsContent::Static(content) => Some(content.clone()),
+                VfsContent::Input => self.input_contents.get(&None).cloned(),
+                VfsContent::Part(key) => self.input_contents.get(&Some(key.clone())).cloned(),
+                VfsContent::Sandbox => fs::read(path).ok(),
+            };
+            let content = match content {
+                Some(content) => content,
+                None if create => Vec::new(),
+                None => return Ok(None),
+            };
+            let file = memfd(&content)?;
+            self.files.insert(path.to_string(), file);
+        }
+        Ok(self.files.get(path))
+    }
+
+    /// Open a new host file description for the guest, with its own offset
+    fn open(&mut self, path: &str, flags: GuestAddr) -> Result<Option<i32>, Error> {
+        let Some(file) = self.backing_file(path, flags & GUEST_O_CREAT != 0)? else {
+            return Ok(None);
+        };
+        let (read, write) = match flags & 3 {
+            0 => (true, false),
+            1 => (false, true),
+            _ => (true, true),
+        };
+        let metadata = file.metadata()?;
+        let fd = OpenOptions::new()
+            .read(read)
+            .write(write)
+            .truncate(write && flags & GUEST_O_TRUNC != 0)
+            .append(flags & GUEST_O_APPEND != 0)
+            .open(format!("/proc/self/fd/{}", file.as_raw_fd()))?
+            .into_raw_fd();
+        self.guest_fds.insert(fd, (metadata.dev(), metadata.ino()));
+        Ok(Some(fd))
+    }
+
+    /// Close the descriptors handed out to the guest that still refer to our `memfd`s.
+    /// The guest may have closed them in ways we do not track, like `dup2` or `close_range`,
+    /// and the numbers may belong to other files by now.
+    fn close_guest_fds(&mut self) {
+        for (fd, (dev, ino)) in self.guest_fds.drain() {
+            let mut stat = MaybeUninit::<libc::stat>::uninit();
+            if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } != 0 {
+                continue;
+            }
+            let stat = unsafe { stat.assume_init() };
+            #[allow(clippy::useless_conversion)] // the types differ between libcs
+            let ours = u64::from(stat.st_dev) == dev && u64::from(stat.st_ino) == ino;
+            if ours {
+                unsafe {
+                    libc::close(fd);
+                }
+            }
+        }
+    }
+
+    /// The size of the file at `path`, if it is served
+    fn file_size(&mut self, path: &str) -> Result<Option<u64>, Error> {
+        match self.backing_file(path, false)? {
+            Some(file) => Ok(Some(file.metadata()?.len())),
+            None => Ok(None),
+        }
+    }
+
+    /// Fill the guest `struct stat` (`struct stat64` on 32-bit targets) at `buf` for `path`
+    #[cfg(not(cpu_target = "riscv32"))]
+    fn stat(&mut self, qemu: Qemu, path: &str, buf: GuestAddr) -> Result<Option<()>, Error> {
+        let Some(size) = self.file_size(path)? else {
+            return Ok(None);
+        };
+        let layout = GUEST_STAT_LAYOUT;
+        let mut stat = vec![0_u8; layout.len];
+        put_guest(&mut stat, layout.mode, 4, u64::from(libc::S_IFREG | 0o644));
+        put_guest(&mut stat, layout.nlink.0, layout.nlink.1, 1);
+        put_guest(&mut stat, layout.size, 8, size);
+        qemu.write_mem(buf, &stat)
+            .map_err(|e| Error::illegal_state(format!("Could not write stat buffer: {e:?}")))?;
+        Ok(Some(()))
+    }
+
+    /// Fill the guest `struct statx` at `buf` for `path`
+    fn statx(&mut self, qemu: Qemu, path: &str, buf: GuestAddr) -> Result<Option<()>, Error> {
+        let Some(size) = self.file_size(path)? else {
+            return Ok(None);
+        };
+        let mut statx = [0_u8; GUEST_STATX_LEN];
+        put_guest(&mut statx, 0, 4, GUEST_STATX_MASK);
+        put_guest(&mut statx, 16, 4, 1);
+        put_guest(&mut statx, 28, 2, u64::from(libc::S_IFREG | 0o644));
+        put_guest(&mut statx, 40, 8, size);
+        qemu.write_mem(buf, &statx)
+            .map_err(|e| Error::illegal_state(format!("Could not write statx buffer: {e:?}")))?;
+        Ok(Some(()))
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for VfsModule
+where
+    I: Unpin + VfsInput,
+    S: Unpin,
+{
+    fn post_qemu_init<ET>(&mut self, _qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        emulator_modules.pre_syscalls(Hook::Function(vfs_syscall_hook::<ET, I, S>));
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        // Close what the last run left open, and forget about its writes
+        self.close_guest_fds();
+        self.files.clear();
+
+        self.input_contents.clear();
+        for entry in &self.entries {
+            let key = match &entry.content {
+                VfsContent::Input => None,
+                VfsContent::Part(key) => Some(key.clone()),
+                _ => continue,
+            };
+            if self.input_contents.contains_key(&key) {
+                continue;
+            }
+            let mut content = Vec::new();
+            if input.vfs_content(key.as_deref(), &mut content) {
+                self.input_contents.insert(key, content);
+            }
+        }
+    }
+}
+
+impl HasAddressFilter for VfsModule {
+    type AddressFilter = NopAddressFilter;
+
+    fn address_filter(&self) -> &Self::AddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::AddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+/// Read a `NUL` terminated path from the guest
+fn guest_path(qemu: Qemu, addr: GuestAddr) -> Option<String> {
+    if addr == 0 {
+        return None;
+    }
+    let path = unsafe { CStr::from_ptr(qemu.g2h::<c_char>(addr)) };
+    path.to_str().ok().map(ToString::to_string)
+}
+
+/// Check if the dynamic loader opens `path`: its cache, and shared objects
+fn is_loader_path(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    LOADER_PATHS.contains(&path) || file_name.ends_with(".so") || file_name.contains(".so.")
+}
+
+/// Write the `size` low bytes of `value` at `off` into the guest struct `buf`, in guest byte order
+fn put_guest(buf: &mut [u8], off: usize, size: usize, value: u64) {
+    let field = &mut buf[off..off + size];
+    field.copy_from_slice(&value.to_le_bytes()[..size]);
+    if cfg!(any(feature = "be", cpu_target = "ppc")) {
+        field.reverse();
+    }
+}
+
+/// Read a `u64` in guest byte order from the guest
+fn read_guest_u64(qemu: Qemu, addr: GuestAddr) -> Option<u64> {
+    let mut bytes = [0_u8; 8];
+    qemu.read_mem(addr, &mut bytes).ok()?;
+    Some(if cfg!(any(feature = "be", cpu_target = "ppc")) {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+/// Only paths relative to the working directory, or absolute ones, are served.
+/// `dirfd` is `None` for syscalls without one.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn is_cwd_relative(dirfd: Option<GuestAddr>, path: &str) -> bool {
+    path.starts_with('/') || dirfd.is_none_or(|dirfd| dirfd as i32 == libc::AT_FDCWD)
+}
+
+#[expect(clippy::cast_sign_loss)]
+fn syscall_result(result: Result<Option<i32>, Error>) -> SyscallHookResult {
+    match result {
+        Ok(Some(ret)) => SyscallHookResult::Skip(ret as GuestAddr),
+        Ok(None) => SyscallHookResult::Skip(libc::ENOENT.wrapping_neg() as GuestAddr),
+        Err(e) => {
+            log::warn!("VFS: {e}");
+            SyscallHookResult::Skip(libc::EIO.wrapping_neg() as GuestAddr)
+        }
+    }
+}
+
+#[expect(non_upper_case_globals, clippy::too_many_arguments)]
+fn vfs_syscall_hook<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    _a3: GuestAddr,
+    a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    ET: EmulatorModuleTuple<I, S>,
+    I: Unpin + VfsInput,
+    S: Unpin,
+{
+    let h = emulator_modules.get_mut::<VfsModule>().unwrap();
+
+    // Check if the guest path is ours, and otherwise let the syscall run
+    macro_rules! vfs_path {
+        ($dirfd:expr, $addr:expr) => {
+            match guest_path(qemu, $addr) {
+                // An empty path with `AT_EMPTY_PATH` refers to the `dirfd` itself
+                Some(path)
+                    if !path.is_empty()
+                        && is_cwd_relative($dirfd, &path)
+                        && h.entry(&path).is_some() =>
+                {
+                    path
+                }
+                _ => return SyscallHookResult::Run,
+            }
+        };
+    }
+
+    match i64::from(sys_num) {
+        #[cfg(not(any(cpu_target = "aarch64", cpu_target = "riscv32", cpu_target = "riscv64")))]
+        SYS_open => {
+            let path = vfs_path!(None, a0);
+            syscall_result(h.open(&path, a1))
+        }
+        SYS_openat => {
+            let path = vfs_path!(Some(a0), a1);
+            syscall_result(h.open(&path, a2))
+        }
+        SYS_openat2 => {
+            let path = vfs_path!(Some(a0), a1);
+            // The flags are the first `u64` of `struct open_how`
+            let Some(flags) = read_guest_u64(qemu, a2) else {
+                return SyscallHookResult::Run;
+            };
+            syscall_result(h.open(&path, flags as GuestAddr))
+        }
+        #[cfg(cpu_target = "x86_64")]
+        SYS_stat | SYS_lstat => {
+            let path = vfs_path!(None, a0);
+            syscall_result(h.stat(qemu, &path, a1).map(|found| found.map(|()| 0)))
+        }
+        #[cfg(any(
+            cpu_target = "arm",
+            cpu_target = "i386",
+            cpu_target = "mips",
+            cpu_target = "ppc"
+        ))]
+        SYS_stat64 | SYS_lstat64 => {
+            let path = vfs_path!(None, a0);
+            syscall_result(h.stat(qemu, &path, a1).map(|found| found.map(|()| 0)))
+        }
+        #[cfg(any(cpu_target = "x86_64", cpu_target = "aarch64", cpu_target = "riscv64"))]
+        SYS_newfstatat => {
+            let path = vfs_path!(Some(a0), a1);
+            syscall_result(h.stat(qemu, &path, a2).map(|found| found.map(|()| 0)))
+        }
+        #[cfg(any(
+            cpu_target = "arm",
+            cpu_target = "i386",
+            cpu_target = "mips",
+            cpu_target = "ppc"
+        ))]
+        SYS_fstatat64 => {
+            let path = vfs_path!(Some(a0), a1);
+            syscall_result(h.stat(qemu, &path, a2).map(|found| found.map(|()| 0)))
+        }
+        SYS_statx => {
+            let path = vfs_path!(Some(a0), a1);
+            // Whatever mask was requested, we fill in the same fields and report them in `stx_mask`
+            syscall_result(h.statx(qemu, &path, a4).map(|found| found.map(|()| 0)))
+        }
+        #[cfg(not(any(cpu_target = "aarch64", cpu_target = "riscv32", cpu_target = "riscv64")))]
+        SYS_access => {
+            let path = vfs_path!(None, a0);
+            syscall_result(h.backing_file(&path, false).map(|file| file.map(|_| 0)))
+        }
+        SYS_faccessat => {
+            let path = vfs_path!(Some(a0), a1);
+            syscall_result(h.backing_file(&path, false).map(|file| file.map(|_| 0)))
+        }
+        SYS_close => {
+            if let Ok(fd) = i32::try_from(a0) {
+                h.guest_fds.remove(&fd);
+            }
+            SyscallHookResult::Run
+        }
+        _ => SyscallHookResult::Run,
+    }
+}
+
+/// Create an anonymous in-memory file holding `content`
+fn memfd(content: &[u8]) -> Result<File, Error> {
+    let fd = unsafe { libc::memfd_create(c"libafl_vfs".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(content)?;
+    Ok(file)
+}
+
+/// Match `path` against a glob `pattern`, supporting `*` and `?`
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // The position after the last `*`, and where in `path` it started matching
+    let mut backtrack = None;
+    while s < path.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, s));
+            }
+            Some(&c) if c == b'?' || c == path[s] => {
+                p += 1;
+                s += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bs)) => {
+                    p = bp;
+                    s = bs + 1;
+                    backtrack = Some((bp, bs + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::inputs::{BytesInput, MultipartInput};
+
+    use super::{VfsContent, VfsInput, VfsModule, glob_match, put_guest};
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"/etc/passwd", b"/etc/passwd"));
+        assert!(glob_match(b"*.conf", b"/etc/app/app.conf"));
+        assert!(glob_match(b"/tmp/*", b"/tmp/a/b"));
+        assert!(glob_match(b"/tmp/file?.txt", b"/tmp/file1.txt"));
+        assert!(glob_match(b"*a*b*", b"xxaxxbxx"));
+        assert!(!glob_match(b"*.conf", b"/etc/app/app.config"));
+        assert!(!glob_match(b"/tmp/file?.txt", b"/tmp/file.txt"));
+        assert!(!glob_match(b"/etc/passwd", b"/etc/passwd2"));
+    }
+
+    #[test]
+    fn test_vfs_entries() {
+        let module = VfsModule::new()
+            .static_file("/opt/plugin.so", b"plugin".to_vec())
+            .part_file("/input/config", "config")
+            .static_file("/etc/*", b"static".to_vec())
+            .input_file("*");
+        assert!(matches!(
+            module.entry("/input/config").unwrap().content,
+            VfsContent::Part(_)
+        ));
+        assert!(matches!(
+            module.entry("/etc/hosts").unwrap().content,
+            VfsContent::Static(_)
+        ));
+        assert!(matches!(
+            module.entry("relative").unwrap().content,
+            VfsContent::Input
+        ));
+
+        // The loader's files are only served for exact patterns
+        assert!(module.entry("/etc/ld.so.cache").is_none());
+        assert!(module.entry("/lib/x86_64-linux-gnu/libc.so.6").is_none());
+        assert!(module.entry("libfoo.so").is_none());
+        assert!(matches!(
+            module.entry("/opt/plugin.so").unwrap().content,
+            VfsContent::Static(_)
+        ));
+        assert!(matches!(
+            module.entry("/tmp/not.socket").unwrap().content,
+            VfsContent::Input
+        ));
+    }
+
+    #[test]
+    fn test_put_guest() {
+        let mut buf = [0_u8; 8];
+        put_guest(&mut buf, 2, 4, 0x1122_3344);
+        if cfg!(any(feature = "be", cpu_target = "ppc")) {
+            assert_eq!(buf, [0, 0, 0x11, 0x22, 0x33, 0x44, 0, 0]);
+        } else {
+            assert_eq!(buf, [0, 0, 0x44, 0x33, 0x22, 0x11, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn test_vfs_input() {
+        let input = MultipartInput::from(vec![
+            ("config".to_string(), BytesInput::new(b"a=b".to_vec())),
+            ("data".to_string(), BytesInput::new(b"data".to_vec())),
+        ]);
+        let mut content = Vec::new();
+        assert!(input.vfs_content(Some("config"), &mut content));
+        assert_eq!(content, b"a=b");
+        assert!(!input.vfs_content(Some("missing"), &mut content));
+
+        let mut content = Vec::new();
+        assert!(BytesInput::new(b"abc".to_vec()).vfs_content(None, &mut content));
+        assert_eq!(content, b"abc");
+    }
+}
@@ -57,6 +57,9 @@ mod hooks;
 pub use hooks::*;
 use libafl_bolts::{AsSliceMut, vec_init};
 
+/// Size of the buffer filled by [`CPU::read_reg_bytes`], large enough for a 512-bit register.
+pub const MAX_REG_BYTES: usize = 64;
+
 static mut QEMU_IS_INITIALIZED: bool = false;
 static mut QEMU_IS_RUNNING: bool = false;
 
@@ -345,6 +348,29 @@ impl CPU {
         }
     }
 
+    /// Read the raw content of a register, in target byte order.
+    ///
+    /// Unlike [`CPU::read_reg`], this also works for registers wider than [`GuestReg`],
+    /// such as vector and floating-point registers, using their gdb register number.
+    /// Bytes past the width of the register are zero.
+    pub fn read_reg_bytes<R>(&self, reg: R) -> Result<[u8; MAX_REG_BYTES], QemuRWError>
+    where
+        R: Into<i32> + Clone,
+    {
+        let reg_id = reg.clone().into();
+        let mut val = [0; MAX_REG_BYTES];
+        let success = unsafe { libafl_qemu_read_reg(self.cpu_ptr, reg_id, val.as_mut_ptr()) };
+        if success == 0 {
+            Err(QemuRWError::wrong_reg(
+                QemuRWErrorKind::Read,
+                reg,
+                Some(self.cpu_ptr),
+            ))
+        } else {
+            Ok(val)
+        }
+    }
+
     pub fn write_reg<R, T>(&self, reg: R, val: T) -> Result<(), QemuRWError>
     where
         R: Into<i32> + Clone,
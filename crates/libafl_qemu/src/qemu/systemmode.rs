@@ -258,6 +258,19 @@ impl Qemu {
         unsafe { libafl_load_qemu_snapshot(s.as_ptr().cast_mut(), sync) };
     }
 
+    /// Delete a snapshot taken with [`Qemu::save_snapshot`] from all the block devices.
+    /// Returns `false` if the snapshot could not be deleted.
+    pub fn delete_snapshot(&self, name: &str) -> bool {
+        let s = CString::new(name).expect("Invalid snapshot name");
+        unsafe { libafl_qemu_sys::delete_snapshot(s.as_ptr(), false, null_mut(), null_mut()) }
+    }
+
+    /// The size of the guest RAM, in bytes
+    #[must_use]
+    pub fn ram_size(&self) -> u64 {
+        unsafe { libafl_qemu_sys::ram_size }
+    }
+
     #[must_use]
     pub fn create_fast_snapshot(&self, track: bool) -> FastSnapshotPtr {
         unsafe {
@@ -294,6 +307,14 @@ impl Qemu {
         }
     }
 
+    /// Free a fast snapshot. It must not be used afterwards.
+    #[expect(clippy::missing_safety_doc)]
+    pub unsafe fn free_fast_snapshot(&self, snapshot: FastSnapshotPtr) {
+        unsafe {
+            libafl_qemu_sys::syx_snapshot_free(snapshot);
+        }
+    }
+
     #[must_use]
     #[expect(clippy::missing_safety_doc)]
     pub unsafe fn check_fast_snapshot(
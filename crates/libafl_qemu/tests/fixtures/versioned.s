@@ -0,0 +1,44 @@
+# A small x86_64 shared library exporting versioned symbols, used by the tests of `src/elf.rs`.
+#
+# Build with:
+#   as versioned.s -o versioned.o
+#   ld -shared -nostdlib --version-script versioned.map --hash-style=gnu --build-id=none \
+#       -z noseparate-code -z norelro -z max-page-size=0x10 -z common-page-size=0x10 \
+#       -o versioned.elf versioned.o
+#   strip -R .comment versioned.elf
+    .text
+
+    # memcpy@V1 is the hidden old version, memcpy@@V2 the default one
+    .globl memcpy_old
+    .type memcpy_old, @function
+    .p2align 4
+memcpy_old:
+    ret
+    .symver memcpy_old, memcpy@V1
+
+    .globl memcpy_new
+    .type memcpy_new, @function
+    .p2align 4
+memcpy_new:
+    ret
+    .symver memcpy_new, memcpy@@V2
+
+    # legacy only exists in a hidden version
+    .globl legacy_old
+    .type legacy_old, @function
+    .p2align 4
+legacy_old:
+    ret
+    .symver legacy_old, legacy@V1
+
+    .globl exported
+    .type exported, @function
+    .p2align 4
+exported:
+    ret
+
+    # not exported, and imports the undefined symbol imported
+    .p2align 4
+internal:
+    call imported@PLT
+    ret
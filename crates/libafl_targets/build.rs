@@ -52,6 +52,26 @@ fn main() {
         .map_or(Ok(SIXTY_FOUR_KIB), str::parse)
         .expect("Could not parse LIBAFL_ACCOUNTING_MAP_SIZE");
 
+    let ijon_map_size: usize = option_env!("LIBAFL_IJON_MAP_SIZE")
+        .map_or(Ok(SIXTY_FOUR_KIB), str::parse)
+        .expect("Could not parse LIBAFL_IJON_MAP_SIZE");
+
+    let ijon_max_slots: usize = option_env!("LIBAFL_IJON_MAX_SLOTS")
+        .map_or(Ok(512), str::parse)
+        .expect("Could not parse LIBAFL_IJON_MAX_SLOTS");
+
+    let memlock_map_size: usize = option_env!("LIBAFL_MEMLOCK_MAP_SIZE")
+        .map_or(Ok(SIXTY_FOUR_KIB), str::parse)
+        .expect("Could not parse LIBAFL_MEMLOCK_MAP_SIZE");
+
+    let dataflow_map_size: usize = option_env!("LIBAFL_DATAFLOW_MAP_SIZE")
+        .map_or(Ok(SIXTY_FOUR_KIB), str::parse)
+        .expect("Could not parse LIBAFL_DATAFLOW_MAP_SIZE");
+
+    let dataflow_shadow_size: usize = option_env!("LIBAFL_DATAFLOW_SHADOW_SIZE")
+        .map_or(Ok(1 << 18), str::parse)
+        .expect("Could not parse LIBAFL_DATAFLOW_SHADOW_SIZE");
+
     assert!(edges_map_default_size <= edges_map_allocated_size);
     assert!(edges_map_default_size.is_power_of_two());
 
@@ -71,6 +91,16 @@ fn main() {
         pub const CMPLOG_MAP_H: usize = {cmplog_map_h};
         /// The size of the accounting maps
         pub const ACCOUNTING_MAP_SIZE: usize = {acc_map_size};
+        /// The size of the IJON annotation map
+        pub const IJON_MAP_SIZE: usize = {ijon_map_size};
+        /// The number of IJON max and min slots
+        pub const IJON_MAX_SLOTS: usize = {ijon_max_slots};
+        /// The size of the `MemLock` stack depth and allocation maps
+        pub const MEMLOCK_MAP_SIZE: usize = {memlock_map_size};
+        /// The size of the def-use map of the dataflow runtime
+        pub const DATAFLOW_MAP_SIZE: usize = {dataflow_map_size};
+        /// The number of addresses the dataflow runtime remembers the last store for
+        pub const DATAFLOW_SHADOW_SIZE: usize = {dataflow_shadow_size};
 "
     )
     .expect("Could not write file");
@@ -83,6 +113,11 @@ fn main() {
     println!("cargo:rerun-if-env-changed=LIBAFL_CMPLOG_MAP_W");
     println!("cargo:rerun-if-env-changed=LIBAFL_CMPLOG_MAP_H");
     println!("cargo:rerun-if-env-changed=LIBAFL_ACCOUNTING_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_IJON_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_IJON_MAX_SLOTS");
+    println!("cargo:rerun-if-env-changed=LIBAFL_MEMLOCK_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_DATAFLOW_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_DATAFLOW_SHADOW_SIZE");
 
     #[cfg(feature = "common")]
     {
@@ -101,6 +101,26 @@ void __libafl_targets_cmplog_instructions(uintptr_t k, uint8_t size,
   cmplog_instructions_checked(k, size, arg1, arg2, 0);
 }
 
+// Floating-point compare callback, the operands are the raw bits of the values
+// and size is 4 for single and 8 for double precision.
+// Also logged in the afl++ style map, if enabled
+void __libafl_targets_cmplog_fp(uintptr_t k, uint8_t size, uint64_t arg1,
+                                uint64_t arg2) {
+  cmplog_operands_checked(k, CMPLOG_KIND_FP, size, arg1, arg2, 0);
+  cmplog_instructions_checked_extended(k, size, arg1, arg2,
+                                       CMPLOG_ATTRIBUTE_IS_FP);
+}
+
+// Jump table callback, logs the index used for an indirect jump together with
+// the number of entries in the table.
+// Also logged in the afl++ style map, if enabled
+void __libafl_targets_cmplog_jump_table(uintptr_t k, uint64_t index,
+                                        uint64_t entries) {
+  cmplog_operands_checked(k, CMPLOG_KIND_JMP_TABLE, 8, index, entries, 0);
+  cmplog_instructions_checked_extended(k, 8, index, entries,
+                                       CMPLOG_ATTRIBUTE_JMP_TABLE);
+}
+
 // Very generic afl++ style cmplog instructions callback
 void __libafl_targets_cmplog_instructions_extended(uintptr_t k, uint8_t size,
   uint64_t arg1, uint64_t arg2) {
@@ -30,6 +30,13 @@
 
 #define CMPLOG_KIND_INS 0
 #define CMPLOG_KIND_RTN 1
+#define CMPLOG_KIND_FP 2
+#define CMPLOG_KIND_JMP_TABLE 3
+
+// AFL++ header attributes, the low three bits are the compare predicate
+#define CMPLOG_ATTRIBUTE_IS_FP 8
+// No compare is equal, greater and lesser at once, this marks a jump table
+#define CMPLOG_ATTRIBUTE_JMP_TABLE 15
 
 typedef struct CmpLogHeader {
   uint16_t hits;
@@ -100,24 +107,27 @@ extern CmpLogMapExtended *libafl_cmplog_map_extended_ptr;
 
 extern uint8_t libafl_cmplog_enabled;
 
-// 5 of CMPLOG inner APIs, we static inline everything
-// area_is_valid, cmplog_instructions_checked,
+// 6 of CMPLOG inner APIs, we static inline everything
+// area_is_valid, cmplog_operands_checked, cmplog_instructions_checked,
 // cmplog_instructions_checked_extended,
 // cmplog_routines_checked,
 // cmplog_routines_checked_extended
 
 // size is the operand size of instruction, which should be greater than 0
-static inline void cmplog_instructions_checked(uintptr_t k, uint8_t size,
-                                               uint64_t arg1, uint64_t arg2,
-                                               uint8_t arg1_is_const) {
+// kind is one of the operand kinds (CMPLOG_KIND_INS, CMPLOG_KIND_FP or
+// CMPLOG_KIND_JMP_TABLE)
+static inline void cmplog_operands_checked(uintptr_t k, uint8_t kind,
+                                           uint8_t size, uint64_t arg1,
+                                           uint64_t arg2,
+                                           uint8_t arg1_is_const) {
   if (!libafl_cmplog_enabled) { return; }
   libafl_cmplog_enabled = false;
 
   if (size == 0) { return; }
   uint8_t shape = size - 1;
   uint16_t hits;
-  if (libafl_cmplog_map_ptr->headers[k].kind != CMPLOG_KIND_INS) {
-    libafl_cmplog_map_ptr->headers[k].kind = CMPLOG_KIND_INS;
+  if (libafl_cmplog_map_ptr->headers[k].kind != kind) {
+    libafl_cmplog_map_ptr->headers[k].kind = kind;
     libafl_cmplog_map_ptr->headers[k].hits = 1;
     libafl_cmplog_map_ptr->headers[k].shape = shape;
     hits = 0;
@@ -135,6 +145,13 @@ static inline void cmplog_instructions_checked(uintptr_t k, uint8_t size,
   libafl_cmplog_enabled = true;
 }
 
+// size is the operand size of instruction, which should be greater than 0
+static inline void cmplog_instructions_checked(uintptr_t k, uint8_t size,
+                                               uint64_t arg1, uint64_t arg2,
+                                               uint8_t arg1_is_const) {
+  cmplog_operands_checked(k, CMPLOG_KIND_INS, size, arg1, arg2, arg1_is_const);
+}
+
 // size is the operand size of instruction, which should be greater than 0
 static inline void cmplog_instructions_checked_extended(
     uintptr_t k, uint8_t size, uint64_t arg1, uint64_t arg2, uint8_t attr) {
@@ -42,6 +42,16 @@ pub const CMPLOG_MAP_RTN_EXTENDED_H: usize =
 pub const CMPLOG_KIND_INS: u8 = 0;
 /// `CmpLog` routine kind
 pub const CMPLOG_KIND_RTN: u8 = 1;
+/// `CmpLog` floating-point instruction kind, operands are stored as raw bits
+pub const CMPLOG_KIND_FP: u8 = 2;
+/// `CmpLog` jump table kind, operands are the index and the number of entries
+pub const CMPLOG_KIND_JMP_TABLE: u8 = 3;
+
+/// AFL++ `CmpLog` header attribute bit marking a floating-point compare
+pub const CMPLOG_ATTRIBUTE_IS_FP: u8 = 8;
+/// AFL++ `CmpLog` header attribute marking a jump table, operands are the index and the number
+/// of entries. No compare is equal, greater and lesser at once.
+pub const CMPLOG_ATTRIBUTE_JMP_TABLE: u8 = 15;
 
 // EXTERNS, GLOBALS
 
@@ -55,6 +65,14 @@ unsafe extern "C" {
     /// Logs an instruction for feedback during fuzzing
     pub fn __libafl_targets_cmplog_instructions(k: usize, size: u8, arg1: u64, arg2: u64);
 
+    /// Logs a floating-point compare, passing the raw bits of both operands.
+    /// With `cmplog_extended_instrumentation`, it also goes to the AFL++ style map.
+    pub fn __libafl_targets_cmplog_fp(k: usize, size: u8, arg1: u64, arg2: u64);
+
+    /// Logs the index taken through a jump table with `entries` entries.
+    /// With `cmplog_extended_instrumentation`, it also goes to the AFL++ style map.
+    pub fn __libafl_targets_cmplog_jump_table(k: usize, index: u64, entries: u64);
+
     /// Logs an AFL++ style instruction for feedback during fuzzing
     pub fn __libafl_targets_cmplog_instructions_extended(k: usize, size: u8, arg1: u64, arg2: u64);
 
@@ -386,7 +404,7 @@ impl CmpMap for CmpLogMap {
     }
 
     fn usable_executions_for(&self, idx: usize) -> usize {
-        if self.headers[idx].kind == CMPLOG_KIND_INS {
+        if self.headers[idx].kind != CMPLOG_KIND_RTN {
             if self.executions_for(idx) < CMPLOG_MAP_H {
                 self.executions_for(idx)
             } else {
@@ -400,7 +418,33 @@ impl CmpMap for CmpLogMap {
     }
 
     fn values_of(&self, idx: usize, execution: usize) -> Option<CmpValues> {
-        if self.headers[idx].kind == CMPLOG_KIND_INS {
+        let kind = self.headers[idx].kind;
+        if kind == CMPLOG_KIND_FP {
+            let shape = self.headers[idx].shape;
+            unsafe {
+                match shape {
+                    3 => Some(CmpValues::F32((
+                        self.vals.operands[idx][execution].0 as u32,
+                        self.vals.operands[idx][execution].1 as u32,
+                        self.vals.operands[idx][execution].2 == 1,
+                    ))),
+                    7 => Some(CmpValues::F64((
+                        self.vals.operands[idx][execution].0,
+                        self.vals.operands[idx][execution].1,
+                        self.vals.operands[idx][execution].2 == 1,
+                    ))),
+                    // x87 extended precision and vector compares are not logged
+                    _ => None,
+                }
+            }
+        } else if kind == CMPLOG_KIND_JMP_TABLE {
+            unsafe {
+                Some(CmpValues::JumpTable((
+                    self.vals.operands[idx][execution].0,
+                    self.vals.operands[idx][execution].1,
+                )))
+            }
+        } else if kind == CMPLOG_KIND_INS {
             let shape = self.headers[idx].shape;
             unsafe {
                 match shape {
@@ -596,6 +640,32 @@ impl CmpMap for AflppCmpLogMap {
         let header = self.headers[idx];
         if header.type_().value() == CMPLOG_KIND_INS {
             let shape = self.headers[idx].shape().value();
+            if header.attribute().value() == CMPLOG_ATTRIBUTE_JMP_TABLE {
+                return unsafe {
+                    Some(CmpValues::JumpTable((
+                        self.vals.operands[idx][execution].v0,
+                        self.vals.operands[idx][execution].v1,
+                    )))
+                };
+            }
+            if header.attribute().value() & CMPLOG_ATTRIBUTE_IS_FP != 0 {
+                return unsafe {
+                    match shape {
+                        3 => Some(CmpValues::F32((
+                            self.vals.operands[idx][execution].v0 as u32,
+                            self.vals.operands[idx][execution].v1 as u32,
+                            false,
+                        ))),
+                        7 => Some(CmpValues::F64((
+                            self.vals.operands[idx][execution].v0,
+                            self.vals.operands[idx][execution].v1,
+                            false,
+                        ))),
+                        // x87 extended precision is not supported
+                        _ => None,
+                    }
+                };
+            }
             unsafe {
                 match shape {
                     0 => Some(CmpValues::U8((
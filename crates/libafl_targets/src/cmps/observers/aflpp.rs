@@ -297,3 +297,91 @@ pub fn add_to_aflpp_cmp_metadata(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use libafl::{
+        HasMetadata,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        mutators::{AflppRedQueen, MultiMutator},
+        observers::cmp::{AflppCmpLogHeader, AflppCmpValuesMetadata, CmpMap, CmpValues},
+        stages::TaintMetadata,
+        state::{HasCorpus, HasCurrentCorpusId, StdState},
+    };
+    use libafl_bolts::rands::StdRand;
+
+    use super::add_to_aflpp_cmp_metadata;
+    use crate::cmps::{
+        AflppCmpLogMap, AflppCmpLogOperands, CMPLOG_ATTRIBUTE_IS_FP, CMPLOG_ATTRIBUTE_JMP_TABLE,
+    };
+
+    /// A header as logged by `cmplog_instructions_checked_extended`, for a single hit
+    fn header(size: u16, attribute: u16) -> AflppCmpLogHeader {
+        AflppCmpLogHeader::new_with_raw_value(1 | ((size - 1) << 6) | (attribute << 12))
+    }
+
+    #[test]
+    fn test_aflpp_map_values() {
+        let mut map = AflppCmpLogMap::boxed();
+        map.headers_mut()[1] = header(8, CMPLOG_ATTRIBUTE_JMP_TABLE.into());
+        map.values_mut().operands_mut()[1][0] = AflppCmpLogOperands::new(2, 4);
+        map.headers_mut()[2] = header(8, CMPLOG_ATTRIBUTE_IS_FP.into());
+        map.values_mut().operands_mut()[2][0] =
+            AflppCmpLogOperands::new(1.5f64.to_bits(), 2.5f64.to_bits());
+
+        assert_eq!(map.values_of(1, 0), Some(CmpValues::JumpTable((2, 4))));
+        assert_eq!(
+            map.values_of(2, 0),
+            Some(CmpValues::F64((1.5f64.to_bits(), 2.5f64.to_bits(), false)))
+        );
+    }
+
+    #[test]
+    fn test_aflpp_jump_table_redqueen() {
+        let orig = b"ab\x02c".to_vec();
+        let colorized = b"xy\x05z".to_vec();
+
+        let mut corpus = InMemoryCorpus::new();
+        let id = corpus
+            .add(Testcase::new(BytesInput::new(orig.clone())))
+            .unwrap();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut ConstFeedback::new(false),
+            &mut ConstFeedback::new(false),
+        )
+        .unwrap();
+        state.set_corpus_id(id).unwrap();
+
+        // The byte at index 2 selects the jump table entry, in both runs
+        let mut meta = AflppCmpValuesMetadata::new();
+        let mut map = AflppCmpLogMap::boxed();
+        for (index, original) in [(2, true), (5, false)] {
+            map.headers_mut()[7] = header(8, CMPLOG_ATTRIBUTE_JMP_TABLE.into());
+            map.values_mut().operands_mut()[7][0] = AflppCmpLogOperands::new(index, 4);
+            add_to_aflpp_cmp_metadata(&mut meta, map.len(), &mut map, original);
+        }
+        state.add_metadata(meta);
+        state.add_metadata(TaintMetadata::new(colorized, vec![2..3]));
+
+        let input = state.corpus().cloned_input_for_id(id).unwrap();
+        let mutants = AflppRedQueen::new()
+            .multi_mutate(&mut state, &input, None)
+            .unwrap();
+        let mutants: Vec<Vec<u8>> = mutants.into_iter().map(Vec::from).collect();
+        assert_eq!(
+            mutants,
+            [
+                b"ab\x00c".to_vec(),
+                b"ab\x01c".to_vec(),
+                b"ab\x03c".to_vec()
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Runtime for the `Dataflow` pass of `libafl_cc`.
+//!
+//! The pass reports every instrumented store and load with its address. This runtime remembers the
+//! last store site for each address in a shadow table and, on each load, hashes the pair of store
+//! (def) and load (use) site into [`DATAFLOW_MAP`]. The map is a hitcount map like the edges map:
+//! wrap [`dataflow_map_observer`] in a [`libafl::observers::HitcountsMapObserver`] and reward it
+//! with a [`libafl::feedbacks::MaxMapFeedback`], next to the edge coverage feedback.
+//!
+//! Add a [`DataflowHook`] to the executor, so stores of earlier executions are forgotten.
+//! Addresses are tracked with 8 byte granularity and may collide in the shadow table, like edges
+//! in the edges map. To reduce the overhead, instrument only a part of all sites with
+//! `-mllvm -dataflow_ratio=<percent>`, or restrict the pass with an allowlist.
+
+use alloc::borrow::Cow;
+use core::{ffi::c_void, marker::PhantomData};
+
+use libafl::{executors::hooks::ExecutorHook, observers::StdMapObserver};
+use libafl_bolts::ownedref::OwnedMutSlice;
+
+use crate::{DATAFLOW_MAP_SIZE, DATAFLOW_SHADOW_SIZE};
+
+/// The map of def-use pairs
+#[unsafe(no_mangle)]
+#[allow(non_upper_case_globals)] // expect breaks here for some reason
+pub static mut libafl_dataflow_map: [u8; DATAFLOW_MAP_SIZE] = [0; DATAFLOW_MAP_SIZE];
+pub use libafl_dataflow_map as DATAFLOW_MAP;
+
+/// The last store site per address, tagged with the execution it happened in
+static mut DATAFLOW_SHADOW: [u64; DATAFLOW_SHADOW_SIZE] = [0; DATAFLOW_SHADOW_SIZE];
+
+/// The current execution, so the shadow table does not need to be cleared between executions
+static mut DATAFLOW_EPOCH: u32 = 1;
+
+#[inline]
+fn shadow_index(addr: *const c_void) -> usize {
+    (addr as usize >> 3) % DATAFLOW_SHADOW_SIZE
+}
+
+/// Hash a def-use pair into an index of [`DATAFLOW_MAP`]
+#[inline]
+#[must_use]
+pub fn dataflow_pair_index(def_site: u32, use_site: u32) -> usize {
+    // rotate, so `a -> b` and `b -> a` hit different entries
+    (def_site.rotate_left(1) ^ use_site) as usize % DATAFLOW_MAP_SIZE
+}
+
+/// The runtime code inserted before every instrumented store by the `Dataflow` pass
+///
+/// # Safety
+/// Writes to the shadow table, must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __libafl_dataflow_store(addr: *const c_void, site: u32) {
+    unsafe {
+        let entry = (&raw mut DATAFLOW_SHADOW)
+            .cast::<u64>()
+            .add(shadow_index(addr));
+        *entry = (u64::from(DATAFLOW_EPOCH) << 32) | u64::from(site);
+    }
+}
+
+/// The runtime code inserted before every instrumented load by the `Dataflow` pass
+///
+/// # Safety
+/// Writes to [`DATAFLOW_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __libafl_dataflow_load(addr: *const c_void, site: u32) {
+    unsafe {
+        let def = *(&raw const DATAFLOW_SHADOW)
+            .cast::<u64>()
+            .add(shadow_index(addr));
+        // memory not stored to in this execution, e.g. filled by `read`, has no def site
+        if (def >> 32) as u32 != DATAFLOW_EPOCH {
+            return;
+        }
+        let entry = (&raw mut DATAFLOW_MAP)
+            .cast::<u8>()
+            .add(dataflow_pair_index(def as u32, site));
+        *entry = (*entry).wrapping_add(1);
+    }
+}
+
+/// Gets a new [`StdMapObserver`] on [`DATAFLOW_MAP`]
+///
+/// # Safety
+/// The observer aliases the static map, there must be only one.
+pub unsafe fn dataflow_map_observer<'a, S>(name: S) -> StdMapObserver<'a, u8, false>
+where
+    S: Into<Cow<'static, str>>,
+{
+    unsafe {
+        StdMapObserver::from_mut_slice(
+            name,
+            OwnedMutSlice::from_raw_parts_mut(
+                (&raw mut DATAFLOW_MAP).cast::<u8>(),
+                DATAFLOW_MAP_SIZE,
+            ),
+        )
+    }
+}
+
+/// Forgets the stores of the previous execution before each execution
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DataflowHook<I, S> {
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, S> DataflowHook<I, S> {
+    /// The constructor
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, S> ExecutorHook<I, S> for DataflowHook<I, S> {
+    fn init(&mut self, _state: &mut S) {}
+
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) {
+        // # Safety
+        // This happens while no execution is running.
+        unsafe {
+            DATAFLOW_EPOCH = DATAFLOW_EPOCH.wrapping_add(1).max(1);
+        }
+    }
+
+    fn post_exec(&mut self, _state: &mut S, _input: &I) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::c_void;
+
+    use libafl::executors::hooks::ExecutorHook;
+
+    use super::{
+        __libafl_dataflow_load, __libafl_dataflow_store, DATAFLOW_MAP, DataflowHook,
+        dataflow_pair_index,
+    };
+
+    #[test]
+    fn test_dataflow_def_use() {
+        let value = 0_u64;
+        let addr = (&raw const value).cast::<c_void>();
+        let mut hook = DataflowHook::<(), ()>::new();
+
+        unsafe {
+            hook.pre_exec(&mut (), &());
+            // a load without a preceding store is no pair
+            __libafl_dataflow_load(addr, 20);
+            __libafl_dataflow_store(addr, 10);
+            __libafl_dataflow_load(addr, 20);
+            __libafl_dataflow_load(addr, 20);
+            assert_eq!((*(&raw const DATAFLOW_MAP))[dataflow_pair_index(10, 20)], 2);
+
+            // the store is forgotten in the next execution
+            hook.pre_exec(&mut (), &());
+            __libafl_dataflow_load(addr, 20);
+            assert_eq!((*(&raw const DATAFLOW_MAP))[dataflow_pair_index(10, 20)], 2);
+        }
+        assert_ne!(dataflow_pair_index(10, 20), dataflow_pair_index(20, 10));
+    }
+}
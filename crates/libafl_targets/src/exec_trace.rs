@@ -0,0 +1,516 @@
+//! A compact, versioned binary format for execution traces.
+//!
+//! Execution traces record the events of one or more executions, such as executed blocks, edges, memory accesses,
+//! comparisons, calls and syscalls, in order. They are written by `libafl_qemu`'s `LoggerModule`,
+//! and can be read back with an [`ExecTraceReader`], for example to diff the execution of a crashing input against its parent.
+//!
+//! The format starts with the [`EXEC_TRACE_MAGIC`] and a little-endian `u16` version, followed by the events.
+//! Each event is a one-byte tag, followed by its fields as unsigned LEB128 varints.
+//! Strings are written as a varint length, followed by the UTF-8 bytes.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+
+use libafl::Error;
+use serde::{Deserialize, Serialize};
+
+/// The magic bytes every execution trace starts with
+pub const EXEC_TRACE_MAGIC: &[u8; 8] = b"LIBAFLET";
+
+/// The current version of the execution trace format
+pub const EXEC_TRACE_VERSION: u16 = 1;
+
+/// A single event in an execution trace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecTraceEvent {
+    /// A new execution starts
+    ExecStart {
+        /// The index of this execution in the trace
+        index: u64,
+    },
+    /// The current execution ended
+    ExecEnd {
+        /// How the execution ended, such as `Ok` or `Crash`
+        exit_kind: String,
+    },
+    /// A block got executed
+    Block {
+        /// The address of the block
+        pc: u64,
+    },
+    /// An edge got taken
+    Edge {
+        /// The source block
+        src: u64,
+        /// The destination block
+        dst: u64,
+    },
+    /// A memory read
+    Read {
+        /// The address of the instruction
+        pc: u64,
+        /// The accessed address
+        addr: u64,
+        /// The size of the access, in bytes
+        size: u64,
+    },
+    /// A memory write
+    Write {
+        /// The address of the instruction
+        pc: u64,
+        /// The accessed address
+        addr: u64,
+        /// The size of the access, in bytes
+        size: u64,
+    },
+    /// A comparison
+    Cmp {
+        /// The address of the instruction
+        pc: u64,
+        /// The size of the operands, in bytes
+        size: u64,
+        /// The first operand
+        v0: u64,
+        /// The second operand
+        v1: u64,
+    },
+    /// A call instruction is about to be executed
+    Call {
+        /// The address of the call instruction
+        pc: u64,
+        /// The address the callee will return to
+        return_address: u64,
+    },
+    /// A return instruction is about to be executed
+    Ret {
+        /// The address of the return instruction
+        pc: u64,
+        /// The address it returns to
+        target: u64,
+    },
+    /// A syscall is about to be executed
+    Syscall {
+        /// The syscall number
+        num: u64,
+        /// The syscall arguments
+        args: [u64; 8],
+    },
+    /// A syscall returned
+    SyscallRet {
+        /// The syscall number
+        num: u64,
+        /// The return value
+        result: u64,
+    },
+    /// A new thread got created
+    NewThread {
+        /// The thread id
+        tid: u64,
+    },
+    /// A custom (backdoor) instruction got executed
+    CustomInsn {
+        /// The address of the instruction
+        pc: u64,
+    },
+    /// A watched instruction got executed
+    Instruction {
+        /// The address of the instruction
+        pc: u64,
+    },
+}
+
+impl ExecTraceEvent {
+    const TAG_EXEC_START: u8 = 1;
+    const TAG_EXEC_END: u8 = 2;
+    const TAG_BLOCK: u8 = 3;
+    const TAG_EDGE: u8 = 4;
+    const TAG_READ: u8 = 5;
+    const TAG_WRITE: u8 = 6;
+    const TAG_CMP: u8 = 7;
+    const TAG_CALL: u8 = 8;
+    const TAG_RET: u8 = 9;
+    const TAG_SYSCALL: u8 = 10;
+    const TAG_SYSCALL_RET: u8 = 11;
+    const TAG_NEW_THREAD: u8 = 12;
+    const TAG_CUSTOM_INSN: u8 = 13;
+    const TAG_INSTRUCTION: u8 = 14;
+}
+
+impl Display for ExecTraceEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExecStart { index } => write!(f, "=== exec {index} ==="),
+            Self::ExecEnd { exit_kind } => write!(f, "=== end: {exit_kind} ==="),
+            Self::Block { pc } => write!(f, "[PC {pc:#x}] block"),
+            Self::Edge { src, dst } => write!(f, "[PC {src:#x}] edge to {dst:#x}"),
+            Self::Read { pc, addr, size } => {
+                write!(f, "[PC {pc:#x}] read of {size} bytes @addr {addr:#x}")
+            }
+            Self::Write { pc, addr, size } => {
+                write!(f, "[PC {pc:#x}] write of {size} bytes @addr {addr:#x}")
+            }
+            Self::Cmp { pc, size, v0, v1 } => {
+                write!(f, "[PC {pc:#x}] cmp of {size} bytes: {v0:#x} vs {v1:#x}")
+            }
+            Self::Call { pc, return_address } => {
+                write!(f, "[PC {pc:#x}] call, returning to {return_address:#x}")
+            }
+            Self::Ret { pc, target } => write!(f, "[PC {pc:#x}] ret to {target:#x}"),
+            Self::Syscall { num, args } => {
+                write!(f, "syscall {num}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg:#x}")?;
+                }
+                write!(f, ")")
+            }
+            Self::SyscallRet { num, result } => write!(f, "syscall {num} = {result:#x}"),
+            Self::NewThread { tid } => write!(f, "new thread {tid}"),
+            Self::CustomInsn { pc } => write!(f, "[PC {pc:#x}] custom instruction"),
+            Self::Instruction { pc } => write!(f, "[PC {pc:#x}] instruction"),
+        }
+    }
+}
+
+/// Writes [`ExecTraceEvent`]s in the execution trace format
+#[derive(Debug)]
+pub struct ExecTraceWriter<W>
+where
+    W: Write,
+{
+    writer: BufWriter<W>,
+}
+
+impl<W> ExecTraceWriter<W>
+where
+    W: Write,
+{
+    /// Create a new [`ExecTraceWriter`], writing the header to `writer`
+    pub fn new(writer: W) -> Result<Self, Error> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(EXEC_TRACE_MAGIC)?;
+        writer.write_all(&EXEC_TRACE_VERSION.to_le_bytes())?;
+        Ok(Self { writer })
+    }
+
+    /// Write a single event
+    pub fn write(&mut self, event: &ExecTraceEvent) -> Result<(), Error> {
+        match event {
+            ExecTraceEvent::ExecStart { index } => {
+                self.tag(ExecTraceEvent::TAG_EXEC_START)?;
+                self.varint(*index)?;
+            }
+            ExecTraceEvent::ExecEnd { exit_kind } => {
+                self.tag(ExecTraceEvent::TAG_EXEC_END)?;
+                self.varint(exit_kind.len() as u64)?;
+                self.writer.write_all(exit_kind.as_bytes())?;
+            }
+            ExecTraceEvent::Block { pc } => {
+                self.tag(ExecTraceEvent::TAG_BLOCK)?;
+                self.varint(*pc)?;
+            }
+            ExecTraceEvent::Edge { src, dst } => {
+                self.tag(ExecTraceEvent::TAG_EDGE)?;
+                self.varint(*src)?;
+                self.varint(*dst)?;
+            }
+            ExecTraceEvent::Read { pc, addr, size } => {
+                self.tag(ExecTraceEvent::TAG_READ)?;
+                self.varint(*pc)?;
+                self.varint(*addr)?;
+                self.varint(*size)?;
+            }
+            ExecTraceEvent::Write { pc, addr, size } => {
+                self.tag(ExecTraceEvent::TAG_WRITE)?;
+                self.varint(*pc)?;
+                self.varint(*addr)?;
+                self.varint(*size)?;
+            }
+            ExecTraceEvent::Cmp { pc, size, v0, v1 } => {
+                self.tag(ExecTraceEvent::TAG_CMP)?;
+                self.varint(*pc)?;
+                self.varint(*size)?;
+                self.varint(*v0)?;
+                self.varint(*v1)?;
+            }
+            ExecTraceEvent::Call { pc, return_address } => {
+                self.tag(ExecTraceEvent::TAG_CALL)?;
+                self.varint(*pc)?;
+                self.varint(*return_address)?;
+            }
+            ExecTraceEvent::Ret { pc, target } => {
+                self.tag(ExecTraceEvent::TAG_RET)?;
+                self.varint(*pc)?;
+                self.varint(*target)?;
+            }
+            ExecTraceEvent::Syscall { num, args } => {
+                self.tag(ExecTraceEvent::TAG_SYSCALL)?;
+                self.varint(*num)?;
+                for arg in args {
+                    self.varint(*arg)?;
+                }
+            }
+            ExecTraceEvent::SyscallRet { num, result } => {
+                self.tag(ExecTraceEvent::TAG_SYSCALL_RET)?;
+                self.varint(*num)?;
+                self.varint(*result)?;
+            }
+            ExecTraceEvent::NewThread { tid } => {
+                self.tag(ExecTraceEvent::TAG_NEW_THREAD)?;
+                self.varint(*tid)?;
+            }
+            ExecTraceEvent::CustomInsn { pc } => {
+                self.tag(ExecTraceEvent::TAG_CUSTOM_INSN)?;
+                self.varint(*pc)?;
+            }
+            ExecTraceEvent::Instruction { pc } => {
+                self.tag(ExecTraceEvent::TAG_INSTRUCTION)?;
+                self.varint(*pc)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush all buffered events to the underlying writer
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flush all buffered events, and return the underlying writer
+    pub fn into_inner(self) -> Result<W, Error> {
+        self.writer
+            .into_inner()
+            .map_err(|err| Error::from(err.into_error()))
+    }
+
+    fn tag(&mut self, tag: u8) -> Result<(), io::Error> {
+        self.writer.write_all(&[tag])
+    }
+
+    /// Write `value` as unsigned LEB128
+    #[expect(clippy::cast_possible_truncation)]
+    fn varint(&mut self, mut value: u64) -> Result<(), io::Error> {
+        let mut buf = [0_u8; 10];
+        let mut len = 0;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        self.writer.write_all(&buf[..len])
+    }
+}
+
+/// Reads [`ExecTraceEvent`]s from an execution trace.
+///
+/// Iterate over it to get all events.
+#[derive(Debug)]
+pub struct ExecTraceReader<R>
+where
+    R: Read,
+{
+    reader: BufReader<R>,
+    version: u16,
+}
+
+impl<R> ExecTraceReader<R>
+where
+    R: Read,
+{
+    /// Create a new [`ExecTraceReader`], checking the header of the trace in `reader`
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = BufReader::new(reader);
+        let mut magic = [0_u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != EXEC_TRACE_MAGIC {
+            return Err(Error::illegal_argument("Not an execution trace"));
+        }
+        let mut version = [0_u8; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version == 0 || version > EXEC_TRACE_VERSION {
+            return Err(Error::illegal_argument(format!(
+                "Unsupported execution trace version {version}, the latest supported is {EXEC_TRACE_VERSION}"
+            )));
+        }
+        Ok(Self { reader, version })
+    }
+
+    /// The format version of this trace
+    #[must_use]
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Read the next event, or `None` at the end of the trace
+    pub fn read_event(&mut self) -> Result<Option<ExecTraceEvent>, Error> {
+        let mut tag = [0_u8; 1];
+        match self.reader.read(&mut tag) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::Interrupted => return self.read_event(),
+            Err(err) => return Err(err.into()),
+        }
+
+        let event = match tag[0] {
+            ExecTraceEvent::TAG_EXEC_START => ExecTraceEvent::ExecStart {
+                index: self.varint()?,
+            },
+            ExecTraceEvent::TAG_EXEC_END => {
+                let len = usize::try_from(self.varint()?)?;
+                let mut exit_kind = Vec::new();
+                (&mut self.reader)
+                    .take(len as u64)
+                    .read_to_end(&mut exit_kind)?;
+                if exit_kind.len() != len {
+                    return Err(Error::illegal_state("Truncated execution trace"));
+                }
+                ExecTraceEvent::ExecEnd {
+                    exit_kind: String::from_utf8(exit_kind)?,
+                }
+            }
+            ExecTraceEvent::TAG_BLOCK => ExecTraceEvent::Block { pc: self.varint()? },
+            ExecTraceEvent::TAG_EDGE => ExecTraceEvent::Edge {
+                src: self.varint()?,
+                dst: self.varint()?,
+            },
+            ExecTraceEvent::TAG_READ => ExecTraceEvent::Read {
+                pc: self.varint()?,
+                addr: self.varint()?,
+                size: self.varint()?,
+            },
+            ExecTraceEvent::TAG_WRITE => ExecTraceEvent::Write {
+                pc: self.varint()?,
+                addr: self.varint()?,
+                size: self.varint()?,
+            },
+            ExecTraceEvent::TAG_CMP => ExecTraceEvent::Cmp {
+                pc: self.varint()?,
+                size: self.varint()?,
+                v0: self.varint()?,
+                v1: self.varint()?,
+            },
+            ExecTraceEvent::TAG_CALL => ExecTraceEvent::Call {
+                pc: self.varint()?,
+                return_address: self.varint()?,
+            },
+            ExecTraceEvent::TAG_RET => ExecTraceEvent::Ret {
+                pc: self.varint()?,
+                target: self.varint()?,
+            },
+            ExecTraceEvent::TAG_SYSCALL => {
+                let num = self.varint()?;
+                let mut args = [0; 8];
+                for arg in &mut args {
+                    *arg = self.varint()?;
+                }
+                ExecTraceEvent::Syscall { num, args }
+            }
+            ExecTraceEvent::TAG_SYSCALL_RET => ExecTraceEvent::SyscallRet {
+                num: self.varint()?,
+                result: self.varint()?,
+            },
+            ExecTraceEvent::TAG_NEW_THREAD => ExecTraceEvent::NewThread {
+                tid: self.varint()?,
+            },
+            ExecTraceEvent::TAG_CUSTOM_INSN => ExecTraceEvent::CustomInsn { pc: self.varint()? },
+            ExecTraceEvent::TAG_INSTRUCTION => ExecTraceEvent::Instruction { pc: self.varint()? },
+            tag => {
+                return Err(Error::illegal_state(format!(
+                    "Unknown event tag {tag} in execution trace"
+                )));
+            }
+        };
+        Ok(Some(event))
+    }
+
+    /// Read an unsigned LEB128 value
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0_u8; 1];
+            self.reader.read_exact(&mut byte)?;
+            value |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::illegal_state("Varint too long in execution trace"))
+    }
+}
+
+impl<R> Iterator for ExecTraceReader<R>
+where
+    R: Read,
+{
+    type Item = Result<ExecTraceEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{ExecTraceEvent, ExecTraceReader, ExecTraceWriter};
+
+    #[test]
+    fn test_exec_trace_roundtrip() {
+        let events = [
+            ExecTraceEvent::ExecStart { index: 0 },
+            ExecTraceEvent::Block { pc: 0x40_1000 },
+            ExecTraceEvent::Edge {
+                src: 0x40_1000,
+                dst: 0x40_1020,
+            },
+            ExecTraceEvent::Cmp {
+                pc: 0x40_1024,
+                size: 8,
+                v0: u64::MAX,
+                v1: 0,
+            },
+            ExecTraceEvent::Syscall {
+                num: 1,
+                args: [1, 0x7fff_0000, 5, 0, 0, 0, 0, 0],
+            },
+            ExecTraceEvent::ExecEnd {
+                exit_kind: "Crash".into(),
+            },
+        ];
+
+        let mut writer = ExecTraceWriter::new(Vec::new()).unwrap();
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let trace = writer.into_inner().unwrap();
+
+        let reader = ExecTraceReader::new(trace.as_slice()).unwrap();
+        let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read, events);
+    }
+
+    #[test]
+    fn test_exec_trace_invalid() {
+        assert!(ExecTraceReader::new(&b"NOTATRACE!"[..]).is_err());
+
+        let mut trace = super::EXEC_TRACE_MAGIC.to_vec();
+        trace.extend_from_slice(&1_u16.to_le_bytes());
+        // A block without its address
+        trace.push(ExecTraceEvent::TAG_BLOCK);
+        let mut reader = ExecTraceReader::new(trace.as_slice()).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+#ifndef __LIBAFL_TARGETS_IJON__
+#define __LIBAFL_TARGETS_IJON__
+
+/* IJON-style state annotations, implemented in ijon.rs (feature `ijon`).
+
+   IJON_SET(x)   reward every distinct value of x at this location
+   IJON_INC(x)   reward how often each value of x is seen at this location
+   IJON_STATE(x) advance the state hash with x, reward every sequence of states
+   IJON_MAX(x)   reward new maxima of x at this location
+   IJON_MIN(x)   reward new minima of x at this location
+*/
+
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+uint32_t ijon_hashint(uint32_t old, uint32_t value);
+uint32_t ijon_hashmem(uint32_t old, const uint8_t *ptr, size_t len);
+
+void ijon_map_set(uint32_t addr);
+void ijon_map_inc(uint32_t addr);
+void ijon_set(uint32_t value);
+void ijon_inc(uint32_t value);
+void ijon_hash_state(uint32_t value);
+void ijon_reset_state(void);
+void ijon_max(uint32_t slot, uint64_t value);
+void ijon_min(uint32_t slot, uint64_t value);
+
+#ifdef __cplusplus
+}
+#endif
+
+static inline uint32_t ijon_hashstr(uint32_t old, const char *str) {
+  return ijon_hashmem(old, (const uint8_t *)str, strlen(str));
+}
+
+#define IJON_LOCATION() ijon_hashstr(__LINE__, __FILE__)
+
+#define IJON_SET(x) ijon_map_set(ijon_hashint(IJON_LOCATION(), (x)))
+#define IJON_INC(x) ijon_map_inc(ijon_hashint(IJON_LOCATION(), (x)))
+#define IJON_STATE(x) ijon_hash_state((x))
+#define IJON_MAX(x) ijon_max(IJON_LOCATION(), (x))
+#define IJON_MIN(x) ijon_min(IJON_LOCATION(), (x))
+
+#endif
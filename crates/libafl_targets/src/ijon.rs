@@ -0,0 +1,313 @@
+//! IJON-style state annotations.
+//!
+//! Harness and target authors can use these to expose program state that edge coverage does not
+//! see, such as the position of a player in a maze or the state of a protocol handler.
+//! The annotations write to dedicated maps that are observed like any other coverage map:
+//!
+//! - [`ijon_set`], [`ijon_inc`] and [`ijon_hash_state`] write to the byte map [`IJON_MAP`], use
+//!   [`ijon_map_observer`] with a [`libafl::feedbacks::MaxMapFeedback`].
+//! - [`ijon_max`] keeps the largest value seen per slot in [`IJON_MAX_MAP`], use
+//!   [`ijon_max_map_observer`] with an [`IjonMaxMapFeedback`].
+//! - [`ijon_min`] keeps the smallest value seen per slot in [`IJON_MIN_MAP`], use
+//!   [`ijon_min_map_observer`] with an [`IjonMinMapFeedback`].
+//!
+//! C and C++ targets can include `ijon.h` for the same API plus the `IJON_*` macros that key the
+//! annotations by their source location.
+
+use alloc::borrow::Cow;
+use core::{marker::PhantomData, slice};
+
+use libafl::{
+    executors::hooks::ExecutorHook,
+    feedbacks::{DifferentIsNovel, MapFeedback},
+    observers::StdMapObserver,
+};
+use libafl_bolts::{
+    ownedref::OwnedMutSlice,
+    simd::{MaxReducer, MinReducer},
+};
+
+use crate::{IJON_MAP_SIZE, IJON_MAX_SLOTS};
+
+/// The map for [`ijon_set`], [`ijon_inc`] and [`ijon_hash_state`]
+#[unsafe(no_mangle)]
+#[allow(non_upper_case_globals)] // expect breaks here for some reason
+pub static mut libafl_ijon_map: [u8; IJON_MAP_SIZE] = [0; IJON_MAP_SIZE];
+pub use libafl_ijon_map as IJON_MAP;
+
+/// The map for [`ijon_max`]
+#[unsafe(no_mangle)]
+#[allow(non_upper_case_globals)] // expect breaks here for some reason
+pub static mut libafl_ijon_max_map: [u64; IJON_MAX_SLOTS] = [0; IJON_MAX_SLOTS];
+pub use libafl_ijon_max_map as IJON_MAX_MAP;
+
+/// The map for [`ijon_min`], unset slots hold `u64::MAX`
+#[unsafe(no_mangle)]
+#[allow(non_upper_case_globals)] // expect breaks here for some reason
+pub static mut libafl_ijon_min_map: [u64; IJON_MAX_SLOTS] = [u64::MAX; IJON_MAX_SLOTS];
+pub use libafl_ijon_min_map as IJON_MIN_MAP;
+
+/// The running state hash of [`ijon_hash_state`]
+static mut IJON_STATE: u32 = 0;
+
+/// A [`MapFeedback`] rewarding new maxima in [`IJON_MAX_MAP`]
+pub type IjonMaxMapFeedback<C, O> = MapFeedback<C, DifferentIsNovel, O, MaxReducer>;
+/// A [`MapFeedback`] rewarding new minima in [`IJON_MIN_MAP`]
+pub type IjonMinMapFeedback<C, O> = MapFeedback<C, DifferentIsNovel, O, MinReducer>;
+
+/// Mix `value` into the hash `old`
+#[unsafe(no_mangle)]
+pub extern "C" fn ijon_hashint(old: u32, value: u32) -> u32 {
+    let mut hash = ((u64::from(old) << 32) | u64::from(value)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    hash ^= hash >> 29;
+    (hash >> 32) as u32
+}
+
+/// Mix `len` bytes at `ptr` into the hash `old`
+///
+/// # Safety
+/// `ptr` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_hashmem(old: u32, ptr: *const u8, len: usize) -> u32 {
+    if len == 0 {
+        return ijon_hashint(old, ijon_hash_bytes(&[]));
+    }
+    let bytes = unsafe { slice::from_raw_parts(ptr, len) };
+    ijon_hashint(old, ijon_hash_bytes(bytes))
+}
+
+/// Hash a byte slice, e.g. a source location, for use as annotation key
+#[must_use]
+pub fn ijon_hash_bytes(bytes: &[u8]) -> u32 {
+    // FNV-1a
+    bytes.iter().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Mark the entry `addr` of [`IJON_MAP`] as seen
+///
+/// # Safety
+/// Writes to [`IJON_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_map_set(addr: u32) {
+    unsafe {
+        let entry = (&raw mut IJON_MAP)
+            .cast::<u8>()
+            .add(addr as usize % IJON_MAP_SIZE);
+        *entry |= 1;
+    }
+}
+
+/// Count a hit of the entry `addr` of [`IJON_MAP`]
+///
+/// # Safety
+/// Writes to [`IJON_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_map_inc(addr: u32) {
+    unsafe {
+        let entry = (&raw mut IJON_MAP)
+            .cast::<u8>()
+            .add(addr as usize % IJON_MAP_SIZE);
+        *entry = (*entry).saturating_add(1);
+    }
+}
+
+/// Reward every distinct `value` seen
+///
+/// # Safety
+/// Writes to [`IJON_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_set(value: u32) {
+    unsafe { ijon_map_set(ijon_hashint(0, value)) }
+}
+
+/// Reward how often each distinct `value` is seen
+///
+/// # Safety
+/// Writes to [`IJON_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_inc(value: u32) {
+    unsafe { ijon_map_inc(ijon_hashint(0, value)) }
+}
+
+/// Advance the state hash with `value` and reward every distinct sequence of states
+///
+/// The state is reset before each execution by [`IjonHook`], or manually with
+/// [`ijon_reset_state`].
+///
+/// # Safety
+/// Writes to [`IJON_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_hash_state(value: u32) {
+    unsafe {
+        IJON_STATE = ijon_hashint(IJON_STATE, value);
+        ijon_map_set(IJON_STATE);
+    }
+}
+
+/// Reset the state hash of [`ijon_hash_state`]
+///
+/// # Safety
+/// Must not be called concurrently with [`ijon_hash_state`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_reset_state() {
+    unsafe {
+        IJON_STATE = 0;
+    }
+}
+
+/// Keep the largest `value` seen in `slot`
+///
+/// # Safety
+/// Writes to [`IJON_MAX_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_max(slot: u32, value: u64) {
+    unsafe {
+        let entry = (&raw mut IJON_MAX_MAP)
+            .cast::<u64>()
+            .add(slot as usize % IJON_MAX_SLOTS);
+        *entry = (*entry).max(value);
+    }
+}
+
+/// Keep the smallest `value` seen in `slot`
+///
+/// # Safety
+/// Writes to [`IJON_MIN_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ijon_min(slot: u32, value: u64) {
+    unsafe {
+        let entry = (&raw mut IJON_MIN_MAP)
+            .cast::<u64>()
+            .add(slot as usize % IJON_MAX_SLOTS);
+        *entry = (*entry).min(value);
+    }
+}
+
+/// Gets a new [`StdMapObserver`] on [`IJON_MAP`]
+///
+/// # Safety
+/// The observer aliases the static map, there must be only one.
+pub unsafe fn ijon_map_observer<'a, S>(name: S) -> StdMapObserver<'a, u8, false>
+where
+    S: Into<Cow<'static, str>>,
+{
+    unsafe {
+        StdMapObserver::from_mut_slice(
+            name,
+            OwnedMutSlice::from_raw_parts_mut((&raw mut IJON_MAP).cast::<u8>(), IJON_MAP_SIZE),
+        )
+    }
+}
+
+/// Gets a new [`StdMapObserver`] on [`IJON_MAX_MAP`], to be used with an [`IjonMaxMapFeedback`]
+///
+/// # Safety
+/// The observer aliases the static map, there must be only one.
+pub unsafe fn ijon_max_map_observer<'a, S>(name: S) -> StdMapObserver<'a, u64, false>
+where
+    S: Into<Cow<'static, str>>,
+{
+    unsafe {
+        StdMapObserver::from_mut_slice(
+            name,
+            OwnedMutSlice::from_raw_parts_mut(
+                (&raw mut IJON_MAX_MAP).cast::<u64>(),
+                IJON_MAX_SLOTS,
+            ),
+        )
+    }
+}
+
+/// Gets a new [`StdMapObserver`] on [`IJON_MIN_MAP`], to be used with an [`IjonMinMapFeedback`]
+///
+/// Unset slots hold `u64::MAX`, which is also the initial value of the observer.
+///
+/// # Safety
+/// The observer aliases the static map, there must be only one.
+pub unsafe fn ijon_min_map_observer<'a, S>(name: S) -> StdMapObserver<'a, u64, false>
+where
+    S: Into<Cow<'static, str>>,
+{
+    let mut observer = unsafe {
+        StdMapObserver::from_mut_slice(
+            name,
+            OwnedMutSlice::from_raw_parts_mut(
+                (&raw mut IJON_MIN_MAP).cast::<u64>(),
+                IJON_MAX_SLOTS,
+            ),
+        )
+    };
+    *observer.initial_mut() = u64::MAX;
+    observer
+}
+
+/// Resets the [`ijon_hash_state`] state hash before each execution
+#[derive(Debug, Copy, Clone, Default)]
+pub struct IjonHook<I, S> {
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, S> IjonHook<I, S> {
+    /// The constructor
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, S> ExecutorHook<I, S> for IjonHook<I, S> {
+    fn init(&mut self, _state: &mut S) {}
+
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) {
+        // # Safety
+        // This happens while no execution is running.
+        unsafe {
+            ijon_reset_state();
+        }
+    }
+
+    fn post_exec(&mut self, _state: &mut S, _input: &I) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        IJON_MAP, IJON_MAP_SIZE, IJON_MAX_MAP, IJON_MIN_MAP, ijon_hash_bytes, ijon_hash_state,
+        ijon_hashint, ijon_inc, ijon_max, ijon_min, ijon_reset_state,
+    };
+
+    #[test]
+    fn test_ijon_annotations() {
+        assert_ne!(ijon_hashint(0, 1), ijon_hashint(0, 2));
+        assert_ne!(ijon_hashint(1, 2), ijon_hashint(2, 1));
+        assert_ne!(ijon_hash_bytes(b"a.c:1"), ijon_hash_bytes(b"a.c:2"));
+
+        unsafe {
+            ijon_max(3, 10);
+            ijon_max(3, 5);
+            assert_eq!((*(&raw const IJON_MAX_MAP))[3], 10);
+
+            ijon_min(3, 10);
+            ijon_min(3, 5);
+            assert_eq!((*(&raw const IJON_MIN_MAP))[3], 5);
+
+            ijon_inc(7);
+            ijon_inc(7);
+            let idx = ijon_hashint(0, 7) as usize % IJON_MAP_SIZE;
+            assert_eq!((*(&raw const IJON_MAP))[idx], 2);
+
+            // the same states in a different order hit a different entry
+            ijon_reset_state();
+            ijon_hash_state(1);
+            ijon_hash_state(2);
+            let first = ijon_hashint(ijon_hashint(0, 1), 2) as usize % IJON_MAP_SIZE;
+            let swapped = ijon_hashint(ijon_hashint(0, 2), 1) as usize % IJON_MAP_SIZE;
+            assert_eq!((*(&raw const IJON_MAP))[first], 1);
+            assert_eq!((*(&raw const IJON_MAP))[swapped], 0);
+        }
+    }
+}
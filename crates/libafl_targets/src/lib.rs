@@ -115,6 +115,21 @@ pub mod call;
 #[cfg(feature = "function-logging")]
 pub use call::*;
 
+#[cfg(feature = "ijon")]
+pub mod ijon;
+#[cfg(feature = "ijon")]
+pub use ijon::*;
+
+#[cfg(feature = "memlock")]
+pub mod memlock;
+#[cfg(feature = "memlock")]
+pub use memlock::*;
+
+#[cfg(feature = "dataflow")]
+pub mod dataflow;
+#[cfg(feature = "dataflow")]
+pub use dataflow::*;
+
 /// runtime related to comparisons
 pub mod cmps;
 pub use cmps::*;
@@ -122,6 +137,9 @@ pub use cmps::*;
 #[cfg(feature = "std")]
 pub mod drcov;
 
+#[cfg(feature = "std")]
+pub mod exec_trace;
+
 #[cfg(all(windows, feature = "std", feature = "windows_asan"))]
 pub mod windows_asan;
 #[cfg(all(windows, feature = "std", feature = "windows_asan"))]
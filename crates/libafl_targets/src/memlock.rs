@@ -0,0 +1,327 @@
+//! Runtime for the `MemLock` pass of `libafl_cc`.
+//!
+//! The pass reports the call depth at every function entry and the requested size of every heap
+//! allocation. This runtime keeps the maximum depth per function in [`MEMLOCK_STACK_MAP`] and the
+//! maximum size per allocation site in [`MEMLOCK_ALLOC_MAP`]. Observe them with
+//! [`memlock_stack_map_observer`] and [`memlock_alloc_map_observer`], reward new maxima with a
+//! [`MemLockMapFeedback`] and report inputs past a limit with a [`MemLockThresholdFeedback`]
+//! objective, so the fuzzer climbs towards uncontrolled recursion and memory consumption bugs.
+//!
+//! The call depth is tracked for the whole process, not per thread.
+
+use alloc::{borrow::Cow, format};
+use core::{fmt::Debug, marker::PhantomData};
+
+use libafl::{
+    Error,
+    executors::{ExitKind, hooks::ExecutorHook},
+    feedbacks::{DifferentIsNovel, Feedback, MapFeedback, StateInitializer},
+    observers::{MapObserver, StdMapObserver},
+};
+use libafl_bolts::{
+    AsIter, Named,
+    ownedref::OwnedMutSlice,
+    simd::MaxReducer,
+    tuples::{Handle, Handled, MatchName, MatchNameRef},
+};
+
+use crate::MEMLOCK_MAP_SIZE;
+
+/// The maximum call depth seen at the entry of each function
+#[unsafe(no_mangle)]
+#[allow(non_upper_case_globals)] // expect breaks here for some reason
+pub static mut libafl_memlock_stack_map: [u32; MEMLOCK_MAP_SIZE] = [0; MEMLOCK_MAP_SIZE];
+pub use libafl_memlock_stack_map as MEMLOCK_STACK_MAP;
+
+/// The maximum allocation size requested at each allocation site
+#[unsafe(no_mangle)]
+#[allow(non_upper_case_globals)] // expect breaks here for some reason
+pub static mut libafl_memlock_alloc_map: [u64; MEMLOCK_MAP_SIZE] = [0; MEMLOCK_MAP_SIZE];
+pub use libafl_memlock_alloc_map as MEMLOCK_ALLOC_MAP;
+
+/// The current call depth
+static mut MEMLOCK_DEPTH: u32 = 0;
+
+/// A [`MapFeedback`] rewarding new maxima in the `MemLock` maps
+pub type MemLockMapFeedback<C, O> = MapFeedback<C, DifferentIsNovel, O, MaxReducer>;
+
+/// The runtime code inserted at every function entry by the `MemLock` pass
+///
+/// # Safety
+/// Writes to [`MEMLOCK_STACK_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __libafl_memlock_func_enter(id: u32) {
+    unsafe {
+        MEMLOCK_DEPTH = MEMLOCK_DEPTH.saturating_add(1);
+        let entry = (&raw mut MEMLOCK_STACK_MAP)
+            .cast::<u32>()
+            .add(id as usize % MEMLOCK_MAP_SIZE);
+        *entry = (*entry).max(MEMLOCK_DEPTH);
+    }
+}
+
+/// The runtime code inserted before every function return by the `MemLock` pass
+///
+/// # Safety
+/// Must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __libafl_memlock_func_exit() {
+    unsafe {
+        MEMLOCK_DEPTH = MEMLOCK_DEPTH.saturating_sub(1);
+    }
+}
+
+/// The runtime code inserted before every heap allocation by the `MemLock` pass
+///
+/// # Safety
+/// Writes to [`MEMLOCK_ALLOC_MAP`], must not be called concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __libafl_memlock_alloc(id: u32, size: u64) {
+    unsafe {
+        let entry = (&raw mut MEMLOCK_ALLOC_MAP)
+            .cast::<u64>()
+            .add(id as usize % MEMLOCK_MAP_SIZE);
+        *entry = (*entry).max(size);
+    }
+}
+
+/// Gets a new [`StdMapObserver`] on [`MEMLOCK_STACK_MAP`]
+///
+/// # Safety
+/// The observer aliases the static map, there must be only one.
+pub unsafe fn memlock_stack_map_observer<'a, S>(name: S) -> StdMapObserver<'a, u32, false>
+where
+    S: Into<Cow<'static, str>>,
+{
+    unsafe {
+        StdMapObserver::from_mut_slice(
+            name,
+            OwnedMutSlice::from_raw_parts_mut(
+                (&raw mut MEMLOCK_STACK_MAP).cast::<u32>(),
+                MEMLOCK_MAP_SIZE,
+            ),
+        )
+    }
+}
+
+/// Gets a new [`StdMapObserver`] on [`MEMLOCK_ALLOC_MAP`]
+///
+/// # Safety
+/// The observer aliases the static map, there must be only one.
+pub unsafe fn memlock_alloc_map_observer<'a, S>(name: S) -> StdMapObserver<'a, u64, false>
+where
+    S: Into<Cow<'static, str>>,
+{
+    unsafe {
+        StdMapObserver::from_mut_slice(
+            name,
+            OwnedMutSlice::from_raw_parts_mut(
+                (&raw mut MEMLOCK_ALLOC_MAP).cast::<u64>(),
+                MEMLOCK_MAP_SIZE,
+            ),
+        )
+    }
+}
+
+/// Resets the call depth before each execution.
+///
+/// Crashes, `longjmp` and exceptions leave functions without passing their return, so the depth
+/// would drift upwards without it.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MemLockHook<I, S> {
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, S> MemLockHook<I, S> {
+    /// The constructor
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, S> ExecutorHook<I, S> for MemLockHook<I, S> {
+    fn init(&mut self, _state: &mut S) {}
+
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) {
+        // # Safety
+        // This happens while no execution is running.
+        unsafe {
+            MEMLOCK_DEPTH = 0;
+        }
+    }
+
+    fn post_exec(&mut self, _state: &mut S, _input: &I) {}
+}
+
+/// An objective reporting inputs for which any entry of a `MemLock` map exceeds a threshold,
+/// e.g. a call depth of 10000 or an allocation of 1 GiB.
+#[derive(Debug)]
+pub struct MemLockThresholdFeedback<C, O>
+where
+    O: MapObserver,
+{
+    name: Cow<'static, str>,
+    map_ref: Handle<C>,
+    threshold: O::Entry,
+    #[cfg(feature = "track_hit_feedbacks")]
+    last_result: Option<bool>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> MemLockThresholdFeedback<C, O>
+where
+    C: Named,
+    O: MapObserver,
+{
+    /// Create a new [`MemLockThresholdFeedback`] for the map of `map_observer`
+    #[must_use]
+    pub fn new(map_observer: &C, threshold: O::Entry) -> Self {
+        Self {
+            name: Cow::Owned(format!("memlock_threshold_{}", map_observer.name())),
+            map_ref: map_observer.handle(),
+            threshold,
+            #[cfg(feature = "track_hit_feedbacks")]
+            last_result: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O> Named for MemLockThresholdFeedback<C, O>
+where
+    O: MapObserver,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<C, O, S> StateInitializer<S> for MemLockThresholdFeedback<C, O> where O: MapObserver {}
+
+impl<C, EM, I, O, OT, S> Feedback<EM, I, OT, S> for MemLockThresholdFeedback<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver + for<'it> AsIter<'it, Item = O::Entry>,
+    O::Entry: PartialOrd + Debug,
+    OT: MatchName,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let Some(observer) = observers.get(&self.map_ref) else {
+            return Err(Error::illegal_state(format!(
+                "Observer {:?} not found",
+                self.map_ref
+            )));
+        };
+        let exceeded = observer
+            .as_ref()
+            .as_iter()
+            .any(|entry| *entry > self.threshold);
+
+        #[cfg(feature = "track_hit_feedbacks")]
+        {
+            self.last_result = Some(exceeded);
+        }
+        Ok(exceeded)
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        self.last_result.ok_or_else(|| {
+            Error::illegal_state(
+                "No last result set in `MemLockThresholdFeedback`. Either `is_interesting` has never been called or the fuzzer restarted in the meantime.",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        executors::{ExitKind, hooks::ExecutorHook},
+        feedbacks::Feedback,
+        observers::StdMapObserver,
+    };
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::{
+        __libafl_memlock_alloc, __libafl_memlock_func_enter, __libafl_memlock_func_exit,
+        MEMLOCK_ALLOC_MAP, MEMLOCK_MAP_SIZE, MEMLOCK_STACK_MAP, MemLockHook,
+        MemLockThresholdFeedback,
+    };
+
+    #[test]
+    fn test_memlock_depth() {
+        let mut hook = MemLockHook::<(), ()>::new();
+
+        unsafe {
+            hook.pre_exec(&mut (), &());
+            __libafl_memlock_func_enter(1);
+            __libafl_memlock_func_enter(2);
+            __libafl_memlock_func_enter(2);
+            assert_eq!((*(&raw const MEMLOCK_STACK_MAP))[1], 1);
+            assert_eq!((*(&raw const MEMLOCK_STACK_MAP))[2], 3);
+
+            // a shallower call keeps the maximum
+            __libafl_memlock_func_exit();
+            __libafl_memlock_func_exit();
+            __libafl_memlock_func_enter(2);
+            assert_eq!((*(&raw const MEMLOCK_STACK_MAP))[2], 3);
+
+            // unbalanced exits do not wrap around
+            for _ in 0..4 {
+                __libafl_memlock_func_exit();
+            }
+            __libafl_memlock_func_enter(3);
+            assert_eq!((*(&raw const MEMLOCK_STACK_MAP))[3], 1);
+
+            // the depth of a run that never returned is forgotten in the next execution
+            hook.pre_exec(&mut (), &());
+            __libafl_memlock_func_enter(4);
+            assert_eq!((*(&raw const MEMLOCK_STACK_MAP))[4], 1);
+        }
+    }
+
+    #[test]
+    fn test_memlock_alloc() {
+        unsafe {
+            __libafl_memlock_alloc(7, 100);
+            __libafl_memlock_alloc(7, 50);
+            assert_eq!((*(&raw const MEMLOCK_ALLOC_MAP))[7], 100);
+
+            // ids past the map size wrap around
+            __libafl_memlock_alloc(7 + MEMLOCK_MAP_SIZE as u32, 200);
+            assert_eq!((*(&raw const MEMLOCK_ALLOC_MAP))[7], 200);
+        }
+    }
+
+    #[test]
+    fn test_memlock_threshold() {
+        let observer = StdMapObserver::owned("memlock", vec![0_u64; 4]);
+        let mut feedback =
+            MemLockThresholdFeedback::<_, StdMapObserver<u64, false>>::new(&observer, 10);
+
+        for (size, exceeded) in [(9, false), (10, false), (11, true)] {
+            let observers = tuple_list!(StdMapObserver::owned("memlock", vec![0, 0, size, 0]));
+            let interesting = Feedback::<(), (), _, ()>::is_interesting(
+                &mut feedback,
+                &mut (),
+                &mut (),
+                &(),
+                &observers,
+                &ExitKind::Ok,
+            )
+            .unwrap();
+            assert_eq!(interesting, exceeded, "size {size}");
+        }
+    }
+}
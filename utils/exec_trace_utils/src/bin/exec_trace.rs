@@ -0,0 +1,69 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, ValueEnum};
+use libafl_targets::exec_trace::ExecTraceReader;
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum Format {
+    /// One human-readable line per event
+    Text,
+    /// One JSON object per line and event
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[command(
+    name = "exec_trace",
+    about,
+    long_about = "Converts LibAFL execution traces to text or JSON"
+)]
+pub struct Opt {
+    #[arg(short, long, help = "The execution trace to dump", required = true)]
+    pub dump: PathBuf,
+
+    #[arg(short, long, value_enum, default_value_t = Format::Text, help = "The output format")]
+    pub format: Format,
+
+    #[arg(
+        short,
+        long,
+        help = "The file to write to. If none is set, this will output to stdout."
+    )]
+    pub output: Option<PathBuf>,
+}
+
+fn dump(opts: &Opt) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = ExecTraceReader::new(File::open(&opts.dump)?)?;
+
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match &opts.output {
+        Some(output) => Box::new(File::create(output)?),
+        None => Box::new(std::io::stdout()),
+    });
+
+    for event in reader {
+        let event = event?;
+        match opts.format {
+            Format::Text => writeln!(writer, "{event}")?,
+            Format::Json => writeln!(writer, "{}", serde_json::to_string(&event)?)?,
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let opts = Opt::parse();
+
+    if let Err(err) = dump(&opts) {
+        eprintln!("Failed to dump {}: {err}", opts.dump.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
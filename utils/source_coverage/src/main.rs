@@ -0,0 +1,114 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use clap::Parser;
+use libafl::stages::LlvmCovReporter;
+use walkdir::WalkDir;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[command(
+    name = "source_coverage",
+    about,
+    long_about = "Replays corpora against a binary built with -fprofile-instr-generate -fcoverage-mapping and writes lcov and HTML coverage reports"
+)]
+pub struct Opt {
+    #[arg(short, long, help = "The binary built with coverage mapping")]
+    pub binary: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Corpus directories or files to replay",
+        required = true
+    )]
+    pub corpus: Vec<PathBuf>,
+
+    #[arg(short, long, help = "Output folder for the profiles and reports")]
+    pub out_dir: PathBuf,
+
+    #[arg(
+        long,
+        help = "Shared libraries built with coverage mapping to include in the report"
+    )]
+    pub object: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "The llvm-profdata binary",
+        default_value = "llvm-profdata"
+    )]
+    pub llvm_profdata: PathBuf,
+
+    #[arg(long, help = "The llvm-cov binary", default_value = "llvm-cov")]
+    pub llvm_cov: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Timeout for a single run in milliseconds",
+        default_value = "5000"
+    )]
+    pub timeout: u64,
+
+    #[arg(long, help = "Only write the lcov report, skip the HTML report")]
+    pub no_html: bool,
+
+    #[arg(
+        last = true,
+        help = "Arguments for the binary, @@ is replaced by the input file"
+    )]
+    pub args: Vec<String>,
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opt::parse();
+
+    let mut reporter = LlvmCovReporter::new(&opts.binary, &opts.out_dir)
+        .expect("Failed to create the output directory")
+        .args(&opts.args)
+        .objects(&opts.object)
+        .llvm_profdata(&opts.llvm_profdata)
+        .llvm_cov(&opts.llvm_cov)
+        .timeout(Duration::from_millis(opts.timeout))
+        .html(!opts.no_html);
+
+    let mut replayed = 0_usize;
+    for corpus in &opts.corpus {
+        for entry in WalkDir::new(corpus)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+        {
+            // skip metadata and lock files of on-disk corpora
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(input) = fs::read(entry.path()).map_err(|err| {
+                eprintln!(
+                    "Warning: failed to read input {}: {err:?}",
+                    entry.path().display()
+                );
+            }) else {
+                continue;
+            };
+            reporter
+                .replay(&input)
+                .expect("Failed to run the coverage binary");
+            replayed += 1;
+        }
+    }
+    println!("Replayed {replayed} inputs");
+
+    reporter.merge().expect("Failed to merge the raw profiles");
+    reporter.report().expect("Failed to write the reports");
+    println!(
+        "Wrote {} to {}",
+        if opts.no_html {
+            "lcov report"
+        } else {
+            "lcov and HTML reports"
+        },
+        opts.out_dir.display()
+    );
+}